@@ -0,0 +1,201 @@
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, Responder, post, web};
+use log::{info, warn};
+use rand::RngCore;
+use rand::rngs::OsRng;
+
+use super::balance::add_capped;
+use super::models::{AppState, RebuildUtxoResponse, RotateTokenResponse};
+use crate::blockchain::rebuild_utxo;
+use crate::transaction::UtxoSet;
+
+/// Admin endpoints need `Authorization: Bearer <token>` (`ADMIN_TOKEN`, or
+/// the latest rotated one); without `ADMIN_TOKEN` configured they are
+/// refused outright.
+pub(super) fn authorize(state: &AppState, req: &HttpRequest) -> Result<(), HttpResponse> {
+    let expected = state.admin_token.lock().expect("mutex poisoned").clone();
+    let Some(expected) = expected else {
+        return Err(HttpResponse::Forbidden().body("admin endpoints disabled (set ADMIN_TOKEN)"));
+    };
+    let given = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    if constant_time_eq(given.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        warn!("admin: rejected request to {}", req.path());
+        Err(HttpResponse::Unauthorized().body("invalid admin token"))
+    }
+}
+
+/// Compare without bailing at the first differing byte (no timing hint).
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Replace the admin token with a fresh random one (32 bytes, hex) and
+/// return it; the old token stops working immediately.
+#[post("/admin/rotate-token/")]
+pub async fn rotate_admin_token(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = authorize(&state, &req) {
+        return resp;
+    }
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    *state.admin_token.lock().expect("mutex poisoned") = Some(token.clone());
+    info!("admin: token rotated");
+    HttpResponse::Ok().json(RotateTokenResponse { token })
+}
+
+/// Recompute the UTXO set from genesis (plus the faucet's off-chain outputs)
+/// and replace the live one, reporting how far it had drifted.
+#[post("/admin/rebuild-utxo/")]
+pub async fn rebuild_utxo_set(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = authorize(&state, &req) {
+        return resp;
+    }
+    // chain locked throughout: no block can land between replay and swap
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    if bc.has_pruned_blocks() {
+        return HttpResponse::Conflict()
+            .body("chain has pruned blocks; the UTXO set can't be replayed from it");
+    }
+    let rebuilt = {
+        let faucet = state.faucet_outputs.lock().expect("mutex poisoned");
+        rebuild_utxo(&bc.chain, &faucet)
+    };
+    let mut utxo = state.utxo_set.lock().expect("mutex poisoned");
+
+    let added = rebuilt
+        .entries()
+        .filter(|(op, entry)| utxo.entry(op) != Some(*entry))
+        .count();
+    let removed = utxo
+        .entries()
+        .filter(|(op, entry)| rebuilt.entry(op) != Some(*entry))
+        .count();
+    let resp = RebuildUtxoResponse {
+        added,
+        removed,
+        count_before: utxo.len(),
+        count_after: rebuilt.len(),
+        total_before: total_value(&utxo),
+        total_after: total_value(&rebuilt),
+    };
+    *utxo = rebuilt;
+    // cached mempool fees were computed against the old set
+    state.fee_cache.lock().expect("mutex poisoned").clear();
+    info!(
+        "admin: rebuilt UTXO set from {} blocks (+{} -{} entries)",
+        bc.len(),
+        resp.added,
+        resp.removed
+    );
+    HttpResponse::Ok().json(resp)
+}
+
+pub(super) fn total_value(utxo: &UtxoSet) -> u128 {
+    utxo.iter()
+        .fold(0, |sum, (_, out)| add_capped(sum, out.amount as u128))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test;
+    use serde_json::Value;
+
+    use crate::api::testutil::{app, faucet, mine, state_with};
+    use crate::config::NodeConfig;
+    use crate::transaction::{OutPoint, UtxoEntry, UtxoSource};
+
+    fn sorted_entries(state: &crate::api::AppState) -> Vec<(OutPoint, UtxoEntry)> {
+        let utxo = state.utxo_set.lock().unwrap();
+        let mut entries: Vec<_> = utxo
+            .entries()
+            .map(|(op, e)| (op.clone(), e.clone()))
+            .collect();
+        entries.sort_by(|a, b| (&a.0.txid, a.0.vout).cmp(&(&b.0.txid, b.0.vout)));
+        entries
+    }
+
+    #[actix_web::test]
+    async fn rebuild_restores_a_tampered_utxo_set() {
+        let state = state_with(NodeConfig {
+            admin_token: Some("s3cret".into()),
+            ..NodeConfig::default()
+        });
+        let app = app(&state).await;
+        faucet(&app, "alice", 100).await;
+        mine(&app, "miner").await;
+        mine(&app, "miner").await;
+        let honest = sorted_entries(&state);
+
+        {
+            // lose a real output, invent a fake one
+            let mut utxo = state.utxo_set.lock().unwrap();
+            utxo.spend(&honest[0].0);
+            let mut fake = honest[1].1.clone();
+            fake.output.amount = 1_000_000;
+            fake.source = UtxoSource::Chain;
+            utxo.insert(
+                OutPoint {
+                    txid: "forged".into(),
+                    vout: 0,
+                },
+                fake,
+            );
+        }
+
+        let rebuild = |token: &'static str| {
+            test::TestRequest::post()
+                .uri("/api/v1/admin/rebuild-utxo/")
+                .insert_header(("Authorization", format!("Bearer {token}")))
+                .to_request()
+        };
+        let resp = test::call_service(&app, rebuild("wrong")).await;
+        assert_eq!(resp.status(), 401);
+
+        let resp = test::call_service(&app, rebuild("s3cret")).await;
+        assert!(resp.status().is_success());
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["added"], 1);
+        assert_eq!(body["removed"], 1);
+        assert_eq!(body["count_after"], honest.len());
+        assert_eq!(sorted_entries(&state), honest);
+    }
+
+    #[actix_web::test]
+    async fn rotated_token_replaces_the_old_one() {
+        let state = state_with(NodeConfig {
+            admin_token: Some("s3cret".into()),
+            ..NodeConfig::default()
+        });
+        let app = app(&state).await;
+        let call = |uri: &'static str, token: String| {
+            test::TestRequest::post()
+                .uri(uri)
+                .insert_header(("Authorization", format!("Bearer {token}")))
+                .to_request()
+        };
+
+        let resp =
+            test::call_service(&app, call("/api/v1/admin/rotate-token/", "wrong".into())).await;
+        assert_eq!(resp.status(), 401);
+        let resp =
+            test::call_service(&app, call("/api/v1/admin/rotate-token/", "s3cret".into())).await;
+        assert!(resp.status().is_success());
+        let body: Value = test::read_body_json(resp).await;
+        let token = body["token"].as_str().unwrap().to_string();
+        assert_eq!(token.len(), 64);
+
+        let rebuild = "/api/v1/admin/rebuild-utxo/";
+        let resp = test::call_service(&app, call(rebuild, "s3cret".into())).await;
+        assert_eq!(resp.status(), 401);
+        let resp = test::call_service(&app, call(rebuild, token)).await;
+        assert!(resp.status().is_success());
+    }
+}
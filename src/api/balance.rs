@@ -1,25 +1,307 @@
-use actix_web::{HttpResponse, Responder, get, web};
+use actix_web::{Responder, get, web};
+use log::warn;
+use std::collections::{BTreeMap, HashSet};
 
-use super::models::{AppState, BalanceResponse};
+use super::format::{FormatQuery, json_formatted};
+use super::models::{
+    AccountEntry, AccountsQuery, AccountsResponse, AppState, BalanceResponse,
+    DetailedBalanceResponse,
+};
+use crate::transaction::{OutPoint, UtxoSet};
+
+/// Default and max page sizes for `/accounts/`.
+const ACCOUNTS_DEFAULT_LIMIT: usize = 100;
+const ACCOUNTS_MAX_LIMIT: usize = 1000;
 
 #[get("/balance/{address}/")]
-pub async fn get_balance(state: web::Data<AppState>, path: web::Path<(String,)>) -> impl Responder {
+pub async fn get_balance(
+    state: web::Data<AppState>,
+    path: web::Path<(String,)>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let address = path.into_inner().0;
+
+    // scan a snapshot: the UTXO lock is held only to clone an Arc
+    let utxo = state.utxo_set.lock().expect("mutex poisoned").snapshot();
+    let (sum, count) = balance_of(&utxo, &address);
+
+    json_formatted(
+        &BalanceResponse {
+            address,
+            balance: sum,
+            utxos: count,
+        },
+        &fmt,
+    )
+}
+
+/// Balance split by what pending transactions already reserve:
+/// `available = confirmed - pending_spent`, where `pending_spent` is the part
+/// of the address's UTXOs that mempool transactions consume.
+#[get("/balance/{address}/detailed/")]
+pub async fn get_detailed_balance(
+    state: web::Data<AppState>,
+    path: web::Path<(String,)>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
     let address = path.into_inner().0;
 
+    // lock order: UTXO (just for the snapshot) -> mempool
+    let utxo = state.utxo_set.lock().expect("mutex poisoned").snapshot();
+    let reserved: HashSet<OutPoint> = {
+        let mempool = state.mempool.lock().expect("mutex poisoned");
+        mempool
+            .iter()
+            .flat_map(|e| e.tx.inputs.iter().map(|i| i.outpoint.clone()))
+            .collect()
+    };
+
+    let (confirmed, _) = balance_of(&utxo, &address);
+    let pending_spent: u128 = utxo
+        .iter()
+        .filter(|(op, out)| out.address == address && reserved.contains(*op))
+        .fold(0, |sum, (_, out)| add_capped(sum, out.amount as u128));
+
+    json_formatted(
+        &DetailedBalanceResponse {
+            address,
+            confirmed,
+            pending_spent,
+            available: confirmed.saturating_sub(pending_spent),
+        },
+        &fmt,
+    )
+}
+
+/// Total amount and number of unspent outputs owned by `address`.
+fn balance_of(utxo: &UtxoSet, address: &str) -> (u128, usize) {
     let (mut sum, mut count) = (0u128, 0usize);
-    {
-        let utxo = state.utxo_set.lock().expect("mutex poisoned");
-        for (_op, out) in utxo.iter() {
-            if out.address == address {
-                sum += out.amount as u128;
-                count += 1;
-            }
+    for (_op, out) in utxo.iter() {
+        if out.address == address {
+            sum = add_capped(sum, out.amount as u128);
+            count += 1;
         }
     }
+    (sum, count)
+}
 
-    HttpResponse::Ok().json(BalanceResponse {
-        address,
-        balance: sum,
-        utxos: count,
+/// `total + amount` for the read-path scans. Amounts are u64, so no honest
+/// UTXO set gets near `u128::MAX`, but an imported one is untrusted: cap at
+/// the max (logging the first overflow) instead of wrapping or panicking.
+pub(super) fn add_capped(total: u128, amount: u128) -> u128 {
+    total.checked_add(amount).unwrap_or_else(|| {
+        if total != u128::MAX {
+            warn!("amount sum overflows u128 (corrupt or imported UTXO set?); capping");
+        }
+        u128::MAX
     })
 }
+
+/// All addresses with a nonzero balance, sorted by address (stable paging).
+#[get("/accounts/")]
+pub async fn get_accounts(
+    state: web::Data<AppState>,
+    query: web::Query<AccountsQuery>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let offset = query.offset.unwrap_or(0);
+    let limit = query
+        .limit
+        .unwrap_or(ACCOUNTS_DEFAULT_LIMIT)
+        .min(ACCOUNTS_MAX_LIMIT);
+
+    let utxo = state.utxo_set.lock().expect("mutex poisoned").snapshot();
+    let mut balances: BTreeMap<String, u128> = BTreeMap::new();
+    for (_op, out) in utxo.iter() {
+        let balance = balances.entry(out.address.clone()).or_default();
+        *balance = add_capped(*balance, out.amount as u128);
+    }
+    balances.retain(|_, balance| *balance > 0);
+
+    let total = balances.len();
+    let accounts = balances
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(address, balance)| AccountEntry { address, balance })
+        .collect();
+
+    json_formatted(
+        &AccountsResponse {
+            total,
+            offset,
+            limit,
+            accounts,
+        },
+        &fmt,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_capped, balance_of};
+    use crate::api::admin::total_value;
+    use crate::api::testutil::{
+        TestWallet, app, faucet, get, get_json, mine, output, signed_tx, state, submit_tx,
+    };
+    use crate::transaction::{OutPoint, UtxoEntry, UtxoSource};
+    use actix_web::test::read_body;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn long_balance_scan_does_not_block_mining() {
+        let state = state();
+        let app = app(&state).await;
+        faucet(&app, "alice", 100).await;
+
+        // a "scan" that takes as long as we want: it holds its snapshot until released
+        let (started_tx, started_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let scan_state = state.clone();
+        let scan = std::thread::spawn(move || {
+            let utxo = scan_state.utxo_set.lock().unwrap().snapshot();
+            started_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            (balance_of(&utxo, "alice"), utxo.len())
+        });
+        started_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        // mining applies the block to the UTXO set while the scan is still running
+        mine(&app, "miner").await;
+        assert!(!scan.is_finished());
+        assert_eq!(state.utxo_set.lock().unwrap().len(), 2);
+
+        release_tx.send(()).unwrap();
+        let ((balance, _), len) = scan.join().unwrap();
+        assert_eq!(balance, 100);
+        assert_eq!(len, 1); // the scan saw a consistent pre-block view
+    }
+
+    #[actix_web::test]
+    async fn accounts_lists_funded_addresses_with_paging() {
+        let state = state();
+        let app = app(&state).await;
+        faucet(&app, "bob", 30).await;
+        faucet(&app, "alice", 100).await;
+        faucet(&app, "alice", 5).await;
+
+        let body = get_json(&app, "/api/v1/accounts/").await;
+        assert_eq!(body["total"], 2);
+        assert_eq!(body["accounts"][0]["address"], "alice");
+        assert_eq!(body["accounts"][0]["balance"], 105);
+        assert_eq!(body["accounts"][1]["address"], "bob");
+        assert_eq!(body["accounts"][1]["balance"], 30);
+
+        let page = get_json(&app, "/api/v1/accounts/?offset=1&limit=1").await;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["accounts"].as_array().unwrap().len(), 1);
+        assert_eq!(page["accounts"][0]["address"], "bob");
+    }
+
+    #[actix_web::test]
+    async fn amounts_as_strings_keep_large_balances_exact() {
+        let state = state();
+        let app = app(&state).await;
+        let big: u64 = (1 << 53) + 1; // not representable as an f64
+        faucet(&app, "whale", big).await;
+
+        let body = get_json(&app, "/api/v1/balance/whale/?amounts=string").await;
+        assert_eq!(body["balance"], big.to_string());
+        assert_eq!(
+            body["balance"].as_str().unwrap().parse::<u64>().unwrap(),
+            big
+        );
+        assert_eq!(body["utxos"], 1); // non-monetary fields stay numeric
+
+        let body = get_json(&app, "/api/v1/balance/whale/").await;
+        assert_eq!(body["balance"].as_u64(), Some(big));
+    }
+
+    #[actix_web::test]
+    async fn scans_over_huge_amounts_neither_wrap_nor_panic() {
+        let state = state();
+        let app = app(&state).await;
+        {
+            // what a malicious /utxo/import/ could plant: max-value outputs
+            let mut utxo = state.utxo_set.lock().unwrap();
+            for vout in 0..3 {
+                utxo.insert(
+                    OutPoint {
+                        txid: "imported".into(),
+                        vout,
+                    },
+                    UtxoEntry {
+                        source: UtxoSource::Faucet,
+                        ..UtxoEntry::chain(output("whale", u64::MAX), 0)
+                    },
+                );
+            }
+        }
+        let exact = 3 * u64::MAX as u128;
+        let utxo = state.utxo_set.lock().unwrap().snapshot();
+        assert_eq!(balance_of(&utxo, "whale"), (exact, 3));
+        assert_eq!(total_value(&utxo), exact);
+
+        // past u64, so compare the raw body (a `Value` would round to f64),
+        // in every rendering the read endpoints offer
+        for (uri, field) in [
+            ("/api/v1/balance/whale/", "balance"),
+            ("/api/v1/utxo/summary/", "total_value"),
+            ("/api/v1/accounts/", "balance"),
+        ] {
+            for (query, rendered) in [
+                ("", format!("\"{field}\":{exact}")),
+                ("?pretty=true", format!("\"{field}\": {exact}")),
+                ("?amounts=string", format!("\"{field}\":\"{exact}\"")),
+                (
+                    "?amounts=string&pretty=true",
+                    format!("\"{field}\": \"{exact}\""),
+                ),
+            ] {
+                let resp = get(&app, &format!("{uri}{query}")).await;
+                assert!(resp.status().is_success(), "{uri}{query}");
+                let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+                assert!(body.contains(&rendered), "{uri}{query}: {body}");
+            }
+        }
+
+        // past u128 the sum caps instead of wrapping around to a small number
+        assert_eq!(add_capped(u128::MAX - 1, u64::MAX as u128), u128::MAX);
+        assert_eq!(add_capped(u128::MAX, 1), u128::MAX);
+        assert_eq!(add_capped(1, 2), 3);
+    }
+
+    #[actix_web::test]
+    async fn pending_spends_reduce_available_until_mined() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        faucet(&app, &alice.address, 30).await;
+        let uri = format!("/api/v1/balance/{}/detailed/", alice.address);
+
+        let body = get_json(&app, &uri).await;
+        assert_eq!(
+            (
+                &body["confirmed"],
+                &body["pending_spent"],
+                &body["available"]
+            ),
+            (&130.into(), &0.into(), &130.into())
+        );
+
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        let body = get_json(&app, &uri).await;
+        assert_eq!(body["confirmed"], 130);
+        assert_eq!(body["pending_spent"], 100);
+        assert_eq!(body["available"], 30);
+
+        mine(&app, "miner").await;
+        let body = get_json(&app, &uri).await;
+        assert_eq!(body["confirmed"], 30);
+        assert_eq!(body["pending_spent"], 0);
+        assert_eq!(body["available"], 30);
+    }
+}
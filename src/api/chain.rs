@@ -1,26 +1,267 @@
 use crate::blockchain::{
-    BASE_REWARD, Blockchain, DEFAULT_DIFFICULTY, MAX_BLOCK_BYTES, MAX_TXS_PER_BLOCK,
+    Block, CoinbaseShare, difficulty_bits, select_transactions_cached, validate_chain_full,
 };
-use actix_web::{HttpResponse, Responder, get, post, web};
+use crate::node::adopt_chain;
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use futures_util::StreamExt;
 use log::{debug, info, warn};
 use std::collections::HashSet;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
 
+use super::BLOCK_JSON_MAX_BYTES;
+use super::admin::authorize;
+use super::format::{FormatQuery, json_formatted};
 use super::models::{
-    AppState, ChainResponse, DifficultyResponse, MineRequest, MineResponse, SetDifficultyRequest,
-    ValidateResponse,
+    AppState, BlockEffectsResponse, BlockHashResponse, BlockTxWithFee, BlockTxsResponse, BlockView,
+    BlocksByTimeQuery, BlocksByTimeResponse, ChainImportResponse, ChainResponse, CheckpointStatus,
+    CheckpointsResponse, CreatedOutput, DifficultyQuery, DifficultyResponse, FullValidateResponse,
+    LatestBlockResponse, MerkleProofResponse, MineRequest, MineResponse, PreimageResponse,
+    SetDifficultyRequest, SpentOutpoint, TipQuery, TipResponse, TxView, ValidateResponse,
 };
-use crate::transaction::{OutPoint, Transaction, TxInput, TxOutput, UtxoSet};
+
+/// `410 Gone` body for endpoints that need a block's transactions.
+const PRUNED_BODY: &str = "block body pruned (PRUNE_DEPTH); only the header is kept";
 
 /// Get the full blockchain.
 #[get("/chain/")]
-pub async fn get_chain(state: web::Data<AppState>) -> impl Responder {
+pub async fn get_chain(state: web::Data<AppState>, fmt: web::Query<FormatQuery>) -> impl Responder {
     let bc = state.blockchain.lock().expect("mutex poisoned");
     let resp = ChainResponse {
         length: bc.len(),
         difficulty: bc.difficulty(),
-        chain: &bc.chain,
+        chain: bc.chain.iter().map(BlockView::from).collect(),
     };
-    HttpResponse::Ok().json(resp)
+    json_formatted(&resp, &fmt)
+}
+
+/// The whole chain as newline-delimited JSON, one block per line, streamed:
+/// each block is serialized only when the client is ready for it, so a long
+/// chain never sits in memory as one JSON document. Read it back with
+/// `storage::read_ndjson_blocks`. If a reorg replaces blocks that haven't
+/// been sent yet, the stream is cut off with an error instead of mixing the
+/// two forks.
+#[get("/chain/export/ndjson/")]
+pub async fn export_chain_ndjson(state: web::Data<AppState>) -> impl Responder {
+    let end = state.blockchain.lock().expect("mutex poisoned").len();
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .body(ChainNdjson {
+            state,
+            next: 0,
+            end,
+            prev_hash: None,
+        })
+}
+
+/// Body of `/chain/export/ndjson/`: blocks `next..end`, the chain lock taken
+/// once per block rather than for the whole transfer.
+struct ChainNdjson {
+    state: web::Data<AppState>,
+    next: usize,
+    end: usize,
+    /// hash of the last block sent; the next one must link to it
+    prev_hash: Option<String>,
+}
+
+impl MessageBody for ChainNdjson {
+    type Error = io::Error;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        if self.next >= self.end {
+            return Poll::Ready(None);
+        }
+        let block = {
+            let bc = self.state.blockchain.lock().expect("mutex poisoned");
+            bc.chain.get(self.next).cloned()
+        };
+        // a reorg may have shortened the chain, or swapped the blocks past
+        // the ones already sent, mid-export
+        let linked = match (&block, &self.prev_hash) {
+            (Some(block), Some(prev)) => block.previous_hash == *prev,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        let Some(block) = block.filter(|_| linked) else {
+            self.next = self.end;
+            return Poll::Ready(Some(Err(io::Error::other(
+                "chain reorganized during the export",
+            ))));
+        };
+        self.next += 1;
+        let line = serde_json::to_vec(&block).map(|mut bytes| {
+            bytes.push(b'\n');
+            Bytes::from(bytes)
+        });
+        self.prev_hash = Some(block.hash);
+        Poll::Ready(Some(line.map_err(io::Error::other)))
+    }
+}
+
+/// Replace the chain with an NDJSON export (admin only), if it has more
+/// work than ours. The body is read as it arrives, one block per line, so
+/// the upload is never buffered as a whole; each line may be at most
+/// `BLOCK_JSON_MAX_BYTES`. The chain then goes through the same checks as a
+/// reorg to a peer (`node::adopt_chain`).
+#[post("/chain/import/ndjson/")]
+pub async fn import_chain_ndjson(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    mut body: web::Payload,
+) -> impl Responder {
+    if let Err(resp) = authorize(&state, &req) {
+        return resp;
+    }
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut line_no = 0;
+    let mut parse_line = |line: &[u8], blocks: &mut Vec<Block>| -> Result<(), HttpResponse> {
+        line_no += 1;
+        if line.trim_ascii().is_empty() {
+            return Ok(());
+        }
+        let block = serde_json::from_slice(line).map_err(|e| {
+            HttpResponse::BadRequest().body(format!("line {line_no}: invalid block: {e}"))
+        })?;
+        blocks.push(block);
+        Ok(())
+    };
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => return HttpResponse::BadRequest().body(format!("reading body: {e}")),
+        };
+        pending.extend_from_slice(&chunk);
+        let mut start = 0;
+        while let Some(end) = pending[start..].iter().position(|&b| b == b'\n') {
+            if let Err(resp) = parse_line(&pending[start..start + end], &mut blocks) {
+                return resp;
+            }
+            start += end + 1;
+        }
+        pending.drain(..start);
+        if pending.len() > BLOCK_JSON_MAX_BYTES {
+            return HttpResponse::PayloadTooLarge().body(format!(
+                "line {}: block longer than {BLOCK_JSON_MAX_BYTES} bytes",
+                line_no + 1
+            ));
+        }
+    }
+    if let Err(resp) = parse_line(&pending, &mut blocks) {
+        return resp;
+    }
+
+    let received = blocks.len();
+    let adopting = state.clone();
+    let adopted = match web::block(move || adopt_chain(&adopting, blocks, "NDJSON import")).await {
+        Ok(Ok(adopted)) => adopted,
+        Ok(Err(bad)) => return HttpResponse::BadRequest().body(bad.to_string()),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let length = state.blockchain.lock().expect("mutex poisoned").len();
+    info!("admin: NDJSON import of {received} block(s) adopted {adopted}");
+    HttpResponse::Ok().json(ChainImportResponse {
+        received,
+        adopted,
+        length,
+    })
+}
+
+/// Max blocks returned by `/blocks/bytime/`.
+const BLOCKS_BYTIME_MAX: usize = 500;
+
+/// Blocks whose timestamp is in `[from_ts, to_ts]`, oldest first (capped).
+#[get("/blocks/bytime/")]
+pub async fn get_blocks_by_time(
+    state: web::Data<AppState>,
+    query: web::Query<BlocksByTimeQuery>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let from_ts = query.from_ts.unwrap_or(i64::MIN);
+    let to_ts = query.to_ts.unwrap_or(i64::MAX);
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    let indices = bc.block_indices_by_time(from_ts, to_ts);
+    let resp = BlocksByTimeResponse {
+        total: indices.len(),
+        truncated: indices.len() > BLOCKS_BYTIME_MAX,
+        blocks: indices
+            .into_iter()
+            .take(BLOCKS_BYTIME_MAX)
+            .map(|i| BlockView::from(&bc.chain[i]))
+            .collect(),
+    };
+    json_formatted(&resp, &fmt)
+}
+
+/// Max time a `/chain/tip/` long-poll is held open.
+const TIP_LONG_POLL_MAX_SECS: u64 = 30;
+
+/// Current chain tip. With `?known_hash=<hash>` equal to the current tip, the
+/// request is held until a new block arrives (or `timeout_secs`, max 30s).
+#[get("/chain/tip/")]
+pub async fn get_tip(state: web::Data<AppState>, query: web::Query<TipQuery>) -> impl Responder {
+    // Subscribe before reading the tip so a block mined in between isn't missed.
+    let mut rx = state.new_tip.subscribe();
+    let tip = current_tip(&state);
+
+    let waiting_on_tip = query.known_hash.as_deref() == Some(tip.hash.as_str());
+    if !waiting_on_tip {
+        return HttpResponse::Ok().json(tip);
+    }
+
+    let secs = query
+        .timeout_secs
+        .unwrap_or(TIP_LONG_POLL_MAX_SECS)
+        .min(TIP_LONG_POLL_MAX_SECS);
+    // Ok(_) = new block (Lagged counts too); Err(_) = timed out
+    let woke = tokio::time::timeout(Duration::from_secs(secs), rx.recv()).await;
+    debug!(
+        "TIP long-poll finished (woken by new block: {})",
+        woke.is_ok()
+    );
+
+    let mut tip = current_tip(&state);
+    tip.changed = query.known_hash.as_deref() != Some(tip.hash.as_str());
+    HttpResponse::Ok().json(tip)
+}
+
+fn current_tip(state: &AppState) -> TipResponse {
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    let last = bc.last_block();
+    TipResponse {
+        height: bc.len(),
+        index: last.index,
+        hash: last.hash.clone(),
+        changed: true,
+    }
+}
+
+/// The configured checkpoints (`CHECKPOINTS`) and whether the local chain
+/// agrees with each (`null` until it reaches that height).
+#[get("/checkpoints/")]
+pub async fn get_checkpoints(state: web::Data<AppState>) -> impl Responder {
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    let checkpoints = bc
+        .checkpoints
+        .iter()
+        .map(|(&height, hash)| CheckpointStatus {
+            height,
+            hash: hash.clone(),
+            matches: bc.chain.get(height as usize).map(|b| &b.hash == hash),
+        })
+        .collect();
+    HttpResponse::Ok().json(CheckpointsResponse { checkpoints })
 }
 
 /// Validate the whole chain.
@@ -35,97 +276,151 @@ pub async fn validate_chain(state: web::Data<AppState>) -> impl Responder {
     HttpResponse::Ok().json(resp)
 }
 
+/// Validate the whole chain by replaying it: signatures, UTXO existence and
+/// ownership, coinbase reward. Costs a signature check per input and a UTXO
+/// rebuild, so it is much heavier than `/validate/`; runs on a copy of the
+/// chain so mining isn't blocked meanwhile.
+#[get("/chain/validate/full/")]
+pub async fn validate_chain_full_replay(state: web::Data<AppState>) -> impl Responder {
+    let (blocks, pow_algo) = {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        (bc.chain.clone(), bc.pow_algo)
+    };
+    let faucet = state
+        .faucet_outputs
+        .lock()
+        .expect("mutex poisoned")
+        .snapshot();
+    let result = validate_chain_full(
+        &blocks,
+        pow_algo,
+        &state.config.chain_id,
+        &faucet,
+        state.config.coinbase_maturity,
+    );
+    if let Err(bad) = &result {
+        info!("full chain validation failed at {bad}");
+    }
+    let (first_invalid_block, reason) = match result {
+        Ok(()) => (None, None),
+        Err(bad) => (Some(bad.index), Some(bad.reason)),
+    };
+    HttpResponse::Ok().json(FullValidateResponse {
+        valid: first_invalid_block.is_none(),
+        length: blocks.len(),
+        first_invalid_block,
+        reason,
+    })
+}
+
 /// Mine a new block from the current mempool:
 /// - Select valid txs against current UTXO (prevent double spends inside block)
-/// - Create coinbase to `miner_address` with BASE_REWARD + total fees
+/// - Create coinbase to `miner_address` (or split across `payouts`) with BASE_REWARD + total fees
 /// - Mine PoW
 /// - Apply block to UTXO (spend inputs, add outputs)
 /// - Remove included txs from mempool
+/// Honors `Idempotency-Key`.
 #[post("/mine/")]
-pub async fn mine_block(state: web::Data<AppState>, req: web::Json<MineRequest>) -> impl Responder {
-    let miner_address = req.miner_address.trim().to_string();
-    if miner_address.is_empty() {
-        return HttpResponse::BadRequest().body("miner_address required");
+pub async fn mine_block(
+    state: web::Data<AppState>,
+    req: web::Json<MineRequest>,
+    http: HttpRequest,
+) -> impl Responder {
+    let handler = |req| run_mining(state.clone(), req);
+    state.idempotency.run("mine", &http, req, handler).await
+}
+
+async fn run_mining(state: web::Data<AppState>, req: web::Json<MineRequest>) -> HttpResponse {
+    let payees = match req.payees() {
+        Ok(payees) => payees,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    match mine_next_block(state, payees).await {
+        Ok(resp) => HttpResponse::Ok().json(resp),
+        Err(err) => err.into_response(),
+    }
+}
+
+/// Why `mine_next_block` produced no block.
+pub(super) enum MineError {
+    ShuttingDown,
+    TaskFailed,
+    /// the mined block landed on a `CHECKPOINTS` height with another hash
+    Checkpoint(String),
+}
+
+impl MineError {
+    pub(super) fn into_response(self) -> HttpResponse {
+        match self {
+            MineError::ShuttingDown => {
+                HttpResponse::ServiceUnavailable().body("node is shutting down")
+            }
+            MineError::TaskFailed => HttpResponse::InternalServerError().body("mining task failed"),
+            MineError::Checkpoint(e) => HttpResponse::Conflict().body(e),
+        }
     }
+}
 
-    // Snapshot mempool (clone) to decide what to include
-    let mempool_snapshot = {
-        let mempool = state.mempool.lock().expect("mutex poisoned");
-        mempool.clone()
+/// Mine one block paying `payees` and apply it (the body of `/mine/`,
+/// also driven by the auto-miner). Runs one at a time (`AppState::mining`):
+/// the txs are picked, sealed and applied under the chain lock, so two
+/// miners never include the same tx and each applies the block it mined.
+pub(super) async fn mine_next_block(
+    state: web::Data<AppState>,
+    payees: Vec<CoinbaseShare>,
+) -> Result<MineResponse, MineError> {
+    let miner_outputs = payees.len();
+    let Some(_mining) = state.begin_mining() else {
+        return Err(MineError::ShuttingDown);
     };
+    let _run = state.mining.lock().await;
 
-    // Lock UTXO to select txs + compute fees; release before PoW
-    let (mut selected, total_fees_u128) = {
-        let utxo = state.utxo_set.lock().expect("mutex poisoned");
-        let (txs, fees) = select_transactions(&mempool_snapshot, &utxo);
+    // PoW on the blocking pool: the HTTP worker stays free for other requests
+    let pow_state = state.clone();
+    let mined = web::block(move || {
+        let state = pow_state;
+        let mut bc = state.blockchain.lock().expect("mutex poisoned");
+
+        // pick txs against the UTXO set as of the tip this block extends
+        let mempool_snapshot = state.mempool_txs();
+        let (mut selected, total_fees) = {
+            let utxo = state.utxo_set.lock().expect("mutex poisoned");
+            let cache = state.fee_cache.lock().expect("mutex poisoned");
+            select_transactions_cached(&mempool_snapshot, &utxo, state.config.fee_rate_mode, &cache)
+        };
         debug!(
             "MINER - selected {} txs from mempool (fees={} sat)",
-            txs.len(),
-            fees
+            selected.len(),
+            total_fees
         );
-        (txs, fees)
-    };
-
-    // Build coinbase (first tx)
-    let total_fees_u64 = (total_fees_u128 as u128).min(u128::from(u64::MAX - BASE_REWARD)) as u64;
-    let coinbase_amount = BASE_REWARD + total_fees_u64;
-    let coinbase = Transaction::new(
-        vec![], // no inputs
-        vec![TxOutput {
-            address: miner_address.clone(),
-            amount: coinbase_amount,
-        }],
-    );
 
-    // Prepend coinbase to block transactions
-    let mut txs_for_block = Vec::with_capacity(1 + selected.len());
-    txs_for_block.push(coinbase.clone());
-    txs_for_block.append(&mut selected);
+        // coinbase (first tx, plus the queued faucet payouts it takes) for
+        // the height it is mined at
+        let coinbase = state.take_coinbase_tx(bc.len() as u64, &payees, total_fees);
+        let mut txs_for_block = Vec::with_capacity(1 + selected.len());
+        txs_for_block.push(coinbase.clone());
+        txs_for_block.append(&mut selected);
+        let block = match bc.mine_block(txs_for_block) {
+            Ok(block) => block.clone(),
+            Err(e) => {
+                // nothing was paid: the next block pays the faucet payouts
+                state.requeue_faucet_payouts(&coinbase, miner_outputs);
+                return Err(e);
+            }
+        };
 
-    // Mine PoW
-    let mined_block_hash;
-    let mined_block_index;
-    let mined_block_nonce;
-    {
-        let mut bc = state.blockchain.lock().expect("mutex poisoned");
-        let b = bc.mine_block(txs_for_block);
-        mined_block_hash = b.hash.clone();
-        mined_block_index = b.index;
-        mined_block_nonce = b.nonce;
-    } // release blockchain lock before heavy apply
-
-    // Apply block effects to UTXO and clean mempool
-    {
-        // Reconstruct the transactions we just mined to apply:
-        // We can fetch last block from chain (safe in single-proc).
-        let bc = state.blockchain.lock().expect("mutex poisoned");
-        let last_block = bc.last_block();
-        let included_txids: HashSet<String> = last_block
+        // apply the block just mined to the UTXO set and clean the mempool
+        let included_txids: HashSet<&str> = block
             .transactions
             .iter()
             .skip(1)
-            .map(|t| t.txid.clone())
+            .map(|t| t.txid.as_str())
             .collect();
-        let coinbase_tx = &last_block.transactions[0];
-
-        // Apply to UTXO
+        let coinbase_tx = &block.transactions[0];
         {
             let mut utxo = state.utxo_set.lock().expect("mutex poisoned");
-
-            // Spend inputs of normal txs
-            for tx in last_block.transactions.iter().skip(1) {
-                for input in &tx.inputs {
-                    utxo.spend(&input.outpoint);
-                }
-            }
-
-            // Add outputs of normal txs
-            for tx in last_block.transactions.iter().skip(1) {
-                utxo.add_tx_outputs(tx);
-            }
-
-            // Add coinbase output(s)
-            utxo.add_tx_outputs(coinbase_tx);
+            utxo.apply_block_txs(&block.transactions, block.index);
+            state.settle_faucet_payouts(coinbase_tx, miner_outputs, block.index, &mut utxo);
             debug!(
                 "UTXO applied: +coinbase {}, txs_included={}, utxo_size={}",
                 coinbase_tx.txid,
@@ -133,12 +428,11 @@ pub async fn mine_block(state: web::Data<AppState>, req: web::Json<MineRequest>)
                 utxo.len()
             );
         }
-
-        // Remove included txs from mempool
         {
             let mut mempool = state.mempool.lock().expect("mutex poisoned");
             let before = mempool.len();
-            mempool.retain(|t| !included_txids.contains(&t.txid));
+            mempool.retain(|e| !included_txids.contains(e.tx.txid.as_str()));
+            state.forget_spent_fees(&mempool, &block.transactions);
             let after = mempool.len();
             debug!(
                 "Mempool cleaned: {} -> {} (removed {})",
@@ -147,30 +441,233 @@ pub async fn mine_block(state: web::Data<AppState>, req: web::Json<MineRequest>)
                 before.saturating_sub(after)
             );
         }
-    }
+        Ok::<_, String>((block.hash.clone(), block.index, block.nonce))
+    })
+    .await;
+    let (mined_block_hash, mined_block_index, mined_block_nonce) = match mined {
+        Ok(Ok(mined)) => mined,
+        Ok(Err(e)) => {
+            warn!("MINER - {e}");
+            return Err(MineError::Checkpoint(e));
+        }
+        Err(_) => return Err(MineError::TaskFailed),
+    };
+
+    state.notify_new_tip(mined_block_index);
 
+    let difficulty = state
+        .blockchain
+        .lock()
+        .expect("mutex poisoned")
+        .difficulty();
     let resp = MineResponse {
         mined_index: mined_block_index,
         hash: mined_block_hash,
         nonce: mined_block_nonce,
-        difficulty: {
-            let bc = state.blockchain.lock().expect("mutex poisoned");
-            bc.difficulty()
-        },
+        difficulty,
+        bits: difficulty_bits(difficulty),
     };
     info!(
         "MINER - sealed block #{} (hash={}, nonce={})",
         resp.mined_index, resp.hash, resp.nonce
     );
-    HttpResponse::Ok().json(resp)
+    Ok(resp)
 }
 
-/// Get current PoW difficulty.
+/// Get current PoW difficulty, with the expected work per block
+/// (and the resulting block time at `?hashrate=`).
 #[get("/difficulty/")]
-pub async fn get_difficulty(state: web::Data<AppState>) -> impl Responder {
+pub async fn get_difficulty(
+    state: web::Data<AppState>,
+    query: web::Query<DifficultyQuery>,
+) -> impl Responder {
     let bc = state.blockchain.lock().expect("mutex poisoned");
-    HttpResponse::Ok().json(DifficultyResponse {
-        difficulty: bc.difficulty(),
+    HttpResponse::Ok().json(DifficultyResponse::new(bc.difficulty()).at_hashrate(query.hashrate))
+}
+
+/// DEV: the exact string `compute_hash` hashed for a block (by height or hash).
+#[get("/block/{id}/preimage/")]
+pub async fn get_block_preimage(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    if !state.config.dev_endpoints {
+        return HttpResponse::NotFound().finish();
+    }
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    match bc.find_block(&path) {
+        Some(block) if block.pruned => HttpResponse::Gone().body(PRUNED_BODY),
+        Some(block) => HttpResponse::Ok().json(PreimageResponse {
+            preimage: block.preimage(),
+            hash: Some(block.hash.clone()),
+        }),
+        None => HttpResponse::NotFound().body("unknown block"),
+    }
+}
+
+/// Merkle inclusion proof of `txid` in a block (by height or hash): the SPV
+/// client folds `proof` over the txid and compares with `merkle_root`.
+#[get("/block/{id}/proof/{txid}/")]
+pub async fn get_merkle_proof(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (id, txid) = path.into_inner();
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    let Some(block) = bc.find_block(&id) else {
+        return HttpResponse::NotFound().body("unknown block");
+    };
+    if block.pruned {
+        return HttpResponse::Gone().body(PRUNED_BODY);
+    }
+    if block.transactions.is_empty() {
+        // genesis: nothing to prove, the root is EMPTY_MERKLE_ROOT
+        return HttpResponse::NotFound().body("block has no transactions");
+    }
+    match block.merkle_proof(&txid) {
+        Some(proof) => HttpResponse::Ok().json(MerkleProofResponse {
+            block_index: block.index,
+            block_hash: block.hash.clone(),
+            merkle_root: block.merkle_root.clone(),
+            txid,
+            proof,
+        }),
+        None => HttpResponse::NotFound().body("transaction not in this block"),
+    }
+}
+
+/// The tip block in full, without knowing its index first.
+#[get("/block/latest/")]
+pub async fn get_latest_block(
+    state: web::Data<AppState>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    let tip = bc.last_block();
+    let resp = LatestBlockResponse {
+        block: BlockView::from(tip),
+        confirmations: 1,
+        cumulative_tx_count: bc.cumulative_tx_count(tip.index).unwrap_or_default(),
+    };
+    json_formatted(&resp, &fmt)
+}
+
+/// One block (by height or hash) as stored: what syncing peers download.
+/// A pruned block comes back as its header.
+#[get("/block/{id}/")]
+pub async fn get_block(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    match bc.find_block(&path) {
+        Some(block) => json_formatted(&BlockView::from(block), &fmt),
+        None => HttpResponse::NotFound().body("unknown block"),
+    }
+}
+
+/// Transactions of a block (by height or hash), each with the fee it paid.
+/// Spent inputs are gone from the UTXO set, so fees are replayed from the
+/// outputs of earlier blocks.
+#[get("/block/{id}/txs/")]
+pub async fn get_block_txs(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    let Some(block) = bc.find_block(&path) else {
+        return HttpResponse::NotFound().body("unknown block");
+    };
+    if block.pruned {
+        return HttpResponse::Gone().body(PRUNED_BODY);
+    }
+    let fees = bc.block_tx_fees(block.index as usize).unwrap_or_default();
+    let resp = BlockTxsResponse {
+        index: block.index,
+        hash: &block.hash,
+        coinbase_txid: block.coinbase().map(|tx| tx.txid.as_str()),
+        transactions: block
+            .transactions
+            .iter()
+            .zip(fees)
+            .map(|(tx, fee)| BlockTxWithFee {
+                tx: TxView::from(tx),
+                fee,
+            })
+            .collect(),
+    };
+    json_formatted(&resp, &fmt)
+}
+
+/// The outpoints a block (by height or hash) spent and the outputs it
+/// created, derived from its transactions alone: a read-only replay of its
+/// effect on the UTXO set, for chasing UTXO drift. An output spent later in
+/// the same block shows up in both lists.
+#[get("/block/{id}/effects/")]
+pub async fn get_block_effects(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    let Some(block) = bc.find_block(&path) else {
+        return HttpResponse::NotFound().body("unknown block");
+    };
+    if block.pruned {
+        return HttpResponse::Gone().body(PRUNED_BODY);
+    }
+    let spent = block
+        .transactions
+        .iter()
+        .flat_map(|tx| {
+            tx.inputs.iter().map(|input| SpentOutpoint {
+                txid: &input.outpoint.txid,
+                vout: input.outpoint.vout,
+                spent_by: &tx.txid,
+            })
+        })
+        .collect();
+    let created = block
+        .transactions
+        .iter()
+        .flat_map(|tx| {
+            tx.outputs.iter().enumerate().map(|(i, out)| CreatedOutput {
+                txid: &tx.txid,
+                vout: i as u32,
+                address: &out.address,
+                amount: out.amount,
+                coinbase: tx.inputs.is_empty(),
+            })
+        })
+        .collect();
+    let resp = BlockEffectsResponse {
+        index: block.index,
+        hash: &block.hash,
+        spent,
+        created,
+    };
+    json_formatted(&resp, &fmt)
+}
+
+/// DEV: hash an arbitrary block body the way the node does, so external
+/// miners can compare against their own implementation.
+/// `POST /block/hash/`, routed in `init_routes` with a block-sized body limit.
+pub async fn post_block_hash(state: web::Data<AppState>, body: web::Json<Block>) -> impl Responder {
+    if !state.config.dev_endpoints {
+        return HttpResponse::NotFound().finish();
+    }
+    let (difficulty, algo) = {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        (bc.difficulty(), bc.pow_algo)
+    };
+    let mut block = body.into_inner();
+    block.hash = block.compute_hash();
+    HttpResponse::Ok().json(BlockHashResponse {
+        valid_for_difficulty: block.is_valid_with(difficulty, algo),
+        hash: block.hash,
+        difficulty,
     })
 }
 
@@ -180,121 +677,883 @@ pub async fn set_difficulty(
     state: web::Data<AppState>,
     body: web::Json<SetDifficultyRequest>,
 ) -> impl Responder {
-    if body.difficulty > 6 {
-        return HttpResponse::BadRequest().body("difficulty too high for dev mode (max 6)");
-    }
     let mut bc = state.blockchain.lock().expect("mutex poisoned");
+    if !bc.bounds.contains(body.difficulty) {
+        return HttpResponse::BadRequest().body(format!(
+            "difficulty out of range ({}..={}, set with DIFF_MIN/DIFF_MAX; raise the cap with UNSAFE_MAX_DIFFICULTY)",
+            bc.bounds.min, bc.bounds.max
+        ));
+    }
     bc.set_difficulty(body.difficulty);
-    HttpResponse::Ok().json(DifficultyResponse {
-        difficulty: bc.difficulty(),
-    })
+    HttpResponse::Ok().json(DifficultyResponse::new(bc.difficulty()))
 }
 
-/* -------------------- Helpers -------------------- */
-
-/// Seleciona transações da mempool priorizando fee rate (sat/byte),
-/// respeitando limites de bytes e contagem, e evitando double-spend
-/// dentro do mesmo bloco. Retorna (txs_selecionadas, total_fees).
-fn select_transactions(mempool: &[Transaction], utxo: &UtxoSet) -> (Vec<Transaction>, u128) {
-    // 1) Pré-calcular fee e tamanho de cada tx; descartar inválidas de cara
-    #[derive(Clone)]
-    struct Cand {
-        idx: usize,
-        fee: u128,
-        size: usize,
-        fee_rate: f64,
-    }
-
-    let mut cands: Vec<Cand> = Vec::new();
-    for (idx, tx) in mempool.iter().enumerate() {
-        if tx.inputs.is_empty() {
-            // não aceitamos coinbase-like na mempool
-            continue;
+#[cfg(test)]
+mod tests {
+    use crate::api::AppState;
+    use crate::api::models::MempoolEntry;
+    use crate::api::testutil::{
+        TestWallet, app, faucet, get, get_json, mine, output, post, signed_tx, state, state_with,
+        submit_tx, temp_data_dir,
+    };
+    use crate::blockchain::{BASE_REWARD, Block, EMPTY_MERKLE_ROOT};
+    use crate::config::NodeConfig;
+    use crate::transaction::{OutPoint, Transaction};
+    use actix_web::test;
+    use serde_json::{Value, json};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn mining_splits_the_coinbase_by_weight() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 90)]); // fee 10
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+
+        let split = json!({ "payouts": [
+            { "address": "pool-a", "weight": 3 },
+            { "address": "pool-b", "weight": 1 },
+        ] });
+        let resp = post(&app, "/api/v1/mine/", split).await;
+        assert!(resp.status().is_success());
+
+        let coinbase = state.blockchain.lock().unwrap().last_block().transactions[0].clone();
+        let paid: Vec<(&str, u64)> = coinbase
+            .outputs
+            .iter()
+            .map(|o| (o.address.as_str(), o.amount))
+            .collect();
+        assert_eq!(paid, vec![("pool-a", 45), ("pool-b", 15)]);
+        // reward + fees, to the unit
+        assert_eq!(
+            coinbase.total_output_amount(),
+            crate::blockchain::BASE_REWARD as u128 + 10
+        );
+        let body = get_json(&app, "/api/v1/balance/pool-b/").await;
+        assert_eq!(body["balance"], 15);
+
+        let zero = json!({ "payouts": [{ "address": "pool-a", "weight": 0 }] });
+        assert_eq!(post(&app, "/api/v1/mine/", zero).await.status(), 400);
+        // one idle member is fine: it gets no output at all
+        let idle = json!({ "payouts": [
+            { "address": "pool-a", "weight": 0 },
+            { "address": "pool-b", "weight": 1 },
+        ] });
+        assert!(
+            post(&app, "/api/v1/mine/", idle)
+                .await
+                .status()
+                .is_success()
+        );
+        let coinbase = state.blockchain.lock().unwrap().last_block().transactions[0].clone();
+        assert!(coinbase.outputs.iter().all(|o| o.address != "pool-a"));
+        assert_eq!(
+            coinbase.outputs,
+            vec![output("pool-b", crate::blockchain::BASE_REWARD)]
+        );
+        let both = json!({ "miner_address": "m", "payouts": [{ "address": "a", "weight": 1 }] });
+        assert_eq!(post(&app, "/api/v1/mine/", both).await.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn concurrent_mining_runs_build_a_valid_chain() {
+        use super::mine_next_block;
+        use crate::blockchain::{CoinbaseShare, validate_chain_full};
+
+        let state = state();
+        state.blockchain.lock().unwrap().set_difficulty(1);
+        let node = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&node, &alice.address, 100).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&node, &tx).await.status().is_success());
+
+        let (a, b) = tokio::join!(
+            mine_next_block(state.clone(), vec![CoinbaseShare::solo("miner-a")]),
+            mine_next_block(state.clone(), vec![CoinbaseShare::solo("miner-b")]),
+        );
+        assert!(a.is_ok() && b.is_ok());
+
+        let blocks = state.blockchain.lock().unwrap().chain.clone();
+        assert_eq!(blocks.len(), 3);
+        // the tx went into exactly one of the two blocks
+        let including = blocks
+            .iter()
+            .filter(|b| b.transactions.iter().any(|t| t.txid == tx.txid))
+            .count();
+        assert_eq!(including, 1);
+        let faucet_ledger = state.faucet_outputs.lock().unwrap().snapshot();
+        let pow_algo = state.blockchain.lock().unwrap().pow_algo;
+        assert_eq!(
+            validate_chain_full(&blocks, pow_algo, &state.config.chain_id, &faucet_ledger, 0),
+            Ok(())
+        );
+        // bob's output plus both coinbases; the faucet output is spent
+        assert_eq!(state.utxo_set.lock().unwrap().len(), 3);
+        assert!(state.mempool.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn a_queued_faucet_payout_is_paid_by_one_block_only() {
+        use super::mine_next_block;
+        use crate::blockchain::CoinbaseShare;
+
+        let config = NodeConfig::from_lookup(|k| (k == "FAUCET_MODE").then(|| "coinbase".into()));
+        let state = state_with(config);
+        state.blockchain.lock().unwrap().set_difficulty(1);
+        let node = app(&state).await;
+        let resp = post(
+            &node,
+            "/api/v1/faucet/",
+            json!({ "address": "alice", "amount": 75 }),
+        )
+        .await;
+        assert_eq!(resp.status(), 202);
+
+        let (a, b) = tokio::join!(
+            mine_next_block(state.clone(), vec![CoinbaseShare::solo("miner-a")]),
+            mine_next_block(state.clone(), vec![CoinbaseShare::solo("miner-b")]),
+        );
+        assert!(a.is_ok() && b.is_ok());
+
+        let blocks = state.blockchain.lock().unwrap().chain.clone();
+        let paying = blocks
+            .iter()
+            .skip(1)
+            .filter(|b| b.transactions[0].outputs.contains(&output("alice", 75)))
+            .count();
+        assert_eq!(paying, 1);
+        assert_eq!(state.faucet_outputs.lock().unwrap().len(), 1);
+        let balance = get_json(&node, "/api/v1/balance/alice/").await;
+        assert_eq!(balance["balance"], 75);
+        assert!(state.faucet_queue.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn pretty_query_indents_json() {
+        let state = state();
+        let app = app(&state).await;
+        for uri in ["/api/v1/chain/", "/api/v1/block/0/txs/", "/api/v1/stats/"] {
+            let compact = test::read_body(get(&app, uri).await).await;
+            assert!(!compact.contains(&b'\n'), "{uri} should be compact");
+
+            let resp = get(&app, &format!("{uri}?pretty=true")).await;
+            assert_eq!(
+                resp.headers().get("content-type").unwrap(),
+                "application/json"
+            );
+            let pretty = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+            assert!(pretty.contains("{\n  \""), "{uri}: {pretty}");
+            assert_eq!(
+                serde_json::from_str::<Value>(&pretty).unwrap(),
+                serde_json::from_slice::<Value>(&compact).unwrap()
+            );
         }
+    }
 
-        // soma de inputs a partir do UTXO; se algum não existir, descarta
-        let mut input_sum: u128 = 0;
-        let mut ok = true;
-        for input in &tx.inputs {
-            match utxo.get(&input.outpoint) {
-                Some(prev) => input_sum += prev.amount as u128,
-                None => {
-                    ok = false;
-                    break;
-                }
-            }
+    #[actix_web::test]
+    async fn string_amounts_reach_nested_outputs_only() {
+        let state = state();
+        let app = app(&state).await;
+        mine(&app, "miner").await;
+
+        let body = get_json(&app, "/api/v1/chain/?amounts=string").await;
+        let block = &body["chain"][1];
+        assert_eq!(block["index"], 1); // not an amount: still a number
+        let paid = &block["transactions"][0]["outputs"][0]["amount"];
+        assert_eq!(*paid, crate::blockchain::BASE_REWARD.to_string());
+        // the default rendering is untouched
+        let body = get_json(&app, "/api/v1/chain/").await;
+        assert_eq!(
+            body["chain"][1]["transactions"][0]["outputs"][0]["amount"],
+            crate::blockchain::BASE_REWARD
+        );
+    }
+
+    #[actix_web::test]
+    async fn block_views_render_like_the_stored_blocks() {
+        use crate::api::models::BlockView;
+        use crate::api::testutil::signed_replaceable_tx;
+        use crate::serde_util::with_amounts_as_strings;
+
+        let state = state();
+        let node = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&node, &alice.address, 100).await;
+        let tx = signed_replaceable_tx(&[(op, &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&node, &tx).await.status().is_success());
+        mine(&node, "miner").await;
+
+        let block = state.blockchain.lock().unwrap().last_block().clone();
+        assert_eq!(
+            serde_json::to_value(BlockView::from(&block)).unwrap(),
+            serde_json::to_value(&block).unwrap()
+        );
+        // string amounts never reach the consensus types themselves
+        let stored = with_amounts_as_strings(|| serde_json::to_value(&block).unwrap());
+        assert_eq!(stored, serde_json::to_value(&block).unwrap());
+    }
+
+    #[actix_web::test]
+    async fn pruned_blocks_answer_gone_but_keep_their_headers() {
+        let state = state_with(NodeConfig {
+            prune_depth: Some(2),
+            ..NodeConfig::default()
+        });
+        let app = app(&state).await;
+        for _ in 0..4 {
+            mine(&app, "miner").await;
         }
-        if !ok {
-            continue;
+
+        let chain = get_json(&app, "/api/v1/chain/").await;
+        assert_eq!(chain["chain"][1]["pruned"], true);
+        assert_eq!(chain["chain"][1]["tx_count"], 1);
+        assert!(chain["chain"][4].get("pruned").is_none());
+        assert_eq!(get_json(&app, "/api/v1/validate/").await["valid"], true);
+
+        for uri in [
+            "/api/v1/block/1/txs/",
+            "/api/v1/block/1/preimage/",
+            "/api/v1/block/1/proof/whatever/",
+        ] {
+            assert_eq!(get(&app, uri).await.status(), 410, "{uri}");
         }
+        assert!(
+            get(&app, "/api/v1/block/4/txs/")
+                .await
+                .status()
+                .is_success()
+        );
+    }
 
-        let output_sum = tx.total_output_amount();
-        if input_sum < output_sum {
-            continue; // economics inválida
+    #[actix_web::test]
+    async fn checkpoints_report_agreement_with_the_local_chain() {
+        let state = state();
+        let app = app(&state).await;
+        mine(&app, "miner").await;
+        {
+            let mut bc = state.blockchain.lock().unwrap();
+            let genesis = bc.chain[0].hash.clone();
+            bc.checkpoints.insert(0, genesis);
+            bc.checkpoints.insert(1, "0".repeat(64));
+            bc.checkpoints.insert(5, "f".repeat(64));
         }
-        let fee = input_sum - output_sum;
-        let size = tx.vsize_bytes();
-        let fee_rate = if size > 0 {
-            fee as f64 / size as f64
-        } else {
-            0.0
+        let body = get_json(&app, "/api/v1/checkpoints/").await;
+        let matches: Vec<&Value> = body["checkpoints"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| &c["matches"])
+            .collect();
+        assert_eq!(matches, [&json!(true), &json!(false), &Value::Null]);
+        assert_eq!(get_json(&app, "/api/v1/validate/").await["valid"], false);
+    }
+
+    #[actix_web::test]
+    async fn block_by_height_or_hash() {
+        let state = state();
+        let app = app(&state).await;
+        mine(&app, "miner").await;
+        let block = state.blockchain.lock().unwrap().chain[1].clone();
+
+        let by_height = get_json(&app, "/api/v1/block/1/").await;
+        assert_eq!(by_height, json!(block));
+        let by_hash = get_json(&app, &format!("/api/v1/block/{}/", block.hash)).await;
+        assert_eq!(by_hash, by_height);
+        assert_eq!(get(&app, "/api/v1/block/7/").await.status(), 404);
+        // the fixed routes still win over `{id}`
+        let latest = get_json(&app, "/api/v1/block/latest/").await;
+        assert_eq!(latest["confirmations"], 1);
+    }
+
+    #[actix_web::test]
+    async fn mining_refuses_a_block_that_misses_a_checkpoint() {
+        let state = state();
+        let app = app(&state).await;
+        state
+            .blockchain
+            .lock()
+            .unwrap()
+            .checkpoints
+            .insert(1, "0".repeat(64));
+
+        let resp = post(&app, "/api/v1/mine/", json!({ "miner_address": "miner" })).await;
+        assert_eq!(resp.status(), 409);
+        let body = test::read_body(resp).await;
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("conflicts with checkpoint"), "{body}");
+        assert_eq!(state.blockchain.lock().unwrap().len(), 1);
+        assert!(state.utxo_set.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn block_txs_list_replayed_fees() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        mine(&app, &alice.address).await;
+        let coinbase = state.blockchain.lock().unwrap().last_block().transactions[0].clone();
+        let reward = coinbase.outputs[0].amount;
+
+        // spends a mined output (fee 7) and a direct faucet UTXO (off-chain)
+        let op = OutPoint {
+            txid: coinbase.txid,
+            vout: 0,
         };
+        let mined_spend = signed_tx(&[(op, &alice)], vec![output("bob", reward - 7)]);
+        assert!(submit_tx(&app, &mined_spend).await.status().is_success());
+        let op = faucet(&app, &alice.address, 50).await;
+        let faucet_spend = signed_tx(&[(op, &alice)], vec![output("bob", 45)]);
+        assert!(submit_tx(&app, &faucet_spend).await.status().is_success());
+        mine(&app, "miner").await;
 
-        cands.push(Cand {
-            idx,
-            fee,
-            size,
-            fee_rate,
-        });
+        let body = get_json(&app, "/api/v1/block/2/txs/").await;
+        assert_eq!(body["index"], 2);
+        let txs = body["transactions"].as_array().unwrap();
+        assert_eq!(txs.len(), 3);
+        assert!(txs[0]["fee"].is_null(), "coinbase has no fee");
+        let fee_of = |txid: &str| txs.iter().find(|t| t["txid"] == txid).unwrap()["fee"].clone();
+        assert_eq!(fee_of(&mined_spend.txid), 7);
+        assert!(fee_of(&faucet_spend.txid).is_null());
+
+        let resp = get(&app, "/api/v1/block/99/txs/").await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn genesis_answers_every_block_endpoint_with_the_empty_state() {
+        let state = state();
+        let app = app(&state).await;
+        mine(&app, "miner").await;
+        let genesis = state.blockchain.lock().unwrap().chain[0].clone();
+        assert!(genesis.coinbase().is_none());
+
+        for id in ["0", genesis.hash.as_str()] {
+            let body = get_json(&app, &format!("/api/v1/block/{id}/txs/")).await;
+            assert_eq!(body["index"], 0);
+            assert!(body["coinbase_txid"].is_null());
+            assert_eq!(body["transactions"], json!([]));
+        }
+
+        let resp = get(&app, &format!("/api/v1/block/0/proof/{}/", "ab".repeat(32))).await;
+        assert_eq!(resp.status(), 404);
+        let body = get_json(&app, "/api/v1/block/0/preimage/").await;
+        assert_eq!(body["hash"], genesis.hash.as_str());
+        assert_eq!(genesis.merkle_root, EMPTY_MERKLE_ROOT);
+
+        // the next block does have one
+        let body = get_json(&app, "/api/v1/block/1/txs/").await;
+        assert_eq!(body["coinbase_txid"], body["transactions"][0]["txid"]);
+    }
+
+    #[actix_web::test]
+    async fn latest_block_is_the_tip() {
+        let state = state();
+        let app = app(&state).await;
+        let body = get_json(&app, "/api/v1/block/latest/").await;
+        assert_eq!(body["index"], 0);
+
+        mine(&app, "miner").await;
+        let tip = state.blockchain.lock().unwrap().last_block().clone();
+        let body = get_json(&app, "/api/v1/block/latest/").await;
+        assert_eq!(body["index"], 1);
+        assert_eq!(body["hash"], tip.hash.as_str());
+        assert_eq!(
+            body["transactions"][0]["txid"],
+            tip.transactions[0].txid.as_str()
+        );
+        assert_eq!(body["confirmations"], 1);
+    }
+
+    #[actix_web::test]
+    async fn block_effects_list_spent_and_created_outpoints() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        let tx = signed_tx(
+            &[(op.clone(), &alice)],
+            vec![output("bob", 60), output(&alice.address, 35)],
+        );
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        let mined = mine(&app, "miner").await;
+
+        let body = get_json(&app, "/api/v1/block/1/effects/").await;
+        assert_eq!(body["hash"], mined["hash"]);
+        assert_eq!(
+            body["spent"],
+            json!([{ "txid": op.txid, "vout": op.vout, "spent_by": tx.txid }])
+        );
+        let coinbase_txid = state.blockchain.lock().unwrap().chain[1].transactions[0]
+            .txid
+            .clone();
+        assert_eq!(
+            body["created"],
+            json!([
+                { "txid": coinbase_txid, "vout": 0, "address": "miner", "amount": 55, "coinbase": true },
+                { "txid": tx.txid, "vout": 0, "address": "bob", "amount": 60, "coinbase": false },
+                { "txid": tx.txid, "vout": 1, "address": alice.address, "amount": 35, "coinbase": false },
+            ])
+        );
+
+        let resp = get(&app, "/api/v1/block/9/effects/").await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn chain_exports_as_ndjson_one_block_per_line() {
+        use crate::storage::read_ndjson_blocks;
+
+        let state = state();
+        state.blockchain.lock().unwrap().set_difficulty(1);
+        let app = app(&state).await;
+        for _ in 0..3 {
+            mine(&app, "miner").await;
+        }
+
+        let resp = get(&app, "/api/v1/chain/export/ndjson/").await;
+        assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/x-ndjson"
+        );
+        let body = test::read_body(resp).await;
+        assert_eq!(body.iter().filter(|&&b| b == b'\n').count(), 4);
+        let blocks: Vec<Block> = read_ndjson_blocks(&body[..])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let indices: Vec<u64> = blocks.iter().map(|b| b.index).collect();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+        assert_eq!(
+            blocks[3].hash,
+            state.blockchain.lock().unwrap().last_block().hash
+        );
     }
 
-    // 2) Ordenar por fee_rate desc; tie-break por fee desc, depois txid asc
-    cands.sort_by(|a, b| {
-        b.fee_rate
-            .partial_cmp(&a.fee_rate)
-            .unwrap_or(std::cmp::Ordering::Equal)
-            .then_with(|| b.fee.cmp(&a.fee))
-            .then_with(|| mempool[a.idx].txid.cmp(&mempool[b.idx].txid))
-    });
-
-    // 3) Greedy packing respeitando limites + prevenindo double-spend
-    let mut total_fees: u128 = 0;
-    let mut total_bytes: usize = 0;
-    let mut picked: Vec<Transaction> = Vec::new();
-    let mut consumed = std::collections::HashSet::<(String, u32)>::new();
-
-    for c in cands {
-        if picked.len() >= MAX_TXS_PER_BLOCK {
-            break;
+    #[actix_web::test]
+    async fn chain_imports_a_heavier_ndjson_export() {
+        let config = || NodeConfig {
+            initial_difficulty: 1,
+            admin_token: Some("s3cret".into()),
+            ..NodeConfig::default()
+        };
+        let local = state_with(config());
+        let node = app(&local).await;
+        mine(&node, "local-miner").await;
+        let source = state_with(config());
+        source.blockchain.lock().unwrap().chain =
+            local.blockchain.lock().unwrap().chain[..1].to_vec();
+        let source_app = app(&source).await;
+        for _ in 0..3 {
+            mine(&source_app, "source-miner").await;
         }
-        if total_bytes + c.size > MAX_BLOCK_BYTES {
-            continue;
+        let export = test::read_body(get(&source_app, "/api/v1/chain/export/ndjson/").await).await;
+
+        let import = |token: &str, body: Vec<u8>| {
+            test::TestRequest::post()
+                .uri("/api/v1/chain/import/ndjson/")
+                .insert_header(("Authorization", format!("Bearer {token}")))
+                .set_payload(body)
+                .to_request()
+        };
+        let resp = test::call_service(&node, import("wrong", export.to_vec())).await;
+        assert_eq!(resp.status(), 401);
+        let mut garbage = export.to_vec();
+        garbage.extend_from_slice(b"not a block\n");
+        let resp = test::call_service(&node, import("s3cret", garbage)).await;
+        assert_eq!(resp.status(), 400);
+        assert_eq!(local.blockchain.lock().unwrap().len(), 2);
+
+        let resp = test::call_service(&node, import("s3cret", export.to_vec())).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["received"], 4);
+        assert_eq!(body["adopted"], 3);
+        assert_eq!(body["length"], 4);
+        assert_eq!(
+            local.blockchain.lock().unwrap().last_block().hash,
+            source.blockchain.lock().unwrap().last_block().hash
+        );
+        let balance = get_json(&node, "/api/v1/balance/local-miner/").await;
+        assert_eq!(balance["balance"], 0);
+
+        // the same chain again adds nothing
+        let resp = test::call_service(&node, import("s3cret", export.to_vec())).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["adopted"], 0);
+    }
+
+    #[actix_web::test]
+    async fn ndjson_export_stops_at_a_reorg() {
+        use actix_web::body::MessageBody;
+
+        async fn next_line<B: MessageBody + Unpin>(
+            body: &mut B,
+        ) -> Option<Result<actix_web::web::Bytes, B::Error>> {
+            std::future::poll_fn(|cx| std::pin::Pin::new(&mut *body).poll_next(cx)).await
         }
 
-        let tx = &mempool[c.idx];
+        let state = state();
+        state.blockchain.lock().unwrap().set_difficulty(1);
+        let node = app(&state).await;
+        for _ in 0..3 {
+            mine(&node, "miner").await;
+        }
+        let mut body = get(&node, "/api/v1/chain/export/ndjson/").await.into_body();
+        // genesis and block #1 go out
+        assert!(next_line(&mut body).await.unwrap().is_ok());
+        assert!(next_line(&mut body).await.unwrap().is_ok());
 
-        // checar double-spend contra `consumed`
-        let mut ok = true;
-        for input in &tx.inputs {
-            let key = (input.outpoint.txid.clone(), input.outpoint.vout);
-            if consumed.contains(&key) {
-                ok = false;
-                break;
+        // then another fork replaces everything past genesis
+        {
+            let mut bc = state.blockchain.lock().unwrap();
+            bc.chain.truncate(1);
+            for _ in 0..3 {
+                bc.mine_block(vec![]).unwrap();
             }
         }
-        if !ok {
-            continue;
+        assert!(next_line(&mut body).await.unwrap().is_err());
+        assert!(next_line(&mut body).await.is_none());
+    }
+
+    #[actix_web::test]
+    async fn tip_long_poll_wakes_on_new_block() {
+        let state = state();
+        let app = app(&state).await;
+
+        let genesis = get_json(&app, "/api/v1/chain/tip/").await;
+        assert_eq!(genesis["index"], 0);
+        let uri = format!(
+            "/api/v1/chain/tip/?known_hash={}",
+            genesis["hash"].as_str().unwrap()
+        );
+
+        let poll = async {
+            let resp = get(&app, &uri).await;
+            let body: Value = test::read_body_json(resp).await;
+            body
+        };
+        let miner = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            mine(&app, "miner").await
+        };
+        let (tip, mined) = tokio::join!(poll, miner);
+
+        assert_eq!(tip["changed"], true);
+        assert_eq!(tip["index"], 1);
+        assert_eq!(tip["hash"], mined["hash"]);
+    }
+
+    #[actix_web::test]
+    async fn tip_returns_immediately_when_hash_differs() {
+        let state = state();
+        let app = app(&state).await;
+        let tip = get_json(&app, "/api/v1/chain/tip/?known_hash=stale").await;
+        assert_eq!(tip["index"], 0);
+        assert_eq!(tip["changed"], true);
+    }
+
+    #[actix_web::test]
+    async fn tip_long_poll_times_out_unchanged() {
+        let state = state();
+        let app = app(&state).await;
+        let genesis = get_json(&app, "/api/v1/chain/tip/").await;
+        let uri = format!(
+            "/api/v1/chain/tip/?known_hash={}&timeout_secs=0",
+            genesis["hash"].as_str().unwrap()
+        );
+        let tip = get_json(&app, &uri).await;
+        assert_eq!(tip["changed"], false);
+        assert_eq!(tip["hash"], genesis["hash"]);
+    }
+
+    #[actix_web::test]
+    async fn full_validation_accepts_a_healthy_chain_after_restart() {
+        let dir = temp_data_dir();
+        let config = NodeConfig {
+            data_dir: Some(dir.clone()),
+            ..NodeConfig::default()
+        };
+        let state = state_with(config.clone());
+        let node = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&node, &alice.address, 100).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 95)]);
+        assert!(submit_tx(&node, &tx).await.status().is_success());
+        mine(&node, "miner").await;
+        mine(&node, "miner2").await;
+
+        let body = get_json(&node, "/api/v1/chain/validate/full/").await;
+        assert_eq!(body["valid"], true, "{body}");
+        assert_eq!(body["length"], 3);
+        assert!(body["first_invalid_block"].is_null());
+
+        // the spent faucet output is gone from the UTXO set but not from the replay
+        state.flush().unwrap();
+        let restarted = state_with(config);
+        let node = app(&restarted).await;
+        let body = get_json(&node, "/api/v1/chain/validate/full/").await;
+        assert_eq!(body["valid"], true, "{body}");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn immature_coinbase_spends_fail_block_and_mempool_checks() {
+        let state = state_with(NodeConfig {
+            initial_difficulty: 1,
+            coinbase_maturity: 3,
+            ..NodeConfig::default()
+        });
+        let node = app(&state).await;
+        let miner = TestWallet::new();
+        mine(&node, &miner.address).await;
+        let reward = OutPoint {
+            txid: state.blockchain.lock().unwrap().last_block().transactions[0]
+                .txid
+                .clone(),
+            vout: 0,
+        };
+        let spend = signed_tx(&[(reward, &miner)], vec![output("bob", BASE_REWARD - 5)]);
+
+        // a reorg leaves it in the mempool one block too early
+        state
+            .mempool
+            .lock()
+            .unwrap()
+            .push(MempoolEntry::new(spend.clone(), 0));
+        let utxo = state.utxo_set.lock().unwrap().clone();
+        state.revalidate_mempool(&utxo, 2);
+        assert!(state.mempool.lock().unwrap().is_empty());
+
+        // and a block that includes it anyway fails the full replay
+        state
+            .blockchain
+            .lock()
+            .unwrap()
+            .mine_block(vec![
+                Transaction::coinbase(2, vec![output("miner2", BASE_REWARD)]),
+                spend,
+            ])
+            .unwrap();
+        let body = get_json(&node, "/api/v1/chain/validate/full/").await;
+        assert_eq!(body["valid"], false);
+        assert_eq!(body["first_invalid_block"], 2);
+        assert!(
+            body["reason"]
+                .as_str()
+                .unwrap()
+                .contains("immature coinbase"),
+            "{body}"
+        );
+    }
+
+    #[actix_web::test]
+    async fn shutdown_flushes_last_mined_block() {
+        let dir = temp_data_dir();
+        let config = NodeConfig {
+            data_dir: Some(dir.clone()),
+            ..NodeConfig::default()
+        };
+        let state = state_with(config.clone());
+        let app = app(&state).await;
+        faucet(&app, "alice", 10).await;
+        let mined = mine(&app, "miner").await;
+
+        state.shutdown(Duration::from_secs(1)).await.unwrap();
+
+        // no new mining once shutdown started
+        let resp = post(&app, "/api/v1/mine/", json!({ "miner_address": "m" })).await;
+        assert_eq!(resp.status(), 503);
+
+        // "restart" from the same data dir
+        let restarted = AppState::with_config(config);
+        let bc = restarted.blockchain.lock().unwrap();
+        assert_eq!(bc.len(), 2);
+        assert_eq!(bc.last_block().hash, mined["hash"]);
+        assert_eq!(restarted.utxo_set.lock().unwrap().len(), 2); // faucet + coinbase
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn initial_difficulty_comes_from_config() {
+        let config = NodeConfig::from_lookup(|k| (k == "INITIAL_DIFFICULTY").then(|| "1".into()));
+        let state = state_with(config);
+        let app = app(&state).await;
+
+        let body = get_json(&app, "/api/v1/difficulty/").await;
+        assert_eq!(body["difficulty"], 1);
+        assert_eq!(body["bits"], "200fffff");
+    }
+
+    #[actix_web::test]
+    async fn difficulty_reports_expected_hashes() {
+        let config = NodeConfig::from_lookup(|k| (k == "INITIAL_DIFFICULTY").then(|| "3".into()));
+        let state = state_with(config);
+        let app = app(&state).await;
+
+        let body = get_json(&app, "/api/v1/difficulty/").await;
+        assert_eq!(body["expected_hashes"], 4096);
+        assert!(body.get("seconds_per_block").is_none());
+
+        let body = get_json(&app, "/api/v1/difficulty/?hashrate=1024").await;
+        assert_eq!(body["seconds_per_block"], 4.0);
+    }
+
+    #[actix_web::test]
+    async fn unsafe_max_difficulty_lifts_the_cap() {
+        let capped = state();
+        let node = app(&capped).await;
+        let resp = post(&node, "/api/v1/difficulty/", json!({ "difficulty": 8 })).await;
+        assert_eq!(resp.status(), 400);
+
+        let config =
+            NodeConfig::from_lookup(|k| (k == "UNSAFE_MAX_DIFFICULTY").then(|| "8".into()));
+        let state = state_with(config);
+        let app = app(&state).await;
+        let resp = post(&app, "/api/v1/difficulty/", json!({ "difficulty": 8 })).await;
+        assert!(resp.status().is_success());
+        assert_eq!(state.blockchain.lock().unwrap().difficulty(), 8);
+        let resp = post(&app, "/api/v1/difficulty/", json!({ "difficulty": 9 })).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn merkle_proofs_verify_against_the_block_root() {
+        use crate::blockchain::{MerkleStep, verify_merkle_proof};
+
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        for amount in [100, 101, 102] {
+            let op = faucet(&app, &alice.address, amount).await;
+            let tx = signed_tx(&[(op, &alice)], vec![output("bob", amount - 5)]);
+            assert!(submit_tx(&app, &tx).await.status().is_success());
+        }
+        mine(&app, "miner").await;
+
+        let block = state.blockchain.lock().unwrap().last_block().clone();
+        assert_eq!(block.transactions.len(), 4);
+        for tx in &block.transactions {
+            let body = get_json(&app, &format!("/api/v1/block/1/proof/{}/", tx.txid)).await;
+            assert_eq!(body["merkle_root"], block.merkle_root);
+            let proof: Vec<MerkleStep> = serde_json::from_value(body["proof"].clone()).unwrap();
+            assert!(verify_merkle_proof(&tx.txid, &proof, &block.merkle_root));
+        }
+        let coinbase = &block.transactions[0].txid;
+        let uri = format!("/api/v1/block/0/proof/{coinbase}/");
+        assert_eq!(get(&app, &uri).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn empty_blocks_to_one_miner_get_distinct_coinbases() {
+        let state = state();
+        let app = app(&state).await;
+        mine(&app, "miner").await;
+        mine(&app, "miner").await;
+
+        let (first, second) = {
+            let bc = state.blockchain.lock().unwrap();
+            (
+                bc.chain[1].transactions[0].clone(),
+                bc.chain[2].transactions[0].clone(),
+            )
+        };
+        assert_eq!(first.outputs, second.outputs);
+        assert_eq!(
+            (first.coinbase_height, second.coinbase_height),
+            (Some(1), Some(2))
+        );
+        assert_ne!(first.txid, second.txid);
+        // both rewards are spendable: neither outpoint overwrote the other
+        assert_eq!(state.utxo_set.lock().unwrap().len(), 2);
+        let balance = get_json(&app, "/api/v1/balance/miner/").await;
+        assert_eq!(balance["balance"], 100);
+        assert!(state.blockchain.lock().unwrap().is_valid_chain());
+    }
+
+    #[actix_web::test]
+    async fn block_preimage_hashes_to_stored_hash() {
+        use sha2::{Digest, Sha256};
+
+        let state = state();
+        let app = app(&state).await;
+        let mined = mine(&app, "miner").await;
+
+        for id in ["1".to_string(), mined["hash"].as_str().unwrap().to_string()] {
+            let body = get_json(&app, &format!("/api/v1/block/{id}/preimage/")).await;
+            let preimage = body["preimage"].as_str().unwrap();
+            assert_eq!(hex::encode(Sha256::digest(preimage)), mined["hash"]);
+            assert_eq!(body["hash"], mined["hash"]);
         }
+        assert_eq!(get(&app, "/api/v1/block/99/preimage/").await.status(), 404);
+    }
 
-        // passa: adiciona, marca inputs como consumidos
-        for input in &tx.inputs {
-            consumed.insert((input.outpoint.txid.clone(), input.outpoint.vout));
+    #[actix_web::test]
+    async fn preimage_endpoints_are_dev_gated() {
+        let config = NodeConfig::from_lookup(|k| (k == "DEV_ENDPOINTS").then(|| "false".into()));
+        let state = state_with(config);
+        let app = app(&state).await;
+        assert_eq!(get(&app, "/api/v1/block/0/preimage/").await.status(), 404);
+        let genesis = state.blockchain.lock().unwrap().chain[0].clone();
+        let resp = post(&app, "/api/v1/block/hash/", json!(genesis)).await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn blocks_by_time_returns_window() {
+        let state = state();
+        let app = app(&state).await;
+        {
+            let mut bc = state.blockchain.lock().unwrap();
+            bc.chain[0].timestamp = 1_000;
+            for t in [1_060, 1_120, 1_180, 1_240] {
+                let prev = bc.last_block().hash.clone();
+                let index = bc.len() as u64;
+                let mut b = Block::new_with_timestamp(index, prev, vec![], t);
+                b.mine(1);
+                bc.chain.push(b);
+            }
         }
 
-        total_fees += c.fee;
-        total_bytes += c.size;
-        picked.push(tx.clone());
+        let body = get_json(&app, "/api/v1/blocks/bytime/?from_ts=1060&to_ts=1180").await;
+        assert_eq!(body["total"], 3);
+        assert_eq!(body["truncated"], false);
+        let idx: Vec<u64> = body["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["index"].as_u64().unwrap())
+            .collect();
+        assert_eq!(idx, vec![1, 2, 3]);
+
+        let body = get_json(&app, "/api/v1/blocks/bytime/?from_ts=1200").await;
+        assert_eq!(body["blocks"][0]["timestamp"], 1_240);
+        let body = get_json(&app, "/api/v1/blocks/bytime/?to_ts=999").await;
+        assert_eq!(body["total"], 0);
     }
 
-    (picked, total_fees)
+    #[actix_web::test]
+    async fn block_hash_matches_compute_hash() {
+        let state = state();
+        let app = app(&state).await;
+        mine(&app, "miner").await;
+        let mut block = state.blockchain.lock().unwrap().last_block().clone();
+        let expected = block.compute_hash();
+
+        let resp = post(&app, "/api/v1/block/hash/", json!(block)).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["hash"], expected);
+        assert_eq!(body["valid_for_difficulty"], true);
+
+        // the posted `hash` is ignored; a different nonce gives a different hash
+        block.nonce += 1;
+        let resp = post(&app, "/api/v1/block/hash/", json!(block)).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["hash"], block.compute_hash());
+        assert_ne!(body["hash"], expected);
+    }
 }
@@ -0,0 +1,244 @@
+use actix_web::{HttpResponse, Responder, get, web};
+
+use super::format::{FormatQuery, json_formatted};
+use super::models::{
+    AppState, ExplorerBlock, ExplorerQuery, ExplorerSummaryResponse, MinedBlock, MinerBlocksQuery,
+    MinerBlocksResponse,
+};
+use crate::blockchain::{BASE_REWARD, Block, difficulty_bits};
+
+const EXPLORER_DEFAULT_BLOCKS: usize = 10;
+const EXPLORER_MAX_BLOCKS: usize = 100;
+const MINER_BLOCKS_DEFAULT_LIMIT: usize = 50;
+const MINER_BLOCKS_MAX_LIMIT: usize = 500;
+
+/// One-call dashboard view: tip, difficulty, the last `?blocks=` blocks,
+/// mempool size and UTXO count.
+#[get("/explorer/summary/")]
+pub async fn get_explorer_summary(
+    state: web::Data<AppState>,
+    query: web::Query<ExplorerQuery>,
+) -> impl Responder {
+    let n = query
+        .blocks
+        .unwrap_or(EXPLORER_DEFAULT_BLOCKS)
+        .min(EXPLORER_MAX_BLOCKS);
+
+    // each lock held only while reading its part
+    let (tip_height, tip_hash, difficulty, blocks) = {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        let tip = bc.last_block();
+        // walk down from the tip's running total
+        let mut cumulative = bc.cumulative_tx_count(tip.index).unwrap_or_default();
+        let blocks = bc
+            .chain
+            .iter()
+            .rev()
+            .take(n)
+            .map(|block| {
+                let summary = summarize(block, cumulative);
+                cumulative -= block.tx_count;
+                summary
+            })
+            .collect();
+        (tip.index, tip.hash.clone(), bc.difficulty(), blocks)
+    };
+    let mempool_size = state.mempool.lock().expect("mutex poisoned").len();
+    let utxo_count = state.utxo_set.lock().expect("mutex poisoned").len();
+
+    HttpResponse::Ok().json(ExplorerSummaryResponse {
+        tip_height,
+        tip_hash,
+        difficulty,
+        blocks,
+        mempool_size,
+        utxo_count,
+    })
+}
+
+/// Blocks whose coinbase pays `address`, oldest first, from `?from_height=`
+/// (default 0), at most `?limit=` per page. Scans the chain: O(chain length).
+#[get("/miner/{address}/blocks/")]
+pub async fn get_miner_blocks(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<MinerBlocksQuery>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let address = path.into_inner();
+    let limit = query
+        .limit
+        .unwrap_or(MINER_BLOCKS_DEFAULT_LIMIT)
+        .clamp(1, MINER_BLOCKS_MAX_LIMIT);
+
+    let mut blocks = Vec::new();
+    let mut next_from_height = None;
+    {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        let start = usize::try_from(query.from_height).unwrap_or(usize::MAX);
+        let mut cumulative: u64 = bc.chain.iter().take(start).map(|b| b.tx_count).sum();
+        for block in bc.chain.iter().skip(start) {
+            cumulative += block.tx_count;
+            let Some(coinbase) = block.coinbase() else {
+                continue;
+            };
+            let paid: u64 = coinbase
+                .outputs
+                .iter()
+                .filter(|o| o.address == address)
+                .map(|o| o.amount)
+                .sum();
+            if paid == 0 {
+                continue;
+            }
+            if blocks.len() == limit {
+                next_from_height = Some(block.index);
+                break;
+            }
+            blocks.push(MinedBlock {
+                index: block.index,
+                hash: block.hash.clone(),
+                timestamp: block.timestamp,
+                reward: paid,
+                cumulative_tx_count: cumulative,
+            });
+        }
+    }
+
+    json_formatted(
+        &MinerBlocksResponse {
+            address,
+            blocks,
+            next_from_height,
+        },
+        &fmt,
+    )
+}
+
+/// Fees aren't stored per block; the coinbase's first output pays BASE_REWARD + fees
+/// (any further outputs are `FAUCET_MODE=coinbase` payouts).
+fn summarize(block: &Block, cumulative_tx_count: u64) -> ExplorerBlock {
+    let coinbase_total: u64 = block
+        .transactions
+        .iter()
+        .find(|tx| tx.inputs.is_empty())
+        .and_then(|tx| tx.outputs.first())
+        .map(|o| o.amount)
+        .unwrap_or(0);
+    ExplorerBlock {
+        index: block.index,
+        hash: block.hash.clone(),
+        tx_count: block.tx_count as usize,
+        cumulative_tx_count,
+        timestamp: block.timestamp,
+        bits: difficulty_bits(block.difficulty),
+        total_fees: coinbase_total.saturating_sub(BASE_REWARD),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::testutil::{
+        TestWallet, app, faucet, get_json, mine, output, signed_tx, state, submit_tx,
+    };
+
+    #[actix_web::test]
+    async fn miner_blocks_lists_only_that_miners_blocks() {
+        let state = state();
+        let app = app(&state).await;
+        let first = mine(&app, "alice-miner").await;
+        mine(&app, "bob-miner").await;
+        // a fee-paying tx, so the third block's reward differs from the first
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 97)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        let third = mine(&app, "alice-miner").await;
+
+        let body = get_json(&app, "/api/v1/miner/alice-miner/blocks/").await;
+        let blocks = body["blocks"].as_array().unwrap();
+        let indices: Vec<_> = blocks.iter().map(|b| b["index"].clone()).collect();
+        assert_eq!(
+            indices,
+            vec![first["mined_index"].clone(), third["mined_index"].clone()]
+        );
+        assert_eq!(blocks[0]["hash"], first["hash"]);
+        assert_eq!(blocks[1]["reward"], 53);
+        assert!(body.get("next_from_height").is_none());
+
+        // one per page, resuming where the previous page stopped
+        let body = get_json(&app, "/api/v1/miner/alice-miner/blocks/?limit=1").await;
+        assert_eq!(body["blocks"].as_array().unwrap().len(), 1);
+        assert_eq!(body["next_from_height"], third["mined_index"]);
+        let body = get_json(&app, "/api/v1/miner/alice-miner/blocks/?from_height=2").await;
+        assert_eq!(body["blocks"][0]["index"], third["mined_index"]);
+    }
+
+    #[actix_web::test]
+    async fn summary_tip_and_block_count() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 93)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        mine(&app, "miner").await;
+        let last = mine(&app, "miner").await;
+
+        let body = get_json(&app, "/api/v1/explorer/summary/?blocks=2").await;
+        assert_eq!(body["tip_height"], last["mined_index"]);
+        assert_eq!(body["tip_hash"], last["hash"]);
+        let blocks = body["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0]["hash"], last["hash"]);
+        assert_eq!(blocks[1]["total_fees"], 7);
+        assert_eq!(blocks[1]["tx_count"], 2);
+        assert_eq!(body["mempool_size"], 0);
+
+        // default covers the whole (short) chain: genesis + 2
+        let body = get_json(&app, "/api/v1/explorer/summary/").await;
+        assert_eq!(body["blocks"].as_array().unwrap().len(), 3);
+    }
+
+    #[actix_web::test]
+    async fn cumulative_tx_count_runs_through_the_chain() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        // block 1: coinbase + 2 txs; block 2: coinbase only; block 3: coinbase + 1
+        for amount in [100, 200] {
+            let op = faucet(&app, &alice.address, amount).await;
+            let tx = signed_tx(&[(op, &alice)], vec![output("bob", amount - 5)]);
+            assert!(submit_tx(&app, &tx).await.status().is_success());
+        }
+        mine(&app, "miner").await;
+        mine(&app, "miner").await;
+        let op = faucet(&app, &alice.address, 300).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 295)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        mine(&app, "miner").await;
+
+        // newest first: heights 3, 2, 1, 0
+        let body = get_json(&app, "/api/v1/explorer/summary/").await;
+        let counts: Vec<_> = body["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| {
+                (
+                    b["tx_count"].as_u64().unwrap(),
+                    b["cumulative_tx_count"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(counts, vec![(2, 6), (1, 4), (3, 3), (0, 0)]);
+
+        let latest = get_json(&app, "/api/v1/block/latest/").await;
+        assert_eq!(latest["cumulative_tx_count"], 6);
+        let confirmed = get_json(&app, &format!("/api/v1/tx/{}/block/", tx.txid)).await;
+        assert_eq!(confirmed["cumulative_tx_count"], 6);
+        let mined = get_json(&app, "/api/v1/miner/miner/blocks/?from_height=2").await;
+        assert_eq!(mined["blocks"][0]["cumulative_tx_count"], 4);
+        assert_eq!(mined["blocks"][1]["cumulative_tx_count"], 6);
+    }
+}
@@ -0,0 +1,50 @@
+//! Response formatting shared by the read endpoints.
+
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use serde::{Deserialize, Serialize};
+
+use crate::serde_util::with_amounts_as_strings;
+
+/// How monetary fields are rendered: JSON numbers (default) or decimal strings.
+/// Strings keep values above 2^53 exact for JavaScript clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AmountFormat {
+    #[default]
+    Number,
+    String,
+}
+
+/// `?amounts=number|string&pretty=true`
+#[derive(Deserialize)]
+pub struct FormatQuery {
+    #[serde(default)]
+    pub amounts: AmountFormat,
+    /// indented JSON for reading with curl; compact by default
+    #[serde(default)]
+    pub pretty: bool,
+}
+
+/// `200 OK` with `body` as JSON, rendering monetary fields (those marked
+/// `serialize_with = "amount"`) per `fmt.amounts` and indenting when
+/// `fmt.pretty`. Serializes `body` directly, so u128 values stay exact.
+pub fn json_formatted<T: Serialize>(body: &T, fmt: &FormatQuery) -> HttpResponse {
+    let render = || {
+        if fmt.pretty {
+            serde_json::to_string_pretty(body)
+        } else {
+            serde_json::to_string(body)
+        }
+    };
+    let text = match fmt.amounts {
+        AmountFormat::Number => render(),
+        AmountFormat::String => with_amounts_as_strings(render),
+    };
+    match text {
+        Ok(text) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .body(text),
+        Err(e) => HttpResponse::InternalServerError().body(format!("serialize: {e}")),
+    }
+}
@@ -1,7 +1,121 @@
-use actix_web::{HttpResponse, Responder, get};
+use actix_web::{HttpResponse, Responder, get, web};
+use std::collections::HashSet;
+
+use super::models::{AppState, ReadyResponse};
+use crate::blockchain::Blockchain;
+use crate::transaction::{OutPoint, UtxoSet};
 
 /// Health check (trailing slash)
 #[get("/health/")]
 pub async fn health_check() -> impl Responder {
     HttpResponse::Ok().body("API is up and running 🦀")
 }
+
+/// Readiness probe: unlike `/health/` (liveness), checks that the state is
+/// usable and answers `503` with the reason when it isn't.
+#[get("/ready/")]
+pub async fn readiness_check(state: web::Data<AppState>) -> impl Responder {
+    // lock order: chain -> UTXO
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    let utxo = state.utxo_set.lock().expect("mutex poisoned");
+    match check_ready(&bc, &utxo) {
+        Ok(()) => HttpResponse::Ok().json(ReadyResponse {
+            ready: true,
+            height: bc.len(),
+            reason: None,
+        }),
+        Err(reason) => HttpResponse::ServiceUnavailable().json(ReadyResponse {
+            ready: false,
+            height: bc.len(),
+            reason: Some(reason),
+        }),
+    }
+}
+
+/// Non-empty chain, intact genesis and a spot check of the UTXO set against
+/// the tip: nothing can have spent the tip's outputs yet, and its inputs must
+/// be gone. Cheap on purpose; the full replay is `/chain/validate/full/`.
+fn check_ready(bc: &Blockchain, utxo: &UtxoSet) -> Result<(), String> {
+    if bc.chain.is_empty() {
+        return Err("chain is empty".into());
+    }
+    if let Some((_, reason)) = Blockchain::first_invalid_block(&bc.chain[..1], bc.pow_algo) {
+        return Err(format!("genesis is invalid: {reason}"));
+    }
+
+    let tip = bc.last_block();
+    // outputs spent by a later tx of the same block never reach the set
+    let spent_in_tip: HashSet<&OutPoint> = tip
+        .transactions
+        .iter()
+        .flat_map(|tx| tx.inputs.iter().map(|i| &i.outpoint))
+        .collect();
+    for tx in &tip.transactions {
+        for vout in 0..tx.outputs.len() as u32 {
+            let op = OutPoint {
+                txid: tx.txid.clone(),
+                vout,
+            };
+            if !spent_in_tip.contains(&op) && utxo.get(&op).is_none() {
+                return Err(format!(
+                    "UTXO set is missing output {}:{vout} of tip block #{}",
+                    tx.txid, tip.index
+                ));
+            }
+        }
+    }
+    if let Some(op) = spent_in_tip.iter().find(|op| utxo.get(op).is_some()) {
+        return Err(format!(
+            "UTXO set still holds {}:{} spent in tip block #{}",
+            op.txid, op.vout, tip.index
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::api::testutil::{app, get, mine, state};
+    use crate::transaction::OutPoint;
+
+    #[actix_web::test]
+    async fn fresh_node_is_ready_and_a_corrupt_one_is_not() {
+        let state = state();
+        let app = app(&state).await;
+        let resp = get(&app, "/api/v1/ready/").await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body["ready"], true);
+
+        mine(&app, "miner").await;
+        assert_eq!(get(&app, "/api/v1/ready/").await.status(), 200);
+
+        // lose the tip's coinbase output
+        let coinbase = state.blockchain.lock().unwrap().last_block().transactions[0]
+            .txid
+            .clone();
+        state.utxo_set.lock().unwrap().spend(&OutPoint {
+            txid: coinbase,
+            vout: 0,
+        });
+        let resp = get(&app, "/api/v1/ready/").await;
+        assert_eq!(resp.status(), 503);
+        let body: Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body["ready"], false);
+        assert!(body["reason"].as_str().unwrap().contains("missing output"));
+
+        // liveness doesn't care
+        assert_eq!(get(&app, "/api/v1/health/").await.status(), 200);
+
+        state.blockchain.lock().unwrap().chain[0].nonce += 1;
+        let body: Value = actix_web::test::read_body_json(get(&app, "/api/v1/ready/").await).await;
+        assert!(
+            body["reason"]
+                .as_str()
+                .unwrap()
+                .starts_with("genesis is invalid")
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! `Idempotency-Key` support for POST endpoints: the first response for a key
+//! is cached (for `IDEMPOTENCY_TTL`, at most `MAX_ENTRIES` keys) and replayed
+//! on retries instead of running the handler again. A retry must carry the
+//! same request body as the first request.
+
+use actix_web::body::{self, BoxBody};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{CONTENT_TYPE, HeaderValue};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse};
+use log::{debug, warn};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+/// Set on replayed responses.
+pub const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+const IDEMPOTENCY_TTL: Duration = Duration::from_secs(60 * 60);
+const MAX_KEY_LEN: usize = 255;
+/// Keys remembered at once; past it the oldest finished one is forgotten.
+const MAX_ENTRIES: usize = 10_000;
+
+/// SHA-256 of the request body, as deserialized (so formatting doesn't matter).
+type Fingerprint = [u8; 32];
+
+enum Entry {
+    /// first request still running
+    InFlight { at: Instant, request: Fingerprint },
+    Done {
+        at: Instant,
+        request: Fingerprint,
+        status: StatusCode,
+        content_type: Option<HeaderValue>,
+        body: Bytes,
+    },
+}
+
+impl Entry {
+    fn created(&self) -> Instant {
+        match self {
+            Entry::InFlight { at, .. } | Entry::Done { at, .. } => *at,
+        }
+    }
+
+    fn request(&self) -> &Fingerprint {
+        match self {
+            Entry::InFlight { request, .. } | Entry::Done { request, .. } => request,
+        }
+    }
+}
+
+type Key = (&'static str, String);
+
+/// Responses keyed by `(route, Idempotency-Key)`.
+#[derive(Default)]
+pub struct IdempotencyCache {
+    entries: Mutex<HashMap<Key, Entry>>,
+}
+
+impl IdempotencyCache {
+    /// Run `handler` on `body` unless `req` carries an `Idempotency-Key`
+    /// already seen on `route`, in which case the stored response is returned.
+    /// Without the header the handler always runs. 5xx responses are not
+    /// cached. Reusing a key with a different `body` is refused (`422`), never
+    /// replayed.
+    pub async fn run<B, H, F>(
+        &self,
+        route: &'static str,
+        req: &HttpRequest,
+        body: B,
+        handler: H,
+    ) -> HttpResponse
+    where
+        B: Serialize,
+        H: FnOnce(B) -> F,
+        F: Future<Output = HttpResponse>,
+    {
+        let Some(key) = req.headers().get(IDEMPOTENCY_HEADER) else {
+            return handler(body).await;
+        };
+        let key = match key.to_str() {
+            Ok(k) if !k.is_empty() && k.len() <= MAX_KEY_LEN => (route, k.to_string()),
+            _ => return HttpResponse::BadRequest().body("invalid Idempotency-Key header"),
+        };
+
+        let request: Fingerprint = match serde_json::to_vec(&body) {
+            Ok(bytes) => Sha256::digest(bytes).into(),
+            Err(_) => return HttpResponse::InternalServerError().finish(),
+        };
+
+        {
+            let mut entries = self.entries.lock().expect("mutex poisoned");
+            let now = Instant::now();
+            entries.retain(|_, e| now.duration_since(e.created()) < IDEMPOTENCY_TTL);
+            match entries.get(&key) {
+                Some(e) if *e.request() != request => {
+                    warn!(
+                        "IDEMPOTENCY - {} key={} reused with another body",
+                        route, key.1
+                    );
+                    return HttpResponse::UnprocessableEntity()
+                        .body("Idempotency-Key already used with a different request body");
+                }
+                Some(Entry::InFlight { .. }) => {
+                    return HttpResponse::Conflict()
+                        .body("a request with this Idempotency-Key is still in progress");
+                }
+                Some(Entry::Done {
+                    status,
+                    content_type,
+                    body,
+                    ..
+                }) => {
+                    debug!("IDEMPOTENCY - replaying {} key={}", route, key.1);
+                    let mut resp = HttpResponse::build(*status);
+                    if let Some(ct) = content_type {
+                        resp.insert_header((CONTENT_TYPE, ct.clone()));
+                    }
+                    return resp
+                        .insert_header((REPLAYED_HEADER, "true"))
+                        .body(body.clone());
+                }
+                None => {
+                    if entries.len() >= MAX_ENTRIES {
+                        evict_oldest(&mut entries);
+                    }
+                    entries.insert(key.clone(), Entry::InFlight { at: now, request });
+                }
+            }
+        }
+
+        // forgets the key if the handler panics or the request is dropped
+        let pending = Pending {
+            cache: self,
+            key: Some(key),
+        };
+        let resp = handler(body).await;
+        let status = resp.status();
+        let content_type = resp.headers().get(CONTENT_TYPE).cloned();
+        let (resp, resp_body) = resp.into_parts();
+        let Ok(bytes) = body::to_bytes(resp_body).await else {
+            return HttpResponse::InternalServerError().finish();
+        };
+
+        if !status.is_server_error() {
+            let key = pending.finish();
+            self.entries.lock().expect("mutex poisoned").insert(
+                key,
+                Entry::Done {
+                    at: Instant::now(),
+                    request,
+                    status,
+                    content_type,
+                    body: bytes.clone(),
+                },
+            );
+        }
+        resp.set_body(BoxBody::new(bytes))
+    }
+}
+
+/// Make room for one more key: drop the oldest finished entry (in-flight
+/// ones are only dropped by their own request).
+fn evict_oldest(entries: &mut HashMap<Key, Entry>) {
+    let oldest = entries
+        .iter()
+        .filter(|(_, e)| matches!(e, Entry::Done { .. }))
+        .min_by_key(|(_, e)| e.created())
+        .map(|(k, _)| k.clone());
+    if let Some(key) = oldest {
+        entries.remove(&key);
+    }
+}
+
+/// The `InFlight` entry of a running request; removed on drop unless the
+/// response was stored (`finish`).
+struct Pending<'a> {
+    cache: &'a IdempotencyCache,
+    key: Option<Key>,
+}
+
+impl Pending<'_> {
+    fn finish(mut self) -> Key {
+        self.key.take().expect("finished once")
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            // may run while unwinding from a panic: don't panic again
+            let mut entries = self
+                .cache
+                .entries
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            entries.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IDEMPOTENCY_HEADER, IdempotencyCache};
+    use actix_web::HttpResponse;
+    use actix_web::test::TestRequest;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn key_is_released_when_the_request_is_dropped_or_panics() {
+        let cache = Arc::new(IdempotencyCache::default());
+        let req = TestRequest::default()
+            .insert_header((IDEMPOTENCY_HEADER, "k1"))
+            .to_http_request();
+
+        // client gone mid-request: the handler future is dropped
+        let abandoned = cache.run("tx", &req, 1, |_| std::future::pending());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), abandoned)
+                .await
+                .is_err()
+        );
+
+        // handler panics
+        let (panicking, panic_req) = (cache.clone(), req.clone());
+        let task = actix_web::rt::spawn(async move {
+            panicking
+                .run("tx", &panic_req, 1, |_| async { panic!("handler bug") })
+                .await
+        });
+        assert!(task.await.is_err());
+
+        // neither left the key stuck as in flight (409)
+        let resp = cache
+            .run("tx", &req, 1, |_| async { HttpResponse::Ok().finish() })
+            .await;
+        assert_eq!(resp.status(), 200);
+    }
+}
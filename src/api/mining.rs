@@ -1,105 +1,23 @@
-use actix_web::{HttpResponse, Responder, post, web};
+use actix_web::{HttpResponse, Responder, get, post, web};
 use log::{debug, info, warn};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 use uuid::Uuid;
 
+use super::chain::{MineError, mine_next_block};
 use super::models::{
-    AppState, MiningTemplate, SubmitRequest, SubmitResponse, TemplateRequest, TemplateResponse,
+    AUTO_MINE_PAUSE_MS, AppState, AutoMineRequest, AutoMineResponse, AutoMiner, AutoMinerControl,
+    MiningEtaQuery, MiningEtaResponse, MiningRejectionsResponse, MiningTemplate, MiningWork,
+    NONCE_PLACEHOLDER, PreimageResponse, PreviewQuery, PreviewResponse, RewardEstimateResponse,
+    SubmitChecks, SubmitQuery, SubmitRequest, SubmitResponse, SubmitTxCheck, TemplateRequest,
+    TemplateResponse,
 };
-use crate::blockchain::{BASE_REWARD, Block, MAX_BLOCK_BYTES, MAX_TXS_PER_BLOCK};
-use crate::transaction::{Transaction, TxOutput, UtxoSet};
-
-/// Seleciona transações (mesma lógica greedy por fee-rate do chain.rs).
-fn select_transactions(mempool: &[Transaction], utxo: &UtxoSet) -> (Vec<Transaction>, u128) {
-    #[derive(Clone)]
-    struct Cand {
-        idx: usize,
-        fee: u128,
-        size: usize,
-        fee_rate: f64,
-    }
-    let mut cands: Vec<Cand> = Vec::new();
-
-    for (idx, tx) in mempool.iter().enumerate() {
-        if tx.inputs.is_empty() {
-            continue;
-        }
-        let mut input_sum: u128 = 0;
-        let mut ok = true;
-        for input in &tx.inputs {
-            match utxo.get(&input.outpoint) {
-                Some(prev) => input_sum += prev.amount as u128,
-                None => {
-                    ok = false;
-                    break;
-                }
-            }
-        }
-        if !ok {
-            continue;
-        }
-        let output_sum = tx.total_output_amount();
-        if input_sum < output_sum {
-            continue;
-        }
-        let fee = input_sum - output_sum;
-        let size = tx.vsize_bytes();
-        let fee_rate = if size > 0 {
-            fee as f64 / size as f64
-        } else {
-            0.0
-        };
-        cands.push(Cand {
-            idx,
-            fee,
-            size,
-            fee_rate,
-        });
-    }
-
-    cands.sort_by(|a, b| {
-        b.fee_rate
-            .partial_cmp(&a.fee_rate)
-            .unwrap_or(std::cmp::Ordering::Equal)
-            .then_with(|| b.fee.cmp(&a.fee))
-            .then_with(|| mempool[a.idx].txid.cmp(&mempool[b.idx].txid))
-    });
-
-    let mut total_fees: u128 = 0;
-    let mut total_bytes: usize = 0;
-    let mut picked: Vec<Transaction> = Vec::new();
-    let mut consumed = std::collections::HashSet::<(String, u32)>::new();
-
-    for c in cands {
-        if picked.len() >= MAX_TXS_PER_BLOCK {
-            break;
-        }
-        if total_bytes + c.size > MAX_BLOCK_BYTES {
-            continue;
-        }
-        let tx = &mempool[c.idx];
-
-        let mut ok = true;
-        for input in &tx.inputs {
-            let key = (input.outpoint.txid.clone(), input.outpoint.vout);
-            if consumed.contains(&key) {
-                ok = false;
-                break;
-            }
-        }
-        if !ok {
-            continue;
-        }
-
-        for input in &tx.inputs {
-            consumed.insert((input.outpoint.txid.clone(), input.outpoint.vout));
-        }
-        total_fees += c.fee;
-        total_bytes += c.size;
-        picked.push(tx.clone());
-    }
-
-    (picked, total_fees)
-}
+use super::tx::{check_maturity, check_transaction};
+use crate::blockchain::{
+    BASE_REWARD, Block, CoinbaseShare, expected_hashes, select_transactions_cached, tx_fee,
+};
+use crate::transaction::{Transaction, UtxoSet};
 
 /// Produz um template fixando timestamp e a lista de txs (coinbase primeiro).
 #[post("/mining/template/")]
@@ -107,66 +25,41 @@ pub async fn get_template(
     state: web::Data<AppState>,
     req: web::Json<TemplateRequest>,
 ) -> impl Responder {
-    let miner_addr = req.miner_address.trim();
-    if miner_addr.is_empty() {
-        return HttpResponse::BadRequest().body("miner_address required");
-    }
+    let payees = match req.payees() {
+        Ok(payees) => payees,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
 
     // snapshot da head/difficulty
-    let (index, previous_hash, difficulty) = {
+    let (index, previous_hash, difficulty, pow_algo, timestamp) = {
         let bc = state.blockchain.lock().expect("mutex");
         (
             bc.len() as u64,
             bc.last_block().hash.clone(),
             bc.difficulty(),
+            bc.pow_algo,
+            // fixar timestamp para o template
+            bc.now_timestamp(),
         )
     };
 
-    // snapshot mempool + utxo para seleção e cálculo de fees
-    let mempool_snapshot = {
-        let mem = state.mempool.lock().expect("mutex");
-        mem.clone()
-    };
-    let (mut selected, total_fees) = {
-        let utxo = state.utxo_set.lock().expect("mutex");
-        select_transactions(&mempool_snapshot, &utxo)
-    };
-
-    // coinbase
-    let total_fees_u64 = (total_fees as u128).min(u128::from(u64::MAX - BASE_REWARD)) as u64;
-    let coinbase_amount = BASE_REWARD + total_fees_u64;
-    let coinbase = Transaction::new(
-        vec![],
-        vec![TxOutput {
-            address: miner_addr.to_string(),
-            amount: coinbase_amount,
-        }],
-    );
-
-    // txs do bloco = coinbase + selecionadas
-    let mut txs = Vec::with_capacity(1 + selected.len());
-    txs.push(coinbase);
-    txs.append(&mut selected);
-
-    // fixar timestamp para o template
-    let timestamp = chrono::Utc::now().timestamp();
+    let (txs, _) = next_block_txs(&state, index, &payees);
 
     // armazenar template
     let template_id = Uuid::new_v4().to_string();
+    let template = MiningTemplate {
+        template_id: template_id.clone(),
+        index,
+        previous_hash: previous_hash.clone(),
+        timestamp,
+        difficulty,
+        payees,
+        transactions: txs.clone(),
+    };
+    let work = MiningWork::for_block(&template.to_block(), pow_algo);
     {
         let mut map = state.mining_templates.lock().expect("mutex");
-        map.insert(
-            template_id.clone(),
-            MiningTemplate {
-                template_id: template_id.clone(),
-                index,
-                previous_hash: previous_hash.clone(),
-                timestamp,
-                difficulty,
-                miner_address: miner_addr.to_string(),
-                transactions: txs.clone(),
-            },
-        );
+        map.insert(template_id.clone(), template);
     }
 
     debug!(
@@ -183,95 +76,554 @@ pub async fn get_template(
         previous_hash,
         timestamp,
         difficulty,
+        pow_algo,
+        nonce_start: 0,
+        target: work.target.clone(),
+        bits: work.bits.clone(),
+        work,
         transactions: txs,
     })
 }
 
+/// Seleciona txs da mempool e monta a coinbase do bloco `index`: (coinbase + selecionadas, total de fees).
+fn next_block_txs(
+    state: &AppState,
+    index: u64,
+    payees: &[CoinbaseShare],
+) -> (Vec<Transaction>, u128) {
+    // snapshot mempool + utxo para seleção e cálculo de fees
+    let mempool_snapshot = state.mempool_txs();
+    let (mut selected, total_fees) = {
+        let utxo = state.utxo_set.lock().expect("mutex");
+        let cache = state.fee_cache.lock().expect("mutex");
+        select_transactions_cached(&mempool_snapshot, &utxo, state.config.fee_rate_mode, &cache)
+    };
+
+    // coinbase (+ pagamentos do faucet em fila)
+    let coinbase = state.coinbase_tx(index, payees, total_fees);
+
+    // txs do bloco = coinbase + selecionadas
+    let mut txs = Vec::with_capacity(1 + selected.len());
+    txs.push(coinbase);
+    txs.append(&mut selected);
+    (txs, total_fees)
+}
+
+/// Prévia do próximo bloco (mesma seleção e coinbase do template), sem
+/// guardar template nenhum. Para dashboards.
+#[get("/mining/preview/")]
+pub async fn get_preview(
+    state: web::Data<AppState>,
+    query: web::Query<PreviewQuery>,
+) -> impl Responder {
+    let miner_addr = query.miner_address.trim();
+    if miner_addr.is_empty() {
+        return HttpResponse::BadRequest().body("miner_address required");
+    }
+    let (index, difficulty) = {
+        let bc = state.blockchain.lock().expect("mutex");
+        (bc.len() as u64, bc.difficulty())
+    };
+    let (transactions, total_fees) =
+        next_block_txs(&state, index, &[CoinbaseShare::solo(miner_addr)]);
+    // só a saída do minerador: as demais são pagamentos do faucet
+    let reward = transactions[0].outputs[0].amount as u128;
+
+    HttpResponse::Ok().json(PreviewResponse {
+        index,
+        difficulty,
+        total_fees,
+        reward,
+        transactions,
+    })
+}
+
+/// Quanto minerar agora pagaria: subsídio da próxima altura + fees das txs
+/// que a seleção incluiria. Só leitura, como a prévia.
+#[get("/mining/reward-estimate/")]
+pub async fn get_reward_estimate(
+    state: web::Data<AppState>,
+    query: web::Query<PreviewQuery>,
+) -> impl Responder {
+    let miner_addr = query.miner_address.trim();
+    if miner_addr.is_empty() {
+        return HttpResponse::BadRequest().body("miner_address required");
+    }
+    let height = state.blockchain.lock().expect("mutex").len() as u64;
+    let (transactions, total_fees) =
+        next_block_txs(&state, height, &[CoinbaseShare::solo(miner_addr)]);
+
+    HttpResponse::Ok().json(RewardEstimateResponse {
+        miner_address: miner_addr.to_string(),
+        height,
+        subsidy: BASE_REWARD,
+        total_fees,
+        reward: transactions[0].outputs[0].amount as u128,
+        tx_count: transactions.len() - 1,
+    })
+}
+
+/// How long a miner doing `?hashrate=` H/s should expect to take to find the
+/// next block (`16^difficulty / hashrate`), and optionally the odds of
+/// finding one within `?within_secs=`. Attempts are independent, so block
+/// finding is a Poisson process and the wait is exponentially distributed.
+#[get("/mining/eta/")]
+pub async fn get_mining_eta(
+    state: web::Data<AppState>,
+    query: web::Query<MiningEtaQuery>,
+) -> impl Responder {
+    let MiningEtaQuery {
+        hashrate,
+        within_secs,
+    } = query.into_inner();
+    if !hashrate.is_finite() || hashrate < 0.0 {
+        return HttpResponse::BadRequest().body("hashrate must be a non-negative number");
+    }
+    if within_secs.is_some_and(|t| !t.is_finite() || t < 0.0) {
+        return HttpResponse::BadRequest().body("within_secs must be a non-negative number");
+    }
+
+    let difficulty = state.blockchain.lock().expect("mutex").difficulty();
+    let expected = expected_hashes(difficulty);
+    let expected_secs = (hashrate > 0.0).then(|| expected as f64 / hashrate);
+    let probability_within = within_secs.map(|t| match expected_secs {
+        Some(eta) => 1.0 - (-t / eta).exp(),
+        None => 0.0,
+    });
+    HttpResponse::Ok().json(MiningEtaResponse {
+        difficulty,
+        expected_hashes: expected,
+        hashrate,
+        expected_secs,
+        within_secs,
+        probability_within,
+    })
+}
+
+/// DEV: preimage de um template com `{nonce}` no lugar do nonce, para o
+/// minerador externo comparar com o que ele mesmo está hasheando.
+#[get("/mining/template/{id}/preimage/")]
+pub async fn get_template_preimage(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    if !state.config.dev_endpoints {
+        return HttpResponse::NotFound().finish();
+    }
+    let block = {
+        let map = state.mining_templates.lock().expect("mutex");
+        match map.get(path.as_str()) {
+            Some(t) => t.to_block(),
+            None => return HttpResponse::NotFound().body("unknown template"),
+        }
+    };
+    let (prefix, suffix) = block.preimage_parts();
+    HttpResponse::Ok().json(PreimageResponse {
+        preimage: format!("{prefix}{NONCE_PLACEHOLDER}{suffix}"),
+        hash: None,
+    })
+}
+
+/// Últimas submissões recusadas (mais nova primeiro), para o minerador
+/// entender por que a solução não entrou.
+#[get("/mining/rejections/")]
+pub async fn get_rejections(state: web::Data<AppState>) -> impl Responder {
+    let rejections = state
+        .mining_rejections
+        .lock()
+        .expect("mutex")
+        .iter()
+        .rev()
+        .cloned()
+        .collect();
+    HttpResponse::Ok().json(MiningRejectionsResponse { rejections })
+}
+
+/// Start mining blocks continuously to `miner_address` in the background,
+/// pausing `pause_ms` between blocks. Starting while already running changes
+/// nothing and reports the running miner.
+#[post("/mining/auto/start/")]
+pub async fn start_auto_mining(
+    state: web::Data<AppState>,
+    req: web::Json<AutoMineRequest>,
+) -> impl Responder {
+    let miner_address = req.miner_address.trim().to_string();
+    if miner_address.is_empty() {
+        return HttpResponse::BadRequest().body("miner_address required");
+    }
+    {
+        let mut slot = state.auto_miner.lock().expect("mutex");
+        let running = slot
+            .as_ref()
+            .is_some_and(|m| m.control.running.load(Ordering::SeqCst) && !m.handle.is_finished());
+        if !running {
+            let pause = Duration::from_millis(req.pause_ms.unwrap_or(AUTO_MINE_PAUSE_MS));
+            let control = Arc::new(AutoMinerControl::default());
+            control.running.store(true, Ordering::SeqCst);
+            let handle = actix_web::rt::spawn(auto_mine(
+                state.clone(),
+                miner_address.clone(),
+                pause,
+                control.clone(),
+            ));
+            info!("auto-miner started for {miner_address} (pause {pause:?})");
+            *slot = Some(AutoMiner {
+                miner_address,
+                control,
+                handle,
+            });
+        }
+    }
+    HttpResponse::Ok().json(auto_mine_status(&state))
+}
+
+/// Stop the background miner and wait for it to finish the block in progress.
+#[post("/mining/auto/stop/")]
+pub async fn stop_auto_mining(state: web::Data<AppState>) -> impl Responder {
+    // take it out of the slot: the lock isn't held while joining
+    let miner = state.auto_miner.lock().expect("mutex").take();
+    if let Some(miner) = miner {
+        miner.control.running.store(false, Ordering::SeqCst);
+        miner.control.stop.notify_one();
+        if miner.handle.await.is_err() {
+            warn!("auto-miner task panicked");
+        }
+        let mined = miner.control.blocks_mined.load(Ordering::SeqCst);
+        info!("auto-miner stopped after {mined} block(s)");
+        let height = state.blockchain.lock().expect("mutex").len() as u64;
+        return HttpResponse::Ok().json(AutoMineResponse {
+            running: false,
+            miner_address: Some(miner.miner_address),
+            blocks_mined: mined,
+            height,
+        });
+    }
+    HttpResponse::Ok().json(auto_mine_status(&state))
+}
+
+fn auto_mine_status(state: &AppState) -> AutoMineResponse {
+    let height = state.blockchain.lock().expect("mutex").len() as u64;
+    let slot = state.auto_miner.lock().expect("mutex");
+    AutoMineResponse {
+        running: slot
+            .as_ref()
+            .is_some_and(|m| m.control.running.load(Ordering::SeqCst)),
+        miner_address: slot.as_ref().map(|m| m.miner_address.clone()),
+        blocks_mined: slot
+            .as_ref()
+            .map_or(0, |m| m.control.blocks_mined.load(Ordering::SeqCst)),
+        height,
+    }
+}
+
+/// Loop do auto-miner: um bloco, uma pausa, até pedirem parada (ou o node desligar).
+async fn auto_mine(
+    state: web::Data<AppState>,
+    miner_address: String,
+    pause: Duration,
+    control: Arc<AutoMinerControl>,
+) {
+    while control.running.load(Ordering::SeqCst) {
+        match mine_next_block(state.clone(), vec![CoinbaseShare::solo(&miner_address)]).await {
+            Ok(block) => {
+                control.blocks_mined.fetch_add(1, Ordering::SeqCst);
+                debug!("auto-miner sealed block #{}", block.mined_index);
+            }
+            Err(MineError::ShuttingDown) => break,
+            Err(MineError::TaskFailed) => warn!("auto-miner: mining task failed"),
+            Err(MineError::Checkpoint(e)) => warn!("auto-miner: {e}"),
+        }
+        tokio::select! {
+            _ = actix_web::rt::time::sleep(pause) => {}
+            _ = control.stop.notified() => {}
+        }
+    }
+    control.running.store(false, Ordering::SeqCst);
+}
+
+/// A coinbase may pay its `miner_outputs` (the template's payees) at most
+/// `BASE_REWARD` + `fees` together; any further output must be the next
+/// queued faucet payout, in queue order.
+fn check_coinbase(
+    state: &AppState,
+    coinbase: &Transaction,
+    miner_outputs: usize,
+    fees: u128,
+) -> Result<(), String> {
+    let allowed = BASE_REWARD as u128 + fees;
+    let paid: u128 = coinbase
+        .outputs
+        .iter()
+        .take(miner_outputs)
+        .map(|o| o.amount as u128)
+        .sum();
+    if paid > allowed {
+        return Err(format!(
+            "coinbase pays the miner {paid}, more than reward + fees ({allowed})"
+        ));
+    }
+    let queue = state.faucet_queue.lock().expect("mutex");
+    let payouts = coinbase.outputs.iter().skip(miner_outputs);
+    if payouts.len() > queue.len() || !payouts.zip(queue.iter()).all(|(a, b)| a == b) {
+        return Err("coinbase pays outputs that are not queued faucet payouts".into());
+    }
+    Ok(())
+}
+
+/// Fees the block's non-coinbase txs pay against `utxo` (unresolvable ones count 0).
+fn block_fees(block: &Block, utxo: &UtxoSet) -> u128 {
+    block
+        .transactions
+        .iter()
+        .skip(1)
+        .filter_map(|tx| tx_fee(tx, utxo))
+        .sum()
+}
+
+/// Run every submit check on `block` (the template with the submitted nonce)
+/// without short-circuiting, for `?verbose=true`.
+fn inspect_submission(
+    state: &AppState,
+    template: &MiningTemplate,
+    block: &Block,
+    submitted_hash: &str,
+) -> SubmitChecks {
+    let mut failures = Vec::new();
+    let (head_matches, pow_satisfied) = {
+        let bc = state.blockchain.lock().expect("mutex");
+        let tip = &bc.last_block().hash;
+        if *tip != template.previous_hash {
+            failures.push(format!(
+                "template builds on {} but the tip is now {tip}",
+                template.previous_hash
+            ));
+        }
+        let pow = block.is_valid_with(bc.difficulty(), bc.pow_algo);
+        if !pow {
+            failures.push(format!(
+                "{} does not meet difficulty {}",
+                block.hash,
+                bc.difficulty()
+            ));
+        }
+        (*tip == template.previous_hash, pow)
+    };
+    let hash_matches = block.hash == submitted_hash;
+    if !hash_matches {
+        failures.push(format!(
+            "nonce {} hashes to {}, not {submitted_hash}",
+            block.nonce, block.hash
+        ));
+    }
+    let signatures = state.verify_block_signatures(block);
+    if let Err(bad) = &signatures {
+        failures.push(format!("invalid block signature: {bad}"));
+    }
+
+    let (transactions, fees) = {
+        let utxo = state.utxo_set.lock().expect("mutex");
+        let checks = block
+            .transactions
+            .iter()
+            .skip(1)
+            .map(|tx| {
+                let check =
+                    check_transaction(tx, &utxo, &state.config.chain_id, state.signature_checks());
+                SubmitTxCheck {
+                    txid: tx.txid.clone(),
+                    valid: check.errors.is_empty(),
+                    errors: check.errors.iter().map(|e| e.code()).collect(),
+                }
+            })
+            .collect();
+        (checks, block_fees(block, &utxo))
+    };
+    let coinbase = check_coinbase(state, &block.transactions[0], template.payees.len(), fees);
+    if let Err(e) = &coinbase {
+        failures.push(e.clone());
+    }
+
+    SubmitChecks {
+        head_matches,
+        hash_matches,
+        pow_satisfied,
+        signatures_valid: signatures.is_ok(),
+        coinbase_reward_valid: coinbase.is_ok(),
+        failures,
+        transactions,
+    }
+}
+
 /// Submete uma solução de PoW (nonce/hash) para um template.
 /// Revalida head/diff e aplica bloco no UTXO/mempool se aceitar.
+/// Com `?verbose=true` a resposta (aceita ou não) traz `checks`, o resultado
+/// de cada verificação.
 #[post("/mining/submit/")]
 pub async fn submit_solution(
     state: web::Data<AppState>,
     req: web::Json<SubmitRequest>,
+    query: web::Query<SubmitQuery>,
 ) -> impl Responder {
+    let rejected = SubmitResponse {
+        accepted: false,
+        mined_index: None,
+        hash: None,
+        difficulty: None,
+        checks: None,
+    };
+    let reject = |reason, detail: String| {
+        state.record_mining_rejection(&req.template_id, reason, detail);
+    };
+
     // pega e remove o template (consumo único)
     let template = {
         let mut map = state.mining_templates.lock().expect("mutex");
         match map.remove(&req.template_id) {
             Some(t) => t,
             None => {
-                return HttpResponse::BadRequest().json(SubmitResponse {
-                    accepted: false,
-                    mined_index: None,
-                    hash: None,
-                    difficulty: None,
-                });
+                reject(
+                    "unknown_template",
+                    "template unknown, expired or already submitted".into(),
+                );
+                return HttpResponse::BadRequest().json(rejected);
             }
         }
     };
 
+    // reconstrói o bloco com o mesmo timestamp/txs e aplica nonce
+    let mut block = template.to_block();
+    block.nonce = req.nonce;
+    block.hash = block.compute_hash();
+
+    // modo verbose: todas as verificações, e recusas viram JSON com o detalhe
+    let checks = query
+        .verbose
+        .then(|| inspect_submission(&state, &template, &block, &req.hash));
+    let refuse = |plain: HttpResponse| match &checks {
+        Some(c) => HttpResponse::BadRequest().json(SubmitResponse {
+            accepted: false,
+            mined_index: None,
+            hash: None,
+            difficulty: None,
+            checks: Some(c.clone()),
+        }),
+        None => plain,
+    };
+
+    // uma mineração por vez: nada muda a chain entre as checagens e o apply
+    let _run = state.mining.lock().await;
+
     // checa head atual
     {
         let bc = state.blockchain.lock().expect("mutex");
-        if bc.last_block().hash != template.previous_hash {
+        let head = &bc.last_block().hash;
+        if *head != template.previous_hash {
             warn!("stale template {}: head moved", template.template_id);
-            return HttpResponse::BadRequest().json(SubmitResponse {
-                accepted: false,
-                mined_index: None,
-                hash: None,
-                difficulty: None,
-            });
+            reject(
+                "stale_head",
+                format!(
+                    "template builds on {} but the tip is now {head}",
+                    template.previous_hash
+                ),
+            );
+            return refuse(HttpResponse::BadRequest().json(rejected));
         }
     }
 
-    // reconstrói o bloco com o mesmo timestamp/txs e aplica nonce
-    let mut block = Block::new_with_timestamp(
-        template.index,
-        template.previous_hash.clone(),
-        template.transactions.clone(),
-        template.timestamp,
-    );
-    block.nonce = req.nonce;
-    block.hash = block.compute_hash();
-
     // valida hash informado
     if block.hash != req.hash {
-        return HttpResponse::BadRequest().body("hash mismatch");
+        reject(
+            "hash_mismatch",
+            format!(
+                "nonce {} hashes to {}, not {}",
+                req.nonce, block.hash, req.hash
+            ),
+        );
+        return refuse(HttpResponse::BadRequest().body("hash mismatch"));
+    }
+
+    // assinaturas de todas as txs do bloco, verificadas em lote
+    if let Err(bad) = state.verify_block_signatures(&block) {
+        warn!("template {} rejected: {}", template.template_id, bad);
+        reject("bad_signature", bad.to_string());
+        return refuse(HttpResponse::BadRequest().body(format!("invalid block signature: {bad}")));
+    }
+
+    // nenhum input pode gastar coinbase imatura na altura deste bloco
+    let immature = {
+        let utxo = state.utxo_set.lock().expect("mutex");
+        block
+            .transactions
+            .iter()
+            .skip(1)
+            .find(|tx| {
+                check_maturity(tx, &utxo, template.index, state.config.coinbase_maturity).is_err()
+            })
+            .map(|tx| tx.txid.clone())
+    };
+    if let Some(txid) = immature {
+        let e = format!("tx {txid} spends an immature coinbase output");
+        warn!("template {} rejected: {}", template.template_id, e);
+        reject("immature_coinbase", e.clone());
+        return refuse(HttpResponse::BadRequest().body(e));
+    }
+
+    // coinbase: recompensa + fees (mais pagamentos do faucet em fila)
+    let fees = block_fees(&block, &state.utxo_set.lock().expect("mutex"));
+    if let Err(e) = check_coinbase(&state, &block.transactions[0], template.payees.len(), fees) {
+        warn!("template {} rejected: {}", template.template_id, e);
+        reject("bad_coinbase", e.clone());
+        return refuse(HttpResponse::BadRequest().body(e));
     }
 
     // confere PoW via chain (dif atual)
-    {
+    let diff = {
         let mut bc = state.blockchain.lock().expect("mutex");
-        if !block.is_valid(bc.difficulty()) {
-            return HttpResponse::BadRequest().body("hash does not meet difficulty");
+        if !block.is_valid_with(bc.difficulty(), bc.pow_algo) {
+            reject(
+                "below_difficulty",
+                format!(
+                    "{} does not meet difficulty {}",
+                    block.hash,
+                    bc.difficulty()
+                ),
+            );
+            return refuse(HttpResponse::BadRequest().body("hash does not meet difficulty"));
+        }
+        // os pagamentos do faucet saem da fila agora: outro bloco não os paga de novo
+        let coinbase = &block.transactions[0];
+        let miner_outputs = template.payees.len();
+        let payouts = coinbase.outputs.get(miner_outputs..).unwrap_or_default();
+        if !state.take_queued_payouts(payouts) {
+            let e = "coinbase pays faucet payouts that are no longer queued".to_string();
+            reject("bad_coinbase", e.clone());
+            return refuse(HttpResponse::BadRequest().body(e));
         }
         // append premined
         if let Err(e) = bc.append_premined_block(block.clone()) {
-            return HttpResponse::BadRequest().body(e);
+            state.requeue_faucet_payouts(coinbase, miner_outputs);
+            reject("invalid_block", e.clone());
+            return refuse(HttpResponse::BadRequest().body(e));
         }
-    }
 
-    // aplicar efeitos: gastar inputs, adicionar outputs, limpar mempool das txs incluídas
-    {
+        // aplicar efeitos (ainda com a chain travada): gastar inputs,
+        // adicionar outputs, limpar mempool das txs incluídas
         let included_txids: std::collections::HashSet<String> = template
             .transactions
             .iter()
             .skip(1)
             .map(|t| t.txid.clone())
             .collect();
-        let coinbase_tx = &template.transactions[0];
 
         {
             let mut utxo = state.utxo_set.lock().expect("mutex");
-            for tx in template.transactions.iter().skip(1) {
-                for input in &tx.inputs {
-                    utxo.spend(&input.outpoint);
-                }
-            }
-            for tx in template.transactions.iter().skip(1) {
-                utxo.add_tx_outputs(tx);
-            }
-            utxo.add_tx_outputs(coinbase_tx);
+            utxo.apply_block_txs(&template.transactions, template.index);
+            state.settle_faucet_payouts(
+                &template.transactions[0],
+                template.payees.len(),
+                template.index,
+                &mut utxo,
+            );
             debug!(
                 "Applied premined block to UTXO ({} txs + coinbase)",
                 included_txids.len()
@@ -279,28 +631,389 @@ pub async fn submit_solution(
         }
         {
             let mut mem = state.mempool.lock().expect("mutex");
-            mem.retain(|t| !included_txids.contains(&t.txid));
+            mem.retain(|e| !included_txids.contains(&e.tx.txid));
+            state.forget_spent_fees(&mem, &template.transactions);
         }
-    }
-
-    // info final
-    let (height, diff) = {
-        let bc = state.blockchain.lock().expect("mutex");
-        (bc.len(), bc.difficulty())
+        bc.difficulty()
     };
+    state.notify_new_tip(template.index);
 
     info!(
         "ACCEPTED template {} -> block#{} hash={} diff={}",
-        template.template_id,
-        height - 1,
-        req.hash,
-        diff
+        template.template_id, template.index, req.hash, diff
     );
 
     HttpResponse::Ok().json(SubmitResponse {
         accepted: true,
-        mined_index: Some(height as u64 - 1),
+        mined_index: Some(template.index),
         hash: Some(req.hash.clone()),
         difficulty: Some(diff),
+        checks,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::api::models::NONCE_PLACEHOLDER;
+    use crate::api::testutil::{
+        TestWallet, app, faucet, get, get_json, mine, output, post, signed_tx, state, state_with,
+        submit_tx,
+    };
+    use crate::blockchain::BASE_REWARD;
+    use crate::config::NodeConfig;
+    use crate::transaction::Transaction;
+    use actix_web::test::read_body_json;
+    use serde_json::{Value, json};
+    use sha2::{Digest, Sha256};
+
+    #[actix_web::test]
+    async fn eta_is_expected_hashes_over_hashrate() {
+        let config = NodeConfig::from_lookup(|k| (k == "INITIAL_DIFFICULTY").then(|| "2".into()));
+        let state = state_with(config);
+        let app = app(&state).await;
+
+        let body = get_json(&app, "/api/v1/mining/eta/?hashrate=64&within_secs=4").await;
+        assert_eq!(body["difficulty"], 2);
+        assert_eq!(body["expected_secs"], 256.0 / 64.0);
+        let p = body["probability_within"].as_f64().unwrap();
+        assert!((p - (1.0 - (-1.0f64).exp())).abs() < 1e-9);
+
+        let body = get_json(&app, "/api/v1/mining/eta/?hashrate=0&within_secs=60").await;
+        assert!(body["expected_secs"].is_null());
+        assert_eq!(body["probability_within"], 0.0);
+
+        let resp = get(&app, "/api/v1/mining/eta/?hashrate=-5").await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn reward_estimate_is_subsidy_plus_selected_fees() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        for (amount, fee) in [(100, 3), (200, 5)] {
+            let op = faucet(&app, &alice.address, amount).await;
+            let tx = signed_tx(&[(op, &alice)], vec![output("bob", amount - fee)]);
+            assert!(submit_tx(&app, &tx).await.status().is_success());
+        }
+
+        let estimate = get_json(&app, "/api/v1/mining/reward-estimate/?miner_address=miner").await;
+        assert_eq!(estimate["height"], 1);
+        assert_eq!(estimate["subsidy"], BASE_REWARD);
+        assert_eq!(estimate["total_fees"], 8);
+        assert_eq!(estimate["reward"], BASE_REWARD + 8);
+        assert_eq!(estimate["tx_count"], 2);
+        assert!(state.mining_templates.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn preview_matches_next_template_without_storing_it() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        for amount in [100, 200] {
+            let op = faucet(&app, &alice.address, amount).await;
+            let tx = signed_tx(&[(op, &alice)], vec![output("bob", amount - 7)]);
+            assert!(submit_tx(&app, &tx).await.status().is_success());
+        }
+
+        let preview = get_json(&app, "/api/v1/mining/preview/?miner_address=miner").await;
+        assert!(state.mining_templates.lock().unwrap().is_empty());
+        assert_eq!(preview["total_fees"], 14);
+        assert_eq!(preview["index"], 1);
+
+        let resp = post(
+            &app,
+            "/api/v1/mining/template/",
+            json!({ "miner_address": "miner" }),
+        )
+        .await;
+        let template: Value = read_body_json(resp).await;
+        let txids = |v: &Value| -> Vec<String> {
+            v["transactions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| t["txid"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(txids(&preview).len(), 3);
+        assert_eq!(txids(&preview), txids(&template));
+        assert_eq!(
+            preview["reward"],
+            template["transactions"][0]["outputs"][0]["amount"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn stale_submission_is_recorded() {
+        let state = state();
+        let app = app(&state).await;
+        state.blockchain.lock().unwrap().set_difficulty(1);
+
+        let resp = post(
+            &app,
+            "/api/v1/mining/template/",
+            json!({ "miner_address": "miner" }),
+        )
+        .await;
+        let template: Value = read_body_json(resp).await;
+        let id = template["template_id"].as_str().unwrap();
+        // someone else extends the chain first
+        mine(&app, "other").await;
+
+        let resp = post(
+            &app,
+            "/api/v1/mining/submit/",
+            json!({ "template_id": id, "nonce": 0, "hash": "00" }),
+        )
+        .await;
+        assert_eq!(resp.status(), 400);
+        // resubmitting: the template was consumed
+        post(
+            &app,
+            "/api/v1/mining/submit/",
+            json!({ "template_id": id, "nonce": 0, "hash": "00" }),
+        )
+        .await;
+
+        let body = get_json(&app, "/api/v1/mining/rejections/").await;
+        let rejections = body["rejections"].as_array().unwrap();
+        assert_eq!(rejections.len(), 2);
+        assert_eq!(rejections[0]["reason"], "unknown_template");
+        assert_eq!(rejections[1]["reason"], "stale_head");
+        assert_eq!(rejections[1]["template_id"], id);
+        assert!(rejections[1]["timestamp"].as_i64().unwrap() > 0);
+    }
+
+    #[actix_web::test]
+    async fn template_preimage_reproduces_submitted_hash() {
+        let state = state();
+        let app = app(&state).await;
+        state.blockchain.lock().unwrap().set_difficulty(1);
+
+        let resp = post(
+            &app,
+            "/api/v1/mining/template/",
+            json!({ "miner_address": "miner" }),
+        )
+        .await;
+        let template: Value = read_body_json(resp).await;
+        let id = template["template_id"].as_str().unwrap();
+
+        let body = get_json(&app, &format!("/api/v1/mining/template/{id}/preimage/")).await;
+        let preimage = body["preimage"].as_str().unwrap();
+        assert!(preimage.contains(NONCE_PLACEHOLDER));
+
+        // grind using only the preimage
+        let (nonce, hash) = (0u64..)
+            .map(|n| {
+                let h = Sha256::digest(preimage.replace(NONCE_PLACEHOLDER, &n.to_string()));
+                (n, hex::encode(h))
+            })
+            .find(|(_, h)| h.starts_with('0'))
+            .unwrap();
+
+        let resp = post(
+            &app,
+            "/api/v1/mining/submit/",
+            json!({ "template_id": id, "nonce": nonce, "hash": hash }),
+        )
+        .await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["accepted"], true);
+    }
+
+    #[actix_web::test]
+    async fn auto_mining_advances_until_stopped() {
+        let state = state();
+        let app = app(&state).await;
+        state.blockchain.lock().unwrap().set_difficulty(1);
+        let height = || state.blockchain.lock().unwrap().len();
+
+        let start = json!({ "miner_address": "auto", "pause_ms": 5 });
+        let resp = post(&app, "/api/v1/mining/auto/start/", start.clone()).await;
+        let first: Value = read_body_json(resp).await;
+        assert_eq!(first["running"], true);
+        // starting again is a no-op (still one miner, same address)
+        let other = json!({ "miner_address": "someone-else", "pause_ms": 5 });
+        let resp = post(&app, "/api/v1/mining/auto/start/", other).await;
+        let again: Value = read_body_json(resp).await;
+        assert_eq!(again["miner_address"], "auto");
+
+        for _ in 0..400 {
+            if height() >= 3 {
+                break;
+            }
+            actix_web::rt::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(height() >= 3, "only {} blocks", height());
+
+        let resp = post(&app, "/api/v1/mining/auto/stop/", json!({})).await;
+        let stopped: Value = read_body_json(resp).await;
+        assert_eq!(stopped["running"], false);
+        let at_stop = height();
+        assert_eq!(stopped["height"], at_stop);
+        assert_eq!(stopped["blocks_mined"], at_stop - 1);
+
+        actix_web::rt::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(height(), at_stop);
+        assert!(state.blockchain.lock().unwrap().is_valid_chain());
+    }
+
+    #[actix_web::test]
+    async fn verbose_submit_flags_an_inflated_coinbase() {
+        let state = state();
+        let app = app(&state).await;
+        state.blockchain.lock().unwrap().set_difficulty(1);
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+
+        let resp = post(
+            &app,
+            "/api/v1/mining/template/",
+            json!({ "miner_address": "miner" }),
+        )
+        .await;
+        let template: Value = read_body_json(resp).await;
+        let id = template["template_id"].as_str().unwrap();
+        {
+            // a miner paying itself more than reward (50) + fees (10)
+            let mut templates = state.mining_templates.lock().unwrap();
+            let t = templates.get_mut(id).unwrap();
+            let mut outputs = t.transactions[0].outputs.clone();
+            outputs[0].amount = 61;
+            t.transactions[0] = Transaction::coinbase(t.index, outputs);
+        }
+
+        let body = get_json(&app, &format!("/api/v1/mining/template/{id}/preimage/")).await;
+        let preimage = body["preimage"].as_str().unwrap();
+        let (nonce, hash) = (0u64..)
+            .map(|n| {
+                let h = Sha256::digest(preimage.replace(NONCE_PLACEHOLDER, &n.to_string()));
+                (n, hex::encode(h))
+            })
+            .find(|(_, h)| h.starts_with('0'))
+            .unwrap();
+
+        let resp = post(
+            &app,
+            "/api/v1/mining/submit/?verbose=true",
+            json!({ "template_id": id, "nonce": nonce, "hash": hash }),
+        )
+        .await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = read_body_json(resp).await;
+        let checks = &body["checks"];
+        assert_eq!(body["accepted"], false);
+        assert_eq!(checks["head_matches"], true);
+        assert_eq!(checks["hash_matches"], true);
+        assert_eq!(checks["pow_satisfied"], true);
+        assert_eq!(checks["signatures_valid"], true);
+        assert_eq!(checks["coinbase_reward_valid"], false);
+        assert_eq!(checks["failures"].as_array().unwrap().len(), 1);
+        assert_eq!(checks["transactions"][0]["txid"], tx.txid);
+        assert_eq!(checks["transactions"][0]["valid"], true);
+        assert_eq!(state.blockchain.lock().unwrap().len(), 1);
+
+        let rejections = get_json(&app, "/api/v1/mining/rejections/").await;
+        assert_eq!(rejections["rejections"][0]["reason"], "bad_coinbase");
+    }
+
+    #[actix_web::test]
+    async fn template_target_is_what_submit_enforces() {
+        let state = state();
+        let app = app(&state).await;
+        state.blockchain.lock().unwrap().set_difficulty(1);
+        let difficulty = get_json(&app, "/api/v1/difficulty/").await;
+
+        let template = |app| async move {
+            let resp = post(
+                app,
+                "/api/v1/mining/template/",
+                json!({ "miner_address": "miner" }),
+            )
+            .await;
+            read_body_json::<Value, _>(resp).await
+        };
+        // a nonce just outside the target is refused, one inside is accepted
+        for qualifies in [false, true] {
+            let t = template(&app).await;
+            assert_eq!(t["difficulty"], difficulty["difficulty"]);
+            assert_eq!(t["bits"], difficulty["bits"]);
+            assert_eq!(t["target"], t["work"]["target"]);
+            let target = t["target"].as_str().unwrap();
+            let (prefix, suffix) = (
+                t["work"]["prefix"].as_str().unwrap(),
+                t["work"]["suffix"].as_str().unwrap(),
+            );
+            let (nonce, hash) = (t["nonce_start"].as_u64().unwrap()..)
+                .map(|n| {
+                    (
+                        n,
+                        hex::encode(Sha256::digest(format!("{prefix}{n}{suffix}"))),
+                    )
+                })
+                .find(|(_, h)| (h.as_str() <= target) == qualifies)
+                .unwrap();
+            let resp = post(
+                &app,
+                "/api/v1/mining/submit/",
+                json!({ "template_id": t["template_id"], "nonce": nonce, "hash": hash }),
+            )
+            .await;
+            assert_eq!(resp.status().is_success(), qualifies, "hash {hash}");
+        }
+        let rejections = get_json(&app, "/api/v1/mining/rejections/").await;
+        assert_eq!(rejections["rejections"][0]["reason"], "below_difficulty");
+    }
+
+    #[actix_web::test]
+    async fn grinding_the_work_format_alone_is_accepted() {
+        let state = state();
+        let app = app(&state).await;
+        state.blockchain.lock().unwrap().set_difficulty(2);
+
+        let resp = post(
+            &app,
+            "/api/v1/mining/template/",
+            json!({ "miner_address": "miner" }),
+        )
+        .await;
+        let template: Value = read_body_json(resp).await;
+        let work = &template["work"];
+        let prefix = work["prefix"].as_str().unwrap();
+        let suffix = work["suffix"].as_str().unwrap();
+        let target = work["target"].as_str().unwrap();
+        assert_eq!(
+            work["nonce_offset"].as_u64().unwrap() as usize,
+            prefix.len()
+        );
+        assert_eq!(work["target_zeros"], 2);
+
+        // generic grinder: sha256(prefix + decimal nonce + suffix) <= target
+        let (nonce, hash) = (0u64..)
+            .map(|n| {
+                (
+                    n,
+                    hex::encode(Sha256::digest(format!("{prefix}{n}{suffix}"))),
+                )
+            })
+            .find(|(_, h)| h.as_str() <= target)
+            .unwrap();
+
+        let resp = post(
+            &app,
+            "/api/v1/mining/submit/",
+            json!({ "template_id": template["template_id"], "nonce": nonce, "hash": hash }),
+        )
+        .await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["accepted"], true);
+        assert_eq!(body["mined_index"], 1);
+    }
+}
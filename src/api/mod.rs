@@ -1,32 +1,254 @@
+mod admin;
 mod balance;
 mod chain;
+mod explorer;
+mod format;
 mod health;
+mod idempotency;
 mod mining;
 pub mod models;
+pub mod request_id;
 mod stats;
+#[cfg(test)]
+mod testutil;
 mod tx;
+mod utxo;
 mod wallet; // <- NEW
 
+use actix_web::dev::Server;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::middleware::from_fn;
 use actix_web::web::{self, ServiceConfig};
+use actix_web::{App, HttpResponse, HttpServer};
+use std::io;
+use std::net::ToSocketAddrs;
 
+use crate::blockchain::MAX_BLOCK_BYTES;
 pub use models::AppState;
+use models::BodyErrorResponse;
+
+/// HTTP server over `state`, bound to `addr`, with `WORKERS` worker threads.
+/// OS signals are left to the caller, which stops it through its handle.
+pub fn server(state: web::Data<AppState>, addr: impl ToSocketAddrs) -> io::Result<Server> {
+    let grace = state.config.shutdown_grace_secs;
+    let workers = state.config.workers;
+    let mut server = HttpServer::new(move || {
+        App::new()
+            .app_data(state.clone())
+            .app_data(json_config(state.config.json_max_bytes))
+            .configure(init_routes)
+    })
+    .disable_signals()
+    .shutdown_timeout(grace);
+    if let Some(n) = workers {
+        server = server.workers(n);
+    }
+    Ok(server.bind(addr)?.run())
+}
+
+/// Body limit for routes that take a whole block (`/block/hash/`): up to
+/// `MAX_BLOCK_BYTES` of transactions plus the JSON around them, regardless of
+/// `JSON_MAX_BYTES` (which sizes everything else, i.e. transaction bodies).
+pub const BLOCK_JSON_MAX_BYTES: usize = 4 * MAX_BLOCK_BYTES;
+
+/// `web::Json` extractor settings: bodies over `max_bytes` get a structured
+/// `413` (instead of actix's plain-text 400/413), a non-JSON `Content-Type` a `415`.
+pub fn json_config(max_bytes: usize) -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(max_bytes)
+        .error_handler(move |err, _req| {
+            let (mut resp, code, limit) = match &err {
+                JsonPayloadError::OverflowKnownLength { .. }
+                | JsonPayloadError::Overflow { .. } => (
+                    HttpResponse::PayloadTooLarge(),
+                    "payload_too_large",
+                    Some(max_bytes),
+                ),
+                JsonPayloadError::ContentType => (
+                    HttpResponse::UnsupportedMediaType(),
+                    "unsupported_content_type",
+                    None,
+                ),
+                _ => (HttpResponse::BadRequest(), "invalid_json", None),
+            };
+            let body = resp.json(BodyErrorResponse {
+                code,
+                message: err.to_string(),
+                limit,
+            });
+            InternalError::from_response(err, body).into()
+        })
+}
 
 pub fn init_routes(cfg: &mut ServiceConfig) {
     cfg.service(
         web::scope("/api/v1")
+            .wrap(from_fn(request_id::assign))
             .service(health::health_check)
+            .service(health::readiness_check)
             .service(chain::get_chain)
+            .service(chain::export_chain_ndjson)
+            .service(chain::import_chain_ndjson)
+            .service(chain::get_tip)
+            .service(chain::get_blocks_by_time)
             .service(chain::validate_chain)
+            .service(chain::get_checkpoints)
+            .service(chain::validate_chain_full_replay)
             .service(chain::mine_block)
+            .service(chain::get_block_preimage)
+            .service(chain::get_merkle_proof)
+            .service(chain::get_latest_block)
+            .service(chain::get_block)
+            .service(chain::get_block_txs)
+            .service(chain::get_block_effects)
+            .service(
+                web::resource("/block/hash/")
+                    .app_data(json_config(BLOCK_JSON_MAX_BYTES))
+                    .route(web::post().to(chain::post_block_hash)),
+            )
             .service(chain::get_difficulty)
             .service(chain::set_difficulty)
             .service(tx::post_faucet)
             .service(tx::post_transaction)
+            .service(tx::post_raw_transaction)
+            .service(tx::post_tx_size)
+            .service(tx::post_tx_and_mine)
+            .service(tx::validate_tx)
+            .service(tx::post_sighash)
+            .service(tx::post_tx_inputs)
+            .service(tx::get_confirmations)
+            .service(tx::get_tx_outputs)
+            .service(tx::get_tx_block)
             .service(tx::get_mempool)
+            .service(tx::get_next_block)
+            .service(tx::get_mempool_fee_rates)
+            .service(tx::get_mempool_package)
+            .service(tx::get_mempool_conflicts)
             .service(balance::get_balance)
+            .service(balance::get_detailed_balance)
+            .service(balance::get_accounts)
             .service(stats::get_stats)
+            .service(stats::get_hashrate)
+            .service(stats::get_capacity)
+            .service(explorer::get_explorer_summary)
+            .service(explorer::get_miner_blocks)
+            .service(utxo::get_utxo_summary)
+            .service(utxo::get_supply)
+            .service(utxo::export_utxo_set)
+            .service(
+                web::resource("/utxo/import/")
+                    .app_data(json_config(utxo::IMPORT_JSON_MAX_BYTES))
+                    .route(web::post().to(utxo::import_utxo_set)),
+            )
+            .service(utxo::get_utxo)
             .service(wallet::create_wallet)
+            .service(admin::rebuild_utxo_set)
+            .service(admin::rotate_admin_token)
             .service(mining::get_template) // <- add
+            .service(mining::get_template_preimage)
+            .service(mining::get_preview)
+            .service(mining::get_reward_estimate)
+            .service(mining::get_mining_eta)
+            .service(mining::get_rejections)
+            .service(mining::start_auto_mining)
+            .service(mining::stop_auto_mining)
             .service(mining::submit_solution), // <- add
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::testutil::{app, post, state_with};
+    use crate::config::NodeConfig;
+    use actix_web::test;
+    use serde_json::{Value, json};
+
+    #[actix_web::test]
+    async fn oversized_json_body_gets_a_structured_413() {
+        let state = state_with(NodeConfig {
+            json_max_bytes: 1024,
+            ..NodeConfig::default()
+        });
+        let app = app(&state).await;
+
+        let huge = json!({ "address": "a".repeat(4096), "amount": 1 });
+        let req = test::TestRequest::post()
+            .uri("/api/v1/faucet/")
+            .set_json(huge)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 413);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "payload_too_large");
+        assert_eq!(body["limit"], 1024);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/faucet/")
+            .insert_header(("content-type", "text/plain"))
+            .set_payload(r#"{"address":"a","amount":1}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 415);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "unsupported_content_type");
+    }
+
+    #[actix_web::test]
+    async fn block_routes_get_their_own_body_limit() {
+        let state = state_with(NodeConfig {
+            json_max_bytes: 1024,
+            ..NodeConfig::default()
+        });
+        let app = app(&state).await;
+        let mut block = state.blockchain.lock().unwrap().chain[0].clone();
+
+        // over JSON_MAX_BYTES, but a block route only answers to its own limit
+        block.previous_hash = "0".repeat(4096);
+        let resp = post(&app, "/api/v1/block/hash/", json!(block)).await;
+        assert_eq!(resp.status(), 200);
+
+        block.previous_hash = "0".repeat(super::BLOCK_JSON_MAX_BYTES);
+        let resp = post(&app, "/api/v1/block/hash/", json!(block)).await;
+        assert_eq!(resp.status(), 413);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "payload_too_large");
+        assert_eq!(body["limit"], super::BLOCK_JSON_MAX_BYTES);
+    }
+
+    /// Live threads whose name starts with `prefix` (names are cut at 15 bytes).
+    #[cfg(target_os = "linux")]
+    fn threads_named(prefix: &str) -> usize {
+        std::fs::read_dir("/proc/self/task")
+            .unwrap()
+            .filter_map(|t| std::fs::read_to_string(t.ok()?.path().join("comm")).ok())
+            .filter(|name| name.starts_with(prefix))
+            .count()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[actix_web::test]
+    async fn server_starts_configured_workers() {
+        let state = state_with(NodeConfig {
+            workers: Some(3),
+            ..NodeConfig::default()
+        });
+        let server = super::server(state, ("127.0.0.1", 0)).unwrap();
+        let handle = server.handle();
+        let task = actix_web::rt::spawn(server);
+
+        // inside an actix System each worker runs as an arbiter thread; tests
+        // running in parallel may own some too, so only a floor is checked
+        let mut workers = 0;
+        for _ in 0..40 {
+            workers = threads_named("actix-rt|system");
+            if workers >= 3 {
+                break;
+            }
+            actix_web::rt::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert!(workers >= 3, "only {workers} worker thread(s) running");
+
+        handle.stop(false).await;
+        let _ = task.await;
+    }
+}
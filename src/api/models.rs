@@ -1,8 +1,23 @@
-use crate::blockchain::Blockchain;
-use crate::transaction::{Transaction, UtxoSet};
+use super::tx::{check_maturity, validate_transaction};
+use crate::blockchain::{
+    BASE_REWARD, BadSignature, Block, Blockchain, CoinbaseShare, FeeCache, check_shares,
+    split_reward,
+};
+use crate::config::NodeConfig;
+use crate::storage::Store;
+use crate::transaction::{
+    Conflict, OutPoint, Transaction, TxOutput, UtxoEntry, UtxoSet, UtxoSource,
+};
+use crate::wallet::SigCache;
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Mutex;
+use std::borrow::{Borrow, Cow};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, broadcast};
 
 #[derive(Clone)]
 pub struct MiningTemplate {
@@ -11,34 +26,605 @@ pub struct MiningTemplate {
     pub previous_hash: String,
     pub timestamp: i64,
     pub difficulty: u32,
-    pub miner_address: String,
+    /// who the coinbase pays (its first `payees.len()` outputs)
+    pub payees: Vec<CoinbaseShare>,
     pub transactions: Vec<crate::transaction::Transaction>, // coinbase first
 }
+impl MiningTemplate {
+    /// The block this template describes, with nonce 0 (set it before hashing).
+    pub fn to_block(&self) -> crate::blockchain::Block {
+        let mut block = crate::blockchain::Block::new_with_timestamp(
+            self.index,
+            self.previous_hash.clone(),
+            self.transactions.clone(),
+            self.timestamp,
+        );
+        block.difficulty = self.difficulty;
+        block.hash = block.compute_hash();
+        block
+    }
+}
+
+/// How many recent `/mining/submit/` rejections are kept for diagnostics.
+pub const MINING_REJECTIONS_MAX: usize = 100;
+
+/// A refused `/mining/submit/` solution.
+#[derive(Debug, Clone, Serialize)]
+pub struct MiningRejection {
+    pub template_id: String,
+    /// `unknown_template`, `stale_head`, `hash_mismatch`, `bad_signature`,
+    /// `bad_coinbase`, `below_difficulty` or `invalid_block`
+    pub reason: &'static str,
+    pub detail: String,
+    pub timestamp: i64,
+}
+
+/// Pause between blocks when `/mining/auto/start/` doesn't give one.
+pub const AUTO_MINE_PAUSE_MS: u64 = 1000;
+
+/// Shared between the auto-miner loop and the endpoints controlling it.
+#[derive(Default)]
+pub struct AutoMinerControl {
+    /// cleared by `/mining/auto/stop/`; the loop exits at its next check
+    pub running: AtomicBool,
+    /// cuts the pause between blocks short on stop
+    pub stop: Notify,
+    pub blocks_mined: AtomicU64,
+}
+
+/// Background loop started by `/mining/auto/start/`.
+pub struct AutoMiner {
+    pub miner_address: String,
+    pub control: Arc<AutoMinerControl>,
+    pub handle: actix_web::rt::task::JoinHandle<()>,
+}
+
+/// A mempool transaction and when this node first saw it.
+#[derive(Debug, Clone)]
+pub struct MempoolEntry {
+    pub tx: Transaction,
+    /// Unix timestamp (chain clock) of admission; restart time for restored txs.
+    pub received_at: i64,
+}
+
+impl MempoolEntry {
+    pub fn new(tx: Transaction, received_at: i64) -> Self {
+        Self { tx, received_at }
+    }
+}
+
+impl Borrow<Transaction> for MempoolEntry {
+    fn borrow(&self) -> &Transaction {
+        &self.tx
+    }
+}
+
 /// Shared application state with an in-memory blockchain, mempool and UTXO set.
 pub struct AppState {
+    pub config: NodeConfig,
     pub blockchain: Mutex<Blockchain>,
-    pub mempool: Mutex<Vec<Transaction>>,
+    pub mempool: Mutex<Vec<MempoolEntry>>,
+    /// Fee/size of each mempool tx as computed on admission (lock after the mempool).
+    pub fee_cache: Mutex<FeeCache>,
     pub utxo_set: Mutex<UtxoSet>,
     pub mining_templates: Mutex<HashMap<String, MiningTemplate>>,
+    /// Fires the new tip index every time a block is appended (long-poll wakeups).
+    pub new_tip: broadcast::Sender<u64>,
+    /// Cached responses for `Idempotency-Key` retries.
+    pub idempotency: super::idempotency::IdempotencyCache,
+    /// Snapshot store when `DATA_DIR` is set.
+    pub store: Option<Store>,
+    /// Lowest height where a peer's chain was seen diverging from ours.
+    pub fork_detected_at: Mutex<Option<u64>>,
+    /// Amount minted by `/faucet/` per address (for `FAUCET_MAX_PER_ADDRESS`).
+    pub faucet_totals: Mutex<HashMap<String, u64>>,
+    /// Every output `/faucet/` created, spent or not: the off-chain part of a
+    /// full chain replay. With `FAUCET_MODE=coinbase` these are coinbase
+    /// outputs (`is_coinbase`), which the replay counts on top of the reward.
+    pub faucet_outputs: Mutex<UtxoSet>,
+    /// `FAUCET_MODE=coinbase` payouts waiting for the next block's coinbase, oldest first.
+    pub faucet_queue: Mutex<VecDeque<TxOutput>>,
+    /// Last `MINING_REJECTIONS_MAX` refused submissions, oldest first.
+    pub mining_rejections: Mutex<VecDeque<MiningRejection>>,
+    /// Signature checks already done (mempool entry, block submit, full replay).
+    pub sig_cache: SigCache,
+    /// The continuous miner, while `/mining/auto/start/` has it running.
+    pub auto_miner: Mutex<Option<AutoMiner>>,
+    /// Bearer token for admin endpoints: `ADMIN_TOKEN` until rotated with
+    /// `/admin/rotate-token/` (in memory only; a restart goes back to the env).
+    pub admin_token: Mutex<Option<String>>,
+    /// Held for a whole mining run (tx selection through UTXO apply), so
+    /// the auto-miner, `/mine/`, `/tx/and-mine/` and template submits never
+    /// build on the same tip or spend the same mempool txs twice.
+    pub mining: tokio::sync::Mutex<()>,
+    /// `/mine/` calls currently doing PoW (drained on shutdown).
+    mining_in_flight: AtomicUsize,
+    shutting_down: AtomicBool,
+}
+
+/// Genesis-only chain using the configured PoW settings.
+fn fresh_chain(config: &NodeConfig) -> Blockchain {
+    let mut bc = Blockchain::new(config.initial_difficulty);
+    bc.pow_algo = config.pow_algo;
+    bc.target_block_secs = config.target_block_secs;
+    bc.bounds = config.difficulty_bounds;
+    bc.max_reorg_depth = config.max_reorg_depth;
+    bc.prune_depth = config.prune_depth;
+    bc.checkpoints = config.checkpoints.clone();
+    bc
+}
+
+/// Reload the saved mempool, keeping only txs still valid against `utxo`
+/// and spending only coinbase outputs mature for a block at `spend_height`.
+/// A missing or unreadable file just means starting with an empty mempool.
+fn restore_mempool(
+    store: &Store,
+    utxo: &UtxoSet,
+    chain_id: &str,
+    sigs: Option<&SigCache>,
+    spend_height: u64,
+    maturity: u64,
+) -> Vec<Transaction> {
+    let saved = match store.load_mempool() {
+        Ok(Some(txs)) => txs,
+        Ok(None) => return Vec::new(),
+        Err(e) => {
+            warn!("failed to load mempool from {}: {e}", store.dir().display());
+            return Vec::new();
+        }
+    };
+    let total = saved.len();
+    let kept: Vec<Transaction> = saved
+        .into_iter()
+        .filter(|tx| {
+            match validate_transaction(tx, utxo, chain_id, sigs)
+                .and_then(|_| check_maturity(tx, utxo, spend_height, maturity))
+            {
+                Ok(()) => true,
+                Err(err) => {
+                    info!("mempool: dropping txid={} on restore: {err}", tx.txid);
+                    false
+                }
+            }
+        })
+        .collect();
+    info!(
+        "mempool: restored {} transaction(s), dropped {}",
+        kept.len(),
+        total - kept.len()
+    );
+    kept
+}
+
+/// Marks one in-flight mining job; released on drop.
+pub struct MiningGuard<'a>(&'a AtomicUsize);
+
+impl Drop for MiningGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl Default for AppState {
     fn default() -> Self {
-        use crate::blockchain::DEFAULT_DIFFICULTY;
+        Self::with_config(NodeConfig::from_env())
+    }
+}
+
+impl AppState {
+    pub fn with_config(config: NodeConfig) -> Self {
+        let mut blockchain = fresh_chain(&config);
+        let mut utxo_set = UtxoSet::new();
+        let mut faucet_outputs = UtxoSet::new();
+
+        let mut store = config.data_dir.clone().map(Store::new);
+        if let Some(st) = &store {
+            let loaded = st
+                .load_chain(&mut blockchain)
+                .and_then(|has_chain| Ok((has_chain, st.load_utxo()?, st.load_faucet()?)));
+            match loaded {
+                Ok((has_chain, utxo, faucet)) => {
+                    if let Some(utxo) = utxo {
+                        utxo_set = utxo;
+                    }
+                    if let Some(faucet) = faucet {
+                        faucet_outputs = faucet;
+                    }
+                    if has_chain {
+                        info!(
+                            "restored {} blocks and {} UTXOs from {}",
+                            blockchain.len(),
+                            utxo_set.len(),
+                            st.dir().display()
+                        );
+                        // PRUNE_DEPTH may have been set since the last run
+                        blockchain.prune();
+                    }
+                }
+                Err(e) => {
+                    // don't overwrite data we failed to read
+                    error!(
+                        "failed to load state from {}: {e}; persistence disabled",
+                        st.dir().display()
+                    );
+                    blockchain = fresh_chain(&config);
+                    utxo_set = UtxoSet::new();
+                    faucet_outputs = UtxoSet::new();
+                    store = None;
+                }
+            }
+        }
+
+        let sig_cache = SigCache::new(config.sig_cache_size);
+        let mempool = match &store {
+            Some(st) if config.persist_mempool => {
+                let sigs = config.require_signatures.then_some(&sig_cache);
+                let now = blockchain.now_timestamp();
+                let spend_height = blockchain.len() as u64;
+                restore_mempool(
+                    st,
+                    &utxo_set,
+                    &config.chain_id,
+                    sigs,
+                    spend_height,
+                    config.coinbase_maturity,
+                )
+                .into_iter()
+                .map(|tx| MempoolEntry::new(tx, now))
+                .collect()
+            }
+            _ => Vec::new(),
+        };
+
         Self {
-            blockchain: Mutex::new(Blockchain::new(DEFAULT_DIFFICULTY)),
-            mempool: Mutex::new(Vec::new()),
-            utxo_set: Mutex::new(UtxoSet::new()),
+            admin_token: Mutex::new(config.admin_token.clone()),
+            config,
+            blockchain: Mutex::new(blockchain),
+            mempool: Mutex::new(mempool),
+            fee_cache: Mutex::new(FeeCache::default()),
+            utxo_set: Mutex::new(utxo_set),
             mining_templates: Mutex::new(HashMap::new()),
+            new_tip: broadcast::channel(16).0,
+            idempotency: Default::default(),
+            store,
+            fork_detected_at: Mutex::new(None),
+            faucet_totals: Mutex::new(HashMap::new()),
+            faucet_outputs: Mutex::new(faucet_outputs),
+            faucet_queue: Mutex::new(VecDeque::new()),
+            mining_rejections: Mutex::new(VecDeque::with_capacity(MINING_REJECTIONS_MAX)),
+            sig_cache,
+            auto_miner: Mutex::new(None),
+            mining: tokio::sync::Mutex::new(()),
+            mining_in_flight: AtomicUsize::new(0),
+            shutting_down: AtomicBool::new(false),
+        }
+    }
+
+    /// The signature cache to check txs with, or `None` with
+    /// `REQUIRE_SIGNATURES=false` (ownership and signatures are skipped).
+    pub fn signature_checks(&self) -> Option<&SigCache> {
+        self.config.require_signatures.then_some(&self.sig_cache)
+    }
+
+    /// Batch-verify every signature in `block` (always passes with
+    /// `REQUIRE_SIGNATURES=false`).
+    pub fn verify_block_signatures(&self, block: &Block) -> Result<(), BadSignature> {
+        match self.signature_checks() {
+            Some(sigs) => block.verify_signatures_cached(&self.config.chain_id, sigs),
+            None => Ok(()),
+        }
+    }
+
+    /// Register an in-flight mining job. `None` once shutdown has started.
+    pub fn begin_mining(&self) -> Option<MiningGuard<'_>> {
+        self.mining_in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = MiningGuard(&self.mining_in_flight);
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return None; // guard dropped: counter restored
+        }
+        Some(guard)
+    }
+
+    /// After the UTXO set was swapped under it (a reorg): drop the mempool
+    /// txs it no longer backs (or whose coinbase inputs aren't mature for a
+    /// block at `spend_height` any more) and the fees cached against the old
+    /// set. Lock order: mempool -> fee cache.
+    pub fn revalidate_mempool(&self, utxo: &UtxoSet, spend_height: u64) {
+        let chain_id = &self.config.chain_id;
+        let sigs = self.signature_checks();
+        let maturity = self.config.coinbase_maturity;
+        let mut mempool = self.mempool.lock().expect("mutex poisoned");
+        mempool.retain(|e| {
+            match validate_transaction(&e.tx, utxo, chain_id, sigs)
+                .and_then(|_| check_maturity(&e.tx, utxo, spend_height, maturity))
+            {
+                Ok(()) => true,
+                Err(err) => {
+                    info!("mempool: dropping txid={} after reorg: {err}", e.tx.txid);
+                    false
+                }
+            }
+        });
+        self.fee_cache.lock().expect("mutex poisoned").clear();
+    }
+
+    /// Whether `shutdown` has started (background jobs stop then).
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Coinbase of the block at `height`: `BASE_REWARD` + `total_fees` split
+    /// across `payees` (one output each), then every queued faucet payout.
+    /// A preview (templates, `/mining/preview/`): the queue is left as is.
+    pub fn coinbase_tx(
+        &self,
+        height: u64,
+        payees: &[CoinbaseShare],
+        total_fees: u128,
+    ) -> Transaction {
+        let queue = self.faucet_queue.lock().expect("mutex poisoned");
+        let payouts: Vec<TxOutput> = queue.iter().cloned().collect();
+        coinbase_paying(height, payees, total_fees, payouts)
+    }
+
+    /// Like `coinbase_tx`, for a block about to be mined: the queued faucet
+    /// payouts are taken out of the queue, so no other block can pay them
+    /// too. Call with the chain lock held; hand the coinbase to
+    /// `requeue_faucet_payouts` if the block doesn't make it onto the chain.
+    pub fn take_coinbase_tx(
+        &self,
+        height: u64,
+        payees: &[CoinbaseShare],
+        total_fees: u128,
+    ) -> Transaction {
+        let payouts: Vec<TxOutput> = self
+            .faucet_queue
+            .lock()
+            .expect("mutex poisoned")
+            .drain(..)
+            .collect();
+        coinbase_paying(height, payees, total_fees, payouts)
+    }
+
+    /// Take `payouts` (a submitted coinbase's faucet outputs) off the front
+    /// of the queue. `false`, leaving the queue untouched, unless they are
+    /// exactly its next entries.
+    pub fn take_queued_payouts(&self, payouts: &[TxOutput]) -> bool {
+        let mut queue = self.faucet_queue.lock().expect("mutex poisoned");
+        if payouts.len() > queue.len() || !payouts.iter().zip(queue.iter()).all(|(a, b)| a == b) {
+            return false;
+        }
+        queue.drain(..payouts.len());
+        true
+    }
+
+    /// Put the faucet payouts of a coinbase that was never mined (its first
+    /// `miner_outputs` pay the miners) back at the front of the queue.
+    pub fn requeue_faucet_payouts(&self, coinbase: &Transaction, miner_outputs: usize) {
+        let mut queue = self.faucet_queue.lock().expect("mutex poisoned");
+        for out in coinbase.outputs.iter().skip(miner_outputs).rev() {
+            queue.push_front(out.clone());
+        }
+    }
+
+    /// Copy of the pending transactions (without their arrival times).
+    pub fn mempool_txs(&self) -> Vec<Transaction> {
+        let mempool = self.mempool.lock().expect("mutex poisoned");
+        mempool.iter().map(|e| e.tx.clone()).collect()
+    }
+
+    /// After a block with `block_txs` landed and `mempool` was cleaned up:
+    /// drop the cached fees of txs that left the mempool or whose inputs the
+    /// block spent. Lock order: mempool -> fee cache.
+    pub fn forget_spent_fees(&self, mempool: &[MempoolEntry], block_txs: &[Transaction]) {
+        let spent: HashSet<&OutPoint> = block_txs
+            .iter()
+            .flat_map(|t| t.inputs.iter().map(|i| &i.outpoint))
+            .collect();
+        self.fee_cache
+            .lock()
+            .expect("mutex poisoned")
+            .invalidate(mempool.iter().map(|e| &e.tx), &spent);
+    }
+
+    /// After a block at `height` was appended and applied to `utxo`: record
+    /// the faucet payouts its `coinbase` paid (every output after the first
+    /// `miner_outputs`, already taken from the queue by `take_coinbase_tx` or
+    /// `take_queued_payouts`) in the faucet ledger. With
+    /// `FAUCET_MATURITY_EXEMPT` they are spendable right away (the miners'
+    /// own outputs still mature normally). Lock order: UTXO -> faucet ledger.
+    pub fn settle_faucet_payouts(
+        &self,
+        coinbase: &Transaction,
+        miner_outputs: usize,
+        height: u64,
+        utxo: &mut UtxoSet,
+    ) {
+        let exempt = self.config.faucet.maturity_exempt;
+        let mut ledger = self.faucet_outputs.lock().expect("mutex poisoned");
+        // payouts always sit after the miners' outputs, in queue order
+        for (vout, out) in coinbase.outputs.iter().enumerate().skip(miner_outputs) {
+            let outpoint = OutPoint {
+                txid: coinbase.txid.clone(),
+                vout: vout as u32,
+            };
+            utxo.mark_faucet(&outpoint);
+            if exempt {
+                utxo.exempt_from_maturity(&outpoint);
+            }
+            ledger.insert(
+                outpoint,
+                UtxoEntry {
+                    output: out.clone(),
+                    height,
+                    is_coinbase: true,
+                    source: UtxoSource::Faucet,
+                    maturity_exempt: exempt,
+                },
+            );
+        }
+    }
+
+    /// Write chain + UTXO + faucet ledger (and, with `PERSIST_MEMPOOL`, mempool) snapshots.
+    /// No-op without `DATA_DIR`.
+    pub fn flush(&self) -> io::Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        {
+            let bc = self.blockchain.lock().expect("mutex poisoned");
+            let utxo = self.utxo_set.lock().expect("mutex poisoned");
+            store.save_chain(&bc)?;
+            store.save_utxo(&utxo)?;
+        }
+        store.save_faucet(&self.faucet_outputs.lock().expect("mutex poisoned"))?;
+        if self.config.persist_mempool {
+            store.save_mempool(&self.mempool_txs())?;
+        }
+        Ok(())
+    }
+
+    /// Refuse new mining, wait up to `grace` for in-flight mining to finish,
+    /// then flush state to disk.
+    pub async fn shutdown(&self, grace: Duration) -> io::Result<()> {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        let deadline = Instant::now() + grace;
+        loop {
+            let in_flight = self.mining_in_flight.load(Ordering::SeqCst);
+            if in_flight == 0 {
+                info!("shutdown: no mining in flight");
+                break;
+            }
+            if Instant::now() >= deadline {
+                warn!("shutdown: grace period over with {in_flight} mining job(s) still running");
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        match &self.store {
+            Some(store) => {
+                self.flush()?;
+                info!("shutdown: state flushed to {}", store.dir().display());
+            }
+            None => info!("shutdown: persistence disabled (no DATA_DIR), nothing to flush"),
+        }
+        Ok(())
+    }
+
+    /// Remember a refused mining submission, dropping the oldest when full.
+    pub fn record_mining_rejection(&self, template_id: &str, reason: &'static str, detail: String) {
+        let mut log = self.mining_rejections.lock().expect("mutex poisoned");
+        if log.len() == MINING_REJECTIONS_MAX {
+            log.pop_front();
         }
+        log.push_back(MiningRejection {
+            template_id: template_id.to_string(),
+            reason,
+            detail,
+            timestamp: chrono::Utc::now().timestamp(),
+        });
+    }
+
+    /// Wake everyone waiting on `/chain/tip/`. No receivers is fine.
+    pub fn notify_new_tip(&self, index: u64) {
+        let _ = self.new_tip.send(index);
     }
 }
 
 /* ---------- Mining API Models ---------- */
 
+/// Who a mined block pays: `miner_address`, or `payouts` to split the
+/// reward by weight (pools). Exactly one of the two.
 #[derive(Deserialize)]
 pub struct TemplateRequest {
+    #[serde(default)]
+    pub miner_address: String,
+    #[serde(default)]
+    pub payouts: Vec<CoinbaseShare>,
+}
+
+impl TemplateRequest {
+    pub fn payees(&self) -> Result<Vec<CoinbaseShare>, &'static str> {
+        coinbase_payees(&self.miner_address, &self.payouts)
+    }
+}
+
+/// Coinbase paying `BASE_REWARD` + `total_fees` split across `payees`, then
+/// the given faucet `payouts`.
+fn coinbase_paying(
+    height: u64,
+    payees: &[CoinbaseShare],
+    total_fees: u128,
+    payouts: Vec<TxOutput>,
+) -> Transaction {
+    // fees past what a u64 output can carry are forfeited, never wrapped
+    let amount = u64::try_from(total_fees)
+        .ok()
+        .and_then(|fees| BASE_REWARD.checked_add(fees))
+        .unwrap_or(u64::MAX);
+    let mut outputs = split_reward(amount, payees);
+    outputs.extend(payouts);
+    Transaction::coinbase(height, outputs)
+}
+
+/// The coinbase payees a `miner_address`/`payouts` request asks for.
+pub fn coinbase_payees(
+    miner_address: &str,
+    payouts: &[CoinbaseShare],
+) -> Result<Vec<CoinbaseShare>, &'static str> {
+    let miner_address = miner_address.trim();
+    match (miner_address.is_empty(), payouts.is_empty()) {
+        (true, true) => Err("miner_address required"),
+        (false, false) => Err("give either miner_address or payouts, not both"),
+        (false, true) => Ok(vec![CoinbaseShare::solo(miner_address)]),
+        (true, false) => {
+            check_shares(payouts)?;
+            // zero-weight shares get no coinbase output (see `split_reward`)
+            Ok(payouts
+                .iter()
+                .filter(|s| s.weight > 0)
+                .map(|s| CoinbaseShare {
+                    address: s.address.trim().to_string(),
+                    weight: s.weight,
+                })
+                .collect())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PreviewQuery {
+    pub miner_address: String,
+}
+
+/// What the next block would contain right now (nothing is stored).
+#[derive(Serialize)]
+pub struct PreviewResponse {
+    pub index: u64,
+    pub difficulty: u32,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub total_fees: u128,
+    /// coinbase payout: BASE_REWARD + total_fees
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub reward: u128,
+    pub transactions: Vec<crate::transaction::Transaction>, // coinbase first
+}
+
+/// `GET /mining/reward-estimate/`: what mining the next block would pay now.
+#[derive(Serialize)]
+pub struct RewardEstimateResponse {
     pub miner_address: String,
+    /// height of the block being estimated
+    pub height: u64,
+    pub subsidy: u64,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub total_fees: u128,
+    /// coinbase payout: subsidy + total_fees
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub reward: u128,
+    /// mempool txs the selection would include
+    pub tx_count: usize,
 }
 
 #[derive(Serialize)]
@@ -48,9 +634,63 @@ pub struct TemplateResponse {
     pub previous_hash: String,
     pub timestamp: i64,
     pub difficulty: u32,
+    /// Digest the nonce must satisfy (`sha256` or `scrypt`).
+    pub pow_algo: crate::blockchain::PowAlgo,
+    /// First nonce to try; any `u64` from here up is a valid search space.
+    pub nonce_start: u64,
+    /// 64 hex chars: a digest qualifies iff it is `<=` this (same as `work.target`,
+    /// and exactly what `/mining/submit/` enforces).
+    pub target: String,
+    /// `target` in compact form
+    pub bits: String,
+    /// Everything needed to grind without rebuilding the block preimage.
+    pub work: MiningWork,
     pub transactions: Vec<crate::transaction::Transaction>, // coinbase first
 }
 
+/// "getwork"-style description of a template: hash `prefix + nonce + suffix`
+/// (nonce in decimal ASCII, inserted at byte `nonce_offset`) with `pow_algo`
+/// and accept when the hex digest is `<= target` (same as `target_zeros`
+/// leading hex zeros).
+#[derive(Serialize)]
+pub struct MiningWork {
+    pub prefix: String,
+    pub suffix: String,
+    pub nonce_offset: usize,
+    pub nonce_encoding: &'static str,
+    pub target_zeros: u32,
+    /// 64 hex chars: `target_zeros` zeros followed by `f`s
+    pub target: String,
+    /// `target` in compact form
+    pub bits: String,
+    pub pow_algo: crate::blockchain::PowAlgo,
+}
+
+impl MiningWork {
+    pub fn for_block(
+        block: &crate::blockchain::Block,
+        pow_algo: crate::blockchain::PowAlgo,
+    ) -> Self {
+        let (prefix, suffix) = block.preimage_parts();
+        Self {
+            nonce_offset: prefix.len(),
+            prefix,
+            suffix,
+            nonce_encoding: "decimal",
+            target_zeros: block.difficulty,
+            target: hex::encode(crate::blockchain::difficulty_target(block.difficulty)),
+            bits: crate::blockchain::difficulty_bits(block.difficulty),
+            pow_algo,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MiningRejectionsResponse {
+    /// newest first
+    pub rejections: Vec<MiningRejection>,
+}
+
 #[derive(Deserialize)]
 pub struct SubmitRequest {
     pub template_id: String,
@@ -58,21 +698,285 @@ pub struct SubmitRequest {
     pub hash: String,
 }
 
+#[derive(Deserialize)]
+pub struct SubmitQuery {
+    /// include the full `SubmitChecks` breakdown in the response
+    #[serde(default)]
+    pub verbose: bool,
+}
+
+#[derive(Deserialize)]
+pub struct AutoMineRequest {
+    pub miner_address: String,
+    /// pause between blocks (default `AUTO_MINE_PAUSE_MS`)
+    pub pause_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct AutoMineResponse {
+    pub running: bool,
+    /// address the (last) auto-miner pays
+    pub miner_address: Option<String>,
+    /// blocks mined by the (last) auto-miner
+    pub blocks_mined: u64,
+    pub height: u64,
+}
+
 #[derive(Serialize)]
 pub struct SubmitResponse {
     pub accepted: bool,
     pub mined_index: Option<u64>,
     pub hash: Option<String>,
     pub difficulty: Option<u32>,
+    /// `?verbose=true` only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<SubmitChecks>,
+}
+
+/// Every check `/mining/submit/` applies to a solution, all evaluated (not
+/// just up to the first failure), for miner developers debugging a refusal.
+#[derive(Clone, Serialize)]
+pub struct SubmitChecks {
+    /// the template still builds on the current tip
+    pub head_matches: bool,
+    /// the submitted `hash` is what the nonce actually hashes to
+    pub hash_matches: bool,
+    /// the block meets the current difficulty
+    pub pow_satisfied: bool,
+    pub signatures_valid: bool,
+    /// the miner's coinbase output is at most reward + fees, and any other
+    /// output is a queued faucet payout
+    pub coinbase_reward_valid: bool,
+    /// why the failing checks failed, in the order above
+    pub failures: Vec<String>,
+    /// each non-coinbase tx against the current UTXO set
+    pub transactions: Vec<SubmitTxCheck>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SubmitTxCheck {
+    pub txid: String,
+    pub valid: bool,
+    /// `TxError` codes
+    pub errors: Vec<&'static str>,
+}
+
+/// Placeholder marking where the nonce goes in a template preimage.
+pub const NONCE_PLACEHOLDER: &str = "{nonce}";
+
+#[derive(Serialize)]
+pub struct BlockHashResponse {
+    /// `compute_hash()` of the posted block (its own `hash` field is ignored)
+    pub hash: String,
+    /// whether that block meets the node's current difficulty
+    pub valid_for_difficulty: bool,
+    pub difficulty: u32,
+}
+
+#[derive(Serialize)]
+pub struct PreimageResponse {
+    /// exact string hashed by `compute_hash` (templates: nonce is `{nonce}`)
+    pub preimage: String,
+    /// stored block hash (absent for templates)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MerkleProofResponse {
+    pub block_index: u64,
+    pub block_hash: String,
+    pub merkle_root: String,
+    pub txid: String,
+    /// siblings from the leaf up; `side` says where each goes when hashing
+    pub proof: Vec<crate::blockchain::MerkleStep>,
+}
+
+/// `GET /block/latest/`: the tip block plus its confirmations.
+#[derive(Serialize)]
+pub struct LatestBlockResponse<'a> {
+    #[serde(flatten)]
+    pub block: BlockView<'a>,
+    /// always 1: nothing has been built on the tip yet
+    pub confirmations: u64,
+    /// transactions in this block and all before it
+    pub cumulative_tx_count: u64,
+}
+
+/// `GET /block/{id}/txs/`
+#[derive(Serialize)]
+pub struct BlockTxsResponse<'a> {
+    pub index: u64,
+    pub hash: &'a str,
+    /// null for the genesis block, which has no transactions
+    pub coinbase_txid: Option<&'a str>,
+    pub transactions: Vec<BlockTxWithFee<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct BlockTxWithFee<'a> {
+    #[serde(flatten)]
+    pub tx: TxView<'a>,
+    /// replayed from earlier blocks; null for the coinbase and for spends of
+    /// off-chain (direct faucet) outputs
+    #[serde(serialize_with = "crate::serde_util::opt_amount")]
+    pub fee: Option<u128>,
+}
+
+/// `GET /block/{id}/effects/`: what applying the block does to the UTXO set.
+#[derive(Serialize)]
+pub struct BlockEffectsResponse<'a> {
+    pub index: u64,
+    pub hash: &'a str,
+    /// outpoints the block's inputs consume, in block order
+    pub spent: Vec<SpentOutpoint<'a>>,
+    /// outputs the block adds, coinbase first
+    pub created: Vec<CreatedOutput<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct SpentOutpoint<'a> {
+    pub txid: &'a str,
+    pub vout: u32,
+    /// the block transaction spending it
+    pub spent_by: &'a str,
+}
+
+#[derive(Serialize)]
+pub struct CreatedOutput<'a> {
+    pub txid: &'a str,
+    pub vout: u32,
+    pub address: &'a str,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub amount: u64,
+    pub coinbase: bool,
+}
+
+/// `GET /checkpoints/`, lowest height first.
+#[derive(Serialize)]
+pub struct CheckpointsResponse {
+    pub checkpoints: Vec<CheckpointStatus>,
+}
+
+#[derive(Serialize)]
+pub struct CheckpointStatus {
+    pub height: u64,
+    pub hash: String,
+    /// whether our block at `height` has `hash`; null until we reach it
+    pub matches: Option<bool>,
 }
 
 /* ---------- Chain API Models ---------- */
 
+/// A `Block` as the read endpoints render it: the same JSON as the stored
+/// block, except that amounts follow `?amounts=` (see `api::format`). The
+/// consensus types always serialize amounts as numbers.
+#[derive(Serialize)]
+pub struct BlockView<'a> {
+    pub index: u64,
+    pub timestamp: i64,
+    pub previous_hash: &'a str,
+    pub difficulty: u32,
+    pub tx_count: u64,
+    pub merkle_root: &'a str,
+    pub nonce: u64,
+    pub hash: &'a str,
+    pub transactions: Vec<TxView<'a>>,
+    #[serde(skip_serializing_if = "crate::serde_util::is_false")]
+    pub pruned: bool,
+}
+
+impl<'a> From<&'a Block> for BlockView<'a> {
+    fn from(block: &'a Block) -> Self {
+        Self {
+            index: block.index,
+            timestamp: block.timestamp,
+            previous_hash: &block.previous_hash,
+            difficulty: block.difficulty,
+            tx_count: block.tx_count,
+            merkle_root: &block.merkle_root,
+            nonce: block.nonce,
+            hash: &block.hash,
+            transactions: block.transactions.iter().map(TxView::from).collect(),
+            pruned: block.pruned,
+        }
+    }
+}
+
+/// A `Transaction` as the read endpoints render it (see `BlockView`).
+#[derive(Serialize)]
+pub struct TxView<'a> {
+    pub txid: &'a str,
+    pub version: u32,
+    pub inputs: &'a [crate::transaction::TxInput],
+    pub outputs: Vec<OutputView<'a>>,
+    #[serde(skip_serializing_if = "crate::serde_util::is_false")]
+    pub replaceable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coinbase_height: Option<u64>,
+}
+
+impl<'a> From<&'a Transaction> for TxView<'a> {
+    fn from(tx: &'a Transaction) -> Self {
+        Self {
+            txid: &tx.txid,
+            version: tx.version,
+            inputs: &tx.inputs,
+            outputs: tx.outputs.iter().map(OutputView::from).collect(),
+            replaceable: tx.replaceable,
+            coinbase_height: tx.coinbase_height,
+        }
+    }
+}
+
+/// A `TxOutput` as the read endpoints render it (see `BlockView`).
+#[derive(Serialize)]
+pub struct OutputView<'a> {
+    pub address: &'a str,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub amount: u64,
+}
+
+impl<'a> From<&'a TxOutput> for OutputView<'a> {
+    fn from(output: &'a TxOutput) -> Self {
+        Self {
+            address: &output.address,
+            amount: output.amount,
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct ChainResponse<'a> {
     pub length: usize,
     pub difficulty: u32,
-    pub chain: &'a [crate::blockchain::Block],
+    pub chain: Vec<BlockView<'a>>,
+}
+
+/// Result of `POST /chain/import/ndjson/`.
+#[derive(Serialize, Deserialize)]
+pub struct ChainImportResponse {
+    /// blocks read from the upload
+    pub received: usize,
+    /// blocks of it now on our chain past the fork (0 = ours was kept)
+    pub adopted: u64,
+    /// chain length afterwards
+    pub length: usize,
+}
+
+#[derive(Deserialize)]
+pub struct TipQuery {
+    pub known_hash: Option<String>,
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct TipResponse {
+    pub height: usize,
+    pub index: u64,
+    pub hash: String,
+    /// false when the long-poll timed out with the tip unchanged
+    pub changed: bool,
 }
 
 #[derive(Serialize)]
@@ -82,17 +986,82 @@ pub struct ValidateResponse {
     pub difficulty: u32,
 }
 
+#[derive(Serialize)]
+pub struct FullValidateResponse {
+    pub valid: bool,
+    pub length: usize,
+    pub first_invalid_block: Option<u64>,
+    pub reason: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct MineResponse {
     pub mined_index: u64,
     pub hash: String,
     pub nonce: u64,
     pub difficulty: u32,
+    pub bits: String,
 }
 
 #[derive(Serialize)]
 pub struct DifficultyResponse {
     pub difficulty: u32,
+    /// compact form of the difficulty's 256-bit target (Bitcoin `nBits`, hex)
+    pub bits: String,
+    /// hashes needed on average to find a block: 16^difficulty
+    pub expected_hashes: u128,
+    /// `expected_hashes / hashrate`, when `?hashrate=` (H/s) is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seconds_per_block: Option<f64>,
+}
+
+impl DifficultyResponse {
+    pub fn new(difficulty: u32) -> Self {
+        Self {
+            difficulty,
+            bits: crate::blockchain::difficulty_bits(difficulty),
+            expected_hashes: crate::blockchain::expected_hashes(difficulty),
+            seconds_per_block: None,
+        }
+    }
+
+    /// Theoretical block time for a miner doing `hashrate` hashes per second.
+    pub fn at_hashrate(mut self, hashrate: Option<f64>) -> Self {
+        self.seconds_per_block = hashrate
+            .filter(|h| h.is_finite() && *h > 0.0)
+            .map(|h| self.expected_hashes as f64 / h);
+        self
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MiningEtaQuery {
+    /// the miner's hashes per second
+    pub hashrate: f64,
+    /// window to report the odds of finding a block in
+    pub within_secs: Option<f64>,
+}
+
+/// Expected time for one miner to find the next block at the current difficulty.
+#[derive(Serialize)]
+pub struct MiningEtaResponse {
+    pub difficulty: u32,
+    /// 16^difficulty
+    pub expected_hashes: u128,
+    pub hashrate: f64,
+    /// `expected_hashes / hashrate`; `null` at zero hashrate (never)
+    pub expected_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub within_secs: Option<f64>,
+    /// chance of at least one block within `within_secs`: `1 - e^(-t / expected_secs)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probability_within: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct DifficultyQuery {
+    /// hashes per second to project the block time for
+    pub hashrate: Option<f64>,
 }
 
 #[derive(Deserialize)]
@@ -102,28 +1071,273 @@ pub struct SetDifficultyRequest {
 
 /* ---------- TX API Models ---------- */
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct NewTxRequest {
+    #[serde(default = "crate::transaction::default_tx_version")]
+    pub version: u32,
+    /// opt in to fee-bump replacement while unconfirmed
+    #[serde(default)]
+    pub replaceable: bool,
     pub inputs: Vec<crate::transaction::TxInput>,
     pub outputs: Vec<crate::transaction::TxOutput>,
 }
 
+/// `/tx/and-mine/` body: a `/tx/` body plus who gets the block reward.
+#[derive(Deserialize)]
+pub struct TxAndMineRequest {
+    #[serde(flatten)]
+    pub tx: NewTxRequest,
+    pub miner_address: String,
+}
+
+#[derive(Serialize)]
+pub struct TxAndMineResponse {
+    pub txid: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub replaced: Vec<String>,
+    pub block_index: u64,
+    pub block_hash: String,
+}
+
+/// Unsigned draft for `/tx/sighash/` and `/tx/inputs/`: just what the
+/// signature commits to.
+#[derive(Deserialize)]
+pub struct DraftTxRequest {
+    #[serde(default)]
+    pub replaceable: bool,
+    pub inputs: Vec<crate::transaction::OutPoint>,
+    pub outputs: Vec<crate::transaction::TxOutput>,
+}
+
+/// `POST /tx/inputs/`: what a draft would spend, as of the current UTXO set.
+#[derive(Serialize)]
+pub struct TxInputsResponse {
+    pub inputs: Vec<DraftInputStatus>,
+    /// sum of the inputs that exist
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub input_sum: u128,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub output_sum: u128,
+    /// `input_sum - output_sum`; null if an input is missing or the outputs
+    /// exceed the inputs
+    #[serde(serialize_with = "crate::serde_util::opt_amount")]
+    pub fee: Option<u128>,
+}
+
+#[derive(Serialize)]
+pub struct DraftInputStatus {
+    pub txid: String,
+    pub vout: u32,
+    /// still in the UTXO set
+    pub exists: bool,
+    #[serde(serialize_with = "crate::serde_util::opt_amount")]
+    pub amount: Option<u64>,
+    pub address: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SighashResponse {
+    pub chain_id: String,
+    /// exact bytes that get hashed (canonical JSON)
+    pub signing_payload: String,
+    /// hex SHA-256 of `signing_payload`; every input signs this same digest
+    pub sighash: String,
+}
+
+/// Body of a request refused before reaching its handler (bad or oversized JSON).
+#[derive(Serialize)]
+pub struct BodyErrorResponse {
+    /// `payload_too_large`, `unsupported_content_type` or `invalid_json`
+    pub code: &'static str,
+    pub message: String,
+    /// `payload_too_large` only: the limit in bytes (`JSON_MAX_BYTES`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+/// Body of `POST /tx/raw/`.
+#[derive(Deserialize)]
+pub struct RawTxRequest {
+    /// hex of `Transaction::serialize_bytes`
+    pub hex: String,
+}
+
+/// Body of a rejected `/tx/` submission.
+#[derive(Serialize)]
+pub struct TxErrorResponse {
+    /// machine-readable, e.g. `insufficient_funds`, `bad_signature`
+    pub code: &'static str,
+    pub message: Cow<'static, str>,
+    /// fee-related rejections only: the fee rate (same unit as `FEE_RATE_MODE`)
+    /// that would have been accepted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_accepted_fee_rate: Option<f64>,
+}
+
 #[derive(Serialize)]
 pub struct NewTxResponse {
     pub txid: String,
+    /// inputs minus outputs; 0 is a valid (if unattractive) fee
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub fee: u128,
+    /// mempool txs evicted by this replacement
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub replaced: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ValidateTxResponse {
+    pub txid: String,
+    pub valid: bool,
+    pub errors: Vec<String>,
+    #[serde(serialize_with = "crate::serde_util::opt_amount")]
+    pub fee: Option<u128>,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub input_sum: u128,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub output_sum: u128,
+}
+
+/// `POST /tx/size/`: how the tx's `vsize` (serialized bytes) splits up.
+/// `base + Σ inputs + Σ outputs + signature_bytes == vsize`.
+#[derive(Serialize)]
+pub struct TxSizeResponse {
+    pub txid: String,
+    pub vsize: usize,
+    /// txid, version, flags, field names and JSON punctuation
+    pub base: usize,
+    /// per input, without its signature
+    pub inputs: Vec<usize>,
+    /// per output
+    pub outputs: Vec<usize>,
+    /// all input signatures together
+    pub signature_bytes: usize,
+}
+
+#[derive(Deserialize)]
+pub struct MempoolQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    /// also return an `entries` item per tx of the page
+    #[serde(default)]
+    pub verbose: bool,
 }
 
 #[derive(Serialize)]
 pub struct MempoolResponse {
+    /// txs in the whole mempool
+    pub total: usize,
+    /// txs in this page
     pub size: usize,
-    pub transactions: Vec<String>, // list txids for brevity
+    pub offset: usize,
+    pub limit: usize,
+    pub transactions: Vec<String>, // list txids for brevity, highest fee rate first
+    /// `?verbose=true` only: the same txs, with their shape and fee
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries: Option<Vec<MempoolTxInfo>>,
+}
+
+/// `GET /mempool/feerates/`: `null`s while the mempool is empty.
+#[derive(Serialize)]
+pub struct MempoolFeeRatesResponse {
+    /// txs whose fee rate is known
+    pub count: usize,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub median: Option<f64>,
+}
+
+/// One mempool tx in the verbose listing.
+#[derive(Serialize)]
+pub struct MempoolTxInfo {
+    pub txid: String,
+    pub num_inputs: usize,
+    pub num_outputs: usize,
+    /// serialized size in bytes (what the block byte limit counts)
+    pub vsize: usize,
+    /// `null` when an input isn't in the UTXO set (spends another mempool tx)
+    #[serde(serialize_with = "crate::serde_util::opt_amount")]
+    pub fee: Option<u128>,
+    /// seconds since this node first saw it
+    pub age_secs: i64,
+}
+
+#[derive(Serialize)]
+pub struct NextBlockResponse {
+    /// height the next block would get
+    pub index: u64,
+    /// `"coinbase"` (placeholder, it depends on the miner) then txids in packing order
+    pub txids: Vec<String>,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub total_fees: u128,
+    /// serialized size of the selected txs (coinbase excluded)
+    pub total_bytes: usize,
+    /// `MAX_BLOCK_BYTES`
+    pub max_bytes: usize,
+}
+
+#[derive(Serialize)]
+pub struct ConfirmationsResponse {
+    pub confirmed: bool,
+    pub block_index: Option<u64>,
+    /// `height - block_index` (the including block counts as 1); 0 in mempool
+    pub confirmations: u64,
+}
+
+/// `/tx/{txid}/block/`: the block that confirmed a transaction.
+#[derive(Serialize)]
+pub struct TxBlockResponse {
+    pub txid: String,
+    pub index: u64,
+    pub hash: String,
+    pub timestamp: i64,
+    pub tx_count: u64,
+    /// transactions in this block and all before it
+    pub cumulative_tx_count: u64,
+}
+
+#[derive(Serialize)]
+pub struct TxOutputsResponse {
+    pub txid: String,
+    /// false while the tx itself is still in the mempool
+    pub confirmed: bool,
+    pub outputs: Vec<TxOutputStatus>,
+}
+
+/// One output of `/tx/{txid}/outputs/`.
+#[derive(Serialize)]
+pub struct TxOutputStatus {
+    pub vout: u32,
+    pub address: String,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub amount: u64,
+    /// no longer in the UTXO set (spent by a confirmed tx)
+    pub spent: bool,
+    /// spending tx, from the chain or (with `spent: false`) the mempool
+    pub spent_by: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MempoolPackageResponse {
+    pub txid: String,
+    pub ancestors: Vec<String>,
+    pub descendants: Vec<String>,
+    /// whole package, parents before children
+    pub transactions: Vec<Transaction>,
+}
+
+#[derive(Serialize)]
+pub struct MempoolConflictsResponse {
+    pub count: usize,
+    pub conflicts: Vec<Conflict>,
 }
 
 /* ---------- Faucet API Models (dev) ---------- */
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct FaucetRequest {
     pub address: String,
+    #[serde(deserialize_with = "crate::transaction::deserialize_amount")]
     pub amount: u64,
 }
 
@@ -133,22 +1347,259 @@ pub struct FaucetResponse {
     pub outpoints: Vec<crate::transaction::OutPoint>,
 }
 
-#[derive(Deserialize)]
+/// `FAUCET_MODE=coinbase`: the payout waits for the next mined block.
+#[derive(Serialize)]
+pub struct FaucetQueuedResponse {
+    pub address: String,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub amount: u64,
+    /// payouts queued for the next coinbase, this one included
+    pub pending: usize,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct MineRequest {
+    #[serde(default)]
     pub miner_address: String,
+    /// split the reward by weight instead of paying `miner_address`
+    #[serde(default)]
+    pub payouts: Vec<CoinbaseShare>,
+}
+
+impl MineRequest {
+    pub fn payees(&self) -> Result<Vec<CoinbaseShare>, &'static str> {
+        coinbase_payees(&self.miner_address, &self.payouts)
+    }
 }
 
 #[derive(serde::Serialize)]
 pub struct BalanceResponse {
     pub address: String,
+    #[serde(serialize_with = "crate::serde_util::amount")]
     pub balance: u128,
     pub utxos: usize,
 }
 
+#[derive(Serialize)]
+pub struct DetailedBalanceResponse {
+    pub address: String,
+    /// sum of the address's UTXOs
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub confirmed: u128,
+    /// part of `confirmed` that mempool txs spend
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub pending_spent: u128,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub available: u128,
+}
+
+#[derive(Deserialize)]
+pub struct AccountsQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct AccountEntry {
+    pub address: String,
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub balance: u128,
+}
+
+#[derive(Serialize)]
+pub struct AccountsResponse {
+    /// number of addresses with a nonzero balance
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub accounts: Vec<AccountEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct UtxoExportQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+/// `GET /utxo/export/`: one page of the set, sorted by txid then vout.
+#[derive(Serialize)]
+pub struct UtxoExportResponse {
+    /// entries in the whole set
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub utxos: Vec<crate::transaction::UtxoRecord>,
+}
+
+/// `POST /utxo/import/` body; an unpaginated export can be posted back as is.
+#[derive(Deserialize)]
+pub struct UtxoImportRequest {
+    pub utxos: Vec<crate::transaction::UtxoRecord>,
+}
+
+#[derive(Serialize)]
+pub struct UtxoImportResponse {
+    pub count_before: usize,
+    pub count_after: usize,
+    pub total_before: u128,
+    pub total_after: u128,
+}
+
+#[derive(Serialize)]
+pub struct UtxoResponse<'a> {
+    pub exists: bool,
+    pub output: Option<OutputView<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct UtxoSummaryResponse {
+    /// unspent outputs
+    pub count: usize,
+    /// sum of every unspent amount (the spendable supply)
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub total_value: u128,
+    pub distinct_addresses: usize,
+}
+
+/// Unspent value split by origin. `mined_supply + faucet_supply == total_supply`.
+#[derive(Serialize)]
+pub struct SupplyResponse {
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub total_supply: u128,
+    /// outputs created by blocks (coinbase rewards and the txs that moved them)
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub mined_supply: u128,
+    /// outputs still sitting where `/faucet/` created them (dev money)
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub faucet_supply: u128,
+    /// part of `mined_supply` in coinbase outputs not yet spendable (`COINBASE_MATURITY`)
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub immature_supply: u128,
+}
+
+#[derive(Deserialize)]
+pub struct BlocksByTimeQuery {
+    /// inclusive lower bound (unix seconds); default: beginning of time
+    pub from_ts: Option<i64>,
+    /// inclusive upper bound (unix seconds); default: now and beyond
+    pub to_ts: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct BlocksByTimeResponse<'a> {
+    /// blocks in the window (before the cap)
+    pub total: usize,
+    /// true when `blocks` was cut at the cap
+    pub truncated: bool,
+    pub blocks: Vec<BlockView<'a>>,
+}
+
+#[derive(Deserialize)]
+pub struct ExplorerQuery {
+    /// how many recent blocks to include
+    pub blocks: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct ExplorerBlock {
+    pub index: u64,
+    pub hash: String,
+    pub tx_count: usize,
+    /// transactions in this block and all before it
+    pub cumulative_tx_count: u64,
+    pub timestamp: i64,
+    /// compact target of the difficulty the block was mined at
+    pub bits: String,
+    /// coinbase payout above the base reward
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub total_fees: u64,
+}
+
+#[derive(Serialize)]
+pub struct ExplorerSummaryResponse {
+    pub tip_height: u64,
+    pub tip_hash: String,
+    pub difficulty: u32,
+    /// newest first
+    pub blocks: Vec<ExplorerBlock>,
+    pub mempool_size: usize,
+    pub utxo_count: usize,
+}
+
+#[derive(Deserialize)]
+pub struct MinerBlocksQuery {
+    /// first height to scan (default 0)
+    #[serde(default)]
+    pub from_height: u64,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct MinedBlock {
+    pub index: u64,
+    pub hash: String,
+    pub timestamp: i64,
+    /// what the coinbase paid this address
+    #[serde(serialize_with = "crate::serde_util::amount")]
+    pub reward: u64,
+    /// transactions in this block and all before it
+    pub cumulative_tx_count: u64,
+}
+
+#[derive(Serialize)]
+pub struct MinerBlocksResponse {
+    pub address: String,
+    /// oldest first
+    pub blocks: Vec<MinedBlock>,
+    /// pass as `from_height` for the next page; absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_from_height: Option<u64>,
+}
+
+/// `GET /ready/`
+#[derive(Serialize)]
+pub struct ReadyResponse {
+    pub ready: bool,
+    pub height: usize,
+    /// why the node is not ready
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct HashrateResponse {
+    /// work of the last `window` blocks / the seconds they took
+    pub hashes_per_sec: Option<f64>,
+    /// blocks averaged over
+    pub window: usize,
+    pub tip_height: u64,
+}
+
+/// `GET /capacity/`: theoretical throughput of the configured block limits.
+#[derive(serde::Serialize)]
+pub struct CapacityResponse {
+    pub target_block_time_secs: i64,
+    /// `MAX_TXS_PER_BLOCK`
+    pub max_txs_per_block: usize,
+    /// `MAX_BLOCK_BYTES`
+    pub max_block_bytes: usize,
+    /// mean size of the txs in recent blocks (a typical signed tx if none)
+    pub avg_tx_bytes: usize,
+    /// `max_txs_per_block / target_block_time_secs`
+    pub count_bound_tps: f64,
+    /// `max_block_bytes / avg_tx_bytes` (whole txs) `/ target_block_time_secs`
+    pub byte_bound_tps: f64,
+    /// the lower of the two
+    pub tps: f64,
+    /// `"tx_count"` or `"block_bytes"`: which limit fills a block first
+    pub limited_by: &'static str,
+}
+
 #[derive(serde::Serialize)]
 pub struct StatsResponse {
     pub height: usize,
     pub difficulty: u32,
+    pub bits: String,
     pub target_block_time_secs: i64,
     pub adjust_window: usize,
     pub adjust_threshold_pct: f64,
@@ -156,4 +1607,26 @@ pub struct StatsResponse {
     pub avg_interval_secs: Option<f64>,
     pub mempool_size: usize,
     pub utxo_size: usize,
+    /// height where a peer's chain diverged from ours (see `node::check_fork`)
+    pub fork_detected_at: Option<u64>,
+}
+
+/* ---------- Admin API Models ---------- */
+
+/// `POST /admin/rotate-token/`: the only time the new token is shown.
+#[derive(Serialize)]
+pub struct RotateTokenResponse {
+    pub token: String,
+}
+
+#[derive(Serialize)]
+pub struct RebuildUtxoResponse {
+    /// entries the chain implies but the live set lacked (or had different)
+    pub added: usize,
+    /// live entries the chain doesn't back
+    pub removed: usize,
+    pub count_before: usize,
+    pub count_after: usize,
+    pub total_before: u128,
+    pub total_after: u128,
 }
@@ -0,0 +1,68 @@
+//! Per-request correlation IDs: every `/api/v1` request gets one (taken from
+//! `X-Request-Id` or freshly generated), echoed back in the response and made
+//! available to the logger for lines emitted while handling it.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+const MAX_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// ID of the request being handled by the current task, if any.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Middleware (`middleware::from_fn`): run the rest of the chain with the
+/// request ID in scope and add it to the response headers.
+pub async fn assign(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty() && v.len() <= MAX_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let mut res = REQUEST_ID.scope(id.clone(), next.call(req)).await?;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut()
+            .insert(HeaderName::from_static("x-request-id"), value);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::REQUEST_ID_HEADER;
+    use crate::api::testutil::{app, state};
+    use actix_web::test::{TestRequest, call_service};
+
+    #[actix_web::test]
+    async fn responses_carry_a_request_id() {
+        let state = state();
+        let app = app(&state).await;
+
+        let req = TestRequest::get().uri("/api/v1/health/").to_request();
+        let resp = call_service(&app, req).await;
+        let generated = resp.headers().get(REQUEST_ID_HEADER).unwrap();
+        assert!(uuid::Uuid::parse_str(generated.to_str().unwrap()).is_ok());
+
+        let req = TestRequest::get()
+            .uri("/api/v1/health/")
+            .insert_header((REQUEST_ID_HEADER, "trace-42"))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "trace-42");
+    }
+}
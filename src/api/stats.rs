@@ -1,15 +1,26 @@
 use actix_web::{HttpResponse, Responder, get, web};
 
-use super::models::{AppState, StatsResponse};
-use crate::blockchain::{DIFF_ADJUST_THRESHOLD_PCT, DIFF_ADJUST_WINDOW, TARGET_BLOCK_TIME_SECS};
+use super::format::{FormatQuery, json_formatted};
+use super::models::{AppState, CapacityResponse, HashrateResponse, StatsResponse};
+use crate::blockchain::{
+    DIFF_ADJUST_THRESHOLD_PCT, DIFF_ADJUST_WINDOW, MAX_BLOCK_BYTES, MAX_TXS_PER_BLOCK,
+    difficulty_bits,
+};
+
+/// Blocks `/capacity/` samples for the average tx size.
+const CAPACITY_SAMPLE_BLOCKS: usize = 100;
+/// Assumed tx size while recent blocks have none (about a signed
+/// 1-input, 2-output tx).
+const TYPICAL_TX_BYTES: usize = 600;
 
 #[get("/stats/")]
-pub async fn get_stats(state: web::Data<AppState>) -> impl Responder {
+pub async fn get_stats(state: web::Data<AppState>, fmt: web::Query<FormatQuery>) -> impl Responder {
     // Snapshot lightweight parts first
-    let (height, difficulty, last_interval, avg_interval) = {
+    let (height, difficulty, target_block_secs, last_interval, avg_interval) = {
         let bc = state.blockchain.lock().expect("mutex poisoned");
         let height = bc.len();
         let difficulty = bc.difficulty();
+        let target_block_secs = bc.target_block_secs;
 
         // last interval
         let last_interval_secs = if height >= 2 {
@@ -22,7 +33,7 @@ pub async fn get_stats(state: web::Data<AppState>) -> impl Responder {
         };
 
         // average over the configured window (same logic da blockchain, mas só leitura)
-        let avg_secs = if height >= DIFF_ADJUST_WINDOW + 1 {
+        let avg_secs = if height > DIFF_ADJUST_WINDOW {
             let start = height - (DIFF_ADJUST_WINDOW + 1);
             let mut total: i64 = 0;
             for i in (start + 1)..(start + 1 + DIFF_ADJUST_WINDOW) {
@@ -35,7 +46,13 @@ pub async fn get_stats(state: web::Data<AppState>) -> impl Responder {
             None
         };
 
-        (height, difficulty, last_interval_secs, avg_secs)
+        (
+            height,
+            difficulty,
+            target_block_secs,
+            last_interval_secs,
+            avg_secs,
+        )
     };
 
     // Sizes of mempool and utxo (locks curtos e separados)
@@ -47,16 +64,103 @@ pub async fn get_stats(state: web::Data<AppState>) -> impl Responder {
         let utxo = state.utxo_set.lock().expect("mutex poisoned");
         utxo.len()
     };
+    let fork_detected_at = *state.fork_detected_at.lock().expect("mutex poisoned");
+
+    json_formatted(
+        &StatsResponse {
+            height,
+            difficulty,
+            bits: difficulty_bits(difficulty),
+            target_block_time_secs: target_block_secs,
+            adjust_window: DIFF_ADJUST_WINDOW,
+            adjust_threshold_pct: DIFF_ADJUST_THRESHOLD_PCT,
+            last_interval_secs: last_interval,
+            avg_interval_secs: avg_interval,
+            mempool_size,
+            utxo_size,
+            fork_detected_at,
+        },
+        &fmt,
+    )
+}
 
-    HttpResponse::Ok().json(StatsResponse {
-        height,
-        difficulty,
-        target_block_time_secs: TARGET_BLOCK_TIME_SECS,
-        adjust_window: DIFF_ADJUST_WINDOW,
-        adjust_threshold_pct: DIFF_ADJUST_THRESHOLD_PCT,
-        last_interval_secs: last_interval,
-        avg_interval_secs: avg_interval,
-        mempool_size,
-        utxo_size,
+/// Estimated network hashrate over the last `DIFF_ADJUST_WINDOW` blocks
+/// (`null` until the chain is that long).
+#[get("/hashrate/")]
+pub async fn get_hashrate(state: web::Data<AppState>) -> impl Responder {
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    HttpResponse::Ok().json(HashrateResponse {
+        hashes_per_sec: bc.estimate_hashrate(),
+        window: DIFF_ADJUST_WINDOW,
+        tip_height: bc.last_block().index,
     })
 }
+
+/// Throughput ceiling of the block limits at the target block time: by tx
+/// count, by bytes (at the recent average tx size) and which one binds.
+#[get("/capacity/")]
+pub async fn get_capacity(state: web::Data<AppState>) -> impl Responder {
+    let (block_secs, avg_tx_bytes) = {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        let (count, bytes) = bc
+            .chain
+            .iter()
+            .rev()
+            .take(CAPACITY_SAMPLE_BLOCKS)
+            .flat_map(|b| b.transactions.iter().skip(1)) // coinbase excluded, like assembly
+            .fold((0usize, 0usize), |(n, sum), tx| {
+                (n + 1, sum + tx.vsize_bytes())
+            });
+        let avg = bytes
+            .checked_div(count)
+            .map_or(TYPICAL_TX_BYTES, |avg| avg.max(1));
+        (bc.target_block_secs.max(1), avg)
+    };
+
+    let txs_by_bytes = MAX_BLOCK_BYTES / avg_tx_bytes;
+    let count_bound_tps = MAX_TXS_PER_BLOCK as f64 / block_secs as f64;
+    let byte_bound_tps = txs_by_bytes as f64 / block_secs as f64;
+    let (tps, limited_by) = if txs_by_bytes < MAX_TXS_PER_BLOCK {
+        (byte_bound_tps, "block_bytes")
+    } else {
+        (count_bound_tps, "tx_count")
+    };
+    HttpResponse::Ok().json(CapacityResponse {
+        target_block_time_secs: block_secs,
+        max_txs_per_block: MAX_TXS_PER_BLOCK,
+        max_block_bytes: MAX_BLOCK_BYTES,
+        avg_tx_bytes,
+        count_bound_tps,
+        byte_bound_tps,
+        tps,
+        limited_by,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TYPICAL_TX_BYTES;
+    use crate::api::testutil::{app, get_json, state};
+    use crate::blockchain::{MAX_BLOCK_BYTES, MAX_TXS_PER_BLOCK, TARGET_BLOCK_TIME_SECS};
+
+    #[actix_web::test]
+    async fn capacity_follows_the_block_limits() {
+        let state = state();
+        let app = app(&state).await;
+        let body = get_json(&app, "/api/v1/capacity/").await;
+
+        let secs = TARGET_BLOCK_TIME_SECS as f64;
+        let close = |key: &str, expected: f64| {
+            let got = body[key].as_f64().unwrap();
+            assert!((got - expected).abs() < 1e-9, "{key}: {got} != {expected}");
+        };
+        assert_eq!(body["target_block_time_secs"], TARGET_BLOCK_TIME_SECS);
+        close("count_bound_tps", MAX_TXS_PER_BLOCK as f64 / secs);
+        // no txs mined yet: sized as a typical tx
+        assert_eq!(body["avg_tx_bytes"], TYPICAL_TX_BYTES);
+        let by_bytes = (MAX_BLOCK_BYTES / TYPICAL_TX_BYTES) as f64 / secs;
+        close("byte_bound_tps", by_bytes);
+        close("tps", by_bytes.min(MAX_TXS_PER_BLOCK as f64 / secs));
+        assert_eq!(body["limited_by"], "block_bytes");
+    }
+}
@@ -0,0 +1,207 @@
+//! Helpers shared by the API handler tests.
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{App, Error, test, web};
+use serde_json::{Value, json};
+use std::path::PathBuf;
+
+use super::{AppState, init_routes, json_config};
+use crate::blockchain::DEFAULT_CHAIN_ID;
+use crate::config::NodeConfig;
+use crate::transaction::{OutPoint, SigScheme, Transaction, TxInput, TxOutput, UtxoEntry, UtxoSet};
+use crate::wallet::{generate_keypair_hex, sign_sighash_hex};
+
+/// Fresh node state with default (env-independent) config.
+pub fn state() -> web::Data<AppState> {
+    state_with(NodeConfig::default())
+}
+
+pub fn state_with(config: NodeConfig) -> web::Data<AppState> {
+    web::Data::new(AppState::with_config(config))
+}
+
+/// Unique, not-yet-created directory under the system temp dir.
+pub fn temp_data_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("rust-blockchain-test-{}", uuid::Uuid::new_v4()))
+}
+
+/// Build the full `/api/v1` app over `state`.
+pub async fn app(
+    state: &web::Data<AppState>,
+) -> impl Service<Request, Response = ServiceResponse, Error = Error> {
+    test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .app_data(json_config(state.config.json_max_bytes))
+            .configure(init_routes),
+    )
+    .await
+}
+
+pub struct TestWallet {
+    pub private_key: String,
+    pub public_key: String,
+    pub address: String,
+}
+
+impl TestWallet {
+    pub fn new() -> Self {
+        let (private_key, public_key, address) = generate_keypair_hex();
+        Self {
+            private_key,
+            public_key,
+            address,
+        }
+    }
+}
+
+/// Build a transaction spending `inputs` (each signed by its owner) into `outputs`,
+/// signed for the default chain id.
+pub fn signed_tx(inputs: &[(OutPoint, &TestWallet)], outputs: Vec<TxOutput>) -> Transaction {
+    signed_tx_on(DEFAULT_CHAIN_ID, inputs, outputs)
+}
+
+/// Same as `signed_tx`, but signed for `chain_id`.
+pub fn signed_tx_on(
+    chain_id: &str,
+    inputs: &[(OutPoint, &TestWallet)],
+    outputs: Vec<TxOutput>,
+) -> Transaction {
+    sign_tx(chain_id, inputs, outputs, false)
+}
+
+/// Same as `signed_tx`, signaling `replaceable`.
+pub fn signed_replaceable_tx(
+    inputs: &[(OutPoint, &TestWallet)],
+    outputs: Vec<TxOutput>,
+) -> Transaction {
+    sign_tx(DEFAULT_CHAIN_ID, inputs, outputs, true)
+}
+
+fn sign_tx(
+    chain_id: &str,
+    inputs: &[(OutPoint, &TestWallet)],
+    outputs: Vec<TxOutput>,
+    replaceable: bool,
+) -> Transaction {
+    let mut tx_inputs: Vec<TxInput> = inputs
+        .iter()
+        .map(|(op, w)| TxInput {
+            outpoint: op.clone(),
+            pubkey: w.public_key.clone(),
+            signature: String::new(),
+            scheme: SigScheme::Ecdsa,
+        })
+        .collect();
+    let tx = Transaction::new(tx_inputs.clone(), outputs.clone()).with_replaceable(replaceable);
+    let sighash = tx.sighash(chain_id);
+    for (input, (_, w)) in tx_inputs.iter_mut().zip(inputs) {
+        input.signature = sign_sighash_hex(&w.private_key, sighash).expect("sign");
+    }
+    Transaction::new(tx_inputs, outputs).with_replaceable(replaceable)
+}
+
+/// A wallet, a UTXO set holding its 100 sat output `funding:0`, and a tx
+/// signed by it spending that output to bob for 90 (a 10 sat fee).
+pub fn funded_spend() -> (TestWallet, UtxoSet, Transaction) {
+    let wallet = TestWallet::new();
+    let op = OutPoint {
+        txid: "funding".into(),
+        vout: 0,
+    };
+    let mut utxo = UtxoSet::new();
+    utxo.insert(
+        op.clone(),
+        UtxoEntry::chain(output(&wallet.address, 100), 0),
+    );
+    let tx = signed_tx(&[(op, &wallet)], vec![output("bob", 90)]);
+    (wallet, utxo, tx)
+}
+
+pub fn output(address: &str, amount: u64) -> TxOutput {
+    TxOutput {
+        address: address.to_string(),
+        amount,
+    }
+}
+
+pub async fn get<S>(app: &S, uri: &str) -> ServiceResponse
+where
+    S: Service<Request, Response = ServiceResponse, Error = Error>,
+{
+    test::call_service(app, test::TestRequest::get().uri(uri).to_request()).await
+}
+
+pub async fn post<S>(app: &S, uri: &str, body: Value) -> ServiceResponse
+where
+    S: Service<Request, Response = ServiceResponse, Error = Error>,
+{
+    test::call_service(
+        app,
+        test::TestRequest::post()
+            .uri(uri)
+            .set_json(body)
+            .to_request(),
+    )
+    .await
+}
+
+/// GET `uri` and parse the JSON body.
+pub async fn get_json<S>(app: &S, uri: &str) -> Value
+where
+    S: Service<Request, Response = ServiceResponse, Error = Error>,
+{
+    let resp = get(app, uri).await;
+    assert!(resp.status().is_success(), "GET {uri} -> {}", resp.status());
+    test::read_body_json(resp).await
+}
+
+/// Faucet `amount` to `address`; returns the created outpoint.
+pub async fn faucet<S>(app: &S, address: &str, amount: u64) -> OutPoint
+where
+    S: Service<Request, Response = ServiceResponse, Error = Error>,
+{
+    let resp = post(
+        app,
+        "/api/v1/faucet/",
+        json!({ "address": address, "amount": amount }),
+    )
+    .await;
+    assert!(resp.status().is_success(), "faucet -> {}", resp.status());
+    let body: Value = test::read_body_json(resp).await;
+    serde_json::from_value(body["outpoints"][0].clone()).expect("outpoint")
+}
+
+/// POST a transaction to `/tx/`.
+pub async fn submit_tx<S>(app: &S, tx: &Transaction) -> ServiceResponse
+where
+    S: Service<Request, Response = ServiceResponse, Error = Error>,
+{
+    post(
+        app,
+        "/api/v1/tx/",
+        json!({
+            "version": tx.version,
+            "replaceable": tx.replaceable,
+            "inputs": tx.inputs,
+            "outputs": tx.outputs,
+        }),
+    )
+    .await
+}
+
+/// Mine a block to `miner_address` via `/mine/`; returns the response JSON.
+pub async fn mine<S>(app: &S, miner_address: &str) -> Value
+where
+    S: Service<Request, Response = ServiceResponse, Error = Error>,
+{
+    let resp = post(
+        app,
+        "/api/v1/mine/",
+        json!({ "miner_address": miner_address }),
+    )
+    .await;
+    assert!(resp.status().is_success(), "mine -> {}", resp.status());
+    test::read_body_json(resp).await
+}
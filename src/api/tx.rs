@@ -1,25 +1,95 @@
-use crate::wallet::{pubkey_to_address_hex, verify_signature_hex};
-use actix_web::{HttpResponse, Responder, get, post, web};
+use crate::blockchain::{
+    CoinbaseShare, FeeRateMode, MAX_BLOCK_BYTES, select_transactions_cached, tx_fee,
+};
+use crate::config::FaucetMode;
+use crate::wallet::{SigCache, SigCheck, pubkey_to_address_hex};
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
 use log::{debug, info, warn};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
+use super::chain::mine_next_block;
+use super::format::{FormatQuery, json_formatted};
 use super::models::{
-    AppState, FaucetRequest, FaucetResponse, MempoolResponse, NewTxRequest, NewTxResponse,
+    AppState, ConfirmationsResponse, DraftInputStatus, DraftTxRequest, FaucetQueuedResponse,
+    FaucetRequest, FaucetResponse, MempoolConflictsResponse, MempoolEntry, MempoolFeeRatesResponse,
+    MempoolPackageResponse, MempoolQuery, MempoolResponse, MempoolTxInfo, NewTxRequest,
+    NewTxResponse, NextBlockResponse, RawTxRequest, SighashResponse, TxAndMineRequest,
+    TxAndMineResponse, TxBlockResponse, TxErrorResponse, TxInputsResponse, TxOutputStatus,
+    TxOutputsResponse, TxSizeResponse, ValidateTxResponse,
+};
+use crate::transaction::{
+    OutPoint, SigScheme, TX_VERSION, Transaction, TxError, TxInput, TxOutput, UtxoEntry, UtxoSet,
+    UtxoSource, check_standard, mempool_conflicts, mempool_package,
 };
-use crate::transaction::{OutPoint, Transaction, TxInput, TxOutput, UtxoSet};
 
 /// DEV Faucet: create spendable UTXOs directly in the UTXO set.
 /// This avoids hidden seeds and makes testing straightforward.
+/// With `FAUCET_MODE=coinbase` the payout is queued for the next block's
+/// coinbase instead (`202 Accepted`), so the coins have an on-chain origin.
+/// Honors `Idempotency-Key`.
 #[post("/faucet/")]
 pub async fn post_faucet(
     state: web::Data<AppState>,
     body: web::Json<FaucetRequest>,
+    req: HttpRequest,
 ) -> impl Responder {
+    let handler = |body| create_faucet_utxo(state.clone(), body);
+    state.idempotency.run("faucet", &req, body, handler).await
+}
+
+async fn create_faucet_utxo(
+    state: web::Data<AppState>,
+    body: web::Json<FaucetRequest>,
+) -> HttpResponse {
     if body.amount == 0 {
         return HttpResponse::BadRequest().body("amount must be > 0");
     }
 
+    let policy = &state.config.faucet;
+    if let Some(allowed) = &policy.allowlist
+        && !allowed.contains(&body.address)
+    {
+        return HttpResponse::Forbidden().body("address is not allowed to use the faucet");
+    }
+    if let Some(max) = policy.max_amount
+        && body.amount > max
+    {
+        return HttpResponse::BadRequest().body(format!("amount exceeds faucet limit of {max}"));
+    }
+    {
+        // check + record under one lock so concurrent requests can't both pass
+        let mut totals = state.faucet_totals.lock().expect("mutex poisoned");
+        let received = totals.get(&body.address).copied().unwrap_or(0);
+        if let Some(cap) = policy.max_per_address
+            && received.saturating_add(body.amount) > cap
+        {
+            return HttpResponse::BadRequest().body(format!(
+                "faucet cap of {cap} per address exceeded (already received {received})"
+            ));
+        }
+        totals.insert(body.address.clone(), received.saturating_add(body.amount));
+    }
+
+    if policy.mode == FaucetMode::Coinbase {
+        let mut queue = state.faucet_queue.lock().expect("mutex poisoned");
+        queue.push_back(TxOutput {
+            address: body.address.clone(),
+            amount: body.amount,
+        });
+        debug!(
+            "FAUCET - queued {} for {} in the next coinbase ({} pending)",
+            body.amount,
+            body.address,
+            queue.len()
+        );
+        return HttpResponse::Accepted().json(FaucetQueuedResponse {
+            address: body.address.clone(),
+            amount: body.amount,
+            pending: queue.len(),
+        });
+    }
+
     // Create a fake coinbase tx with 1 output (address/amount).
     // We don't put it in the mempool or chain; we just expose the UTXO for dev.
     let tx = Transaction::new(
@@ -36,8 +106,26 @@ pub async fn post_faucet(
     };
 
     {
+        let height = state
+            .blockchain
+            .lock()
+            .expect("mutex poisoned")
+            .last_block()
+            .index;
+        let entry = UtxoEntry {
+            output: tx.outputs[0].clone(),
+            height,
+            is_coinbase: false,
+            source: UtxoSource::Faucet,
+            maturity_exempt: false,
+        };
         let mut utxo = state.utxo_set.lock().expect("mutex poisoned");
-        utxo.insert(outpoint.clone(), tx.outputs[0].clone());
+        utxo.insert(outpoint.clone(), entry.clone());
+        state
+            .faucet_outputs
+            .lock()
+            .expect("mutex poisoned")
+            .insert(outpoint.clone(), entry);
         debug!(
             "FAUCET - inserted UTXO {{ txid: {}, vout: 0 }} -> {{ addr: {}, amount: {} }}; UTXO size now {}",
             tx.txid,
@@ -54,11 +142,21 @@ pub async fn post_faucet(
 }
 
 /// Submit a new transaction into the mempool (with UTXO validation).
+/// Honors `Idempotency-Key`.
 #[post("/tx/")]
 pub async fn post_transaction(
     state: web::Data<AppState>,
     body: web::Json<NewTxRequest>,
+    req: HttpRequest,
 ) -> impl Responder {
+    let handler = |body| submit_transaction(state.clone(), body);
+    state.idempotency.run("tx", &req, body, handler).await
+}
+
+async fn submit_transaction(
+    state: web::Data<AppState>,
+    body: web::Json<NewTxRequest>,
+) -> HttpResponse {
     let t0 = Instant::now();
     debug!(
         "POST /tx/ - received: inputs={}, outputs={}",
@@ -66,22 +164,84 @@ pub async fn post_transaction(
         body.outputs.len()
     );
 
-    // Basic structure checks
-    if body.outputs.is_empty() {
-        warn!("POST /tx/ - rejected: no outputs");
-        return HttpResponse::BadRequest().body("transaction must have at least one output");
+    // Build tx
+    let tx = request_tx(&body);
+    debug!("POST /tx/ - built txid={}", tx.txid);
+    accept_transaction(&state, tx, t0)
+}
+
+/// Submit a transaction as the hex of its canonical bytes (the same bytes
+/// the txid hashes), like Bitcoin's `sendrawtransaction`.
+#[post("/tx/raw/")]
+pub async fn post_raw_transaction(
+    state: web::Data<AppState>,
+    body: web::Json<RawTxRequest>,
+) -> impl Responder {
+    let t0 = Instant::now();
+    let decoded = hex::decode(body.hex.trim())
+        .map_err(|_| "hex is not valid")
+        .and_then(|bytes| Transaction::deserialize_bytes(&bytes));
+    match decoded {
+        Ok(tx) => {
+            debug!("POST /tx/raw/ - decoded txid={}", tx.txid);
+            accept_transaction(&state, tx, t0)
+        }
+        Err(reason) => {
+            warn!("POST /tx/raw/ - undecodable transaction: {reason}");
+            HttpResponse::BadRequest().json(TxErrorResponse {
+                code: "malformed_raw_tx",
+                message: reason.into(),
+                min_accepted_fee_rate: None,
+            })
+        }
     }
-    if body.outputs.iter().any(|o| o.amount == 0) {
-        warn!("POST /tx/ - rejected: output with zero amount");
-        return HttpResponse::BadRequest().body("output amount must be > 0");
+}
+
+/// Validate `tx` and admit it to the mempool (shared by `/tx/` and `/tx/raw/`).
+fn accept_transaction(state: &AppState, tx: Transaction, t0: Instant) -> HttpResponse {
+    let txid = tx.txid.clone();
+    match admit_transaction(state, tx, t0, Relay::Policy) {
+        Ok(Admitted { fee, replaced }) => HttpResponse::Ok().json(NewTxResponse {
+            txid,
+            fee,
+            replaced,
+        }),
+        Err(resp) => resp,
     }
+}
 
-    // Build tx
-    let tx = Transaction::new(body.inputs.clone(), body.outputs.clone());
-    debug!("POST /tx/ - built txid={}", tx.txid);
+/// Whether mempool admission applies the relay policy on top of consensus.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Relay {
+    /// `/tx/`, `/tx/raw/`: non-standard txs refused (if `STANDARDNESS_CHECKS`)
+    Policy,
+    /// headed straight into a block: consensus rules only
+    Direct,
+}
 
-    // Snapshot+validation under a single short UTXO lock
-    {
+/// A transaction that made it into the mempool.
+struct Admitted {
+    fee: u128,
+    /// mempool txids it evicted
+    replaced: Vec<String>,
+}
+
+/// `accept_transaction` minus the success body: the fee paid and the txids
+/// it replaced, or the error response to send.
+fn admit_transaction(
+    state: &AppState,
+    tx: Transaction,
+    t0: Instant,
+    relay: Relay,
+) -> Result<Admitted, HttpResponse> {
+    // the earliest block that could include it
+    let (spend_height, received_at) = {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        (bc.len() as u64, bc.now_timestamp())
+    };
+
+    // Validation + mempool admission under a single short UTXO lock
+    let (fee, replaced) = {
         let utxo = state.utxo_set.lock().expect("mutex poisoned");
 
         // Dump UTXO for debug
@@ -105,51 +265,760 @@ pub async fn post_transaction(
             );
         }
 
-        if let Err(msg) = validate_transaction(&tx, &utxo) {
-            warn!(
-                "POST /tx/ - validation failed for txid={}: {}",
-                tx.txid, msg
-            );
-            return HttpResponse::BadRequest().body(msg);
-        }
-    } // <— soltamos lock do UTXO aqui
+        let fee = match validate_transaction(
+            &tx,
+            &utxo,
+            &state.config.chain_id,
+            state.signature_checks(),
+        )
+        .and_then(|fee| {
+            check_maturity(&tx, &utxo, spend_height, state.config.coinbase_maturity).map(|()| fee)
+        })
+        .and_then(|fee| {
+            if relay == Relay::Policy && state.config.standardness_checks {
+                check_standard(&tx)?;
+            }
+            Ok(fee)
+        }) {
+            Ok(fee) => fee,
+            Err(err) => {
+                warn!(
+                    "POST /tx/ - validation failed for txid={}: {} ({})",
+                    tx.txid,
+                    err,
+                    err.code()
+                );
+                return Err(HttpResponse::BadRequest().json(TxErrorResponse {
+                    code: err.code(),
+                    message: err.message(),
+                    min_accepted_fee_rate: None,
+                }));
+            }
+        };
 
-    // Push to mempool
-    {
+        // Push to mempool (lock order: UTXO -> mempool)
         let mut mempool = state.mempool.lock().expect("mutex poisoned");
         let before = mempool.len();
-        mempool.push(tx.clone());
-        let after = mempool.len();
-        debug!(
-            "POST /tx/ - txid={} accepted into mempool (size: {} -> {})",
-            tx.txid, before, after
-        );
-    }
+        let limits = MempoolLimits {
+            max_txs: state.config.mempool_max_txs,
+            mode: state.config.fee_rate_mode,
+        };
+        let entry = MempoolEntry::new(tx.clone(), received_at);
+        match admit_to_mempool(&mut mempool, entry, &utxo, &limits) {
+            Ok(replaced) => {
+                debug!(
+                    "POST /tx/ - txid={} accepted into mempool (size: {} -> {})",
+                    tx.txid,
+                    before,
+                    mempool.len()
+                );
+                // lock order: mempool -> fee cache
+                let mut cache = state.fee_cache.lock().expect("mutex poisoned");
+                if mempool.len() != before + 1 {
+                    // replaced or evicted txs leave the cache too
+                    cache.invalidate(mempool.iter().map(|e| &e.tx), &HashSet::new());
+                }
+                cache.insert(&tx, fee);
+                (fee, replaced)
+            }
+            Err(Rejection {
+                error,
+                min_accepted_fee_rate,
+            }) => {
+                warn!("POST /tx/ - txid={} rejected: {}", tx.txid, error);
+                return Err(HttpResponse::BadRequest().json(TxErrorResponse {
+                    code: error.code(),
+                    message: error.message(),
+                    min_accepted_fee_rate,
+                }));
+            }
+        }
+    }; // <— soltamos locks aqui
 
+    if !replaced.is_empty() {
+        info!("POST /tx/ - txid={} replaced {:?}", tx.txid, replaced);
+    }
     info!(
         "POST /tx/ - txid={} OK ({} ms)",
         tx.txid,
         t0.elapsed().as_millis()
     );
+    Ok(Admitted { fee, replaced })
+}
+
+/// DEV: `/tx/` then `/mine/` in one call. The tx goes through the normal
+/// validation and mempool admission (minus the relay policy: it's mined, not
+/// relayed), then a block is mined right away; the response names the block
+/// that confirmed it. If no block confirms it, it's taken back out of the
+/// mempool: a tx that skipped the relay policy must not linger there.
+#[post("/tx/and-mine/")]
+pub async fn post_tx_and_mine(
+    state: web::Data<AppState>,
+    body: web::Json<TxAndMineRequest>,
+) -> impl Responder {
+    if !state.config.dev_endpoints {
+        return HttpResponse::NotFound().finish();
+    }
+    let miner_address = body.miner_address.trim().to_string();
+    if miner_address.is_empty() {
+        return HttpResponse::BadRequest().body("miner_address required");
+    }
+    let tx = request_tx(&body.tx);
+    let txid = tx.txid.clone();
+    let replaced = match admit_transaction(&state, tx, Instant::now(), Relay::Direct) {
+        Ok(admitted) => admitted.replaced,
+        Err(resp) => return resp,
+    };
+
+    let mined = match mine_next_block(state.clone(), vec![CoinbaseShare::solo(miner_address)]).await
+    {
+        Ok(mined) => mined,
+        Err(err) => {
+            withdraw_from_mempool(&state, &txid);
+            return err.into_response();
+        }
+    };
+    // another request may have mined it first, or the block filled up
+    let included = state
+        .blockchain
+        .lock()
+        .expect("mutex poisoned")
+        .find_tx_block(&txid)
+        .is_some_and(|index| index == mined.mined_index);
+    if !included {
+        warn!(
+            "POST /tx/and-mine/ - txid={txid} not in block #{}",
+            mined.mined_index
+        );
+        withdraw_from_mempool(&state, &txid);
+        return HttpResponse::Conflict().body(format!(
+            "block #{} was mined without tx {txid}",
+            mined.mined_index
+        ));
+    }
+    HttpResponse::Ok().json(TxAndMineResponse {
+        txid,
+        replaced,
+        block_index: mined.mined_index,
+        block_hash: mined.hash,
+    })
+}
 
-    HttpResponse::Ok().json(NewTxResponse { txid: tx.txid })
+/// Drop `txid` and whatever spends it from the mempool, along with their
+/// cached fees (no-op if a block already took it).
+fn withdraw_from_mempool(state: &AppState, txid: &str) {
+    let mut mempool = state.mempool.lock().expect("mutex poisoned");
+    let Some(pkg) = mempool_package(&mempool, txid) else {
+        return;
+    };
+    mempool.retain(|e| e.tx.txid != txid && !pkg.descendants.contains(&e.tx.txid));
+    state.forget_spent_fees(&mempool, &[]);
 }
 
-/// List current mempool (just txids to keep it compact).
+/// Transaction described by a `/tx/`-style request body.
+fn request_tx(body: &NewTxRequest) -> Transaction {
+    Transaction::with_version(body.version, body.inputs.clone(), body.outputs.clone())
+        .with_replaceable(body.replaceable)
+}
+
+/// How far above the cheapest mempool entry a tx must bid to enter a full
+/// mempool (in the configured fee-rate unit).
+const MIN_FEE_RATE_BUMP: f64 = 0.01;
+
+/// Mempool admission settings (from `NodeConfig`).
+struct MempoolLimits {
+    /// `MEMPOOL_MAX_TXS`; `None` = unbounded
+    max_txs: Option<usize>,
+    mode: FeeRateMode,
+}
+
+/// Why `admit_to_mempool` refused a tx. Fee-related refusals say which fee
+/// rate would have been accepted, so wallets can bump and retry.
+struct Rejection {
+    error: TxError,
+    min_accepted_fee_rate: Option<f64>,
+}
+
+impl From<TxError> for Rejection {
+    fn from(error: TxError) -> Self {
+        Self {
+            error,
+            min_accepted_fee_rate: None,
+        }
+    }
+}
+
+/// Add an already-validated `entry` to the mempool. Mempool txs spending any
+/// of the same outpoints are replaced, along with every tx descending from
+/// them, only if each conflict signaled `replaceable` and the new tx pays a
+/// higher fee than all the evicted txs together. When the mempool is full,
+/// it must outbid the cheapest entry (which is dropped, with its
+/// descendants). Returns the txids that were replaced.
+fn admit_to_mempool(
+    mempool: &mut Vec<MempoolEntry>,
+    entry: MempoolEntry,
+    utxo: &UtxoSet,
+    limits: &MempoolLimits,
+) -> Result<Vec<String>, Rejection> {
+    let tx = &entry.tx;
+    let spends: HashSet<&OutPoint> = tx.inputs.iter().map(|i| &i.outpoint).collect();
+    let conflicts: Vec<&Transaction> = mempool
+        .iter()
+        .map(|e| &e.tx)
+        .filter(|m| m.inputs.iter().any(|i| spends.contains(&i.outpoint)))
+        .collect();
+    let fee = tx_fee(tx, utxo).unwrap_or(0);
+    let rate = limits.mode.fee_rate(fee, tx);
+
+    let mut replaced = Vec::new();
+    if !conflicts.is_empty() {
+        if conflicts.iter().any(|c| !c.replaceable) {
+            return Err(TxError::MempoolConflict.into());
+        }
+        // whatever spends a replaced tx's outputs goes with it, at any depth
+        for conflict in &conflicts {
+            let descendants = mempool_package(mempool, &conflict.txid)
+                .map(|pkg| pkg.descendants)
+                .unwrap_or_default();
+            for txid in std::iter::once(conflict.txid.clone()).chain(descendants) {
+                if !replaced.contains(&txid) {
+                    replaced.push(txid);
+                }
+            }
+        }
+        let evicted_fees: u128 = mempool
+            .iter()
+            .filter(|e| replaced.contains(&e.tx.txid))
+            .filter_map(|e| mempool_tx_fee(&e.tx, utxo, mempool))
+            .sum();
+        if fee <= evicted_fees {
+            return Err(Rejection {
+                error: TxError::ReplacementFeeTooLow,
+                min_accepted_fee_rate: Some(limits.mode.fee_rate(evicted_fees + 1, tx)),
+            });
+        }
+    }
+
+    // full mempool: the marginal (cheapest) entry sets the price of admission
+    let mut dropped = Vec::new();
+    if let Some(max) = limits.max_txs
+        && mempool.len() - replaced.len() >= max
+    {
+        let cheapest = mempool
+            .iter()
+            .map(|e| &e.tx)
+            .filter(|m| !replaced.contains(&m.txid))
+            .map(|m| {
+                let rate = tx_fee(m, utxo).map_or(0.0, |f| limits.mode.fee_rate(f, m));
+                (m, rate)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((victim, floor)) = cheapest else {
+            return Err(TxError::MempoolFull.into()); // MEMPOOL_MAX_TXS=0
+        };
+        let min_rate = floor + MIN_FEE_RATE_BUMP;
+        if rate < min_rate {
+            return Err(Rejection {
+                error: TxError::MempoolFull,
+                min_accepted_fee_rate: Some(min_rate),
+            });
+        }
+        dropped.push(victim.txid.clone());
+        if let Some(pkg) = mempool_package(mempool, &victim.txid) {
+            dropped.extend(pkg.descendants);
+        }
+    }
+
+    mempool.retain(|e| !replaced.contains(&e.tx.txid) && !dropped.contains(&e.tx.txid));
+    if !dropped.is_empty() {
+        info!("mempool full: dropped {dropped:?} for txid={}", tx.txid);
+    }
+    mempool.push(entry);
+    Ok(replaced)
+}
+
+/// Fee of a mempool tx whose inputs may be confirmed or created by another
+/// mempool tx; `None` if one of them is neither.
+fn mempool_tx_fee(tx: &Transaction, utxo: &UtxoSet, mempool: &[MempoolEntry]) -> Option<u128> {
+    let mut input_sum: u128 = 0;
+    for input in &tx.inputs {
+        let amount = match utxo.get(&input.outpoint) {
+            Some(output) => output.amount,
+            None => {
+                mempool
+                    .iter()
+                    .find(|e| e.tx.txid == input.outpoint.txid)?
+                    .tx
+                    .outputs
+                    .get(input.outpoint.vout as usize)?
+                    .amount
+            }
+        };
+        input_sum += amount as u128;
+    }
+    input_sum.checked_sub(tx.total_output_amount())
+}
+
+/// Signing payload and sighash of an unsigned draft, so clients signing
+/// locally don't have to reproduce the canonical JSON themselves.
+/// (There is one sighash per tx: every input signs the same digest.)
+#[post("/tx/sighash/")]
+pub async fn post_sighash(
+    state: web::Data<AppState>,
+    body: web::Json<DraftTxRequest>,
+) -> impl Responder {
+    let inputs = body
+        .inputs
+        .iter()
+        .map(|outpoint| TxInput {
+            outpoint: outpoint.clone(),
+            pubkey: String::new(),
+            signature: String::new(),
+            scheme: SigScheme::Ecdsa,
+        })
+        .collect();
+    let tx = Transaction::new(inputs, body.outputs.clone()).with_replaceable(body.replaceable);
+
+    let chain_id = &state.config.chain_id;
+    HttpResponse::Ok().json(SighashResponse {
+        chain_id: chain_id.clone(),
+        signing_payload: String::from_utf8(tx.signing_payload(chain_id))
+            .expect("signing payload is JSON"),
+        sighash: hex::encode(tx.sighash(chain_id)),
+    })
+}
+
+/// The outpoints a draft would consume, each with its current value (or
+/// flagged missing), plus the implied fee. Read-only; no signatures needed.
+#[post("/tx/inputs/")]
+pub async fn post_tx_inputs(
+    state: web::Data<AppState>,
+    body: web::Json<DraftTxRequest>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let inputs: Vec<DraftInputStatus> = {
+        let utxo = state.utxo_set.lock().expect("mutex poisoned");
+        body.inputs
+            .iter()
+            .map(|op| {
+                let out = utxo.get(op);
+                DraftInputStatus {
+                    txid: op.txid.clone(),
+                    vout: op.vout,
+                    exists: out.is_some(),
+                    amount: out.map(|o| o.amount),
+                    address: out.map(|o| o.address.clone()),
+                }
+            })
+            .collect()
+    };
+    let input_sum: u128 = inputs.iter().filter_map(|i| i.amount).map(u128::from).sum();
+    let output_sum: u128 = body.outputs.iter().map(|o| o.amount as u128).sum();
+    let fee = if inputs.iter().all(|i| i.exists) {
+        input_sum.checked_sub(output_sum)
+    } else {
+        None
+    };
+    json_formatted(
+        &TxInputsResponse {
+            inputs,
+            input_sum,
+            output_sum,
+            fee,
+        },
+        &fmt,
+    )
+}
+
+/// Byte breakdown of a draft tx (same body as `/tx/`), to see what drives
+/// its fee. Pure computation: nothing is validated or stored.
+#[post("/tx/size/")]
+pub async fn post_tx_size(body: web::Json<NewTxRequest>) -> impl Responder {
+    let tx = request_tx(&body);
+    let parts = tx.size_breakdown();
+    HttpResponse::Ok().json(TxSizeResponse {
+        vsize: tx.vsize_bytes(),
+        txid: tx.txid,
+        base: parts.base,
+        inputs: parts.inputs,
+        outputs: parts.outputs,
+        signature_bytes: parts.signatures.iter().sum(),
+    })
+}
+
+/// Check a fully-formed transaction against the current UTXO set without
+/// submitting it. Reports every failure found, not just the first.
+#[post("/validate/tx/")]
+pub async fn validate_tx(
+    state: web::Data<AppState>,
+    body: web::Json<NewTxRequest>,
+) -> impl Responder {
+    let tx = request_tx(&body);
+    let spend_height = state.blockchain.lock().expect("mutex poisoned").len() as u64;
+    let check = {
+        let utxo = state.utxo_set.lock().expect("mutex poisoned");
+        let mut check =
+            check_transaction(&tx, &utxo, &state.config.chain_id, state.signature_checks());
+        if let Err(err) = check_maturity(&tx, &utxo, spend_height, state.config.coinbase_maturity) {
+            check.errors.push(err);
+        }
+        check
+    };
+    debug!("VALIDATE tx txid={} errors={:?}", tx.txid, check.errors);
+
+    HttpResponse::Ok().json(ValidateTxResponse {
+        txid: tx.txid,
+        valid: check.errors.is_empty(),
+        fee: check.fee().filter(|_| check.errors.is_empty()),
+        errors: check.errors.iter().map(|e| e.to_string()).collect(),
+        input_sum: check.input_sum,
+        output_sum: check.output_sum,
+    })
+}
+
+/// Confirmation count of a transaction: confirmed in a block, waiting in the
+/// mempool (0 confirmations) or unknown (404).
+#[get("/tx/{txid}/confirmations/")]
+pub async fn get_confirmations(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let txid = path.into_inner();
+
+    let found = {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        bc.find_tx_block(&txid)
+            .map(|index| (index, bc.len() as u64 - index))
+    };
+    if let Some((block_index, confirmations)) = found {
+        return HttpResponse::Ok().json(ConfirmationsResponse {
+            confirmed: true,
+            block_index: Some(block_index),
+            confirmations,
+        });
+    }
+
+    let in_mempool = {
+        let mempool = state.mempool.lock().expect("mutex poisoned");
+        mempool.iter().any(|e| e.tx.txid == txid)
+    };
+    if in_mempool {
+        HttpResponse::Ok().json(ConfirmationsResponse {
+            confirmed: false,
+            block_index: None,
+            confirmations: 0,
+        })
+    } else {
+        HttpResponse::NotFound().body("unknown transaction")
+    }
+}
+
+/// Summary of the block that confirmed `txid`; 404 while it's only in the
+/// mempool or if it's unknown.
+#[get("/tx/{txid}/block/")]
+pub async fn get_tx_block(state: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
+    let txid = path.into_inner();
+    let bc = state.blockchain.lock().expect("mutex poisoned");
+    let Some(index) = bc.find_tx_block(&txid) else {
+        return HttpResponse::NotFound().body("transaction not confirmed");
+    };
+    let block = &bc.chain[index as usize];
+    HttpResponse::Ok().json(TxBlockResponse {
+        txid,
+        index,
+        hash: block.hash.clone(),
+        timestamp: block.timestamp,
+        tx_count: block.tx_count,
+        cumulative_tx_count: bc.cumulative_tx_count(index).unwrap_or_default(),
+    })
+}
+
+/// Per-output spending status of a transaction: which outputs are still in
+/// the UTXO set, and which tx (on chain or in the mempool) spent the others.
+#[get("/tx/{txid}/outputs/")]
+pub async fn get_tx_outputs(state: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
+    let txid = path.into_inner();
+    let spends_of = |tx: &Transaction| -> Vec<(u32, String)> {
+        tx.inputs
+            .iter()
+            .filter(|i| i.outpoint.txid == txid)
+            .map(|i| (i.outpoint.vout, tx.txid.clone()))
+            .collect()
+    };
+
+    // lock order: chain -> UTXO -> mempool, one at a time
+    let (mut found, mut spenders) = {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        let found = bc
+            .chain
+            .iter()
+            .flat_map(|b| &b.transactions)
+            .find(|t| t.txid == txid)
+            .map(|t| (t.outputs.clone(), true));
+        let spenders: HashMap<u32, String> = match found {
+            Some(_) => bc
+                .chain
+                .iter()
+                .flat_map(|b| &b.transactions)
+                .flat_map(spends_of)
+                .collect(),
+            None => HashMap::new(),
+        };
+        (found, spenders)
+    };
+    let unspent: Vec<bool> = {
+        let utxo = state.utxo_set.lock().expect("mutex poisoned");
+        found
+            .as_ref()
+            .map(|(outputs, _)| {
+                (0..outputs.len() as u32)
+                    .map(|vout| {
+                        utxo.get(&OutPoint {
+                            txid: txid.clone(),
+                            vout,
+                        })
+                        .is_some()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    {
+        let mempool = state.mempool.lock().expect("mutex poisoned");
+        if found.is_none() {
+            found = mempool
+                .iter()
+                .find(|e| e.tx.txid == txid)
+                .map(|e| (e.tx.outputs.clone(), false));
+        }
+        for (vout, spender) in mempool.iter().flat_map(|e| spends_of(&e.tx)) {
+            spenders.entry(vout).or_insert(spender);
+        }
+    }
+
+    let Some((outputs, confirmed)) = found else {
+        return HttpResponse::NotFound().body("unknown transaction");
+    };
+    let outputs = outputs
+        .into_iter()
+        .enumerate()
+        .map(|(vout, out)| TxOutputStatus {
+            vout: vout as u32,
+            address: out.address,
+            amount: out.amount,
+            spent: confirmed && !unspent[vout],
+            spent_by: spenders.remove(&(vout as u32)),
+        })
+        .collect();
+    HttpResponse::Ok().json(TxOutputsResponse {
+        txid,
+        confirmed,
+        outputs,
+    })
+}
+
+/// Max page size of `/mempool/` (also the default: "everything" on a normal node).
+const MEMPOOL_MAX_LIMIT: usize = 5000;
+
+/// List current mempool (just txids to keep it compact), highest fee rate
+/// first, paginated with `?offset=`/`?limit=`.
 #[get("/mempool/")]
-pub async fn get_mempool(state: web::Data<AppState>) -> impl Responder {
-    let mempool = state.mempool.lock().expect("mutex poisoned");
-    let txids = mempool.iter().map(|t| t.txid.clone()).collect::<Vec<_>>();
+pub async fn get_mempool(
+    state: web::Data<AppState>,
+    query: web::Query<MempoolQuery>,
+) -> impl Responder {
+    let snapshot = {
+        let mempool = state.mempool.lock().expect("mutex poisoned");
+        mempool.clone()
+    };
+    let now = state
+        .blockchain
+        .lock()
+        .expect("mutex poisoned")
+        .now_timestamp();
+    let mode = state.config.fee_rate_mode;
+    let mut ranked: Vec<(f64, Option<u128>, &MempoolEntry)> = {
+        let utxo = state.utxo_set.lock().expect("mutex poisoned");
+        snapshot
+            .iter()
+            .map(|e| {
+                let fee = tx_fee(&e.tx, &utxo);
+                // unknown fee (e.g. spends another mempool tx) sorts last
+                let rate = fee.map_or(-1.0, |fee| mode.fee_rate(fee, &e.tx));
+                (rate, fee, e)
+            })
+            .collect()
+    };
+    ranked.sort_by(|a, b| {
+        b.0.partial_cmp(&a.0)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.2.tx.txid.cmp(&b.2.tx.txid))
+    });
+
+    let offset = query.offset.unwrap_or(0);
+    let limit = query
+        .limit
+        .unwrap_or(MEMPOOL_MAX_LIMIT)
+        .min(MEMPOOL_MAX_LIMIT);
+    let page: Vec<(Option<u128>, &MempoolEntry)> = ranked
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(_, fee, t)| (fee, t))
+        .collect();
+    let entries = query.verbose.then(|| {
+        page.iter()
+            .map(|(fee, e)| MempoolTxInfo {
+                txid: e.tx.txid.clone(),
+                num_inputs: e.tx.inputs.len(),
+                num_outputs: e.tx.outputs.len(),
+                vsize: e.tx.vsize_bytes(),
+                fee: *fee,
+                age_secs: (now - e.received_at).max(0),
+            })
+            .collect()
+    });
     HttpResponse::Ok().json(MempoolResponse {
-        size: mempool.len(),
-        transactions: txids,
+        total: snapshot.len(),
+        size: page.len(),
+        offset,
+        limit,
+        transactions: page.iter().map(|(_, e)| e.tx.txid.clone()).collect(),
+        entries,
+    })
+}
+
+/// Spread of mempool fee rates (in the `FEE_RATE_MODE` unit), for a quick
+/// gauge without the full listing. Txs whose fee can't be resolved against
+/// the UTXO set (they spend another mempool tx) are left out.
+#[get("/mempool/feerates/")]
+pub async fn get_mempool_fee_rates(state: web::Data<AppState>) -> impl Responder {
+    let snapshot = state.mempool_txs();
+    let mode = state.config.fee_rate_mode;
+    let mut rates: Vec<f64> = {
+        let utxo = state.utxo_set.lock().expect("mutex poisoned");
+        snapshot
+            .iter()
+            .filter_map(|tx| tx_fee(tx, &utxo).map(|fee| mode.fee_rate(fee, tx)))
+            .collect()
+    };
+    rates.sort_by(f64::total_cmp);
+
+    let mid = rates.len() / 2;
+    let median = match rates.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(rates[mid]),
+        _ => Some((rates[mid - 1] + rates[mid]) / 2.0),
+    };
+    HttpResponse::Ok().json(MempoolFeeRatesResponse {
+        count: rates.len(),
+        min: rates.first().copied(),
+        max: rates.last().copied(),
+        median,
+    })
+}
+
+/// Stands in for the coinbase (built per miner) at the head of `/mempool/nextblock/`.
+const COINBASE_PLACEHOLDER: &str = "coinbase";
+
+/// The mempool txids the next block would include, in packing order, as
+/// chosen by the same `select_transactions` the miners use.
+#[get("/mempool/nextblock/")]
+pub async fn get_next_block(state: web::Data<AppState>) -> impl Responder {
+    let snapshot = state.mempool_txs();
+    let (selected, total_fees) = {
+        let utxo = state.utxo_set.lock().expect("mutex poisoned");
+        let cache = state.fee_cache.lock().expect("mutex poisoned");
+        select_transactions_cached(&snapshot, &utxo, state.config.fee_rate_mode, &cache)
+    };
+    let index = state.blockchain.lock().expect("mutex poisoned").len() as u64;
+
+    let total_bytes = selected.iter().map(Transaction::vsize_bytes).sum();
+    let txids = std::iter::once(COINBASE_PLACEHOLDER.to_string())
+        .chain(selected.into_iter().map(|tx| tx.txid))
+        .collect();
+    HttpResponse::Ok().json(NextBlockResponse {
+        index,
+        txids,
+        total_fees,
+        total_bytes,
+        max_bytes: MAX_BLOCK_BYTES,
     })
 }
 
-/// UTXO-level validation (no signatures yet).
-fn validate_transaction(tx: &Transaction, utxo: &UtxoSet) -> Result<(), &'static str> {
+/// A mempool transaction with its unconfirmed ancestors and descendants,
+/// topologically ordered (parents first).
+#[get("/mempool/package/{txid}/")]
+pub async fn get_mempool_package(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let txid = path.into_inner();
+    let mempool = state.mempool.lock().expect("mutex poisoned");
+    match mempool_package(&mempool, &txid) {
+        Some(pkg) => HttpResponse::Ok().json(MempoolPackageResponse {
+            txid,
+            ancestors: pkg.ancestors,
+            descendants: pkg.descendants,
+            transactions: pkg.transactions,
+        }),
+        None => HttpResponse::NotFound().body("transaction not in mempool"),
+    }
+}
+
+/// Groups of mempool transactions spending the same outpoint. Admission
+/// refuses double spends (short of RBF, which evicts the loser), so this is
+/// normally empty; it's a teaching aid for when that check is bypassed.
+#[get("/mempool/conflicts/")]
+pub async fn get_mempool_conflicts(state: web::Data<AppState>) -> impl Responder {
+    let conflicts = {
+        let mempool = state.mempool.lock().expect("mutex poisoned");
+        mempool_conflicts(&mempool)
+    };
+    HttpResponse::Ok().json(MempoolConflictsResponse {
+        count: conflicts.len(),
+        conflicts,
+    })
+}
+
+/// Result of checking a transaction against the UTXO set: every failure found
+/// (not just the first) plus the amounts that could be computed.
+pub(super) struct TxCheck {
+    pub errors: Vec<TxError>,
+    pub input_sum: u128,
+    pub output_sum: u128,
+}
+
+impl TxCheck {
+    /// Fee paid, when the inputs cover the outputs.
+    pub fn fee(&self) -> Option<u128> {
+        self.input_sum.checked_sub(self.output_sum)
+    }
+}
+
+/// UTXO-level validation: structure, existence, ownership, signatures (bound to
+/// `chain_id`) and economics. Keeps going after a failure so callers can report
+/// everything that is wrong with the transaction.
+pub(super) fn check_transaction(
+    tx: &Transaction,
+    utxo: &UtxoSet,
+    chain_id: &str,
+    sigs: Option<&SigCache>,
+) -> TxCheck {
+    let mut errors = Vec::new();
+
+    // Basic structure
+    if tx.version != TX_VERSION {
+        errors.push(TxError::UnsupportedVersion);
+    }
     if tx.inputs.is_empty() {
-        return Err("transactions must have at least one input (use /faucet/ to create UTXOs)");
+        errors.push(TxError::NoInputs);
+    }
+    if tx.outputs.is_empty() {
+        errors.push(TxError::NoOutputs);
+    }
+    if tx.outputs.iter().any(|o| o.amount == 0) {
+        errors.push(TxError::ZeroAmountOutput);
     }
 
     // No duplicate inputs
@@ -157,45 +1026,1364 @@ fn validate_transaction(tx: &Transaction, utxo: &UtxoSet) -> Result<(), &'static
     for input in &tx.inputs {
         let key = (input.outpoint.txid.as_str(), input.outpoint.vout);
         if !seen.insert(key) {
-            return Err("duplicate input outpoint in transaction");
+            errors.push(TxError::DuplicateInput);
+            break;
         }
     }
 
-    // Sum inputs and check existence + ownership + signature
-    let sighash = tx.sighash();
+    // Sum inputs and check existence + ownership; collect signatures to verify
+    let sighash = tx.sighash(chain_id);
     let mut input_sum: u128 = 0;
+    let mut all_inputs_found = true;
+    let mut sig_checks = Vec::with_capacity(tx.inputs.len());
 
-    for (i, input) in tx.inputs.iter().enumerate() {
-        let op = &input.outpoint;
-
+    for input in &tx.inputs {
         // Must exist
-        let prev_out = utxo.get(op).ok_or("referenced UTXO not found")?;
+        let Some(prev_out) = utxo.get(&input.outpoint) else {
+            errors.push(TxError::UtxoNotFound);
+            all_inputs_found = false;
+            continue;
+        };
+        input_sum += prev_out.amount as u128;
+        if sigs.is_none() {
+            continue; // REQUIRE_SIGNATURES=false
+        }
 
         // Ownership: address derived from pubkey must match UTXO's address
-        let derived_addr = pubkey_to_address_hex(&input.pubkey)?;
-        if prev_out.address != derived_addr {
-            return Err("pubkey does not own referenced UTXO (address mismatch)");
+        match pubkey_to_address_hex(&input.pubkey) {
+            Ok(addr) if addr == prev_out.address => {}
+            Ok(_) => errors.push(TxError::NotOwner),
+            Err(e) => {
+                errors.push(TxError::BadPubkey(e));
+                continue;
+            }
         }
 
         // Signature presence
         if input.signature.is_empty() {
-            return Err("missing signature in input");
+            errors.push(TxError::MissingSignature);
+            continue;
+        }
+
+        sig_checks.push(SigCheck {
+            scheme: input.scheme,
+            pubkey_hex: &input.pubkey,
+            sig_hex: &input.signature,
+            msg32: sighash,
+        });
+    }
+
+    // Verify all signatures in one batch (already-seen ones come from the cache)
+    if let Some(sigs) = sigs
+        && let Err((_, reason)) = sigs.verify_batch(&sig_checks)
+    {
+        errors.push(TxError::BadSignature(reason));
+    }
+
+    // Economic: sum(inputs) >= sum(outputs) (only meaningful if all inputs resolved)
+    let output_sum = tx.total_output_amount();
+    if all_inputs_found && input_sum < output_sum {
+        errors.push(TxError::InsufficientFunds {
+            shortfall: output_sum - input_sum,
+        });
+    }
+
+    TxCheck {
+        errors,
+        input_sum,
+        output_sum,
+    }
+}
+
+/// Same checks as `check_transaction`, stopping at the first failure.
+/// On success returns the fee (inputs minus outputs, possibly 0).
+pub(crate) fn validate_transaction(
+    tx: &Transaction,
+    utxo: &UtxoSet,
+    chain_id: &str,
+    sigs: Option<&SigCache>,
+) -> Result<u128, TxError> {
+    let check = check_transaction(tx, utxo, chain_id, sigs);
+    match check.errors.first() {
+        Some(err) => Err(*err),
+        // no InsufficientFunds, so the inputs cover the outputs
+        None => Ok(check.input_sum - check.output_sum),
+    }
+}
+
+/// Coinbase maturity: every spent coinbase output must be old enough to be
+/// spent in a block at `spend_height`. Faucet outputs are never coinbase.
+pub(crate) fn check_maturity(
+    tx: &Transaction,
+    utxo: &UtxoSet,
+    spend_height: u64,
+    maturity: u64,
+) -> Result<(), TxError> {
+    let immature = tx.inputs.iter().any(|input| {
+        utxo.entry(&input.outpoint)
+            .is_some_and(|e| !e.is_mature(spend_height, maturity))
+    });
+    if immature {
+        Err(TxError::ImmatureCoinbase)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_transaction;
+    use crate::api::AppState;
+    use crate::api::models::MempoolEntry;
+    use crate::api::testutil::{
+        TestWallet, app, faucet, funded_spend, get, get_json, mine, output, post,
+        signed_replaceable_tx, signed_tx, signed_tx_on, state, state_with, submit_tx,
+        temp_data_dir,
+    };
+    use crate::blockchain::{BASE_REWARD, DEFAULT_CHAIN_ID, FeeRateMode};
+    use crate::config::NodeConfig;
+    use crate::transaction::{
+        OutPoint, SigScheme, Transaction, TxError, TxInput, UtxoEntry, UtxoSet,
+    };
+    use crate::wallet::SigCache;
+    use crate::wallet::{sign_schnorr_hex, sign_sighash_hex};
+    use actix_web::test::read_body_json;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn signature_is_bound_to_chain_id() {
+        let alice = TestWallet::new();
+        let op = OutPoint {
+            txid: "funding".into(),
+            vout: 0,
+        };
+        let mut utxo = UtxoSet::new();
+        utxo.insert(op.clone(), UtxoEntry::chain(output(&alice.address, 100), 0));
+
+        let tx = signed_tx_on("chain-a", &[(op, &alice)], vec![output("bob", 90)]);
+
+        assert_eq!(
+            validate_transaction(&tx, &utxo, "chain-a", Some(&SigCache::new(0))),
+            Ok(10)
+        );
+        assert_eq!(
+            validate_transaction(&tx, &utxo, "chain-b", Some(&SigCache::new(0))),
+            Err(TxError::BadSignature("invalid signature"))
+        );
+    }
+
+    #[test]
+    fn only_known_tx_version_validates() {
+        let (_, utxo, v1) = funded_spend();
+        assert_eq!(v1.version, 1);
+        assert_eq!(
+            validate_transaction(&v1, &utxo, DEFAULT_CHAIN_ID, Some(&SigCache::new(0))),
+            Ok(10)
+        );
+
+        let v999 = Transaction::with_version(999, v1.inputs.clone(), v1.outputs.clone());
+        assert_ne!(v999.txid, v1.txid);
+        let err = validate_transaction(&v999, &utxo, DEFAULT_CHAIN_ID, Some(&SigCache::new(0)))
+            .unwrap_err();
+        assert_eq!(err, TxError::UnsupportedVersion);
+        assert_eq!(err.message(), "unsupported tx version");
+    }
+
+    #[test]
+    fn only_compressed_pubkeys_are_accepted() {
+        use std::str::FromStr;
+
+        let (alice, utxo, compressed) = funded_spend();
+        assert_eq!(compressed.inputs[0].pubkey.len(), 66);
+        assert_eq!(
+            validate_transaction(
+                &compressed,
+                &utxo,
+                DEFAULT_CHAIN_ID,
+                Some(&SigCache::new(0))
+            ),
+            Ok(10)
+        );
+
+        // same key and signature, other encoding: a different txid, refused
+        let pk = secp256k1::PublicKey::from_str(&alice.public_key).unwrap();
+        let mut uncompressed = compressed.clone();
+        uncompressed.inputs[0].pubkey = hex::encode(pk.serialize_uncompressed());
+        uncompressed.txid = uncompressed.compute_txid();
+        assert_ne!(uncompressed.txid, compressed.txid);
+        let err = validate_transaction(
+            &uncompressed,
+            &utxo,
+            DEFAULT_CHAIN_ID,
+            Some(&SigCache::new(0)),
+        )
+        .unwrap_err();
+        assert_eq!(err, TxError::BadPubkey("uncompressed pubkey not allowed"));
+        assert_eq!(err.message(), "uncompressed pubkey not allowed");
+    }
+
+    #[test]
+    fn only_lowercase_hex_pubkeys_and_signatures_are_accepted() {
+        let (_, utxo, tx) = funded_spend();
+        let validate = |tx: &Transaction| {
+            validate_transaction(tx, &utxo, DEFAULT_CHAIN_ID, Some(&SigCache::new(0)))
+        };
+        assert_eq!(validate(&tx), Ok(10));
+
+        // same bytes, other case: a different txid for the same signed tx
+        let mut upper_pubkey = tx.clone();
+        upper_pubkey.inputs[0].pubkey = tx.inputs[0].pubkey.to_uppercase();
+        upper_pubkey.txid = upper_pubkey.compute_txid();
+        assert_ne!(upper_pubkey.txid, tx.txid);
+        assert_eq!(
+            validate(&upper_pubkey),
+            Err(TxError::BadPubkey("pubkey hex must be lowercase"))
+        );
+
+        let mut upper_sig = tx.clone();
+        upper_sig.inputs[0].signature = tx.inputs[0].signature.to_uppercase();
+        upper_sig.txid = upper_sig.compute_txid();
+        assert_ne!(upper_sig.txid, tx.txid);
+        assert_eq!(
+            validate(&upper_sig),
+            Err(TxError::BadSignature("signature hex must be lowercase"))
+        );
+    }
+
+    #[test]
+    fn schnorr_inputs_validate_and_scheme_must_match() {
+        let alice = TestWallet::new();
+        let op = OutPoint {
+            txid: "funding".into(),
+            vout: 0,
+        };
+        let mut utxo = UtxoSet::new();
+        utxo.insert(op.clone(), UtxoEntry::chain(output(&alice.address, 100), 0));
+
+        let input = TxInput {
+            outpoint: op,
+            pubkey: alice.public_key.clone(),
+            signature: String::new(),
+            scheme: SigScheme::Schnorr,
+        };
+        let outputs = vec![output("bob", 90)];
+        let sighash =
+            Transaction::new(vec![input.clone()], outputs.clone()).sighash(DEFAULT_CHAIN_ID);
+        let sign = |scheme: SigScheme, sig: String| {
+            let mut i = input.clone();
+            i.scheme = scheme;
+            i.signature = sig;
+            Transaction::new(vec![i], outputs.clone())
+        };
+        let schnorr_sig = sign_schnorr_hex(&alice.private_key, sighash).unwrap();
+        let ecdsa_sig = sign_sighash_hex(&alice.private_key, sighash).unwrap();
+
+        let tx = sign(SigScheme::Schnorr, schnorr_sig.clone());
+        assert_eq!(
+            validate_transaction(&tx, &utxo, DEFAULT_CHAIN_ID, Some(&SigCache::new(0))),
+            Ok(10)
+        );
+
+        // Schnorr signature tagged as ECDSA, and vice versa
+        assert_eq!(
+            validate_transaction(
+                &sign(SigScheme::Ecdsa, schnorr_sig),
+                &utxo,
+                DEFAULT_CHAIN_ID,
+                Some(&SigCache::new(0))
+            ),
+            Err(TxError::BadSignature("invalid DER signature"))
+        );
+        assert_eq!(
+            validate_transaction(
+                &sign(SigScheme::Schnorr, ecdsa_sig),
+                &utxo,
+                DEFAULT_CHAIN_ID,
+                Some(&SigCache::new(0))
+            ),
+            Err(TxError::BadSignature("invalid schnorr signature"))
+        );
+    }
+
+    #[actix_web::test]
+    async fn validate_tx_reports_all_errors() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 50).await;
+
+        // wrong chain id => bad signature; 80 > 50 => insufficient funds
+        let tx = signed_tx_on("other-chain", &[(op, &alice)], vec![output("bob", 80)]);
+        let resp = post(
+            &app,
+            "/api/v1/validate/tx/",
+            json!({ "inputs": tx.inputs, "outputs": tx.outputs }),
+        )
+        .await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+
+        assert_eq!(body["valid"], false);
+        let errors: Vec<&str> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e.as_str().unwrap())
+            .collect();
+        assert!(errors.contains(&"invalid signature"), "{errors:?}");
+        assert!(
+            errors.contains(&"outputs exceed inputs by 30"),
+            "{errors:?}"
+        );
+        assert_eq!(body["input_sum"], 50);
+        assert_eq!(body["output_sum"], 80);
+        assert!(body["fee"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn tx_size_parts_add_up_to_vsize() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let a = faucet(&app, &alice.address, 50).await;
+        let b = faucet(&app, &alice.address, 60).await;
+        let tx = signed_tx(
+            &[(a, &alice), (b, &alice)],
+            vec![output("bob", 40), output("carol", 30)],
+        );
+
+        let resp = post(
+            &app,
+            "/api/v1/tx/size/",
+            json!({ "inputs": tx.inputs, "outputs": tx.outputs }),
+        )
+        .await;
+        let body: Value = read_body_json(resp).await;
+        let sum = |key: &str| -> u64 {
+            body[key]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v.as_u64().unwrap())
+                .sum()
+        };
+        assert_eq!(body["txid"], tx.txid);
+        assert_eq!(body["vsize"], tx.vsize_bytes());
+        assert_eq!(body["inputs"].as_array().unwrap().len(), 2);
+        assert_eq!(body["outputs"].as_array().unwrap().len(), 2);
+        let signatures: usize = tx.inputs.iter().map(|i| i.signature.len()).sum();
+        assert_eq!(body["signature_bytes"], signatures);
+        let total = body["base"].as_u64().unwrap()
+            + sum("inputs")
+            + sum("outputs")
+            + body["signature_bytes"].as_u64().unwrap();
+        assert_eq!(total, tx.vsize_bytes() as u64);
+    }
+
+    #[actix_web::test]
+    async fn verbose_mempool_reports_tx_shape() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let a = faucet(&app, &alice.address, 50).await;
+        let b = faucet(&app, &alice.address, 60).await;
+        let tx = signed_tx(
+            &[(a, &alice), (b, &alice)],
+            vec![output("bob", 40), output("carol", 30), output("dave", 20)],
+        );
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+
+        let body = get_json(&app, "/api/v1/mempool/").await;
+        assert!(body.get("entries").is_none());
+
+        let body = get_json(&app, "/api/v1/mempool/?verbose=true").await;
+        let entry = &body["entries"][0];
+        assert_eq!(entry["txid"], tx.txid);
+        assert_eq!(entry["num_inputs"], 2);
+        assert_eq!(entry["num_outputs"], 3);
+        assert_eq!(entry["vsize"], tx.vsize_bytes());
+        assert_eq!(entry["fee"], 20);
+    }
+
+    #[actix_web::test]
+    async fn mempool_age_follows_the_clock() {
+        use crate::blockchain::MockClock;
+        use std::sync::Arc;
+
+        let state = state();
+        let clock = Arc::new(MockClock::new(1_700_000_000));
+        state.blockchain.lock().unwrap().clock = clock.clone();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 50).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 40)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        assert_eq!(state.mempool.lock().unwrap()[0].received_at, 1_700_000_000);
+
+        let age = || async {
+            let body = get_json(&app, "/api/v1/mempool/?verbose=true").await;
+            body["entries"][0]["age_secs"].as_i64().unwrap()
+        };
+        assert_eq!(age().await, 0);
+        clock.advance(90);
+        assert_eq!(age().await, 90);
+        clock.advance(30);
+        assert_eq!(age().await, 120);
+    }
+
+    #[actix_web::test]
+    async fn raw_tx_round_trips_into_the_mempool() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 50).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 40)]);
+
+        let raw = hex::encode(tx.serialize_bytes());
+        let resp = post(&app, "/api/v1/tx/raw/", json!({ "hex": raw })).await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["txid"], tx.txid);
+        let pooled: Vec<String> = state
+            .mempool
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.tx.txid.clone())
+            .collect();
+        assert_eq!(pooled, vec![tx.txid.clone()]);
+
+        let resp = post(
+            &app,
+            "/api/v1/tx/raw/",
+            json!({ "hex": &raw[..raw.len() - 2] }),
+        )
+        .await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "malformed_raw_tx");
+    }
+
+    #[actix_web::test]
+    async fn tx_and_mine_confirms_in_one_call() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 50).await;
+        let tx = signed_tx(&[(op.clone(), &alice)], vec![output("bob", 40)]);
+        let body = json!({
+            "inputs": tx.inputs,
+            "outputs": tx.outputs,
+            "miner_address": "miner",
+        });
+
+        let resp = post(&app, "/api/v1/tx/and-mine/", body.clone()).await;
+        assert!(resp.status().is_success());
+        let body_json: Value = read_body_json(resp).await;
+        assert_eq!(body_json["txid"], tx.txid);
+        assert_eq!(body_json["block_index"], 1);
+
+        let block = get_json(&app, "/api/v1/chain/").await["chain"][1].clone();
+        assert_eq!(body_json["block_hash"], block["hash"]);
+        assert_eq!(block["transactions"][1]["txid"], tx.txid);
+        assert!(state.mempool.lock().unwrap().is_empty());
+
+        // the same tx again is now a double spend: refused, nothing mined
+        let resp = post(&app, "/api/v1/tx/and-mine/", body).await;
+        assert_eq!(resp.status(), 400);
+        assert_eq!(state.blockchain.lock().unwrap().len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn non_standard_txs_are_mined_but_not_relayed() {
+        let state = state();
+        let node = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&node, &alice.address, 50).await;
+        let dusty = signed_tx(&[(op, &alice)], vec![output("bob", 45), output("bob", 2)]);
+
+        let resp = submit_tx(&node, &dusty).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "non_standard");
+        assert_eq!(body["message"], "dust output");
+
+        // policy, not consensus: the direct mine path takes it
+        let body = json!({
+            "inputs": dusty.inputs,
+            "outputs": dusty.outputs,
+            "miner_address": "miner",
+        });
+        let resp = post(&node, "/api/v1/tx/and-mine/", body).await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["txid"], dusty.txid);
+
+        let relaxed = state_with(NodeConfig::from_lookup(|k| {
+            (k == "STANDARDNESS_CHECKS").then(|| "false".into())
+        }));
+        let relaxed_app = app(&relaxed).await;
+        let op = faucet(&relaxed_app, &alice.address, 50).await;
+        let dusty = signed_tx(&[(op, &alice)], vec![output("bob", 45), output("bob", 2)]);
+        assert!(submit_tx(&relaxed_app, &dusty).await.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn failed_tx_and_mine_leaves_nothing_in_the_mempool() {
+        let state = state();
+        let node = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&node, &alice.address, 50).await;
+        let dusty = signed_tx(&[(op, &alice)], vec![output("bob", 45), output("bob", 2)]);
+        state.shutdown(std::time::Duration::ZERO).await.unwrap();
+
+        let body = json!({
+            "inputs": dusty.inputs,
+            "outputs": dusty.outputs,
+            "miner_address": "miner",
+        });
+        let resp = post(&node, "/api/v1/tx/and-mine/", body).await;
+        assert_eq!(resp.status(), 503);
+        // never mined, so never relayed either
+        assert!(state.mempool.lock().unwrap().is_empty());
+        assert!(state.fee_cache.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn tx_outputs_report_who_spent_them() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 50).await;
+        let split = signed_tx(
+            &[(op, &alice)],
+            vec![output(&alice.address, 20), output(&alice.address, 25)],
+        );
+        assert!(submit_tx(&app, &split).await.status().is_success());
+        mine(&app, "miner").await;
+
+        let first = OutPoint {
+            txid: split.txid.clone(),
+            vout: 0,
+        };
+        let spend = signed_tx(&[(first, &alice)], vec![output("bob", 15)]);
+        assert!(submit_tx(&app, &spend).await.status().is_success());
+        let uri = format!("/api/v1/tx/{}/outputs/", split.txid);
+        // spender still in the mempool: nothing has left the UTXO set yet
+        let body = get_json(&app, &uri).await;
+        assert_eq!(body["outputs"][0]["spent"], false);
+        assert_eq!(body["outputs"][0]["spent_by"], spend.txid);
+
+        mine(&app, "miner").await;
+        let body = get_json(&app, &uri).await;
+        assert_eq!(body["confirmed"], true);
+        assert_eq!(body["outputs"][0]["spent"], true);
+        assert_eq!(body["outputs"][0]["spent_by"], spend.txid);
+        assert_eq!(body["outputs"][1]["amount"], 25);
+        assert_eq!(body["outputs"][1]["spent"], false);
+        assert!(body["outputs"][1]["spent_by"].is_null());
+
+        let resp = get(&app, "/api/v1/tx/nope/outputs/").await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn tx_block_names_the_confirming_block() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 50).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 40)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        let uri = format!("/api/v1/tx/{}/block/", tx.txid);
+        assert_eq!(get(&app, &uri).await.status(), 404);
+
+        mine(&app, "miner").await;
+        mine(&app, "miner").await;
+        let body = get_json(&app, &uri).await;
+        let block = state.blockchain.lock().unwrap().chain[1].clone();
+        assert_eq!(body["index"], 1);
+        assert_eq!(body["hash"], block.hash);
+        assert_eq!(body["tx_count"], 2);
+        assert_eq!(get(&app, "/api/v1/tx/nope/block/").await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn revalidating_a_tx_skips_signature_verification() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 50).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 40)]);
+        let body = json!({ "inputs": tx.inputs, "outputs": tx.outputs });
+
+        let resp = post(&app, "/api/v1/validate/tx/", body.clone()).await;
+        let first: Value = read_body_json(resp).await;
+        assert_eq!(first["valid"], true);
+        assert_eq!(state.sig_cache.stats(), (0, 1));
+
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        assert_eq!(state.sig_cache.stats(), (1, 1)); // no second ECDSA call
+    }
+
+    #[actix_web::test]
+    async fn mempool_package_includes_unconfirmed_parent() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let bob = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+
+        let parent = signed_tx(&[(op, &alice)], vec![output(&bob.address, 90)]);
+        assert!(submit_tx(&app, &parent).await.status().is_success());
+
+        // /tx/ only spends confirmed UTXOs, so chain the child in directly
+        let parent_out = OutPoint {
+            txid: parent.txid.clone(),
+            vout: 0,
+        };
+        let child = signed_tx(&[(parent_out, &bob)], vec![output("carol", 80)]);
+        state
+            .mempool
+            .lock()
+            .unwrap()
+            .push(MempoolEntry::new(child.clone(), 0));
+
+        let body = get_json(&app, &format!("/api/v1/mempool/package/{}/", child.txid)).await;
+        assert_eq!(body["ancestors"], json!([parent.txid]));
+        assert_eq!(body["descendants"], json!([]));
+        assert_eq!(body["transactions"][0]["txid"], parent.txid);
+        assert_eq!(body["transactions"][1]["txid"], child.txid);
+
+        let resp = get(&app, "/api/v1/mempool/package/unknown/").await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn mempool_conflicts_group_double_spends() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+
+        let first = signed_tx(&[(op.clone(), &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&app, &first).await.status().is_success());
+        let body = get_json(&app, "/api/v1/mempool/conflicts/").await;
+        assert_eq!(body["count"], 0);
+
+        // admission would refuse this double spend, so bypass it
+        let second = signed_tx(&[(op.clone(), &alice)], vec![output("carol", 80)]);
+        state
+            .mempool
+            .lock()
+            .unwrap()
+            .push(MempoolEntry::new(second.clone(), 0));
+
+        let body = get_json(&app, "/api/v1/mempool/conflicts/").await;
+        assert_eq!(body["count"], 1);
+        let group = &body["conflicts"][0];
+        assert_eq!(group["outpoint"]["txid"], op.txid);
+        assert_eq!(group["outpoint"]["vout"], op.vout);
+        assert_eq!(group["txids"], json!([first.txid, second.txid]));
+    }
+
+    #[actix_web::test]
+    async fn confirmations_count_blocks_on_top() {
+        let state = state();
+        let app = app(&state).await;
+        state.blockchain.lock().unwrap().set_difficulty(1);
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        let uri = format!("/api/v1/tx/{}/confirmations/", tx.txid);
+
+        let body = get_json(&app, &uri).await;
+        assert_eq!(body["confirmed"], false);
+        assert_eq!(body["confirmations"], 0);
+
+        let mined = mine(&app, "miner").await;
+        mine(&app, "miner").await;
+        mine(&app, "miner").await;
+
+        let body = get_json(&app, &uri).await;
+        assert_eq!(body["confirmed"], true);
+        assert_eq!(body["block_index"], mined["mined_index"]);
+        assert_eq!(body["confirmations"], 3);
+
+        let resp = get(&app, "/api/v1/tx/unknown/confirmations/").await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn rejected_tx_returns_error_code() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 50).await;
+
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 80)]);
+        let resp = submit_tx(&app, &tx).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "insufficient_funds");
+        assert_eq!(body["message"], "outputs exceed inputs by 30");
+    }
+
+    #[actix_web::test]
+    async fn unsigned_spends_pass_only_without_require_signatures() {
+        let unsigned = |op: OutPoint, amount: u64| {
+            let input = TxInput {
+                outpoint: op,
+                pubkey: String::new(),
+                signature: String::new(),
+                scheme: SigScheme::Ecdsa,
+            };
+            Transaction::new(vec![input], vec![output("carol", amount)])
+        };
+
+        let strict = state();
+        let strict_app = app(&strict).await;
+        let op = faucet(&strict_app, "bob", 100).await;
+        let resp = submit_tx(&strict_app, &unsigned(op, 90)).await;
+        assert_eq!(resp.status(), 400);
+
+        let lax = state_with(NodeConfig {
+            require_signatures: false,
+            ..NodeConfig::default()
+        });
+        let app = app(&lax).await;
+        let op = faucet(&app, "bob", 100).await;
+        // existence and amounts are still enforced
+        let missing = OutPoint {
+            txid: "nope".into(),
+            vout: 0,
+        };
+        assert_eq!(submit_tx(&app, &unsigned(missing, 90)).await.status(), 400);
+        assert_eq!(
+            submit_tx(&app, &unsigned(op.clone(), 101)).await.status(),
+            400
+        );
+
+        let resp = submit_tx(&app, &unsigned(op, 90)).await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["fee"], 10);
+        mine(&app, "miner").await;
+        let balance = get_json(&app, "/api/v1/balance/carol/").await;
+        assert_eq!(balance["balance"], 90);
+    }
+
+    #[actix_web::test]
+    async fn overspend_names_the_shortfall_and_fee_is_reported() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+
+        let over = signed_tx(&[(op.clone(), &alice)], vec![output("bob", 117)]);
+        let resp = submit_tx(&app, &over).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "insufficient_funds");
+        assert_eq!(body["message"], "outputs exceed inputs by 17");
+
+        // spending exactly the inputs is a zero fee, not an over-spend
+        let exact = signed_tx(&[(op, &alice)], vec![output("bob", 100)]);
+        let resp = submit_tx(&app, &exact).await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["txid"], exact.txid);
+        assert_eq!(body["fee"], 0);
+    }
+
+    #[actix_web::test]
+    async fn idempotency_key_replays_faucet_response() {
+        use crate::api::idempotency::{IDEMPOTENCY_HEADER, REPLAYED_HEADER};
+        use actix_web::test::{TestRequest, call_service};
+
+        let state = state();
+        let app = app(&state).await;
+        let send = |key: &str| {
+            TestRequest::post()
+                .uri("/api/v1/faucet/")
+                .insert_header((IDEMPOTENCY_HEADER, key.to_string()))
+                .set_json(json!({ "address": "alice", "amount": 10 }))
+                .to_request()
+        };
+
+        let first = call_service(&app, send("3f0c6c1e-key")).await;
+        assert!(first.status().is_success());
+        assert!(first.headers().get(REPLAYED_HEADER).is_none());
+        let first: Value = read_body_json(first).await;
+
+        let retry = call_service(&app, send("3f0c6c1e-key")).await;
+        assert!(retry.status().is_success());
+        assert_eq!(retry.headers().get(REPLAYED_HEADER).unwrap(), "true");
+        let retry: Value = read_body_json(retry).await;
+
+        assert_eq!(first, retry);
+        assert_eq!(state.utxo_set.lock().unwrap().len(), 1);
+
+        // a different key is a different request
+        let other = call_service(&app, send("another-key")).await;
+        assert!(other.headers().get(REPLAYED_HEADER).is_none());
+
+        // the same key with another body is refused, not replayed
+        let utxos = state.utxo_set.lock().unwrap().len();
+        let reused = TestRequest::post()
+            .uri("/api/v1/faucet/")
+            .insert_header((IDEMPOTENCY_HEADER, "3f0c6c1e-key"))
+            .set_json(json!({ "address": "alice", "amount": 11 }))
+            .to_request();
+        let resp = call_service(&app, reused).await;
+        assert_eq!(resp.status(), 422);
+        assert!(resp.headers().get(REPLAYED_HEADER).is_none());
+        assert_eq!(state.utxo_set.lock().unwrap().len(), utxos);
+    }
+
+    #[actix_web::test]
+    async fn mempool_fee_rates_report_min_max_median() {
+        let state = state();
+        let app = app(&state).await;
+        let empty = get_json(&app, "/api/v1/mempool/feerates/").await;
+        assert_eq!(empty["count"], 0);
+        assert!(empty["min"].is_null() && empty["max"].is_null() && empty["median"].is_null());
+
+        let alice = TestWallet::new();
+        let mut rates = Vec::new();
+        for (amount, fee) in [(100, 40), (101, 5), (102, 20)] {
+            let op = faucet(&app, &alice.address, amount).await;
+            let tx = signed_tx(&[(op, &alice)], vec![output("bob", amount - fee)]);
+            rates.push(FeeRateMode::PerByte.fee_rate(fee as u128, &tx));
+            assert!(submit_tx(&app, &tx).await.status().is_success());
         }
 
-        // Verify signature
-        let ok = verify_signature_hex(&input.pubkey, &input.signature, sighash)?;
-        if !ok {
-            return Err("invalid signature");
+        let body = get_json(&app, "/api/v1/mempool/feerates/").await;
+        assert_eq!(body["count"], 3);
+        assert_eq!(body["min"].as_f64().unwrap(), rates[1]);
+        assert_eq!(body["max"].as_f64().unwrap(), rates[0]);
+        assert_eq!(body["median"].as_f64().unwrap(), rates[2]);
+    }
+
+    #[actix_web::test]
+    async fn mempool_pages_by_fee_rate() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+
+        // same shape, different fees => fee rate order follows the fee
+        let mut by_fee = Vec::new();
+        for fee in [5u64, 40, 1, 20] {
+            let op = faucet(&app, &alice.address, 1000 + fee).await;
+            let tx = signed_tx(&[(op, &alice)], vec![output("bob", 1000)]);
+            assert!(submit_tx(&app, &tx).await.status().is_success());
+            by_fee.push((fee, tx.txid));
         }
+        by_fee.sort_by_key(|(fee, _)| std::cmp::Reverse(*fee));
 
-        input_sum += prev_out.amount as u128;
+        let body = get_json(&app, "/api/v1/mempool/?offset=1&limit=2").await;
+        assert_eq!(body["total"], 4);
+        assert_eq!(body["size"], 2);
+        assert_eq!(body["transactions"], json!([by_fee[1].1, by_fee[2].1]));
+
+        let body = get_json(&app, "/api/v1/mempool/").await;
+        assert_eq!(body["size"], 4);
+        assert_eq!(body["transactions"][0], by_fee[0].1.as_str());
+    }
+
+    #[actix_web::test]
+    async fn persisted_mempool_is_revalidated_on_restart() {
+        let dir = temp_data_dir();
+        let config = NodeConfig {
+            data_dir: Some(dir.clone()),
+            persist_mempool: true,
+            ..NodeConfig::default()
+        };
+        let state = state_with(config.clone());
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+
+        let op = faucet(&app, &alice.address, 100).await;
+        let tx = signed_tx(&[(op.clone(), &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        // slipped in without validation: must not survive the restart
+        let bogus = signed_tx_on("other-chain", &[(op, &alice)], vec![output("bob", 80)]);
+        state
+            .mempool
+            .lock()
+            .unwrap()
+            .push(MempoolEntry::new(bogus, 0));
+
+        state.flush().unwrap();
+
+        let restarted = AppState::with_config(config);
+        let mempool = restarted.mempool.lock().unwrap();
+        let txids: Vec<_> = mempool.iter().map(|e| e.tx.txid.clone()).collect();
+        assert_eq!(txids, vec![tx.txid]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn faucet_enforces_per_address_cap() {
+        let config =
+            NodeConfig::from_lookup(|k| (k == "FAUCET_MAX_PER_ADDRESS").then(|| "100".into()));
+        let state = state_with(config);
+        let app = app(&state).await;
+
+        faucet(&app, "alice", 60).await;
+        let resp = post(
+            &app,
+            "/api/v1/faucet/",
+            json!({ "address": "alice", "amount": 41 }),
+        )
+        .await;
+        assert_eq!(resp.status(), 400);
+        // other addresses have their own budget; the rejected request didn't count
+        faucet(&app, "bob", 100).await;
+        faucet(&app, "alice", 40).await;
+    }
+
+    #[actix_web::test]
+    async fn faucet_allowlist_and_max_amount() {
+        let config = NodeConfig::from_lookup(|k| match k {
+            "FAUCET_ALLOWLIST" => Some("alice".into()),
+            "FAUCET_MAX_AMOUNT" => Some("10".into()),
+            _ => None,
+        });
+        let state = state_with(config);
+        let app = app(&state).await;
+
+        let resp = post(
+            &app,
+            "/api/v1/faucet/",
+            json!({ "address": "mallory", "amount": 1 }),
+        )
+        .await;
+        assert_eq!(resp.status(), 403);
+        let resp = post(
+            &app,
+            "/api/v1/faucet/",
+            json!({ "address": "alice", "amount": 11 }),
+        )
+        .await;
+        assert_eq!(resp.status(), 400);
+        faucet(&app, "alice", 10).await;
+    }
+
+    #[actix_web::test]
+    async fn next_block_follows_fee_rate_and_byte_limit() {
+        use crate::blockchain::MAX_BLOCK_BYTES;
+        use crate::transaction::{SigScheme, TxInput, UtxoEntry};
+
+        let state = state();
+        let app = app(&state).await;
+        // ~10 KB each (oversized signatures, never verified here): only some fit
+        let mut txs = Vec::new();
+        for fee in 1..=10u64 {
+            let outpoint = OutPoint {
+                txid: format!("prev-{fee}"),
+                vout: 0,
+            };
+            state.utxo_set.lock().unwrap().insert(
+                outpoint.clone(),
+                UtxoEntry::chain(output("owner", 100_000), 0),
+            );
+            txs.push(Transaction::new(
+                vec![TxInput {
+                    outpoint,
+                    pubkey: "02".repeat(33),
+                    signature: "a".repeat(10_000),
+                    scheme: SigScheme::Ecdsa,
+                }],
+                vec![output("dest", 100_000 - fee * 1000)],
+            ));
+        }
+        let size = txs[0].vsize_bytes();
+        *state.mempool.lock().unwrap() = txs
+            .iter()
+            .map(|tx| MempoolEntry::new(tx.clone(), 0))
+            .collect();
+
+        let body = get_json(&app, "/api/v1/mempool/nextblock/").await;
+        let txids: Vec<&str> = body["txids"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t.as_str().unwrap())
+            .collect();
+        let fits = MAX_BLOCK_BYTES / size;
+        assert!(fits < txs.len());
+        // coinbase first, then the highest fees (same size: same order as fee rate)
+        let expected: Vec<&str> = std::iter::once("coinbase")
+            .chain(txs.iter().rev().take(fits).map(|t| t.txid.as_str()))
+            .collect();
+        assert_eq!(txids, expected);
+        let total_bytes = body["total_bytes"].as_u64().unwrap() as usize;
+        assert!(total_bytes <= MAX_BLOCK_BYTES);
+        assert_eq!(body["max_bytes"], MAX_BLOCK_BYTES);
+        let fees: u64 = (11 - fits as u64..=10).map(|f| f * 1000).sum();
+        assert_eq!(body["total_fees"], fees);
+    }
+
+    #[actix_web::test]
+    async fn amounts_above_u64_get_a_clear_error() {
+        let state = state();
+        let app = app(&state).await;
+        let too_big = "18446744073709551616"; // u64::MAX + 1
+        let bodies = [
+            (
+                "/api/v1/faucet/",
+                format!(r#"{{"address":"alice","amount":{too_big}}}"#),
+            ),
+            (
+                "/api/v1/tx/",
+                format!(r#"{{"inputs":[],"outputs":[{{"address":"bob","amount":{too_big}}}]}}"#),
+            ),
+        ];
+        for (uri, body) in bodies {
+            let req = actix_web::test::TestRequest::post()
+                .uri(uri)
+                .insert_header(("content-type", "application/json"))
+                .set_payload(body)
+                .to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 400, "{uri}");
+            let body: Value = actix_web::test::read_body_json(resp).await;
+            assert_eq!(body["code"], "invalid_json");
+            let message = body["message"].as_str().unwrap();
+            assert!(message.contains("amount exceeds maximum"), "{message}");
+        }
+        // u64::MAX itself still parses
+        let resp = post(
+            &app,
+            "/api/v1/faucet/",
+            json!({ "address": "alice", "amount": u64::MAX }),
+        )
+        .await;
+        assert!(resp.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn coinbase_faucet_pays_out_in_the_next_block() {
+        let config = NodeConfig::from_lookup(|k| (k == "FAUCET_MODE").then(|| "coinbase".into()));
+        let state = state_with(config);
+        let app = app(&state).await;
+
+        let resp = post(
+            &app,
+            "/api/v1/faucet/",
+            json!({ "address": "alice", "amount": 75 }),
+        )
+        .await;
+        assert_eq!(resp.status(), 202);
+        let body: Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body["pending"], 1);
+        let balance = get_json(&app, "/api/v1/balance/alice/").await;
+        assert_eq!(balance["balance"], 0);
+
+        mine(&app, "miner").await;
+        let balance = get_json(&app, "/api/v1/balance/alice/").await;
+        assert_eq!(balance["balance"], 75);
+        assert!(state.faucet_queue.lock().unwrap().is_empty());
+        // paid by a real coinbase, and the replay still balances it
+        let tip = state.blockchain.lock().unwrap().last_block().clone();
+        assert_eq!(tip.transactions[0].outputs[1], output("alice", 75));
+        let full = get_json(&app, "/api/v1/chain/validate/full/").await;
+        assert_eq!(full["valid"], true, "{full}");
+        // the payout counts as faucet supply, not as mined coins
+        let supply = get_json(&app, "/api/v1/supply/").await;
+        assert_eq!(supply["faucet_supply"], 75);
+        assert_eq!(supply["mined_supply"], BASE_REWARD);
+    }
+
+    #[actix_web::test]
+    async fn exempt_coinbase_faucet_payouts_skip_maturity() {
+        let config = NodeConfig::from_lookup(|k| match k {
+            "FAUCET_MODE" => Some("coinbase".into()),
+            "FAUCET_MATURITY_EXEMPT" => Some("1".into()),
+            "COINBASE_MATURITY" => Some("100".into()),
+            _ => None,
+        });
+        let state = state_with(config);
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let miner = TestWallet::new();
+
+        let faucet_req = json!({ "address": alice.address, "amount": 75 });
+        assert_eq!(
+            post(&app, "/api/v1/faucet/", faucet_req).await.status(),
+            202
+        );
+        mine(&app, &miner.address).await;
+        let supply = get_json(&app, "/api/v1/supply/").await;
+        assert_eq!(supply["faucet_supply"], 75);
+        assert_eq!(supply["immature_supply"], BASE_REWARD);
+        let coinbase = state.blockchain.lock().unwrap().last_block().transactions[0].clone();
+        let vout = |vout| OutPoint {
+            txid: coinbase.txid.clone(),
+            vout,
+        };
+
+        // height 2: the faucet payout is spendable, the miner's reward is not
+        let paid = signed_tx(&[(vout(1), &alice)], vec![output("bob", 70)]);
+        assert!(submit_tx(&app, &paid).await.status().is_success());
+        let reward = signed_tx(&[(vout(0), &miner)], vec![output("bob", 45)]);
+        let resp = submit_tx(&app, &reward).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "immature_coinbase");
     }
 
-    // Economic: sum(inputs) >= sum(outputs)
-    let output_sum: u128 = tx.outputs.iter().map(|o| o.amount as u128).sum();
-    if input_sum < output_sum {
-        return Err("inputs total is less than outputs total");
+    #[actix_web::test]
+    async fn replacement_requires_signal_and_higher_fee() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+
+        // non-signaling original: a higher-fee conflict is refused
+        let op = faucet(&app, &alice.address, 100).await;
+        let original = signed_tx(&[(op.clone(), &alice)], vec![output("bob", 95)]);
+        assert!(submit_tx(&app, &original).await.status().is_success());
+        let bump = signed_tx(&[(op, &alice)], vec![output("bob", 80)]);
+        let resp = submit_tx(&app, &bump).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "mempool_conflict");
+
+        // signaling original: same fee is refused, a higher fee replaces it
+        let op = faucet(&app, &alice.address, 200).await;
+        let original = signed_replaceable_tx(&[(op.clone(), &alice)], vec![output("bob", 195)]);
+        assert!(submit_tx(&app, &original).await.status().is_success());
+        let same_fee = signed_tx(&[(op.clone(), &alice)], vec![output("carol", 195)]);
+        let body: Value = read_body_json(submit_tx(&app, &same_fee).await).await;
+        assert_eq!(body["code"], "replacement_fee_too_low");
+        assert!(body["min_accepted_fee_rate"].as_f64().unwrap() > 0.0);
+
+        let bump = signed_tx(&[(op, &alice)], vec![output("bob", 180)]);
+        let resp = submit_tx(&app, &bump).await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["replaced"], json!([original.txid.clone()]));
+
+        let mempool: Vec<_> = state
+            .mempool
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.tx.txid.clone())
+            .collect();
+        assert!(mempool.contains(&bump.txid));
+        assert!(!mempool.contains(&original.txid));
     }
 
-    Ok(())
+    #[actix_web::test]
+    async fn replacement_evicts_descendants_and_pays_for_them() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let bob = TestWallet::new();
+        let carol = TestWallet::new();
+        let op = faucet(&app, &alice.address, 200).await;
+
+        let original =
+            signed_replaceable_tx(&[(op.clone(), &alice)], vec![output(&bob.address, 195)]);
+        assert!(submit_tx(&app, &original).await.status().is_success());
+        // /tx/ only spends confirmed UTXOs, so chain the descendants in directly
+        let spend = |tx: &Transaction| OutPoint {
+            txid: tx.txid.clone(),
+            vout: 0,
+        };
+        let child = signed_tx(
+            &[(spend(&original), &bob)],
+            vec![output(&carol.address, 190)],
+        );
+        let grandchild = signed_tx(&[(spend(&child), &carol)], vec![output("dave", 185)]);
+        for tx in [&child, &grandchild] {
+            state
+                .mempool
+                .lock()
+                .unwrap()
+                .push(MempoolEntry::new(tx.clone(), 0));
+        }
+
+        // outbids the original alone (5) but not the three of them (15)
+        let cheap = signed_tx(&[(op.clone(), &alice)], vec![output("erin", 190)]);
+        let body: Value = read_body_json(submit_tx(&app, &cheap).await).await;
+        assert_eq!(body["code"], "replacement_fee_too_low");
+
+        let bump = signed_tx(&[(op, &alice)], vec![output("erin", 180)]);
+        let resp = submit_tx(&app, &bump).await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(
+            body["replaced"],
+            json!([original.txid, child.txid, grandchild.txid])
+        );
+        let mempool: Vec<_> = state
+            .mempool
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.tx.txid.clone())
+            .collect();
+        assert_eq!(mempool, vec![bump.txid]);
+    }
+
+    #[actix_web::test]
+    async fn fee_cache_follows_replacements_and_blocks() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        let original = signed_replaceable_tx(&[(op.clone(), &alice)], vec![output("bob", 95)]);
+        assert!(submit_tx(&app, &original).await.status().is_success());
+        let cached = |txid: &str| state.fee_cache.lock().unwrap().get(txid).map(|c| c.fee);
+        assert_eq!(cached(&original.txid), Some(5));
+
+        let bump = signed_tx(&[(op, &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&app, &bump).await.status().is_success());
+        assert_eq!(cached(&original.txid), None);
+        assert_eq!(cached(&bump.txid), Some(10));
+
+        let next = get_json(&app, "/api/v1/mempool/nextblock/").await;
+        assert_eq!(next["total_fees"], 10);
+        mine(&app, "miner").await;
+        assert!(state.fee_cache.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn full_mempool_rejection_reports_min_fee_rate() {
+        let state = state_with(NodeConfig {
+            mempool_max_txs: Some(2),
+            ..NodeConfig::default()
+        });
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let pay = async |fee: u64| {
+            // distinct amounts: identical faucet requests share a txid
+            let op = faucet(&app, &alice.address, 1000 + fee).await;
+            signed_tx(&[(op, &alice)], vec![output("bob", 1000)])
+        };
+
+        let cheap = pay(10).await;
+        let pricey = pay(20).await;
+        for tx in [&cheap, &pricey] {
+            assert!(submit_tx(&app, tx).await.status().is_success());
+        }
+
+        let lowball = pay(5).await;
+        let resp = submit_tx(&app, &lowball).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "mempool_full");
+        let submitted = FeeRateMode::PerByte.fee_rate(5, &lowball);
+        let min_rate = body["min_accepted_fee_rate"].as_f64().unwrap();
+        assert!(min_rate > submitted, "{min_rate} <= {submitted}");
+
+        // outbidding the cheapest entry evicts it
+        let bumped = pay(50).await;
+        assert!(submit_tx(&app, &bumped).await.status().is_success());
+        let mempool: Vec<_> = state
+            .mempool
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.tx.txid.clone())
+            .collect();
+        assert_eq!(mempool, vec![pricey.txid.clone(), bumped.txid.clone()]);
+    }
+
+    #[actix_web::test]
+    async fn draft_inputs_flag_missing_outpoints() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        let draft = |inputs: Value| json!({ "inputs": inputs, "outputs": [{ "address": "bob", "amount": 90 }] });
+
+        let resp = post(
+            &app,
+            "/api/v1/tx/inputs/",
+            draft(json!([op, { "txid": "ff".repeat(32), "vout": 3 }])),
+        )
+        .await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        let inputs = body["inputs"].as_array().unwrap();
+        assert_eq!(inputs[0]["exists"], true);
+        assert_eq!(inputs[0]["amount"], 100);
+        assert_eq!(inputs[0]["address"], alice.address.as_str());
+        assert_eq!(inputs[1]["exists"], false);
+        assert_eq!(inputs[1]["vout"], 3);
+        assert!(inputs[1]["amount"].is_null());
+        assert_eq!(body["input_sum"], 100);
+        assert_eq!(body["output_sum"], 90);
+        assert!(body["fee"].is_null(), "no fee while an input is missing");
+
+        let body: Value =
+            read_body_json(post(&app, "/api/v1/tx/inputs/", draft(json!([op]))).await).await;
+        assert_eq!(body["fee"], 10);
+    }
+
+    #[actix_web::test]
+    async fn sighash_endpoint_matches_local_computation() {
+        let state = state();
+        let app = app(&state).await;
+        let outpoint = OutPoint {
+            txid: "ab".repeat(32),
+            vout: 1,
+        };
+        let resp = post(
+            &app,
+            "/api/v1/tx/sighash/",
+            json!({
+                "replaceable": true,
+                "inputs": [{ "txid": outpoint.txid, "vout": 1 }],
+                "outputs": [{ "address": "bob", "amount": 40 }],
+            }),
+        )
+        .await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+
+        let mut draft = Transaction::new(
+            vec![TxInput {
+                outpoint,
+                pubkey: String::new(),
+                signature: String::new(),
+                scheme: SigScheme::Ecdsa,
+            }],
+            vec![output("bob", 40)],
+        );
+        draft.replaceable = true;
+        assert_eq!(body["chain_id"], DEFAULT_CHAIN_ID);
+        assert_eq!(
+            body["sighash"],
+            hex::encode(draft.sighash(DEFAULT_CHAIN_ID))
+        );
+        assert_eq!(
+            body["signing_payload"].as_str().unwrap().as_bytes(),
+            draft.signing_payload(DEFAULT_CHAIN_ID)
+        );
+    }
+
+    #[test]
+    fn replaceable_flag_is_signed() {
+        let alice = TestWallet::new();
+        let op = OutPoint {
+            txid: "funding".into(),
+            vout: 0,
+        };
+        let mut utxo = UtxoSet::new();
+        utxo.insert(op.clone(), UtxoEntry::chain(output(&alice.address, 100), 0));
+        let mut tx = signed_tx(&[(op, &alice)], vec![output("bob", 90)]);
+        tx.replaceable = true;
+        assert_eq!(
+            validate_transaction(&tx, &utxo, DEFAULT_CHAIN_ID, Some(&SigCache::new(0))),
+            Err(TxError::BadSignature("invalid signature"))
+        );
+    }
 }
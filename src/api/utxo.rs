@@ -0,0 +1,441 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, get, web};
+use log::info;
+use std::collections::HashSet;
+
+use super::admin::{authorize, total_value};
+use super::balance::add_capped;
+use super::format::{FormatQuery, json_formatted};
+use super::models::{
+    AppState, OutputView, SupplyResponse, UtxoExportQuery, UtxoExportResponse, UtxoImportRequest,
+    UtxoImportResponse, UtxoResponse, UtxoSummaryResponse,
+};
+use crate::transaction::{OutPoint, UtxoSet, UtxoSource};
+
+/// Max page size of `/utxo/export/` (the default too).
+const EXPORT_MAX_LIMIT: usize = 10_000;
+/// Body limit of `/utxo/import/`: one full export page. A record is ~300
+/// bytes of compact JSON (64-hex txid, 66-hex address, u64 amount); 512
+/// leaves room for pretty-printed exports.
+pub(super) const IMPORT_JSON_MAX_BYTES: usize = EXPORT_MAX_LIMIT * 512;
+
+/// Size and total value of the whole UTXO set (one scan).
+#[get("/utxo/summary/")]
+pub async fn get_utxo_summary(
+    state: web::Data<AppState>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let summary = {
+        let utxo = state.utxo_set.lock().expect("mutex poisoned").snapshot();
+        let mut total_value: u128 = 0;
+        let mut addresses = HashSet::new();
+        for (_, out) in utxo.iter() {
+            total_value = add_capped(total_value, out.amount as u128);
+            addresses.insert(out.address.as_str());
+        }
+        UtxoSummaryResponse {
+            count: utxo.len(),
+            total_value,
+            distinct_addresses: addresses.len(),
+        }
+    };
+    json_formatted(&summary, &fmt)
+}
+
+/// Unspent supply, with faucet-created coins reported apart from mined ones.
+#[get("/supply/")]
+pub async fn get_supply(
+    state: web::Data<AppState>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let spend_height = state.blockchain.lock().expect("mutex poisoned").len() as u64;
+    let utxo = state.utxo_set.lock().expect("mutex poisoned").snapshot();
+    let maturity = state.config.coinbase_maturity;
+
+    let (mut mined, mut faucet, mut immature) = (0u128, 0u128, 0u128);
+    for (_, entry) in utxo.entries() {
+        let amount = entry.output.amount as u128;
+        match entry.source {
+            UtxoSource::Faucet => faucet = add_capped(faucet, amount),
+            UtxoSource::Chain => mined = add_capped(mined, amount),
+        }
+        if !entry.is_mature(spend_height, maturity) {
+            immature = add_capped(immature, amount);
+        }
+    }
+    json_formatted(
+        &SupplyResponse {
+            total_supply: add_capped(mined, faucet),
+            mined_supply: mined,
+            faucet_supply: faucet,
+            immature_supply: immature,
+        },
+        &fmt,
+    )
+}
+
+/// Dump the UTXO set (with creation metadata), sorted by txid then vout so
+/// two exports diff cleanly; paginated with `?offset=`/`?limit=`.
+#[get("/utxo/export/")]
+pub async fn export_utxo_set(
+    state: web::Data<AppState>,
+    query: web::Query<UtxoExportQuery>,
+) -> impl Responder {
+    let utxo = state.utxo_set.lock().expect("mutex poisoned").snapshot();
+    let offset = query.offset.unwrap_or(0);
+    let limit = query
+        .limit
+        .unwrap_or(EXPORT_MAX_LIMIT)
+        .min(EXPORT_MAX_LIMIT);
+    let utxos = utxo
+        .records()
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .collect();
+    HttpResponse::Ok().json(UtxoExportResponse {
+        total: utxo.len(),
+        offset,
+        limit,
+        utxos,
+    })
+}
+
+/// Replace the live UTXO set with an exported one (admin only).
+/// `POST /utxo/import/`, routed in `init_routes` with `IMPORT_JSON_MAX_BYTES`.
+pub async fn import_utxo_set(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<UtxoImportRequest>,
+) -> impl Responder {
+    if let Err(resp) = authorize(&state, &req) {
+        return resp;
+    }
+    let records = body.into_inner().utxos;
+    let count = records.len();
+    let imported = UtxoSet::from_records(records);
+    if imported.len() != count {
+        return HttpResponse::BadRequest().body("duplicate outpoint in import");
+    }
+
+    let mut utxo = state.utxo_set.lock().expect("mutex poisoned");
+    let resp = UtxoImportResponse {
+        count_before: utxo.len(),
+        count_after: imported.len(),
+        total_before: total_value(&utxo),
+        total_after: total_value(&imported),
+    };
+    *utxo = imported;
+    // cached mempool fees were computed against the old set
+    state.fee_cache.lock().expect("mutex poisoned").clear();
+    info!(
+        "admin: imported UTXO set ({} -> {} entries)",
+        resp.count_before, resp.count_after
+    );
+    HttpResponse::Ok().json(resp)
+}
+
+/// Check whether a specific outpoint is still unspent.
+#[get("/utxo/{txid}/{vout}/")]
+pub async fn get_utxo(
+    state: web::Data<AppState>,
+    path: web::Path<(String, u32)>,
+    fmt: web::Query<FormatQuery>,
+) -> impl Responder {
+    let (txid, vout) = path.into_inner();
+    let outpoint = OutPoint { txid, vout };
+
+    let output = {
+        let utxo = state.utxo_set.lock().expect("mutex poisoned");
+        utxo.get(&outpoint).cloned()
+    };
+
+    json_formatted(
+        &UtxoResponse {
+            exists: output.is_some(),
+            output: output.as_ref().map(OutputView::from),
+        },
+        &fmt,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testutil::{
+        TestWallet, app, faucet, get_json, mine, output, signed_tx, state, state_with, submit_tx,
+    };
+    use super::EXPORT_MAX_LIMIT;
+    use crate::blockchain::BASE_REWARD;
+    use crate::config::NodeConfig;
+    use crate::transaction::{OutPoint, UtxoEntry, UtxoRecord, UtxoSet, UtxoSource};
+    use actix_web::test::{self, read_body_json};
+    use serde_json::{Value, json};
+
+    #[actix_web::test]
+    async fn summary_totals_unspent_value() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        faucet(&app, "bob", 25).await;
+
+        let body = get_json(&app, "/api/v1/utxo/summary/").await;
+        assert_eq!(body["count"], 2);
+        assert_eq!(body["total_value"], 125);
+        assert_eq!(body["distinct_addresses"], 2);
+
+        // spend alice's 100 into 90 for carol: the 10 fee goes to the coinbase
+        let tx = signed_tx(&[(op, &alice)], vec![output("carol", 90)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        mine(&app, "miner").await;
+
+        let body = get_json(&app, "/api/v1/utxo/summary/").await;
+        assert_eq!(body["count"], 3); // bob, carol, coinbase
+        assert_eq!(body["total_value"], 125 - 100 + 90 + (BASE_REWARD + 10));
+        assert_eq!(body["distinct_addresses"], 3);
+    }
+
+    #[actix_web::test]
+    async fn reports_spent_after_mining_a_spend() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+
+        let op = faucet(&app, &alice.address, 100).await;
+        let uri = format!("/api/v1/utxo/{}/{}/", op.txid, op.vout);
+
+        let body = get_json(&app, &uri).await;
+        assert_eq!(body["exists"], true);
+        assert_eq!(body["output"]["amount"], 100);
+        assert_eq!(body["output"]["address"], alice.address.as_str());
+
+        let tx = signed_tx(&[(op.clone(), &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        mine(&app, "miner").await;
+
+        let body = get_json(&app, &uri).await;
+        assert_eq!(body["exists"], false);
+        assert!(body["output"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn spends_only_the_selected_outputs_of_a_transaction() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+
+        // one tx, three outputs (fee 10)
+        let fanout = signed_tx(
+            &[(op, &alice)],
+            vec![
+                output(&alice.address, 30),
+                output("bob", 30),
+                output(&alice.address, 30),
+            ],
+        );
+        assert!(submit_tx(&app, &fanout).await.status().is_success());
+        mine(&app, "miner").await;
+
+        let vout = |n: u32| OutPoint {
+            txid: fanout.txid.clone(),
+            vout: n,
+        };
+        for n in 0..3 {
+            let body = get_json(&app, &format!("/api/v1/utxo/{}/{n}/", fanout.txid)).await;
+            assert_eq!(body["exists"], true, "vout {n}");
+            assert_eq!(body["output"]["amount"], 30);
+        }
+
+        // spend vout 0 and vout 2 together, leave vout 1 alone
+        let spend = signed_tx(
+            &[(vout(0), &alice), (vout(2), &alice)],
+            vec![output("carol", 55)],
+        );
+        assert!(submit_tx(&app, &spend).await.status().is_success());
+        mine(&app, "miner").await;
+
+        let utxo = state.utxo_set.lock().unwrap();
+        assert!(!utxo.contains(&vout(0)));
+        assert!(utxo.contains(&vout(1)));
+        assert!(!utxo.contains(&vout(2)));
+        assert_eq!(utxo.get(&vout(1)).unwrap().address, "bob");
+    }
+
+    #[actix_web::test]
+    async fn entries_record_height_and_coinbase_flag() {
+        let state = state();
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+
+        let tx = signed_tx(&[(op.clone(), &alice)], vec![output("bob", 90)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        mine(&app, "miner").await;
+        mine(&app, "miner").await;
+
+        let utxo = state.utxo_set.lock().unwrap();
+        let paid = utxo
+            .entry(&OutPoint {
+                txid: tx.txid.clone(),
+                vout: 0,
+            })
+            .unwrap();
+        assert_eq!((paid.height, paid.is_coinbase), (1, false));
+
+        let mut coinbase_heights: Vec<u64> = utxo
+            .entries()
+            .filter(|(_, e)| e.is_coinbase)
+            .map(|(_, e)| e.height)
+            .collect();
+        coinbase_heights.sort();
+        assert_eq!(coinbase_heights, vec![1, 2]);
+    }
+
+    #[actix_web::test]
+    async fn faucet_coins_skip_coinbase_maturity() {
+        let state = state_with(NodeConfig {
+            coinbase_maturity: 3,
+            ..NodeConfig::default()
+        });
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let miner = TestWallet::new();
+
+        // faucet output: spendable right away
+        let op = faucet(&app, &alice.address, 100).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("bob", 100)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        mine(&app, &miner.address).await;
+
+        let coinbase = OutPoint {
+            txid: state.blockchain.lock().unwrap().last_block().transactions[0]
+                .txid
+                .clone(),
+            vout: 0,
+        };
+        let supply = get_json(&app, "/api/v1/supply/").await;
+        assert_eq!(supply["mined_supply"], BASE_REWARD + 100);
+        assert_eq!(supply["faucet_supply"], 0);
+        assert_eq!(supply["immature_supply"], BASE_REWARD);
+
+        // coinbase mined at height 1 is spendable from block 4 on
+        let spend = signed_tx(&[(coinbase, &miner)], vec![output("carol", 10)]);
+        let resp = submit_tx(&app, &spend).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "immature_coinbase");
+
+        faucet(&app, "dave", 7).await;
+        mine(&app, "other1").await;
+        mine(&app, "other2").await;
+        assert!(submit_tx(&app, &spend).await.status().is_success());
+
+        let supply = get_json(&app, "/api/v1/supply/").await;
+        assert_eq!(supply["faucet_supply"], 7);
+        assert_eq!(supply["total_supply"], 3 * BASE_REWARD + 100 + 7);
+    }
+
+    #[actix_web::test]
+    async fn export_then_import_restores_balances() {
+        let state = state_with(NodeConfig {
+            admin_token: Some("s3cret".into()),
+            ..NodeConfig::default()
+        });
+        let app = app(&state).await;
+        let alice = TestWallet::new();
+        let op = faucet(&app, &alice.address, 100).await;
+        faucet(&app, "bob", 25).await;
+        let tx = signed_tx(&[(op, &alice)], vec![output("carol", 90)]);
+        assert!(submit_tx(&app, &tx).await.status().is_success());
+        mine(&app, "miner").await;
+
+        let balance = |addr: &str| format!("/api/v1/balance/{addr}/");
+        let mut before = Vec::new();
+        for addr in ["bob", "carol", "miner"] {
+            before.push(get_json(&app, &balance(addr)).await);
+        }
+
+        let export = get_json(&app, "/api/v1/utxo/export/").await;
+        assert_eq!(export["total"], 3);
+        let keys: Vec<(String, u64)> = export["utxos"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|u| {
+                let op = &u["outpoint"];
+                (
+                    op["txid"].as_str().unwrap().into(),
+                    op["vout"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+        // same set, same bytes
+        assert_eq!(get_json(&app, "/api/v1/utxo/export/").await, export);
+        let page = get_json(&app, "/api/v1/utxo/export/?offset=1&limit=1").await;
+        assert_eq!(page["utxos"][0], export["utxos"][1]);
+
+        *state.utxo_set.lock().unwrap() = UtxoSet::new();
+        assert_eq!(get_json(&app, &balance("carol")).await["balance"], 0);
+
+        let import = |token: &'static str| {
+            test::TestRequest::post()
+                .uri("/api/v1/utxo/import/")
+                .insert_header(("Authorization", format!("Bearer {token}")))
+                .set_json(&export)
+                .to_request()
+        };
+        let resp = test::call_service(&app, import("wrong")).await;
+        assert_eq!(resp.status(), 401);
+        let resp = test::call_service(&app, import("s3cret")).await;
+        assert!(resp.status().is_success());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["count_before"], 0);
+        assert_eq!(body["count_after"], 3);
+
+        for (addr, expected) in ["bob", "carol", "miner"].into_iter().zip(&before) {
+            assert_eq!(&get_json(&app, &balance(addr)).await, expected);
+        }
+        assert_eq!(get_json(&app, "/api/v1/utxo/export/").await, export);
+    }
+
+    #[actix_web::test]
+    async fn import_takes_a_full_export_page() {
+        let state = state_with(NodeConfig {
+            admin_token: Some("s3cret".into()),
+            ..NodeConfig::default()
+        });
+        let app = app(&state).await;
+        let address = TestWallet::new().address;
+        let utxos: Vec<UtxoRecord> = (0..EXPORT_MAX_LIMIT)
+            .map(|i| UtxoRecord {
+                outpoint: OutPoint {
+                    txid: format!("{i:064x}"),
+                    vout: u32::MAX,
+                },
+                entry: UtxoEntry {
+                    output: output(&address, u64::MAX),
+                    height: u64::MAX,
+                    is_coinbase: true,
+                    source: UtxoSource::Faucet,
+                    maturity_exempt: true,
+                },
+            })
+            .collect();
+        let body = json!({ "utxos": utxos });
+        // far over JSON_MAX_BYTES, within the import route's own limit
+        assert!(body.to_string().len() > state.config.json_max_bytes);
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/utxo/import/")
+            .insert_header(("Authorization", "Bearer s3cret"))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["count_after"], EXPORT_MAX_LIMIT);
+    }
+}
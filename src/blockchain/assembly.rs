@@ -0,0 +1,431 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::{MAX_BLOCK_BYTES, MAX_TXS_PER_BLOCK};
+use crate::transaction::{OutPoint, Transaction, TxOutput, UtxoSet};
+
+/// How candidate transactions are ranked when assembling a block.
+///
+/// - `PerByte`: fee / `vsize_bytes()` (every serialized byte costs the same).
+/// - `PerWeight`: fee / `weight()` (segwit-style: base data counts 4x, witness
+///   data — pubkeys and signatures — counts 1x, so signature-heavy transactions
+///   are not penalized as much).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FeeRateMode {
+    #[default]
+    PerByte,
+    PerWeight,
+}
+
+impl FeeRateMode {
+    /// Parse the `FEE_RATE_MODE` env value (`byte` or `weight`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "byte" | "sat/byte" => Some(Self::PerByte),
+            "weight" | "sat/weight" => Some(Self::PerWeight),
+            _ => None,
+        }
+    }
+
+    /// Size of `tx` in the unit used by this mode.
+    pub fn size_of(&self, tx: &Transaction) -> usize {
+        match self {
+            Self::PerByte => tx.vsize_bytes(),
+            Self::PerWeight => tx.weight(),
+        }
+    }
+
+    /// Fee rate of `tx` paying `fee` (sat/byte or sat/weight).
+    pub fn fee_rate(&self, fee: u128, tx: &Transaction) -> f64 {
+        rate(fee, self.size_of(tx))
+    }
+}
+
+fn rate(fee: u128, size: usize) -> f64 {
+    if size > 0 {
+        fee as f64 / size as f64
+    } else {
+        0.0
+    }
+}
+
+/// Fee and sizes of a mempool tx, worked out once when it was admitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachedFee {
+    pub fee: u128,
+    pub vsize: usize,
+    pub weight: usize,
+}
+
+impl CachedFee {
+    pub fn new(fee: u128, tx: &Transaction) -> Self {
+        Self {
+            fee,
+            vsize: tx.vsize_bytes(),
+            weight: tx.weight(),
+        }
+    }
+
+    /// Same value as `mode.fee_rate(fee, tx)`.
+    pub fn fee_rate(&self, mode: FeeRateMode) -> f64 {
+        match mode {
+            FeeRateMode::PerByte => rate(self.fee, self.vsize),
+            FeeRateMode::PerWeight => rate(self.fee, self.weight),
+        }
+    }
+}
+
+/// Fees of admitted mempool txs by txid, so block assembly doesn't look
+/// every input up in the UTXO set again. An entry holds as long as none of
+/// its tx's inputs is spent elsewhere: `invalidate` after every block (and
+/// any mempool eviction), `clear` when the UTXO set is replaced.
+#[derive(Debug, Default)]
+pub struct FeeCache {
+    entries: HashMap<String, CachedFee>,
+}
+
+impl FeeCache {
+    pub fn insert(&mut self, tx: &Transaction, fee: u128) {
+        self.entries
+            .insert(tx.txid.clone(), CachedFee::new(fee, tx));
+    }
+
+    pub fn get(&self, txid: &str) -> Option<&CachedFee> {
+        self.entries.get(txid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Keep only entries of txs still in `mempool` that spend none of `spent`.
+    pub fn invalidate<'a>(
+        &mut self,
+        mempool: impl IntoIterator<Item = &'a Transaction>,
+        spent: &HashSet<&OutPoint>,
+    ) {
+        let live: HashSet<&str> = mempool
+            .into_iter()
+            .filter(|tx| !tx.inputs.iter().any(|i| spent.contains(&i.outpoint)))
+            .map(|tx| tx.txid.as_str())
+            .collect();
+        self.entries.retain(|txid, _| live.contains(txid.as_str()));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Fee paid by `tx` (inputs - outputs) looked up in `utxo`. `None` if an
+/// input is not in the set or the outputs exceed the inputs.
+pub fn tx_fee(tx: &Transaction, utxo: &UtxoSet) -> Option<u128> {
+    let mut input_sum: u128 = 0;
+    for input in &tx.inputs {
+        input_sum += utxo.get(&input.outpoint)?.amount as u128;
+    }
+    input_sum.checked_sub(tx.total_output_amount())
+}
+
+/// Seleciona transações da mempool priorizando fee rate (sat/byte ou sat/weight),
+/// respeitando limites de bytes e contagem, e evitando double-spend
+/// dentro do mesmo bloco. Retorna (txs_selecionadas, total_fees).
+pub fn select_transactions(
+    mempool: &[Transaction],
+    utxo: &UtxoSet,
+    mode: FeeRateMode,
+) -> (Vec<Transaction>, u128) {
+    select_transactions_cached(mempool, utxo, mode, &FeeCache::default())
+}
+
+/// `select_transactions` usando as fees já guardadas em `cache`; só as txs
+/// fora do cache têm os inputs buscados no UTXO.
+pub fn select_transactions_cached(
+    mempool: &[Transaction],
+    utxo: &UtxoSet,
+    mode: FeeRateMode,
+    cache: &FeeCache,
+) -> (Vec<Transaction>, u128) {
+    // 1) Pré-calcular fee e tamanho de cada tx; descartar inválidas de cara
+    #[derive(Clone)]
+    struct Cand {
+        idx: usize,
+        fee: u128,
+        size: usize,
+        fee_rate: f64,
+    }
+
+    let mut cands: Vec<Cand> = Vec::new();
+    for (idx, tx) in mempool.iter().enumerate() {
+        if tx.inputs.is_empty() {
+            // não aceitamos coinbase-like na mempool
+            continue;
+        }
+
+        // fee do cache ou a partir do UTXO; input inexistente ou economics
+        // inválida => descarta
+        let cached = match cache.get(&tx.txid) {
+            Some(cached) => *cached,
+            None => match tx_fee(tx, utxo) {
+                Some(fee) => CachedFee::new(fee, tx),
+                None => continue,
+            },
+        };
+        let fee = cached.fee;
+        // o limite de bloco é sempre em bytes; o modo só afeta a ordenação
+        let size = cached.vsize;
+        let fee_rate = cached.fee_rate(mode);
+
+        cands.push(Cand {
+            idx,
+            fee,
+            size,
+            fee_rate,
+        });
+    }
+
+    // 2) Ordenar por fee_rate desc; tie-break por fee desc, depois txid asc
+    cands.sort_by(|a, b| {
+        b.fee_rate
+            .partial_cmp(&a.fee_rate)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| b.fee.cmp(&a.fee))
+            .then_with(|| mempool[a.idx].txid.cmp(&mempool[b.idx].txid))
+    });
+
+    // 3) Greedy packing respeitando limites + prevenindo double-spend
+    let mut total_fees: u128 = 0;
+    let mut total_bytes: usize = 0;
+    let mut picked: Vec<Transaction> = Vec::new();
+    let mut consumed = HashSet::<(String, u32)>::new();
+
+    for c in cands {
+        if picked.len() >= MAX_TXS_PER_BLOCK {
+            break;
+        }
+        if total_bytes + c.size > MAX_BLOCK_BYTES {
+            continue;
+        }
+
+        let tx = &mempool[c.idx];
+
+        // checar double-spend contra `consumed`
+        let mut ok = true;
+        for input in &tx.inputs {
+            let key = (input.outpoint.txid.clone(), input.outpoint.vout);
+            if consumed.contains(&key) {
+                ok = false;
+                break;
+            }
+        }
+        if !ok {
+            continue;
+        }
+
+        // passa: adiciona, marca inputs como consumidos
+        for input in &tx.inputs {
+            consumed.insert((input.outpoint.txid.clone(), input.outpoint.vout));
+        }
+
+        total_fees += c.fee;
+        total_bytes += c.size;
+        picked.push(tx.clone());
+    }
+
+    (picked, total_fees)
+}
+
+/// One participant of a split coinbase (mining pools): receives
+/// `weight / sum(weights)` of the block reward.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoinbaseShare {
+    pub address: String,
+    pub weight: u64,
+}
+
+impl CoinbaseShare {
+    /// The whole reward to one address (the usual unsplit coinbase).
+    pub fn solo(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            weight: 1,
+        }
+    }
+}
+
+/// Reject share lists `split_reward` can't honour: empty or with weights
+/// summing to 0, or a share without an address.
+pub fn check_shares(shares: &[CoinbaseShare]) -> Result<(), &'static str> {
+    if shares.iter().any(|s| s.address.trim().is_empty()) {
+        return Err("every payout needs an address");
+    }
+    // a single zero weight is fine (that payee gets nothing), all of them isn't
+    if shares.iter().all(|s| s.weight == 0) {
+        return Err("payout weights must sum to more than 0");
+    }
+    Ok(())
+}
+
+/// Miner outputs of a coinbase paying `amount`: one per share with a
+/// non-zero weight (zero-weight shares get none), proportional to it
+/// (rounded down), with the rounding remainder added to the first output so
+/// they always sum to exactly `amount`. `shares` must pass `check_shares`.
+pub fn split_reward(amount: u64, shares: &[CoinbaseShare]) -> Vec<TxOutput> {
+    let total_weight: u128 = shares.iter().map(|s| s.weight as u128).sum();
+    let mut outputs: Vec<TxOutput> = shares
+        .iter()
+        .filter(|s| s.weight > 0)
+        .map(|s| TxOutput {
+            address: s.address.clone(),
+            // share <= amount, so it fits back into a u64
+            amount: (amount as u128 * s.weight as u128 / total_weight.max(1)) as u64,
+        })
+        .collect();
+    let paid: u64 = outputs.iter().map(|o| o.amount).sum();
+    if let Some(first) = outputs.first_mut() {
+        first.amount += amount - paid;
+    }
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CoinbaseShare, FeeCache, FeeRateMode, check_shares, select_transactions,
+        select_transactions_cached, split_reward, tx_fee,
+    };
+    use crate::transaction::{
+        OutPoint, SigScheme, Transaction, TxInput, TxOutput, UtxoEntry, UtxoSet,
+    };
+    use std::collections::HashSet;
+
+    /// Spend a 10_000 sat UTXO, paying `fee`, with a signature of `sig_len` hex chars.
+    fn spend(utxo: &mut UtxoSet, prev_txid: &str, sig_len: usize, fee: u64) -> Transaction {
+        let outpoint = OutPoint {
+            txid: prev_txid.into(),
+            vout: 0,
+        };
+        utxo.insert(
+            outpoint.clone(),
+            UtxoEntry::chain(
+                TxOutput {
+                    address: "owner".into(),
+                    amount: 10_000,
+                },
+                0,
+            ),
+        );
+        Transaction::new(
+            vec![TxInput {
+                outpoint,
+                pubkey: "02".repeat(33),
+                signature: "a".repeat(sig_len),
+                scheme: SigScheme::Ecdsa,
+            }],
+            vec![TxOutput {
+                address: "dest".into(),
+                amount: 10_000 - fee,
+            }],
+        )
+    }
+
+    #[test]
+    fn weight_discounts_witness_data() {
+        let mut utxo = UtxoSet::new();
+        let tx = spend(&mut utxo, "prev", 1000, 1);
+        let witness = tx.inputs[0].pubkey.len() + tx.inputs[0].signature.len();
+        let base = tx.vsize_bytes() - witness;
+        assert_eq!(tx.weight(), base * 4 + witness);
+    }
+
+    #[test]
+    fn modes_order_big_and_small_signatures_differently() {
+        let mut utxo = UtxoSet::new();
+        let big_sig = spend(&mut utxo, "prev-big", 2000, 1500);
+        let small_sig = spend(&mut utxo, "prev-small", 10, 250);
+        let mempool = vec![big_sig.clone(), small_sig.clone()];
+
+        let (by_byte, fees_byte) = select_transactions(&mempool, &utxo, FeeRateMode::PerByte);
+        let (by_weight, fees_weight) = select_transactions(&mempool, &utxo, FeeRateMode::PerWeight);
+
+        // same set (both fit), different priority
+        assert_eq!(fees_byte, fees_weight);
+        assert_eq!(by_byte[0].txid, small_sig.txid);
+        assert_eq!(by_weight[0].txid, big_sig.txid);
+    }
+
+    #[test]
+    fn cached_selection_matches_uncached() {
+        let mut utxo = UtxoSet::new();
+        let mempool: Vec<Transaction> = (0..6)
+            .map(|i| {
+                spend(
+                    &mut utxo,
+                    &format!("prev-{i}"),
+                    100 * (i + 1),
+                    70 + 40 * i as u64,
+                )
+            })
+            .collect();
+        let mut cache = FeeCache::default();
+        for tx in &mempool[..4] {
+            cache.insert(tx, tx_fee(tx, &utxo).unwrap());
+        }
+        for mode in [FeeRateMode::PerByte, FeeRateMode::PerWeight] {
+            let (plain, plain_fees) = select_transactions(&mempool, &utxo, mode);
+            let (cached, cached_fees) = select_transactions_cached(&mempool, &utxo, mode, &cache);
+            let txids =
+                |txs: &[Transaction]| txs.iter().map(|t| t.txid.clone()).collect::<Vec<_>>();
+            assert_eq!(txids(&cached), txids(&plain));
+            assert_eq!(cached_fees, plain_fees);
+        }
+
+        // a block spending prev-0 makes mempool[0]'s entry stale
+        let spent: HashSet<&OutPoint> = [&mempool[0].inputs[0].outpoint].into_iter().collect();
+        cache.invalidate(&mempool, &spent);
+        assert!(cache.get(&mempool[0].txid).is_none());
+        assert_eq!(cache.len(), 3);
+        cache.invalidate(&mempool[2..], &HashSet::new());
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn parse_fee_rate_mode() {
+        assert_eq!(FeeRateMode::parse("weight"), Some(FeeRateMode::PerWeight));
+        assert_eq!(FeeRateMode::parse("BYTE"), Some(FeeRateMode::PerByte));
+        assert_eq!(FeeRateMode::parse("nope"), None);
+    }
+
+    #[test]
+    fn reward_split_is_proportional_and_exact() {
+        let share = |address: &str, weight| CoinbaseShare {
+            address: address.into(),
+            weight,
+        };
+        let outs = split_reward(50, &[share("a", 3), share("b", 1)]);
+        // 37.5 / 12.5: the lost half of each goes to the first output
+        assert_eq!((outs[0].amount, outs[1].amount), (38, 12));
+
+        let outs = split_reward(100, &[share("a", 1), share("b", 1), share("c", 1)]);
+        let amounts: Vec<u64> = outs.iter().map(|o| o.amount).collect();
+        assert_eq!(amounts, vec![34, 33, 33]);
+
+        // a zero-weight share gets no output; the remainder goes to the next
+        let outs = split_reward(50, &[share("a", 0), share("b", 3), share("c", 1)]);
+        let paid: Vec<(&str, u64)> = outs
+            .iter()
+            .map(|o| (o.address.as_str(), o.amount))
+            .collect();
+        assert_eq!(paid, vec![("b", 38), ("c", 12)]);
+
+        assert!(check_shares(&[share("a", 0), share("b", 0)]).is_err());
+        assert!(check_shares(&[share("a", 0), share("b", 1)]).is_ok());
+        assert!(check_shares(&[share(" ", 1)]).is_err());
+        assert!(check_shares(&[]).is_err());
+        assert!(check_shares(&[share("a", 1), share("b", 2)]).is_ok());
+    }
+}
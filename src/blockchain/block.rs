@@ -1,8 +1,33 @@
-use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt;
 
+use super::clock::{Clock, SystemClock};
+use super::merkle::{MerkleStep, merkle_proof, merkle_root};
+use super::pow::{PowAlgo, expected_hashes, meets_difficulty};
+use crate::serde_util::is_false;
 use crate::transaction::Transaction;
+use crate::wallet::{SigCache, SigCheck};
+
+/// Where a block's signature verification failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadSignature {
+    pub tx_index: usize,
+    pub input_index: usize,
+    pub txid: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for BadSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tx #{} ({}) input #{}: {}",
+            self.tx_index, self.txid, self.input_index, self.reason
+        )
+    }
+}
 
 /// A single block in the blockchain holding a list of transactions.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,62 +35,114 @@ pub struct Block {
     pub index: u64,
     pub timestamp: i64, // Unix timestamp (UTC)
     pub previous_hash: String,
+    /// Leading hex zeros this block was mined at (committed in the hash; 0 for genesis)
+    #[serde(default)]
+    pub difficulty: u32,
+    /// Number of transactions, committed in the hash so light clients can trust
+    /// it without the body. Must equal `transactions.len()`.
+    #[serde(default)]
+    pub tx_count: u64,
+    /// Merkle root of the txids, committed in the hash: SPV clients check
+    /// `/block/{id}/proof/{txid}/` against it. Must match `transactions`.
+    #[serde(default)]
+    pub merkle_root: String,
     pub nonce: u64,   // Proof-of-Work nonce
     pub hash: String, // Cached hash of the block
     pub transactions: Vec<Transaction>,
+    /// Body dropped by pruning (`PRUNE_DEPTH`): `transactions` is empty while
+    /// `tx_count` and `merkle_root` still describe it. Not part of the hash.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub pruned: bool,
 }
 
 impl Block {
-    /// Create the genesis block (first block in the chain).
+    /// Create the genesis block (first block in the chain), stamped now.
     pub fn genesis() -> Self {
-        let mut block = Self {
-            index: 0,
-            timestamp: Utc::now().timestamp(),
-            previous_hash: String::from("0"),
-            nonce: 0,
-            hash: String::new(),
-            transactions: Vec::new(), // we can later include a coinbase if we want
-        };
-        block.hash = block.compute_hash();
-        block
+        Self::genesis_at(&SystemClock)
+    }
+
+    /// Genesis block stamped by `clock`.
+    pub fn genesis_at(clock: &dyn Clock) -> Self {
+        // no transactions: merkle root is EMPTY_MERKLE_ROOT, difficulty 0
+        Self::new_with_timestamp(0, String::from("0"), Vec::new(), clock.now_timestamp())
     }
 
     /// Create a new block (not mined yet). Call `mine()` to perform PoW.
     pub fn new(index: u64, previous_hash: String, transactions: Vec<Transaction>) -> Self {
-        let mut block = Self {
-            index,
-            timestamp: Utc::now().timestamp(),
-            previous_hash,
-            nonce: 0,
-            hash: String::new(),
-            transactions,
-        };
-        block.hash = block.compute_hash();
-        block
+        Self::new_at(index, previous_hash, transactions, &SystemClock)
+    }
+
+    /// Same as `new`, stamped by `clock`.
+    pub fn new_at(
+        index: u64,
+        previous_hash: String,
+        transactions: Vec<Transaction>,
+        clock: &dyn Clock,
+    ) -> Self {
+        Self::new_with_timestamp(index, previous_hash, transactions, clock.now_timestamp())
+    }
+
+    /// Bytes hashed for both the block hash and the PoW digest: every field
+    /// except `hash`, with transactions serialized deterministically as JSON.
+    /// Exposed so external miners can debug their hashing.
+    pub fn preimage(&self) -> String {
+        let (prefix, suffix) = self.preimage_parts();
+        format!("{prefix}{}{suffix}", self.nonce)
+    }
+
+    /// The preimage split around the nonce: `prefix + nonce + suffix`.
+    pub fn preimage_parts(&self) -> (String, String) {
+        let txs_json = serde_json::to_string(&self.transactions).expect("serialize txs");
+        (
+            format!(
+                "{}:{}:{}:{}:{}:{}:",
+                self.index,
+                self.timestamp,
+                self.previous_hash,
+                self.difficulty,
+                self.tx_count,
+                self.merkle_root
+            ),
+            format!(":{txs_json}"),
+        )
     }
 
     /// Compute the SHA-256 hash of this block using its fields
     /// (excluding the `hash` field itself). Transactions are serialized
     /// deterministically as JSON and included in the preimage.
     pub fn compute_hash(&self) -> String {
-        let txs_json = serde_json::to_string(&self.transactions).expect("serialize txs");
-        let preimage = format!(
-            "{}:{}:{}:{}:{}",
-            self.index, self.timestamp, self.previous_hash, self.nonce, txs_json
-        );
         let mut hasher = Sha256::new();
-        hasher.update(preimage.as_bytes());
+        hasher.update(self.preimage().as_bytes());
         let digest = hasher.finalize();
         hex::encode(digest)
     }
 
+    /// Digest compared against the difficulty target under `algo`.
+    /// For SHA-256 this is the block hash itself.
+    pub fn pow_hash(&self, algo: PowAlgo) -> String {
+        match algo {
+            PowAlgo::Sha256 => self.compute_hash(),
+            _ => algo.digest_hex(self.preimage().as_bytes()),
+        }
+    }
+
     /// Perform Proof-of-Work by finding a nonce that yields a hash
     /// starting with `difficulty` leading zeros (in hex).
     pub fn mine(&mut self, difficulty: u32) {
-        let target_prefix = "0".repeat(difficulty as usize);
+        self.mine_with(difficulty, PowAlgo::Sha256);
+    }
+
+    /// Same as `mine`, but the target check uses `algo`'s digest.
+    /// `hash` is always left as the SHA-256 block hash.
+    pub fn mine_with(&mut self, difficulty: u32, algo: PowAlgo) {
+        self.difficulty = difficulty;
         loop {
             self.hash = self.compute_hash();
-            if self.hash.starts_with(&target_prefix) {
+            let pow = match algo {
+                PowAlgo::Sha256 => self.hash.clone(),
+                _ => self.pow_hash(algo),
+            };
+            if meets_difficulty(&pow, difficulty) {
                 break;
             }
             self.nonce = self.nonce.wrapping_add(1);
@@ -75,14 +152,139 @@ impl Block {
     /// Validate that the block's cached `hash` matches its content and
     /// satisfies the PoW difficulty. (Does NOT validate chain linkage.)
     pub fn is_valid(&self, difficulty: u32) -> bool {
+        self.is_valid_with(difficulty, PowAlgo::Sha256)
+    }
+
+    /// Same as `is_valid`, checking PoW with `algo`.
+    /// A pruned block's hash can't be recomputed (the preimage includes the
+    /// transactions). With SHA-256 PoW its cached hash is still checked
+    /// against the target; with any other algorithm the PoW hash isn't kept,
+    /// so a pruned header is trusted as validated when it was first accepted
+    /// (pruning only ever touches blocks this node already validated).
+    pub fn is_valid_with(&self, difficulty: u32, algo: PowAlgo) -> bool {
+        if self.pruned {
+            return algo != PowAlgo::Sha256 || meets_difficulty(&self.hash, difficulty);
+        }
         let expected = self.compute_hash();
         if self.hash != expected {
             return false;
         }
-        self.hash
-            .chars()
-            .take(difficulty as usize)
-            .all(|c| c == '0')
+        match algo {
+            PowAlgo::Sha256 => meets_difficulty(&self.hash, difficulty),
+            _ => meets_difficulty(&self.pow_hash(algo), difficulty),
+        }
+    }
+
+    /// Expected number of hashes to find this block (see `expected_hashes`).
+    pub fn work(&self) -> u128 {
+        expected_hashes(self.difficulty)
+    }
+
+    /// Structural checks on the block's transaction list:
+    /// the committed `tx_count` and `merkle_root` must match the body, every
+    /// `txid` must be unique (a repeated tx would double-apply its outputs to
+    /// the UTXO set), at most one coinbase (no inputs) is allowed and its
+    /// `coinbase_height`, when set, must be this block's index.
+    pub fn check_transactions(&self) -> Result<(), &'static str> {
+        if self.tx_count != self.transactions.len() as u64 {
+            return Err("tx_count does not match the number of transactions");
+        }
+        if self.merkle_root != txs_merkle_root(&self.transactions) {
+            return Err("merkle_root does not match the transactions");
+        }
+        let mut seen = HashSet::with_capacity(self.transactions.len());
+        for tx in &self.transactions {
+            if !seen.insert(tx.txid.as_str()) {
+                return Err("duplicate transaction in block");
+            }
+        }
+        let coinbases = self
+            .transactions
+            .iter()
+            .filter(|tx| tx.inputs.is_empty())
+            .count();
+        if coinbases > 1 {
+            return Err("more than one coinbase transaction in block");
+        }
+        for tx in &self.transactions {
+            match tx.coinbase_height {
+                Some(_) if !tx.inputs.is_empty() => {
+                    return Err("coinbase_height set on a non-coinbase transaction");
+                }
+                Some(height) if height != self.index => {
+                    return Err("coinbase_height does not match the block index");
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the transactions, keeping the header (`hash`, `tx_count`,
+    /// `merkle_root`, ...). From here on the block's hash is trusted.
+    pub fn prune(&mut self) {
+        self.transactions = Vec::new();
+        self.pruned = true;
+    }
+
+    /// The block's coinbase: its first tx, if that one has no inputs.
+    /// `None` for the genesis block (no transactions at all) and pruned blocks.
+    pub fn coinbase(&self) -> Option<&Transaction> {
+        self.transactions.first().filter(|tx| tx.inputs.is_empty())
+    }
+
+    /// Inclusion proof of `txid` against `merkle_root`. `None` if the
+    /// transaction is not in this block.
+    pub fn merkle_proof(&self, txid: &str) -> Option<Vec<MerkleStep>> {
+        let txids: Vec<String> = self.transactions.iter().map(|t| t.txid.clone()).collect();
+        let index = txids.iter().position(|t| t == txid)?;
+        merkle_proof(&txids, index)
+    }
+
+    /// Verify every input signature of every non-coinbase tx as one batch.
+    /// Only checks signatures against each tx's sighash (ownership/UTXO
+    /// existence is the UTXO layer's job). The error pinpoints the first bad input.
+    pub fn verify_signatures(&self, chain_id: &str) -> Result<(), BadSignature> {
+        self.verify_signatures_cached(chain_id, &SigCache::new(0))
+    }
+
+    /// `verify_signatures`, skipping inputs `sigs` has already seen
+    /// (most of a mined block's txs were checked on mempool entry).
+    pub fn verify_signatures_cached(
+        &self,
+        chain_id: &str,
+        sigs: &SigCache,
+    ) -> Result<(), BadSignature> {
+        let sighashes: Vec<[u8; 32]> = self
+            .transactions
+            .iter()
+            .map(|tx| tx.sighash(chain_id))
+            .collect();
+
+        // (tx_index, input_index) for each check, to map failures back
+        let mut positions = Vec::new();
+        let mut checks = Vec::new();
+        for (t, tx) in self.transactions.iter().enumerate() {
+            for (i, input) in tx.inputs.iter().enumerate() {
+                positions.push((t, i));
+                checks.push(SigCheck {
+                    scheme: input.scheme,
+                    pubkey_hex: &input.pubkey,
+                    sig_hex: &input.signature,
+                    msg32: sighashes[t],
+                });
+            }
+        }
+
+        sigs.verify_batch(&checks).map_err(|(k, reason)| {
+            let (tx_index, input_index) = positions[k];
+            BadSignature {
+                tx_index,
+                input_index,
+                txid: self.transactions[tx_index].txid.clone(),
+                reason,
+            }
+        })
     }
 
     pub fn new_with_timestamp(
@@ -95,19 +297,30 @@ impl Block {
             index,
             timestamp,
             previous_hash,
+            difficulty: 0,
+            tx_count: transactions.len() as u64,
+            merkle_root: txs_merkle_root(&transactions),
             nonce: 0,
             hash: String::new(),
             transactions,
+            pruned: false,
         };
         block.hash = block.compute_hash();
         block
     }
 }
 
+fn txs_merkle_root(transactions: &[Transaction]) -> String {
+    let txids: Vec<String> = transactions.iter().map(|t| t.txid.clone()).collect();
+    merkle_root(&txids)
+}
+
 #[cfg(test)]
 mod tests {
     use super::Block;
-    use crate::transaction::{OutPoint, Transaction, TxInput, TxOutput};
+    use crate::blockchain::PowAlgo;
+    use crate::transaction::{OutPoint, SigScheme, Transaction, TxInput, TxOutput};
+    use crate::wallet::{generate_keypair_hex, sign_sighash_hex};
 
     fn dummy_input(txid: &str, vout: u32) -> TxInput {
         TxInput {
@@ -117,9 +330,49 @@ mod tests {
             },
             pubkey: String::new(),    // not used in these block tests
             signature: String::new(), // not used in these block tests
+            scheme: SigScheme::Ecdsa,
         }
     }
 
+    fn signed_spend(sk: &str, pk: &str, n: u32, chain_id: &str) -> Transaction {
+        let mut input = dummy_input("funding", n);
+        input.pubkey = pk.into();
+        let outputs = vec![TxOutput {
+            address: "dest".into(),
+            amount: 1,
+        }];
+        let sighash = Transaction::new(vec![input.clone()], outputs.clone()).sighash(chain_id);
+        input.signature = sign_sighash_hex(sk, sighash).unwrap();
+        Transaction::new(vec![input], outputs)
+    }
+
+    #[test]
+    fn batch_verifies_block_signatures_and_pinpoints_bad_one() {
+        let chain_id = "test-chain";
+        let (sk, pk, _) = generate_keypair_hex();
+        let coinbase = Transaction::new(
+            vec![],
+            vec![TxOutput {
+                address: "miner".into(),
+                amount: 50,
+            }],
+        );
+        let mut txs = vec![coinbase];
+        txs.extend((0..50).map(|n| signed_spend(&sk, &pk, n, chain_id)));
+
+        let block = Block::new(1, "prev".into(), txs.clone());
+        assert_eq!(block.verify_signatures(chain_id), Ok(()));
+
+        // corrupt tx #17: re-sign it for another chain
+        txs[17] = signed_spend(&sk, &pk, 17, "other-chain");
+        let block = Block::new(1, "prev".into(), txs);
+        let err = block.verify_signatures(chain_id).unwrap_err();
+        assert_eq!(err.tx_index, 17);
+        assert_eq!(err.input_index, 0);
+        assert_eq!(err.reason, "invalid signature");
+        assert_eq!(err.txid, block.transactions[17].txid);
+    }
+
     #[test]
     fn genesis_has_valid_hash() {
         let b = Block::genesis();
@@ -168,4 +421,18 @@ mod tests {
         assert_ne!(old_hash, b.compute_hash());
         assert!(!b.is_valid(2));
     }
+
+    #[test]
+    fn mine_and_validate_with_scrypt() {
+        let mut b = Block::new(1, "prev".into(), vec![]);
+        b.mine_with(1, PowAlgo::Scrypt);
+
+        // committed hash stays SHA-256; the scrypt digest meets the target
+        assert_eq!(b.hash, b.compute_hash());
+        assert!(b.pow_hash(PowAlgo::Scrypt).starts_with('0'));
+        assert!(b.is_valid_with(1, PowAlgo::Scrypt));
+
+        // validation under sha256 only looks at the block hash
+        assert_eq!(b.is_valid_with(1, PowAlgo::Sha256), b.hash.starts_with('0'));
+    }
 }
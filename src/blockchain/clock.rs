@@ -0,0 +1,50 @@
+use chrono::Utc;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Source of block timestamps. The chain asks its clock instead of calling
+/// `Utc::now()` directly so tests can drive time (and difficulty) by hand.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Current Unix timestamp (UTC, seconds).
+    fn now_timestamp(&self) -> i64;
+}
+
+/// Wall-clock time; what every node runs with.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_timestamp(&self) -> i64 {
+        Utc::now().timestamp()
+    }
+}
+
+/// Clock that only moves when told to. Share it through an `Arc` and keep a
+/// handle to `set`/`advance` it while the chain reads from it.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: AtomicI64,
+}
+
+impl MockClock {
+    pub fn new(start: i64) -> Self {
+        Self {
+            now: AtomicI64::new(start),
+        }
+    }
+
+    pub fn set(&self, timestamp: i64) {
+        self.now.store(timestamp, Ordering::SeqCst);
+    }
+
+    /// Move forward by `secs` (negative steps backwards, like a skewed clock).
+    pub fn advance(&self, secs: i64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_timestamp(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Root of a block without transactions (the genesis block).
+pub const EMPTY_MERKLE_ROOT: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Which side of the running hash a proof sibling goes on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One level of a Merkle inclusion proof.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleStep {
+    pub hash: String,
+    pub side: Side,
+}
+
+/// SHA-256 of the two child hashes' hex strings concatenated.
+fn hash_pair(left: &str, right: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Next tree level; an odd last node is paired with itself (as in Bitcoin).
+fn next_level(level: &[String]) -> Vec<String> {
+    level
+        .chunks(2)
+        .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+/// Merkle root over `txids`, in block order.
+pub fn merkle_root(txids: &[String]) -> String {
+    if txids.is_empty() {
+        return EMPTY_MERKLE_ROOT.to_string();
+    }
+    let mut level = txids.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level.remove(0)
+}
+
+/// Sibling hashes from the leaf at `index` up to the root.
+/// `None` if `index` is out of range.
+pub fn merkle_proof(txids: &[String], index: usize) -> Option<Vec<MerkleStep>> {
+    if index >= txids.len() {
+        return None;
+    }
+    let mut proof = Vec::new();
+    let mut level = txids.to_vec();
+    let mut pos = index;
+    while level.len() > 1 {
+        let step = if pos.is_multiple_of(2) {
+            MerkleStep {
+                hash: level.get(pos + 1).unwrap_or(&level[pos]).clone(),
+                side: Side::Right,
+            }
+        } else {
+            MerkleStep {
+                hash: level[pos - 1].clone(),
+                side: Side::Left,
+            }
+        };
+        proof.push(step);
+        level = next_level(&level);
+        pos /= 2;
+    }
+    Some(proof)
+}
+
+/// Fold `proof` over `txid` and compare with `root` (the SPV client's check).
+pub fn verify_merkle_proof(txid: &str, proof: &[MerkleStep], root: &str) -> bool {
+    let computed = proof
+        .iter()
+        .fold(txid.to_string(), |acc, step| match step.side {
+            Side::Left => hash_pair(&step.hash, &acc),
+            Side::Right => hash_pair(&acc, &step.hash),
+        });
+    computed == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merkle_proof, merkle_root, verify_merkle_proof};
+
+    #[test]
+    fn every_leaf_proves_against_the_root() {
+        for n in 1..=7 {
+            let txids: Vec<String> = (0..n).map(|i| format!("{i:064x}")).collect();
+            let root = merkle_root(&txids);
+            for (i, txid) in txids.iter().enumerate() {
+                let proof = merkle_proof(&txids, i).unwrap();
+                assert!(verify_merkle_proof(txid, &proof, &root), "n={n} i={i}");
+                assert!(!verify_merkle_proof("other", &proof, &root));
+            }
+            assert!(merkle_proof(&txids, n).is_none());
+        }
+    }
+}
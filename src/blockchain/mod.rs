@@ -1,8 +1,29 @@
+pub mod assembly;
 pub mod block;
+pub mod clock;
+pub mod merkle;
 pub mod model;
+pub mod pow;
+pub mod replay;
 
-pub use block::Block;
+pub use assembly::{
+    CachedFee, CoinbaseShare, FeeCache, FeeRateMode, check_shares, select_transactions,
+    select_transactions_cached, split_reward, tx_fee,
+};
+pub use block::{BadSignature, Block};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use merkle::{
+    EMPTY_MERKLE_ROOT, MerkleStep, Side, merkle_proof, merkle_root, verify_merkle_proof,
+};
 pub use model::Blockchain;
+pub use pow::{
+    PowAlgo, bits_to_target, difficulty_bits, difficulty_target, expected_hashes, target_to_bits,
+};
+pub use replay::{InvalidBlock, check_block_txs, rebuild_utxo, validate_chain_full};
+
+/// Default chain identifier mixed into every transaction's sighash
+/// (replay protection between forks/nodes). Override with `CHAIN_ID`.
+pub const DEFAULT_CHAIN_ID: &str = "rust-blockchain-dev";
 
 /// Default Proof-of-Work difficulty (number of leading zeros).
 pub const DEFAULT_DIFFICULTY: u32 = 3;
@@ -16,6 +37,19 @@ pub const TARGET_BLOCK_TIME_SECS: i64 = 60;
 /// How many recent intervals to average when adjusting difficulty
 pub const DIFF_ADJUST_WINDOW: usize = 10;
 
+/// How many blocks out of timestamp order we tolerate before time-range
+/// queries stop trusting binary search (same span as Bitcoin's median-time-past)
+pub const TIME_SKEW_WINDOW: usize = 11;
+
+/// Most blocks a chain replacement may roll back from our tip (`MAX_REORG_DEPTH`).
+/// Deeper history is final: a peer with more work still can't rewrite it.
+pub const DEFAULT_MAX_REORG_DEPTH: u64 = 100;
+
+/// Longest interval (as a multiple of the target block time) one block can
+/// contribute to the adjustment average. Without a cap, a single far-future
+/// timestamp outweighs the whole window and drags difficulty down.
+pub const DIFF_ADJUST_MAX_INTERVAL_FACTOR: i64 = 4;
+
 /// Tolerance around the target before we adjust (+/- 20%)
 pub const DIFF_ADJUST_THRESHOLD_PCT: f64 = 0.20;
 
@@ -23,6 +57,33 @@ pub const DIFF_ADJUST_THRESHOLD_PCT: f64 = 0.20;
 pub const DIFF_MIN: u32 = 1;
 pub const DIFF_MAX: u32 = 6;
 
+/// Range the auto-adjuster and `set_difficulty` keep the difficulty in.
+/// Defaults to `DIFF_MIN..=DIFF_MAX`; `DIFF_MIN`/`DIFF_MAX` env vars narrow it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DifficultyBounds {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Default for DifficultyBounds {
+    fn default() -> Self {
+        Self {
+            min: DIFF_MIN,
+            max: DIFF_MAX,
+        }
+    }
+}
+
+impl DifficultyBounds {
+    pub fn clamp(&self, difficulty: u32) -> u32 {
+        difficulty.clamp(self.min, self.max)
+    }
+
+    pub fn contains(&self, difficulty: u32) -> bool {
+        (self.min..=self.max).contains(&difficulty)
+    }
+}
+
 /// ---- Block assembly limits (DEV TUNING) ----
 /// Max number of transactions (exclui coinbase)
 pub const MAX_TXS_PER_BLOCK: usize = 200;
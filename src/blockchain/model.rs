@@ -1,26 +1,61 @@
 use super::{
-    Block, DIFF_ADJUST_THRESHOLD_PCT, DIFF_ADJUST_WINDOW, DIFF_MAX, DIFF_MIN,
-    TARGET_BLOCK_TIME_SECS,
+    Block, Clock, DEFAULT_MAX_REORG_DEPTH, DIFF_ADJUST_MAX_INTERVAL_FACTOR,
+    DIFF_ADJUST_THRESHOLD_PCT, DIFF_ADJUST_WINDOW, DIFF_MIN, DifficultyBounds, PowAlgo,
+    SystemClock, TARGET_BLOCK_TIME_SECS, TIME_SKEW_WINDOW,
 };
-use crate::transaction::Transaction;
+use crate::transaction::{Transaction, TxOutput};
 use log::debug;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
 
 /// Simple in-memory blockchain with Proof-of-Work.
 #[derive(Debug)]
 pub struct Blockchain {
     pub chain: Vec<Block>,
     pub difficulty: u32,
+    /// PoW digest used for both mining and validation of this chain.
+    pub pow_algo: PowAlgo,
+    /// Block interval the difficulty adjustment aims for.
+    pub target_block_secs: i64,
+    /// Floor and ceiling for the adjustment and `set_difficulty`.
+    pub bounds: DifficultyBounds,
+    /// Deepest rollback `try_replace` accepts, in blocks below the tip.
+    pub max_reorg_depth: u64,
+    /// Blocks kept whole below the tip; older ones keep only their header.
+    /// `None` keeps every body.
+    pub prune_depth: Option<u64>,
+    /// Height -> block hash fixed in advance: a chain disagreeing at any of
+    /// them is invalid, however much work it has.
+    pub checkpoints: BTreeMap<u64, String>,
+    /// Timestamps new blocks (and mining templates). `SystemClock` unless a
+    /// test swaps in a `MockClock`.
+    pub clock: Arc<dyn Clock>,
 }
 
 impl Blockchain {
     /// Initialize a new blockchain with a genesis block.
     pub fn new(difficulty: u32) -> Self {
-        let mut bc = Self {
-            chain: Vec::new(),
+        Self::with_clock(difficulty, Arc::new(SystemClock))
+    }
+
+    /// Same as `new`, with every block (genesis included) stamped by `clock`.
+    pub fn with_clock(difficulty: u32, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            chain: vec![Block::genesis_at(clock.as_ref())],
             difficulty,
-        };
-        bc.chain.push(Block::genesis());
-        bc
+            pow_algo: PowAlgo::default(),
+            target_block_secs: TARGET_BLOCK_TIME_SECS,
+            bounds: DifficultyBounds::default(),
+            max_reorg_depth: DEFAULT_MAX_REORG_DEPTH,
+            prune_depth: None,
+            checkpoints: BTreeMap::new(),
+            clock,
+        }
+    }
+
+    /// Current time according to the chain's clock.
+    pub fn now_timestamp(&self) -> i64 {
+        self.clock.now_timestamp()
     }
 
     /// Return the last block in the chain.
@@ -32,71 +67,336 @@ impl Blockchain {
 
     /// Mine and append a new block with the provided transactions.
     /// After appending, maybe adjust difficulty for *future* blocks.
-    pub fn mine_block(&mut self, transactions: Vec<Transaction>) -> &Block {
+    /// A block landing on a checkpoint with another hash is dropped, not appended.
+    pub fn mine_block(&mut self, transactions: Vec<Transaction>) -> Result<&Block, String> {
         let index = self.chain.len() as u64;
         let prev_hash = self.last_block().hash.clone();
 
-        let mut block = Block::new(index, prev_hash, transactions);
-        block.mine(self.difficulty);
+        let mut block = Block::new_at(index, prev_hash, transactions, self.clock.as_ref());
+        block.mine_with(self.difficulty, self.pow_algo);
+        self.check_checkpoint(&block)?;
         self.chain.push(block);
+        self.prune();
 
         // Adjust difficulty for the next block (does not affect the one just mined).
         self.maybe_adjust_difficulty();
 
-        self.last_block()
+        Ok(self.last_block())
     }
 
-    /// Validate the entire chain: linkage, hashes and PoW.
+    /// Validate the entire chain: linkage, hashes, PoW and checkpoints.
     pub fn is_valid_chain(&self) -> bool {
-        if self.chain.is_empty() {
-            return false;
+        Self::validate_blocks(&self.chain, self.pow_algo)
+            && self.checkpoint_conflict(&self.chain).is_none()
+    }
+
+    /// First checkpoint height where `blocks` has a different hash (heights
+    /// past the end of `blocks` don't count).
+    pub fn checkpoint_conflict(&self, blocks: &[Block]) -> Option<u64> {
+        self.checkpoints.iter().find_map(|(&height, expected)| {
+            let block = blocks.get(height as usize)?;
+            (&block.hash != expected).then_some(height)
+        })
+    }
+
+    /// `block` may join the chain only with the hash pinned at its height, if any.
+    fn check_checkpoint(&self, block: &Block) -> Result<(), String> {
+        match self.checkpoints.get(&block.index) {
+            Some(expected) if &block.hash != expected => Err(format!(
+                "block #{} conflicts with checkpoint (expected {expected}, got {})",
+                block.index, block.hash
+            )),
+            _ => Ok(()),
         }
+    }
 
+    /// Linkage, hashes, per-block PoW (each block at the difficulty it commits to)
+    /// and transaction-list structure of `blocks`, starting at a genesis block.
+    fn validate_blocks(blocks: &[Block], pow_algo: PowAlgo) -> bool {
+        !blocks.is_empty() && Self::first_invalid_block(blocks, pow_algo).is_none()
+    }
+
+    /// Position and reason of the first block failing `validate_blocks`' checks.
+    pub(crate) fn first_invalid_block(
+        blocks: &[Block],
+        pow_algo: PowAlgo,
+    ) -> Option<(u64, &'static str)> {
         // Validate genesis block immutability
-        let genesis = &self.chain[0];
-        if genesis.index != 0
-            || genesis.previous_hash != "0"
-            || genesis.hash != genesis.compute_hash()
-        {
-            return false;
+        let genesis = blocks.first()?;
+        if genesis.index != 0 || genesis.previous_hash != "0" {
+            return Some((0, "genesis must have index 0 and previous_hash \"0\""));
+        }
+        if genesis.hash != genesis.compute_hash() {
+            return Some((0, "hash does not match block content"));
+        }
+        if let Err(e) = genesis.check_transactions() {
+            return Some((0, e));
         }
 
         // Validate the rest of the chain
-        for i in 1..self.chain.len() {
-            let current = &self.chain[i];
-            let prev = &self.chain[i - 1];
+        for i in 1..blocks.len() {
+            let current = &blocks[i];
+            let prev = &blocks[i - 1];
+            let at = i as u64;
 
             // Check linkage
-            if current.previous_hash != prev.hash {
-                return false;
+            if current.index != prev.index + 1 || current.previous_hash != prev.hash {
+                return Some((at, "does not link to the previous block"));
+            }
+
+            // Check hash integrity + the difficulty the block commits to
+            // (a zero-work block can't be passed off as mined)
+            if current.difficulty < DIFF_MIN || !current.is_valid_with(current.difficulty, pow_algo)
+            {
+                return Some((at, "bad hash or insufficient proof-of-work"));
+            }
+
+            // No duplicated txs / extra coinbases (nothing left to check once pruned)
+            if !current.pruned
+                && let Err(e) = current.check_transactions()
+            {
+                return Some((at, e));
             }
+        }
+
+        None
+    }
+
+    /// Total PoW behind the chain: sum of 16^difficulty over all blocks.
+    pub fn total_work(&self) -> u128 {
+        self.chain
+            .iter()
+            .fold(0u128, |acc, b| acc.saturating_add(b.work()))
+    }
+
+    /// Estimated network hashes per second over the last `DIFF_ADJUST_WINDOW`
+    /// blocks: the work they represent (16^difficulty each) over the time they
+    /// took. `None` until the chain has a full window.
+    pub fn estimate_hashrate(&self) -> Option<f64> {
+        if self.chain.len() < DIFF_ADJUST_WINDOW + 1 {
+            return None;
+        }
+        let window = &self.chain[self.chain.len() - (DIFF_ADJUST_WINDOW + 1)..];
+        let mut work = 0f64;
+        let mut secs: i64 = 0;
+        for pair in window.windows(2) {
+            work += pair[1].work() as f64;
+            secs += self.adjust_interval(&pair[0], &pair[1]);
+        }
+        Some(work / secs as f64)
+    }
+
+    /// Fork choice: adopt `candidate` if it is a valid chain with strictly more
+    /// total work than ours (not necessarily more blocks). Returns whether the
+    /// chain was replaced; callers must rebuild any state derived from blocks.
+    /// Refuses candidates that would roll back more than `max_reorg_depth` blocks.
+    pub fn try_replace(&mut self, candidate: Vec<Block>) -> Result<bool, String> {
+        // a pruned block can't be verified: only ours, already checked, are taken
+        let common = self.chain.len() as u64 - self.reorg_depth(&candidate);
+        if candidate.iter().skip(common as usize).any(|b| b.pruned) {
+            return Err("candidate chain has pruned blocks past the fork point".into());
+        }
+        if !Self::validate_blocks(&candidate, self.pow_algo) {
+            return Err("candidate chain is invalid".into());
+        }
+        if let Some(height) = self.checkpoint_conflict(&candidate) {
+            return Err(format!(
+                "candidate chain conflicts with the checkpoint at height {height}"
+            ));
+        }
+        let candidate_work = candidate
+            .iter()
+            .fold(0u128, |acc, b| acc.saturating_add(b.work()));
+        let ours = self.total_work();
+        if candidate_work <= ours {
+            debug!("keeping current chain (work {ours} >= candidate {candidate_work})");
+            return Ok(false);
+        }
+        let depth = self.reorg_depth(&candidate);
+        if depth > self.max_reorg_depth {
+            return Err(format!(
+                "reorg too deep: candidate rolls back {depth} blocks (max {})",
+                self.max_reorg_depth
+            ));
+        }
+        debug!(
+            "replacing chain: {} blocks / work {} -> {} blocks / work {}",
+            self.chain.len(),
+            ours,
+            candidate.len(),
+            candidate_work
+        );
+        self.chain = candidate;
+        Ok(true)
+    }
+
+    /// Blocks of ours that adopting `candidate` would discard: everything
+    /// above the last block both chains share.
+    pub fn reorg_depth(&self, candidate: &[Block]) -> u64 {
+        let common = self
+            .chain
+            .iter()
+            .zip(candidate)
+            .take_while(|(a, b)| a.hash == b.hash)
+            .count();
+        (self.chain.len() - common) as u64
+    }
 
-            // Check hash integrity + difficulty
-            // Note: we validate with current difficulty here; in a real chain you'd
-            // store difficulty per block. For our didactic chain, it's acceptable.
-            if !current.is_valid(self.difficulty) {
-                return false;
+    /// Drop the bodies of blocks more than `prune_depth` below the tip.
+    /// Returns how many were pruned this time.
+    pub fn prune(&mut self) -> usize {
+        let Some(depth) = self.prune_depth else {
+            return 0;
+        };
+        let keep_from = self.chain.len().saturating_sub(depth.max(1) as usize);
+        // pruning always advances from genesis, so stop at the first pruned block
+        let mut pruned = 0;
+        for block in self.chain[..keep_from].iter_mut().skip(1).rev() {
+            if block.pruned {
+                break;
             }
+            block.prune();
+            pruned += 1;
+        }
+        if pruned > 0 {
+            debug!("pruned {pruned} block bodies below height {keep_from}");
         }
+        pruned
+    }
 
-        true
+    /// Whether any block has lost its body to pruning.
+    pub fn has_pruned_blocks(&self) -> bool {
+        self.chain.get(1).is_some_and(|b| b.pruned)
     }
 
     pub fn len(&self) -> usize {
         self.chain.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.chain.is_empty()
+    }
+
+    /// Block by height (`"3"`) or by hash.
+    pub fn find_block(&self, id: &str) -> Option<&Block> {
+        match id.parse::<usize>() {
+            Ok(index) => self.chain.get(index),
+            Err(_) => self.chain.iter().find(|b| b.hash == id),
+        }
+    }
+
+    /// Fee each transaction of block `index` paid, replaying its inputs against
+    /// the outputs of that block and the ones before it. `None` for the
+    /// coinbase and for txs spending outputs never on chain (direct faucet
+    /// UTXOs) or whose block was pruned; the outer `None` is an unknown block.
+    pub fn block_tx_fees(&self, index: usize) -> Option<Vec<Option<u128>>> {
+        let block = self.chain.get(index)?;
+        let wanted: HashSet<&str> = block
+            .transactions
+            .iter()
+            .flat_map(|t| &t.inputs)
+            .map(|i| i.outpoint.txid.as_str())
+            .collect();
+        let outputs: HashMap<&str, &[TxOutput]> = self.chain[..=index]
+            .iter()
+            .flat_map(|b| &b.transactions)
+            .filter(|t| wanted.contains(t.txid.as_str()))
+            .map(|t| (t.txid.as_str(), t.outputs.as_slice()))
+            .collect();
+
+        let fees = block
+            .transactions
+            .iter()
+            .map(|tx| {
+                if tx.inputs.is_empty() {
+                    return None;
+                }
+                let input_sum: Option<u128> = tx
+                    .inputs
+                    .iter()
+                    .map(|i| {
+                        outputs
+                            .get(i.outpoint.txid.as_str())
+                            .and_then(|outs| outs.get(i.outpoint.vout as usize))
+                            .map(|o| o.amount as u128)
+                    })
+                    .sum();
+                input_sum?.checked_sub(tx.total_output_amount())
+            })
+            .collect();
+        Some(fees)
+    }
+
+    /// Transactions (coinbases included) in blocks `0..=index`, from the
+    /// headers' `tx_count` so pruned blocks still count. `None` past the tip.
+    pub fn cumulative_tx_count(&self, index: u64) -> Option<u64> {
+        let end = usize::try_from(index).ok()?.checked_add(1)?;
+        let blocks = self.chain.get(..end)?;
+        Some(blocks.iter().map(|b| b.tx_count).sum())
+    }
+
+    /// Index of the block containing `txid`, scanning from the tip.
+    pub fn find_tx_block(&self, txid: &str) -> Option<u64> {
+        self.chain
+            .iter()
+            .rev()
+            .find(|b| b.transactions.iter().any(|t| t.txid == txid))
+            .map(|b| b.index)
+    }
+
+    /// Heights of the blocks with `from_ts <= timestamp <= to_ts`, in chain order.
+    ///
+    /// Timestamps are mostly monotonic, so the bounds are binary-searched.
+    /// They aren't guaranteed to be (clock skew, templates mined late), so the
+    /// result is double-checked against `TIME_SKEW_WINDOW` blocks on each side;
+    /// any block out of place there means the search can't be trusted and the
+    /// whole chain is scanned instead.
+    pub fn block_indices_by_time(&self, from_ts: i64, to_ts: i64) -> Vec<usize> {
+        if from_ts > to_ts {
+            return Vec::new();
+        }
+        let in_range = |b: &Block| b.timestamp >= from_ts && b.timestamp <= to_ts;
+        let lo = self.chain.partition_point(|b| b.timestamp < from_ts);
+        let hi = self.chain.partition_point(|b| b.timestamp <= to_ts).max(lo);
+
+        let before = lo.saturating_sub(TIME_SKEW_WINDOW)..lo;
+        let after = hi..(hi + TIME_SKEW_WINDOW).min(self.chain.len());
+        let ambiguous = self.chain[lo..hi].iter().any(|b| !in_range(b))
+            || self.chain[before].iter().any(in_range)
+            || self.chain[after].iter().any(in_range);
+        if ambiguous {
+            return (0..self.chain.len())
+                .filter(|&i| in_range(&self.chain[i]))
+                .collect();
+        }
+        (lo..hi).collect()
+    }
+
     pub fn difficulty(&self) -> u32 {
         self.difficulty
     }
 
+    /// Set the difficulty for future blocks, clamped to `bounds`.
     pub fn set_difficulty(&mut self, difficulty: u32) {
-        self.difficulty = difficulty;
+        self.difficulty = self.bounds.clamp(difficulty);
+    }
+
+    /// Seconds between `older` and `newer` as the difficulty adjustment (and
+    /// the hashrate estimate) count them, clamped to
+    /// `[1, DIFF_ADJUST_MAX_INTERVAL_FACTOR × target]`: a backwards step counts
+    /// as 1s and one far-future timestamp can't outweigh the rest of the window.
+    fn adjust_interval(&self, older: &Block, newer: &Block) -> i64 {
+        let max_interval = (self.target_block_secs * DIFF_ADJUST_MAX_INTERVAL_FACTOR).max(1);
+        (newer.timestamp - older.timestamp).clamp(1, max_interval)
     }
 
     /// Adjust difficulty towards the target block time using the average of the last N intervals.
-    /// If average < (1 - tol) * target => increase difficulty by 1 (up to DIFF_MAX)
-    /// If average > (1 + tol) * target => decrease difficulty by 1 (down to DIFF_MIN)
+    /// If average < (1 - tol) * target => increase difficulty by 1 (up to `bounds.max`)
+    /// If average > (1 + tol) * target => decrease difficulty by 1 (down to `bounds.min`)
+    /// Each interval is clamped by `adjust_interval` first: timestamps are
+    /// miner-chosen, so a block stamped far in the future (or before its
+    /// parent) would otherwise drag the average enough to swing difficulty
+    /// on its own.
     fn maybe_adjust_difficulty(&mut self) {
         // Need at least (window + 1) blocks to get `window` intervals
         if self.chain.len() < DIFF_ADJUST_WINDOW + 1 {
@@ -107,26 +407,22 @@ impl Blockchain {
         let start = self.chain.len() - (DIFF_ADJUST_WINDOW + 1);
         let mut total: i64 = 0;
         for i in (start + 1)..(start + 1 + DIFF_ADJUST_WINDOW) {
-            let newer = &self.chain[i];
-            let older = &self.chain[i - 1];
-            let dt = newer.timestamp - older.timestamp;
-            // guard against clock anomalies; clamp to at least 1s
-            total += dt.max(1);
+            total += self.adjust_interval(&self.chain[i - 1], &self.chain[i]);
         }
         let avg_secs = total as f64 / DIFF_ADJUST_WINDOW as f64;
 
-        let target = TARGET_BLOCK_TIME_SECS as f64;
+        let target = self.target_block_secs as f64;
         let lower = target * (1.0 - DIFF_ADJUST_THRESHOLD_PCT);
         let upper = target * (1.0 + DIFF_ADJUST_THRESHOLD_PCT);
 
         let old = self.difficulty;
-        if avg_secs < lower && self.difficulty < DIFF_MAX {
+        if avg_secs < lower && self.difficulty < self.bounds.max {
             self.difficulty += 1;
             debug!(
                 "Difficulty ↑ {} -> {} (avg {:.1}s < {:.1}s target; window={})",
                 old, self.difficulty, avg_secs, target, DIFF_ADJUST_WINDOW
             );
-        } else if avg_secs > upper && self.difficulty > DIFF_MIN {
+        } else if avg_secs > upper && self.difficulty > self.bounds.min {
             self.difficulty -= 1;
             debug!(
                 "Difficulty ↓ {} -> {} (avg {:.1}s > {:.1}s target; window={})",
@@ -141,18 +437,467 @@ impl Blockchain {
     }
 
     /// Append a pre-mined block (nonce/hash already set) after validating linkage and PoW.
-    pub fn append_premined_block(&mut self, block: Block) -> Result<(), &'static str> {
+    /// Errors spell out expected vs received values (external miners debug with them).
+    pub fn append_premined_block(&mut self, block: Block) -> Result<(), String> {
         // linkage
-        if block.previous_hash != self.last_block().hash {
-            return Err("stale template: previous_hash mismatch");
+        let tip_hash = &self.last_block().hash;
+        if &block.previous_hash != tip_hash {
+            return Err(format!(
+                "stale template: previous_hash mismatch (expected tip {tip_hash}, got {})",
+                block.previous_hash
+            ));
+        }
+        // PoW: o bloco precisa declarar (e cumprir) pelo menos a dificuldade atual
+        if block.difficulty < self.difficulty {
+            return Err(format!(
+                "block difficulty below current difficulty (required {}, got {})",
+                self.difficulty, block.difficulty
+            ));
         }
-        // PoW at current difficulty (simplificação didática)
-        if !block.is_valid(self.difficulty) {
-            return Err("invalid PoW for current difficulty");
+        if !block.is_valid_with(block.difficulty, self.pow_algo) {
+            return Err(format!(
+                "invalid PoW: hash {} does not meet difficulty {}",
+                block.hash, block.difficulty
+            ));
         }
+        if block.pruned {
+            return Err("block has no body (pruned)".into());
+        }
+        self.check_checkpoint(&block)?;
+        block.check_transactions()?;
         self.chain.push(block);
+        self.prune();
         // adjust difficulty for next blocks
         self.maybe_adjust_difficulty();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Blockchain;
+    use crate::blockchain::Block;
+    use crate::transaction::{OutPoint, SigScheme, Transaction, TxInput, TxOutput, UtxoSet};
+
+    fn coinbase(address: &str, amount: u64) -> Transaction {
+        Transaction::new(
+            vec![],
+            vec![TxOutput {
+                address: address.into(),
+                amount,
+            }],
+        )
+    }
+
+    fn spend(txid: &str) -> Transaction {
+        Transaction::new(
+            vec![TxInput {
+                outpoint: OutPoint {
+                    txid: txid.into(),
+                    vout: 0,
+                },
+                pubkey: String::new(),
+                signature: String::new(),
+                scheme: SigScheme::Ecdsa,
+            }],
+            vec![TxOutput {
+                address: "dest".into(),
+                amount: 1,
+            }],
+        )
+    }
+
+    fn premined(bc: &Blockchain, txs: Vec<Transaction>) -> Block {
+        let mut block = Block::new(bc.len() as u64, bc.last_block().hash.clone(), txs);
+        block.mine(bc.difficulty());
+        block
+    }
+
+    #[test]
+    fn rejects_duplicate_transaction_in_block() {
+        let mut bc = Blockchain::new(1);
+        let tx = spend("prev");
+        let block = premined(&bc, vec![coinbase("miner", 50), tx.clone(), tx]);
+
+        assert_eq!(
+            bc.append_premined_block(block.clone()),
+            Err("duplicate transaction in block".to_string())
+        );
+        assert_eq!(bc.len(), 1);
+
+        // the full validator rejects it too
+        bc.chain.push(block);
+        assert!(!bc.is_valid_chain());
+    }
+
+    #[test]
+    fn rejects_more_than_one_coinbase() {
+        let mut bc = Blockchain::new(1);
+        let block = premined(&bc, vec![coinbase("miner", 50), coinbase("other", 50)]);
+        assert_eq!(
+            bc.append_premined_block(block),
+            Err("more than one coinbase transaction in block".to_string())
+        );
+    }
+
+    #[test]
+    fn coinbase_height_must_match_block_index() {
+        let mut bc = Blockchain::new(1);
+        let pays = |h| {
+            Transaction::coinbase(
+                h,
+                vec![TxOutput {
+                    address: "miner".into(),
+                    amount: 50,
+                }],
+            )
+        };
+        let block = premined(&bc, vec![pays(7)]);
+        assert_eq!(
+            bc.append_premined_block(block),
+            Err("coinbase_height does not match the block index".to_string())
+        );
+        assert_eq!(
+            bc.append_premined_block(premined(&bc, vec![pays(1)])),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn premined_errors_name_expected_and_received() {
+        let mut bc = Blockchain::new(1);
+        let tip = bc.last_block().hash.clone();
+        let stale = "ab".repeat(32);
+        let mut block = Block::new(1, stale.clone(), vec![coinbase("miner", 50)]);
+        block.mine(1);
+        let err = bc.append_premined_block(block).unwrap_err();
+        assert!(err.contains(&tip), "{err}");
+        assert!(err.contains(&stale), "{err}");
+
+        // linked correctly but the hash misses the target
+        let mut block = Block::new(1, tip, vec![coinbase("miner", 50)]);
+        block.difficulty = 2;
+        block.hash = block.compute_hash();
+        while block.hash.starts_with("00") {
+            block.nonce += 1;
+            block.hash = block.compute_hash();
+        }
+        let err = bc.append_premined_block(block.clone()).unwrap_err();
+        assert!(err.contains(&block.hash), "{err}");
+        assert!(err.contains("difficulty 2"), "{err}");
+    }
+
+    #[test]
+    fn tx_count_must_match_transactions() {
+        let mut bc = Blockchain::new(1);
+        let honest = premined(&bc, vec![coinbase("miner", 50), spend("a")]);
+        assert_eq!(honest.tx_count, 2);
+
+        // claim a different count: the old hash no longer matches...
+        let mut lying = honest.clone();
+        lying.tx_count = 3;
+        assert!(!lying.is_valid(1));
+        // ...and even re-mined over the false claim, the block is rejected
+        lying.mine(1);
+        assert_eq!(
+            bc.append_premined_block(lying.clone()),
+            Err("tx_count does not match the number of transactions".to_string())
+        );
+        bc.chain.push(lying);
+        assert!(!bc.is_valid_chain());
+        bc.chain.pop();
+
+        assert_eq!(bc.append_premined_block(honest), Ok(()));
+    }
+
+    fn chain_with_timestamps(ts: &[i64]) -> Blockchain {
+        let mut bc = Blockchain::new(1);
+        bc.chain[0].timestamp = ts[0];
+        for &t in &ts[1..] {
+            let prev = bc.last_block().hash.clone();
+            let b = Block::new_with_timestamp(bc.len() as u64, prev, vec![], t);
+            bc.chain.push(b);
+        }
+        bc
+    }
+
+    #[test]
+    fn blocks_by_time_binary_search_and_fallback() {
+        let bc = chain_with_timestamps(&[100, 110, 120, 130, 140, 150]);
+        assert_eq!(bc.block_indices_by_time(110, 130), vec![1, 2, 3]);
+        assert_eq!(bc.block_indices_by_time(111, 119), Vec::<usize>::new());
+        assert_eq!(bc.block_indices_by_time(0, 1000), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(bc.block_indices_by_time(130, 110), Vec::<usize>::new());
+
+        // block 4 was stamped earlier than block 3: a plain binary search misses it
+        let skewed = chain_with_timestamps(&[100, 110, 120, 130, 115, 150]);
+        assert_eq!(skewed.block_indices_by_time(110, 120), vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn hashrate_estimate_tracks_difficulty_and_block_time() {
+        use crate::blockchain::DIFF_ADJUST_WINDOW;
+
+        let spaced = |secs: i64, difficulty: u32| {
+            let ts: Vec<i64> = (0..=DIFF_ADJUST_WINDOW as i64).map(|i| i * secs).collect();
+            let mut bc = chain_with_timestamps(&ts);
+            for b in &mut bc.chain[1..] {
+                b.difficulty = difficulty;
+            }
+            bc
+        };
+        let short = chain_with_timestamps(&[0, 10, 20]);
+        assert_eq!(short.estimate_hashrate(), None);
+
+        // 16^2 hashes every 8s
+        assert_eq!(spaced(8, 2).estimate_hashrate(), Some(32.0));
+        // faster blocks or a higher difficulty mean more hashing power
+        let base = spaced(10, 2).estimate_hashrate().unwrap();
+        assert!(spaced(5, 2).estimate_hashrate().unwrap() > base);
+        assert!(spaced(10, 3).estimate_hashrate().unwrap() > base);
+
+        // one far-future timestamp is capped like in the difficulty adjustment
+        let mut skewed = spaced(8, 2);
+        let last = skewed.chain.len() - 1;
+        skewed.chain[last].timestamp += 1_000_000;
+        let capped = (DIFF_ADJUST_WINDOW as i64 - 1) * 8
+            + skewed.target_block_secs * crate::blockchain::DIFF_ADJUST_MAX_INTERVAL_FACTOR;
+        let expected = (DIFF_ADJUST_WINDOW as f64 * 256.0) / capped as f64;
+        assert_eq!(skewed.estimate_hashrate(), Some(expected));
+    }
+
+    #[test]
+    fn one_huge_gap_does_not_collapse_difficulty() {
+        use crate::blockchain::DIFF_ADJUST_WINDOW;
+
+        // fast 5s blocks, then one stamped an hour later
+        let mut ts: Vec<i64> = (0..DIFF_ADJUST_WINDOW as i64).map(|i| i * 5).collect();
+        ts.push(ts.last().unwrap() + 3600);
+        let mut bc = chain_with_timestamps(&ts);
+        bc.difficulty = 3;
+        bc.maybe_adjust_difficulty();
+        assert_eq!(bc.difficulty, 4);
+
+        // consistently slow blocks still bring it down
+        let ts: Vec<i64> = (0..=DIFF_ADJUST_WINDOW as i64).map(|i| i * 3600).collect();
+        let mut bc = chain_with_timestamps(&ts);
+        bc.difficulty = 3;
+        bc.maybe_adjust_difficulty();
+        assert_eq!(bc.difficulty, 2);
+    }
+
+    #[test]
+    fn mock_clock_drives_a_difficulty_increase() {
+        use crate::blockchain::{DIFF_ADJUST_WINDOW, MockClock};
+        use std::sync::Arc;
+
+        let clock = Arc::new(MockClock::new(1_700_000_000));
+        let mut bc = Blockchain::with_clock(1, clock.clone());
+        assert_eq!(bc.chain[0].timestamp, 1_700_000_000);
+
+        // blocks every 5s against a 60s target
+        for n in 1..=DIFF_ADJUST_WINDOW {
+            assert_eq!(bc.difficulty, 1, "adjusted early at block {n}");
+            clock.advance(5);
+            bc.mine_block(vec![coinbase("miner", 50)]).unwrap();
+            assert_eq!(bc.last_block().timestamp, 1_700_000_000 + 5 * n as i64);
+        }
+        assert_eq!(bc.difficulty, 2);
+
+        assert!(bc.is_valid_chain());
+    }
+
+    #[test]
+    fn adjuster_respects_a_configured_ceiling() {
+        use crate::blockchain::{DIFF_ADJUST_WINDOW, DifficultyBounds, MockClock};
+        use std::sync::Arc;
+
+        let clock = Arc::new(MockClock::new(1_700_000_000));
+        let mut bc = Blockchain::with_clock(1, clock.clone());
+        bc.bounds = DifficultyBounds { min: 1, max: 2 };
+        // every block 1s after the previous one, far under the 60s target
+        for _ in 0..3 * DIFF_ADJUST_WINDOW {
+            clock.advance(1);
+            bc.mine_block(vec![coinbase("miner", 50)]).unwrap();
+            assert!(bc.difficulty <= 2);
+        }
+        assert_eq!(bc.difficulty, 2);
+
+        bc.set_difficulty(5);
+        assert_eq!(bc.difficulty, 2);
+        bc.set_difficulty(0);
+        assert_eq!(bc.difficulty, 1);
+    }
+
+    #[test]
+    fn accepts_distinct_transactions() {
+        let mut bc = Blockchain::new(1);
+        let block = premined(&bc, vec![coinbase("miner", 50), spend("a"), spend("b")]);
+        assert_eq!(bc.append_premined_block(block), Ok(()));
+        assert!(bc.is_valid_chain());
+    }
+
+    /// Chain of `n` empty blocks mined at `difficulty` on top of `base`'s genesis.
+    fn extend(base: &Blockchain, n: usize, difficulty: u32) -> Vec<Block> {
+        let mut blocks = vec![base.chain[0].clone()];
+        for _ in 0..n {
+            let prev = blocks.last().unwrap();
+            let mut b = Block::new(prev.index + 1, prev.hash.clone(), vec![coinbase("m", 50)]);
+            b.mine(difficulty);
+            blocks.push(b);
+        }
+        blocks
+    }
+
+    #[test]
+    fn fork_choice_prefers_work_over_length() {
+        let mut bc = Blockchain::new(1);
+        let long_easy = extend(&bc, 5, 1); // 5 * 16
+        let short_hard = extend(&bc, 2, 2); // 2 * 256
+
+        assert_eq!(bc.try_replace(long_easy.clone()), Ok(true));
+        assert_eq!(bc.len(), 6);
+        let easy_work = bc.total_work();
+
+        assert_eq!(bc.try_replace(short_hard), Ok(true));
+        assert_eq!(bc.len(), 3);
+        assert!(bc.total_work() > easy_work);
+        assert!(bc.is_valid_chain());
+
+        // the longer, lighter chain no longer wins
+        assert_eq!(bc.try_replace(long_easy), Ok(false));
+        assert_eq!(bc.len(), 3);
+    }
+
+    #[test]
+    fn try_replace_rejects_invalid_candidate() {
+        let mut bc = Blockchain::new(1);
+        let mut forged = extend(&bc, 3, 1);
+        forged[2].difficulty = 5; // claims more work than it did
+        assert_eq!(
+            bc.try_replace(forged),
+            Err("candidate chain is invalid".to_string())
+        );
+        assert_eq!(bc.len(), 1);
+    }
+
+    #[test]
+    fn pruning_drops_old_bodies_but_keeps_headers() {
+        let mut bc = Blockchain::new(1);
+        bc.prune_depth = Some(2);
+        for _ in 0..5 {
+            bc.mine_block(vec![coinbase("miner", 50)]).unwrap();
+        }
+        // 6 blocks: genesis + 1..=3 header-only, 4 and 5 whole
+        for block in &bc.chain[1..4] {
+            assert!(block.pruned && block.transactions.is_empty());
+            assert_eq!(block.tx_count, 1);
+        }
+        assert!(
+            bc.chain[4..]
+                .iter()
+                .all(|b| !b.pruned && b.transactions.len() == 1)
+        );
+        assert!(bc.is_valid_chain());
+
+        // the tip still validates and extends; the window moves up
+        let block = premined(&bc, vec![coinbase("miner", 50)]);
+        assert_eq!(bc.append_premined_block(block), Ok(()));
+        assert!(bc.chain[4].pruned && !bc.chain[5].pruned);
+        assert!(bc.is_valid_chain());
+
+        // a pruned header can't be passed in as a new block
+        let mut headless = premined(&bc, vec![coinbase("miner", 50)]);
+        headless.prune();
+        assert!(bc.append_premined_block(headless).is_err());
+
+        // nothing to replay
+        let err = bc
+            .is_valid_chain_full("test", &UtxoSet::new(), 0)
+            .unwrap_err();
+        assert_eq!(err.index, 1);
+
+        // header checks still bite: a rewritten pruned hash breaks the link
+        bc.chain[2].hash = "0".repeat(64);
+        assert!(!bc.is_valid_chain());
+    }
+
+    #[test]
+    fn checkpoints_reject_chains_that_diverge_at_them() {
+        let mut bc = Blockchain::new(1);
+        for _ in 0..3 {
+            bc.mine_block(vec![coinbase("local", 50)]).unwrap();
+        }
+        bc.checkpoints.insert(2, bc.chain[2].hash.clone());
+        assert!(bc.is_valid_chain());
+
+        // more work, but a different block at height 2
+        let fork = extend(&bc, 4, 2);
+        assert_eq!(
+            bc.try_replace(fork),
+            Err("candidate chain conflicts with the checkpoint at height 2".to_string())
+        );
+
+        // keeps our block 2 and extends past it: accepted
+        let mut agreeing = bc.chain[..3].to_vec();
+        for _ in 0..2 {
+            let prev = agreeing.last().unwrap();
+            let mut b = Block::new(prev.index + 1, prev.hash.clone(), vec![coinbase("m", 50)]);
+            b.mine(2);
+            agreeing.push(b);
+        }
+        assert_eq!(bc.try_replace(agreeing), Ok(true));
+        assert!(bc.is_valid_chain());
+
+        // a pre-mined block can't land on a checkpoint with another hash
+        let mut bc = Blockchain::new(1);
+        bc.checkpoints.insert(1, "0".repeat(64));
+        let block = premined(&bc, vec![coinbase("miner", 50)]);
+        let err = bc.append_premined_block(block).unwrap_err();
+        assert!(err.contains("conflicts with checkpoint"), "{err}");
+        assert_eq!(bc.len(), 1);
+
+        // nor can a locally mined one
+        let err = bc.mine_block(vec![coinbase("miner", 50)]).unwrap_err();
+        assert!(err.contains("conflicts with checkpoint"), "{err}");
+        assert_eq!(bc.len(), 1);
+
+        // and a chain already holding one is no longer valid
+        bc.chain.push(premined(&bc, vec![coinbase("miner", 50)]));
+        assert!(!bc.is_valid_chain());
+    }
+
+    #[test]
+    fn reorgs_deeper_than_the_limit_are_refused() {
+        let mut bc = Blockchain::new(1);
+        bc.max_reorg_depth = 2;
+        for _ in 0..4 {
+            bc.mine_block(vec![coinbase("local", 50)]).unwrap();
+        }
+        let tip = bc.last_block().hash.clone();
+
+        // forks right after genesis: would drop all 4 of our blocks
+        let deep = extend(&bc, 6, 1);
+        assert_eq!(
+            bc.try_replace(deep),
+            Err("reorg too deep: candidate rolls back 4 blocks (max 2)".to_string())
+        );
+        assert_eq!(bc.last_block().hash, tip);
+
+        // forks at height 2: only blocks 3 and 4 are replaced
+        let mut shallow = bc.chain[..3].to_vec();
+        for _ in 0..3 {
+            let prev = shallow.last().unwrap();
+            let mut b = Block::new(
+                prev.index + 1,
+                prev.hash.clone(),
+                vec![coinbase("peer", 50)],
+            );
+            b.mine(1);
+            shallow.push(b);
+        }
+        assert_eq!(bc.reorg_depth(&shallow), 2);
+        assert_eq!(bc.try_replace(shallow), Ok(true));
+        assert_eq!(bc.len(), 6);
+    }
+}
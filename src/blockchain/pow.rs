@@ -0,0 +1,166 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// scrypt cost used for PoW: N = 2^10, r = 8, p = 1 (~1 MiB of memory per hash).
+/// Deliberately light so a demo node still mines in seconds on a laptop.
+const SCRYPT_LOG_N: u8 = 10;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const SCRYPT_SALT: &[u8] = b"rust-blockchain-pow";
+
+/// Hash function used for the Proof-of-Work target check.
+///
+/// The block `hash` committed to the chain (linkage, `previous_hash`) is always
+/// SHA-256; only the digest compared against the difficulty target changes.
+/// - `Sha256`: the PoW digest is the block hash itself.
+/// - `Scrypt`: memory-hard digest of the same preimage (CPU-friendly mining).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowAlgo {
+    #[default]
+    Sha256,
+    Scrypt,
+}
+
+impl PowAlgo {
+    /// Parse the `POW_ALGO` env value (`sha256` or `scrypt`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "sha256" | "sha-256" => Some(Self::Sha256),
+            "scrypt" => Some(Self::Scrypt),
+            _ => None,
+        }
+    }
+
+    /// Hex digest of `preimage` under this algorithm.
+    pub fn digest_hex(&self, preimage: &[u8]) -> String {
+        match self {
+            Self::Sha256 => hex::encode(Sha256::digest(preimage)),
+            Self::Scrypt => {
+                let params = scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, 32)
+                    .expect("valid scrypt params");
+                let mut out = [0u8; 32];
+                scrypt::scrypt(preimage, SCRYPT_SALT, &params, &mut out)
+                    .expect("32-byte scrypt output");
+                hex::encode(out)
+            }
+        }
+    }
+}
+
+/// True if `digest_hex` starts with `difficulty` hex zeros.
+pub fn meets_difficulty(digest_hex: &str, difficulty: u32) -> bool {
+    digest_hex.len() >= difficulty as usize
+        && digest_hex
+            .chars()
+            .take(difficulty as usize)
+            .all(|c| c == '0')
+}
+
+/// 256-bit big-endian target for `difficulty` leading hex zeros: a digest
+/// meets the difficulty iff it is `<=` this value.
+pub fn difficulty_target(difficulty: u32) -> [u8; 32] {
+    let zeros = (difficulty as usize).min(64);
+    let mut target = [0xff; 32];
+    target[..zeros / 2].fill(0);
+    if zeros % 2 == 1 {
+        target[zeros / 2] = 0x0f;
+    }
+    target
+}
+
+/// Bitcoin-style compact encoding of a target: one size byte (length in
+/// bytes of the significant part) and a 3-byte mantissa (its top bytes; the
+/// mantissa's high bit is a sign bit, so it is kept clear).
+/// Lossy: only the 3 most significant bytes survive.
+pub fn target_to_bits(target: &[u8; 32]) -> u32 {
+    let Some(first) = target.iter().position(|&b| b != 0) else {
+        return 0;
+    };
+    let mut size = (32 - first) as u32;
+    let byte = |i: usize| target.get(i).copied().unwrap_or(0) as u32;
+    let mut mantissa = (byte(first) << 16) | (byte(first + 1) << 8) | byte(first + 2);
+    if mantissa & 0x0080_0000 != 0 {
+        mantissa >>= 8;
+        size += 1;
+    }
+    (size << 24) | mantissa
+}
+
+/// Inverse of `target_to_bits`. Values too large for 256 bits saturate.
+pub fn bits_to_target(bits: u32) -> [u8; 32] {
+    let size = (bits >> 24) as usize;
+    let mantissa = (bits & 0x007f_ffff).to_be_bytes(); // [0, m2, m1, m0]
+    let mut target = [0u8; 32];
+    for (k, &b) in mantissa[1..].iter().enumerate() {
+        // mantissa byte k lands at big-endian position 32 - size + k
+        match (32 + k).checked_sub(size) {
+            Some(pos) if pos < 32 => target[pos] = b,
+            Some(_) => {} // below the last byte: shifted out
+            None if b != 0 => return [0xff; 32],
+            None => {}
+        }
+    }
+    target
+}
+
+/// Expected number of hashes to meet `difficulty`: 16^difficulty (each
+/// leading hex zero divides the odds by 16). Saturates at `u128::MAX`.
+pub fn expected_hashes(difficulty: u32) -> u128 {
+    1u128.checked_shl(4 * difficulty).unwrap_or(u128::MAX)
+}
+
+/// `bits` of the target for `difficulty`, as 8 hex chars (e.g. `"1f0fffff"`).
+pub fn difficulty_bits(difficulty: u32) -> String {
+    format!("{:08x}", target_to_bits(&difficulty_target(difficulty)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PowAlgo, bits_to_target, difficulty_target, target_to_bits};
+
+    #[test]
+    fn parse_and_digests_differ() {
+        assert_eq!(PowAlgo::parse("SCRYPT"), Some(PowAlgo::Scrypt));
+        assert_eq!(PowAlgo::parse("sha256"), Some(PowAlgo::Sha256));
+        assert_eq!(PowAlgo::parse("argon2"), None);
+
+        let sha = PowAlgo::Sha256.digest_hex(b"abc");
+        let scr = PowAlgo::Scrypt.digest_hex(b"abc");
+        assert_eq!(sha.len(), 64);
+        assert_eq!(scr.len(), 64);
+        assert_ne!(sha, scr);
+        assert_eq!(scr, PowAlgo::Scrypt.digest_hex(b"abc"));
+    }
+
+    #[test]
+    fn bits_round_trip_within_precision() {
+        assert_eq!(target_to_bits(&difficulty_target(3)), 0x1f0f_ffff);
+        assert_eq!(
+            hex::encode(bits_to_target(0x1f0f_ffff)),
+            format!("000fffff{}", "0".repeat(56))
+        );
+
+        let mut targets: Vec<[u8; 32]> = (0..=64).map(difficulty_target).collect();
+        let mut odd = [0u8; 32];
+        odd[5..9].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+        targets.push(odd);
+        let mut high_bit = [0u8; 32];
+        high_bit[10] = 0x80;
+        targets.push(high_bit);
+
+        for t in targets {
+            let back = bits_to_target(target_to_bits(&t));
+            assert!(back <= t, "{} > {}", hex::encode(back), hex::encode(t));
+            // at least the two most significant bytes survive
+            let first = t.iter().position(|&b| b != 0).unwrap_or(32);
+            let keep = (first + 2).min(32);
+            assert_eq!(back[..keep], t[..keep], "{}", hex::encode(t));
+        }
+        assert_eq!(bits_to_target(target_to_bits(&odd)), {
+            let mut t = [0u8; 32];
+            t[5..8].copy_from_slice(&[0x12, 0x34, 0x56]);
+            t
+        });
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use super::{BASE_REWARD, Block, Blockchain, PowAlgo};
+use crate::transaction::{OutPoint, UtxoSet};
+use crate::wallet::pubkey_to_address_hex;
+
+/// Where (and why) a full chain replay stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidBlock {
+    pub index: u64,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block #{}: {}", self.index, self.reason)
+    }
+}
+
+impl Blockchain {
+    /// `is_valid_chain` plus the economics: replays every block from genesis
+    /// checking signatures, that inputs exist and belong to the signer, and
+    /// that no coinbase pays more than `BASE_REWARD` + the block's fees, and
+    /// that no coinbase output is spent before `maturity` blocks on top of it.
+    /// `off_chain` is the dev faucet's ledger: outputs it created outside blocks,
+    /// plus the coinbase outputs it paid (`is_coinbase`), allowed on top of the reward.
+    pub fn is_valid_chain_full(
+        &self,
+        chain_id: &str,
+        off_chain: &UtxoSet,
+        maturity: u64,
+    ) -> Result<(), InvalidBlock> {
+        validate_chain_full(&self.chain, self.pow_algo, chain_id, off_chain, maturity)
+    }
+}
+
+/// Full validation of `blocks` (see `Blockchain::is_valid_chain_full`).
+/// O(total transactions), with every signature verified: far heavier than
+/// the structural check.
+pub fn validate_chain_full(
+    blocks: &[Block],
+    pow_algo: PowAlgo,
+    chain_id: &str,
+    off_chain: &UtxoSet,
+    maturity: u64,
+) -> Result<(), InvalidBlock> {
+    let invalid = |index: u64, reason: String| Err(InvalidBlock { index, reason });
+
+    if blocks.is_empty() {
+        return invalid(0, "chain has no genesis block".into());
+    }
+    if let Some((index, reason)) = Blockchain::first_invalid_block(blocks, pow_algo) {
+        return invalid(index, reason.into());
+    }
+
+    let mut utxo = seed_off_chain(off_chain);
+    for block in blocks {
+        if block.pruned {
+            return invalid(
+                block.index,
+                "body pruned; a full replay needs every block".into(),
+            );
+        }
+        if let Err(bad) = block.verify_signatures(chain_id) {
+            return invalid(block.index, format!("bad signature: {bad}"));
+        }
+
+        // inputs are resolved against the UTXO set as of the previous block
+        if let Err(reason) = check_block_txs(block, &utxo, off_chain, maturity) {
+            return invalid(block.index, reason);
+        }
+        utxo.apply_block_txs(&block.transactions, block.index);
+    }
+    Ok(())
+}
+
+/// Economic checks of one block against `utxo`, the set as of the previous
+/// block (which is only read): every input exists, is spent once and belongs
+/// to its signer, spent coinbase outputs have `maturity` blocks on top of
+/// them (unless the faucet ledger `off_chain` exempts them), no tx spends
+/// more than its inputs, and the coinbase pays at most `BASE_REWARD` + fees
+/// (+ faucet payouts recorded in `off_chain`). Signatures and PoW are
+/// checked elsewhere.
+pub fn check_block_txs(
+    block: &Block,
+    utxo: &UtxoSet,
+    off_chain: &UtxoSet,
+    maturity: u64,
+) -> Result<(), String> {
+    let mut spent = HashSet::new();
+    let mut fees: u128 = 0;
+    for tx in block.transactions.iter().filter(|tx| !tx.inputs.is_empty()) {
+        let mut input_sum: u128 = 0;
+        for input in &tx.inputs {
+            let Some(entry) = utxo.entry(&input.outpoint) else {
+                return Err(format!(
+                    "tx {} spends missing output {}:{}",
+                    tx.txid, input.outpoint.txid, input.outpoint.vout
+                ));
+            };
+            let exempt = off_chain
+                .entry(&input.outpoint)
+                .is_some_and(|e| e.maturity_exempt);
+            if !exempt && !entry.is_mature(block.index, maturity) {
+                return Err(format!(
+                    "tx {} spends immature coinbase output {}:{}",
+                    tx.txid, input.outpoint.txid, input.outpoint.vout
+                ));
+            }
+            let prev = &entry.output;
+            if !spent.insert(&input.outpoint) {
+                return Err(format!(
+                    "output {}:{} spent twice",
+                    input.outpoint.txid, input.outpoint.vout
+                ));
+            }
+            if pubkey_to_address_hex(&input.pubkey).ok().as_deref() != Some(prev.address.as_str()) {
+                return Err(format!("tx {} spends an output it does not own", tx.txid));
+            }
+            input_sum += prev.amount as u128;
+        }
+        let Some(fee) = input_sum.checked_sub(tx.total_output_amount()) else {
+            return Err(format!("tx {} spends more than its inputs", tx.txid));
+        };
+        fees += fee;
+    }
+
+    if let Some(coinbase) = block.transactions.iter().find(|tx| tx.inputs.is_empty()) {
+        let paid = coinbase.total_output_amount();
+        let faucet_paid: u128 = (0..coinbase.outputs.len())
+            .filter_map(|vout| {
+                off_chain.entry(&OutPoint {
+                    txid: coinbase.txid.clone(),
+                    vout: vout as u32,
+                })
+            })
+            .filter(|e| e.is_coinbase)
+            .map(|e| e.output.amount as u128)
+            .sum();
+        let allowed = BASE_REWARD as u128 + fees + faucet_paid;
+        if paid > allowed {
+            return Err(format!(
+                "coinbase pays {paid}, more than reward + fees ({allowed})"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The UTXO set `blocks` leave behind, applied on top of the faucet's
+/// off-chain outputs: what the live set should be. No validation at all
+/// (see `validate_chain_full` for that).
+pub fn rebuild_utxo(blocks: &[Block], off_chain: &UtxoSet) -> UtxoSet {
+    let mut utxo = seed_off_chain(off_chain);
+    for block in blocks {
+        utxo.apply_block_txs(&block.transactions, block.index);
+    }
+    // coinbase faucet payouts keep their source, and their exemption if any
+    for (op, entry) in off_chain.entries().filter(|(_, e)| e.is_coinbase) {
+        utxo.mark_faucet(op);
+        if entry.maturity_exempt {
+            utxo.exempt_from_maturity(op);
+        }
+    }
+    utxo
+}
+
+/// Faucet outputs that exist before any block. Payouts made through a
+/// coinbase (`is_coinbase`) only exist once their block does.
+fn seed_off_chain(off_chain: &UtxoSet) -> UtxoSet {
+    let mut utxo = UtxoSet::new();
+    for (op, entry) in off_chain.entries().filter(|(_, e)| !e.is_coinbase) {
+        utxo.insert(op.clone(), entry.clone());
+    }
+    utxo
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blockchain::{BASE_REWARD, Blockchain};
+    use crate::transaction::{Transaction, TxOutput, UtxoSet};
+
+    fn coinbase(address: &str, amount: u64) -> Transaction {
+        Transaction::new(
+            vec![],
+            vec![TxOutput {
+                address: address.into(),
+                amount,
+            }],
+        )
+    }
+
+    #[test]
+    fn replay_pinpoints_an_overpaying_coinbase() {
+        let mut bc = Blockchain::new(1);
+        bc.mine_block(vec![coinbase("miner", BASE_REWARD)]).unwrap();
+        assert_eq!(bc.is_valid_chain_full("test", &UtxoSet::new(), 0), Ok(()));
+
+        bc.mine_block(vec![coinbase("greedy", BASE_REWARD + 1)])
+            .unwrap();
+        bc.mine_block(vec![coinbase("miner2", BASE_REWARD)])
+            .unwrap();
+        // structurally fine, economically broken at block 2
+        assert!(bc.is_valid_chain());
+        let err = bc
+            .is_valid_chain_full("test", &UtxoSet::new(), 0)
+            .unwrap_err();
+        assert_eq!(err.index, 2);
+        assert!(err.reason.contains("coinbase pays 51"), "{}", err.reason);
+    }
+}
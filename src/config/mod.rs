@@ -0,0 +1,513 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+use crate::blockchain::{
+    DEFAULT_CHAIN_ID, DEFAULT_DIFFICULTY, DEFAULT_MAX_REORG_DEPTH, DIFF_MAX, DIFF_MIN,
+    DifficultyBounds, FeeRateMode, PowAlgo, TARGET_BLOCK_TIME_SECS,
+};
+
+pub const DEFAULT_SHUTDOWN_GRACE_SECS: u64 = 30;
+/// Largest JSON request body accepted by default (64 KiB: a transaction can't
+/// outgrow the block it must fit in).
+pub const DEFAULT_JSON_MAX_BYTES: usize = 64 * 1024;
+/// Signature verification results kept by default.
+pub const DEFAULT_SIG_CACHE_SIZE: usize = 10_000;
+/// Pause between two sync rounds with the `PEERS` by default.
+pub const DEFAULT_SYNC_INTERVAL_SECS: u64 = 10;
+
+/// How `/faucet/` hands out coins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FaucetMode {
+    /// Insert the output straight into the UTXO set (no block involved).
+    #[default]
+    Direct,
+    /// Queue the payout as an extra output of the next mined block's coinbase.
+    Coinbase,
+}
+
+impl FaucetMode {
+    /// Parse the `FAUCET_MODE` env value (`direct` or `coinbase`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "direct" => Some(Self::Direct),
+            "coinbase" => Some(Self::Coinbase),
+            _ => None,
+        }
+    }
+}
+
+/// Limits for `/faucet/`. Every field unset (the default) = fully permissive.
+#[derive(Debug, Clone, Default)]
+pub struct FaucetPolicy {
+    /// `FAUCET_MODE=direct|coinbase` — see `FaucetMode`.
+    pub mode: FaucetMode,
+    /// `FAUCET_MAX_AMOUNT` — largest amount a single request may mint.
+    pub max_amount: Option<u64>,
+    /// `FAUCET_MAX_PER_ADDRESS` — total an address may receive over the node's lifetime.
+    pub max_per_address: Option<u64>,
+    /// `FAUCET_ALLOWLIST=addr1,addr2` — only these addresses may use the faucet.
+    pub allowlist: Option<Vec<String>>,
+    /// `FAUCET_MATURITY_EXEMPT=1` — with `FAUCET_MODE=coinbase`, payouts are
+    /// spendable in the next block instead of waiting `COINBASE_MATURITY`.
+    pub maturity_exempt: bool,
+}
+
+/// Runtime node settings read from the environment (see `.env`).
+/// Anything not set falls back to the compile-time defaults.
+#[derive(Debug, Clone)]
+pub struct NodeConfig {
+    /// `FEE_RATE_MODE=byte|weight` — how block assembly ranks transactions.
+    pub fee_rate_mode: FeeRateMode,
+    /// `CHAIN_ID` — mixed into every sighash (replay protection).
+    pub chain_id: String,
+    /// `POW_ALGO=sha256|scrypt` — digest used for the PoW target check.
+    pub pow_algo: PowAlgo,
+    /// `INITIAL_DIFFICULTY` — genesis difficulty of a fresh node (clamped to `difficulty_bounds`).
+    pub initial_difficulty: u32,
+    /// `TARGET_BLOCK_TIME_SECS` — block interval the difficulty adjustment aims for.
+    pub target_block_secs: i64,
+    /// `DEV_ENDPOINTS=false` hides debugging endpoints (e.g. hash preimages).
+    pub dev_endpoints: bool,
+    /// `DATA_DIR` — where chain/UTXO snapshots are kept. Unset = in-memory only.
+    pub data_dir: Option<PathBuf>,
+    /// `SHUTDOWN_GRACE_SECS` — how long shutdown waits for in-flight mining.
+    pub shutdown_grace_secs: u64,
+    /// `PERSIST_MEMPOOL=true` also saves pending txs to `DATA_DIR` and reloads them on start.
+    pub persist_mempool: bool,
+    pub faucet: FaucetPolicy,
+    /// `COINBASE_MATURITY` — blocks a coinbase output must wait before it can be spent (0 = off).
+    pub coinbase_maturity: u64,
+    /// `UNSAFE_MAX_DIFFICULTY` — DEV ONLY: raise the difficulty cap above DIFF_MAX
+    /// (stress-testing the miner). Unset = capped at DIFF_MAX.
+    pub unsafe_max_difficulty: Option<u32>,
+    /// `DIFF_MIN` / `DIFF_MAX` — range the auto-adjuster and `/difficulty/`
+    /// keep to (e.g. a low ceiling in CI). `DIFF_MAX` can only go above the
+    /// built-in 6 together with `UNSAFE_MAX_DIFFICULTY`.
+    pub difficulty_bounds: DifficultyBounds,
+    /// `MEMPOOL_MAX_TXS` — once reached, new txs must outbid the cheapest one. Unset = unbounded.
+    pub mempool_max_txs: Option<usize>,
+    /// `WORKERS` — HTTP worker threads. Unset = one per CPU core (actix default).
+    pub workers: Option<usize>,
+    /// `JSON_MAX_BYTES` — larger JSON bodies are refused with `413`. Routes
+    /// that take a whole block or a UTXO dump have their own, larger limit.
+    pub json_max_bytes: usize,
+    /// `ADMIN_TOKEN` — bearer token for `/admin/*`. Unset = admin endpoints disabled.
+    pub admin_token: Option<String>,
+    /// `SIG_CACHE_SIZE` — verified signatures remembered (0 = no cache).
+    pub sig_cache_size: usize,
+    /// `REQUIRE_SIGNATURES=false` — DEV ONLY: accept txs without checking who
+    /// owns the inputs or their signatures (existence and amounts still checked).
+    pub require_signatures: bool,
+    /// `STANDARDNESS_CHECKS` — refuse non-standard txs at `/tx/` (relay
+    /// policy); blocks may still contain them.
+    pub standardness_checks: bool,
+    /// `MAX_REORG_DEPTH` — most blocks a chain replacement may roll back.
+    pub max_reorg_depth: u64,
+    /// `PRUNE_DEPTH` — full blocks kept below the tip; older ones keep only
+    /// their header. Unset = no pruning. Never below `MAX_REORG_DEPTH`.
+    pub prune_depth: Option<u64>,
+    /// `CHECKPOINTS=height:hash,...` — block hashes the chain must have at
+    /// those heights.
+    pub checkpoints: BTreeMap<u64, String>,
+    /// `PEERS=http://host:port,...` — nodes to pull blocks from. Empty = no sync.
+    pub peers: Vec<String>,
+    /// `SYNC_INTERVAL_SECS` — pause between two sync rounds with `PEERS`.
+    pub sync_interval_secs: u64,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            fee_rate_mode: FeeRateMode::default(),
+            chain_id: DEFAULT_CHAIN_ID.to_string(),
+            pow_algo: PowAlgo::default(),
+            initial_difficulty: DEFAULT_DIFFICULTY,
+            target_block_secs: TARGET_BLOCK_TIME_SECS,
+            dev_endpoints: true,
+            data_dir: None,
+            shutdown_grace_secs: DEFAULT_SHUTDOWN_GRACE_SECS,
+            persist_mempool: false,
+            faucet: FaucetPolicy::default(),
+            coinbase_maturity: 0,
+            unsafe_max_difficulty: None,
+            difficulty_bounds: DifficultyBounds::default(),
+            mempool_max_txs: None,
+            workers: None,
+            json_max_bytes: DEFAULT_JSON_MAX_BYTES,
+            admin_token: None,
+            sig_cache_size: DEFAULT_SIG_CACHE_SIZE,
+            require_signatures: true,
+            standardness_checks: true,
+            max_reorg_depth: DEFAULT_MAX_REORG_DEPTH,
+            prune_depth: None,
+            checkpoints: BTreeMap::new(),
+            peers: Vec::new(),
+            sync_interval_secs: DEFAULT_SYNC_INTERVAL_SECS,
+        }
+    }
+}
+
+impl NodeConfig {
+    /// Build the config from process env vars.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    /// Build the config from an arbitrary key lookup (handy for tests).
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut cfg = Self::default();
+        if let Some(v) = lookup("FEE_RATE_MODE") {
+            match FeeRateMode::parse(&v) {
+                Some(mode) => cfg.fee_rate_mode = mode,
+                None => log::warn!("ignoring invalid FEE_RATE_MODE={v:?} (expected byte|weight)"),
+            }
+        }
+        if let Some(v) = lookup("CHAIN_ID").filter(|v| !v.trim().is_empty()) {
+            cfg.chain_id = v.trim().to_string();
+        }
+        if let Some(v) = lookup("POW_ALGO") {
+            match PowAlgo::parse(&v) {
+                Some(algo) => cfg.pow_algo = algo,
+                None => log::warn!("ignoring invalid POW_ALGO={v:?} (expected sha256|scrypt)"),
+            }
+        }
+        if let Some(v) = lookup("UNSAFE_MAX_DIFFICULTY") {
+            match v.trim().parse::<u32>() {
+                Ok(d) if d > DIFF_MAX => {
+                    // a SHA-256 hex digest has 64 digits; beyond that nothing can be mined
+                    let d = d.min(64);
+                    log::warn!(
+                        "UNSAFE_MAX_DIFFICULTY={d}: difficulty cap raised above {DIFF_MAX}, dev/stress testing only"
+                    );
+                    cfg.unsafe_max_difficulty = Some(d);
+                    cfg.difficulty_bounds.max = d;
+                }
+                _ => log::warn!(
+                    "ignoring invalid UNSAFE_MAX_DIFFICULTY={v:?} (expected a number above {DIFF_MAX})"
+                ),
+            }
+        }
+        // the ceiling DIFF_MAX may lower; only UNSAFE_MAX_DIFFICULTY raises it
+        let cap = cfg.unsafe_max_difficulty.unwrap_or(DIFF_MAX);
+        if let Some(v) = lookup("DIFF_MAX") {
+            match v.trim().parse::<u32>() {
+                Ok(d) if (DIFF_MIN..=cap).contains(&d) => cfg.difficulty_bounds.max = d,
+                _ => log::warn!("ignoring invalid DIFF_MAX={v:?} (expected {DIFF_MIN}..={cap})"),
+            }
+        }
+        if let Some(v) = lookup("DIFF_MIN") {
+            let max = cfg.difficulty_bounds.max;
+            match v.trim().parse::<u32>() {
+                Ok(d) if (DIFF_MIN..=max).contains(&d) => cfg.difficulty_bounds.min = d,
+                _ => log::warn!("ignoring invalid DIFF_MIN={v:?} (expected {DIFF_MIN}..={max})"),
+            }
+        }
+        if let Some(v) = lookup("INITIAL_DIFFICULTY") {
+            match v.trim().parse::<u32>() {
+                Ok(d) => cfg.initial_difficulty = d,
+                Err(_) => log::warn!("ignoring invalid INITIAL_DIFFICULTY={v:?}"),
+            }
+        }
+        cfg.initial_difficulty = cfg.difficulty_bounds.clamp(cfg.initial_difficulty);
+        if let Some(v) = lookup("TARGET_BLOCK_TIME_SECS") {
+            match v.trim().parse::<i64>() {
+                Ok(secs) if secs > 0 => cfg.target_block_secs = secs,
+                _ => log::warn!("ignoring invalid TARGET_BLOCK_TIME_SECS={v:?}"),
+            }
+        }
+        if let Some(v) = lookup("DEV_ENDPOINTS") {
+            match parse_bool(&v) {
+                Some(on) => cfg.dev_endpoints = on,
+                None => log::warn!("ignoring invalid DEV_ENDPOINTS={v:?} (expected true|false)"),
+            }
+        }
+        if let Some(v) = lookup("DATA_DIR").filter(|v| !v.trim().is_empty()) {
+            cfg.data_dir = Some(PathBuf::from(v.trim()));
+        }
+        if let Some(v) = lookup("SHUTDOWN_GRACE_SECS") {
+            match v.trim().parse() {
+                Ok(secs) => cfg.shutdown_grace_secs = secs,
+                Err(_) => log::warn!("ignoring invalid SHUTDOWN_GRACE_SECS={v:?}"),
+            }
+        }
+        if let Some(v) = lookup("PERSIST_MEMPOOL") {
+            match parse_bool(&v) {
+                Some(on) => cfg.persist_mempool = on,
+                None => log::warn!("ignoring invalid PERSIST_MEMPOOL={v:?} (expected true|false)"),
+            }
+        }
+        if let Some(v) = lookup("FAUCET_MODE") {
+            match FaucetMode::parse(&v) {
+                Some(mode) => cfg.faucet.mode = mode,
+                None => log::warn!("ignoring invalid FAUCET_MODE={v:?} (expected direct|coinbase)"),
+            }
+        }
+        if let Some(v) = lookup("FAUCET_MATURITY_EXEMPT") {
+            match parse_bool(&v) {
+                Some(on) => cfg.faucet.maturity_exempt = on,
+                None => log::warn!(
+                    "ignoring invalid FAUCET_MATURITY_EXEMPT={v:?} (expected true|false)"
+                ),
+            }
+        }
+        if let Some(v) = lookup("FAUCET_MAX_AMOUNT") {
+            match v.trim().parse() {
+                Ok(n) => cfg.faucet.max_amount = Some(n),
+                Err(_) => log::warn!("ignoring invalid FAUCET_MAX_AMOUNT={v:?}"),
+            }
+        }
+        if let Some(v) = lookup("FAUCET_MAX_PER_ADDRESS") {
+            match v.trim().parse() {
+                Ok(n) => cfg.faucet.max_per_address = Some(n),
+                Err(_) => log::warn!("ignoring invalid FAUCET_MAX_PER_ADDRESS={v:?}"),
+            }
+        }
+        if let Some(v) = lookup("FAUCET_ALLOWLIST").filter(|v| !v.trim().is_empty()) {
+            cfg.faucet.allowlist = Some(
+                v.split(',')
+                    .map(|a| a.trim().to_string())
+                    .filter(|a| !a.is_empty())
+                    .collect(),
+            );
+        }
+        if let Some(v) = lookup("COINBASE_MATURITY") {
+            match v.trim().parse() {
+                Ok(n) => cfg.coinbase_maturity = n,
+                Err(_) => log::warn!("ignoring invalid COINBASE_MATURITY={v:?}"),
+            }
+        }
+        if let Some(v) = lookup("MEMPOOL_MAX_TXS") {
+            match v.trim().parse() {
+                Ok(n) => cfg.mempool_max_txs = Some(n),
+                Err(_) => log::warn!("ignoring invalid MEMPOOL_MAX_TXS={v:?}"),
+            }
+        }
+        if let Some(v) = lookup("WORKERS") {
+            match v.trim().parse() {
+                Ok(n) if n > 0 => cfg.workers = Some(n),
+                _ => log::warn!("ignoring invalid WORKERS={v:?} (expected a number > 0)"),
+            }
+        }
+        if let Some(v) = lookup("ADMIN_TOKEN").filter(|v| !v.trim().is_empty()) {
+            cfg.admin_token = Some(v.trim().to_string());
+        }
+        if let Some(v) = lookup("SIG_CACHE_SIZE") {
+            match v.trim().parse() {
+                Ok(n) => cfg.sig_cache_size = n,
+                Err(_) => log::warn!("ignoring invalid SIG_CACHE_SIZE={v:?}"),
+            }
+        }
+        if let Some(v) = lookup("REQUIRE_SIGNATURES") {
+            match parse_bool(&v) {
+                Some(on) => cfg.require_signatures = on,
+                None => {
+                    log::warn!("ignoring invalid REQUIRE_SIGNATURES={v:?} (expected true|false)")
+                }
+            }
+        }
+        if let Some(v) = lookup("STANDARDNESS_CHECKS") {
+            match parse_bool(&v) {
+                Some(on) => cfg.standardness_checks = on,
+                None => {
+                    log::warn!("ignoring invalid STANDARDNESS_CHECKS={v:?} (expected true|false)")
+                }
+            }
+        }
+        if let Some(v) = lookup("MAX_REORG_DEPTH") {
+            match v.trim().parse() {
+                Ok(n) => cfg.max_reorg_depth = n,
+                Err(_) => log::warn!("ignoring invalid MAX_REORG_DEPTH={v:?}"),
+            }
+        }
+        if let Some(v) = lookup("PRUNE_DEPTH") {
+            match v.trim().parse::<u64>() {
+                Ok(n) if n > 0 => {
+                    if n < cfg.max_reorg_depth {
+                        log::warn!(
+                            "PRUNE_DEPTH={n} is below MAX_REORG_DEPTH={0}; keeping {0} full blocks",
+                            cfg.max_reorg_depth
+                        );
+                    }
+                    cfg.prune_depth = Some(n.max(cfg.max_reorg_depth));
+                }
+                _ => log::warn!("ignoring invalid PRUNE_DEPTH={v:?} (expected a number > 0)"),
+            }
+        }
+        if let Some(v) = lookup("CHECKPOINTS").filter(|v| !v.trim().is_empty()) {
+            match parse_checkpoints(&v) {
+                Some(checkpoints) => cfg.checkpoints = checkpoints,
+                None => log::warn!(
+                    "ignoring invalid CHECKPOINTS={v:?} (expected height:hash pairs, comma-separated)"
+                ),
+            }
+        }
+        if let Some(v) = lookup("JSON_MAX_BYTES") {
+            match v.trim().parse() {
+                Ok(n) if n > 0 => cfg.json_max_bytes = n,
+                _ => log::warn!("ignoring invalid JSON_MAX_BYTES={v:?} (expected a number > 0)"),
+            }
+        }
+        if let Some(v) = lookup("PEERS") {
+            for peer in v.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                if peer.starts_with("http://") {
+                    cfg.peers.push(peer.trim_end_matches('/').to_string());
+                } else {
+                    log::warn!("ignoring peer {peer:?} in PEERS (expected http://host:port)");
+                }
+            }
+        }
+        if let Some(v) = lookup("SYNC_INTERVAL_SECS") {
+            match v.trim().parse() {
+                Ok(n) if n > 0 => cfg.sync_interval_secs = n,
+                _ => {
+                    log::warn!("ignoring invalid SYNC_INTERVAL_SECS={v:?} (expected a number > 0)")
+                }
+            }
+        }
+        cfg
+    }
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// `"10:00ab...,20:00cd..."`; `None` if any pair is malformed (hashes are
+/// 64 hex chars) or a height repeats.
+fn parse_checkpoints(s: &str) -> Option<BTreeMap<u64, String>> {
+    let mut checkpoints = BTreeMap::new();
+    for pair in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (height, hash) = pair.split_once(':')?;
+        let height = height.trim().parse().ok()?;
+        let hash = hash.trim().to_ascii_lowercase();
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        if checkpoints.insert(height, hash).is_some() {
+            return None;
+        }
+    }
+    Some(checkpoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FaucetMode, NodeConfig};
+    use crate::blockchain::{DEFAULT_MAX_REORG_DEPTH, DIFF_MAX, DIFF_MIN};
+
+    fn lookup<'a>(pairs: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |key| {
+            pairs
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn initial_difficulty_is_clamped() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("INITIAL_DIFFICULTY", "99")]));
+        assert_eq!(cfg.initial_difficulty, DIFF_MAX);
+        let cfg = NodeConfig::from_lookup(lookup(&[("INITIAL_DIFFICULTY", "0")]));
+        assert_eq!(cfg.initial_difficulty, DIFF_MIN);
+        let cfg = NodeConfig::from_lookup(lookup(&[("TARGET_BLOCK_TIME_SECS", "5")]));
+        assert_eq!(cfg.target_block_secs, 5);
+    }
+
+    #[test]
+    fn difficulty_bounds_from_env() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("DIFF_MAX", "2")]));
+        assert_eq!(
+            (cfg.difficulty_bounds.min, cfg.difficulty_bounds.max),
+            (DIFF_MIN, 2)
+        );
+        // the default initial difficulty (3) is pulled under the ceiling
+        assert_eq!(cfg.initial_difficulty, 2);
+
+        let cfg =
+            NodeConfig::from_lookup(lookup(&[("DIFF_MIN", "2"), ("INITIAL_DIFFICULTY", "1")]));
+        assert_eq!(cfg.difficulty_bounds.min, 2);
+        assert_eq!(cfg.initial_difficulty, 2);
+
+        // raising the ceiling needs UNSAFE_MAX_DIFFICULTY; a floor above it is ignored
+        let cfg = NodeConfig::from_lookup(lookup(&[("DIFF_MAX", "9"), ("DIFF_MIN", "7")]));
+        assert_eq!(cfg.difficulty_bounds.max, DIFF_MAX);
+        assert_eq!(cfg.difficulty_bounds.min, DIFF_MIN);
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("UNSAFE_MAX_DIFFICULTY", "12"),
+            ("DIFF_MAX", "9"),
+        ]));
+        assert_eq!(cfg.difficulty_bounds.max, 9);
+    }
+
+    #[test]
+    fn prune_depth_never_undercuts_the_reorg_limit() {
+        assert_eq!(NodeConfig::from_lookup(lookup(&[])).prune_depth, None);
+        let cfg = NodeConfig::from_lookup(lookup(&[("PRUNE_DEPTH", "500")]));
+        assert_eq!(cfg.prune_depth, Some(500));
+        let cfg = NodeConfig::from_lookup(lookup(&[("PRUNE_DEPTH", "10")]));
+        assert_eq!(cfg.prune_depth, Some(DEFAULT_MAX_REORG_DEPTH));
+        let cfg =
+            NodeConfig::from_lookup(lookup(&[("PRUNE_DEPTH", "10"), ("MAX_REORG_DEPTH", "4")]));
+        assert_eq!(cfg.prune_depth, Some(10));
+        let cfg = NodeConfig::from_lookup(lookup(&[("PRUNE_DEPTH", "0")]));
+        assert_eq!(cfg.prune_depth, None);
+    }
+
+    #[test]
+    fn checkpoints_from_env() {
+        let a = "0a".repeat(32);
+        let b = "0B".repeat(32);
+        let v = format!("2:{a}, 10:{b}");
+        let cfg = NodeConfig::from_lookup(lookup(&[("CHECKPOINTS", &v)]));
+        assert_eq!(cfg.checkpoints.len(), 2);
+        assert_eq!(cfg.checkpoints[&2], a);
+        assert_eq!(cfg.checkpoints[&10], b.to_ascii_lowercase());
+
+        for bad in [
+            "2:abc".to_string(),
+            format!("x:{a}"),
+            format!("2:{a},2:{a}"),
+        ] {
+            let cfg = NodeConfig::from_lookup(lookup(&[("CHECKPOINTS", &bad)]));
+            assert!(cfg.checkpoints.is_empty(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn peers_from_env() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            (
+                "PEERS",
+                " http://10.0.0.2:8080/, https://x:1,,http://b:8081",
+            ),
+            ("SYNC_INTERVAL_SECS", "0"),
+        ]));
+        assert_eq!(cfg.peers, ["http://10.0.0.2:8080", "http://b:8081"]);
+        assert_eq!(cfg.sync_interval_secs, super::DEFAULT_SYNC_INTERVAL_SECS);
+    }
+
+    #[test]
+    fn faucet_policy_from_env() {
+        let cfg = NodeConfig::from_lookup(lookup(&[]));
+        assert!(cfg.faucet.max_amount.is_none() && cfg.faucet.allowlist.is_none());
+        assert_eq!(cfg.faucet.mode, FaucetMode::Direct);
+
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("FAUCET_MAX_AMOUNT", "500"),
+            ("FAUCET_ALLOWLIST", " alice, bob ,"),
+            ("FAUCET_MODE", "Coinbase"),
+        ]));
+        assert_eq!(cfg.faucet.mode, FaucetMode::Coinbase);
+        assert_eq!(cfg.faucet.max_amount, Some(500));
+        assert_eq!(
+            cfg.faucet.allowlist,
+            Some(vec!["alice".to_string(), "bob".to_string()])
+        );
+    }
+}
@@ -1,5 +1,8 @@
 pub mod api;
 pub mod blockchain;
+pub mod config;
 pub mod node;
+pub(crate) mod serde_util;
+pub mod storage;
 pub mod transaction;
 pub mod wallet;
@@ -1,18 +1,32 @@
-mod api;
-mod blockchain;
-mod transaction;
-mod wallet;
-
-use actix_web::{App, HttpServer, web};
+use actix_web::web;
 use dotenvy::dotenv;
+use log::{error, info, warn};
 use std::env;
+use std::io::Write;
+use std::time::Duration;
 
-use api::AppState;
+use rust_blockchain::api::{self, AppState, request_id};
+use rust_blockchain::node;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     let _ = dotenv();
-    env_logger::init();
+    // default env_logger layout, plus the request ID for lines logged while handling one
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            let rid = request_id::current()
+                .map(|id| format!("[req {id}] "))
+                .unwrap_or_default();
+            writeln!(
+                buf,
+                "[{} {:<5} {}] {rid}{}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                record.args()
+            )
+        })
+        .init();
 
     let host = env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
     let port: u16 = env::var("PORT")
@@ -23,13 +37,57 @@ async fn main() -> std::io::Result<()> {
     println!("⛓️ Starting blockchain API at http://{host}:{port}");
 
     let state = web::Data::new(AppState::default());
+    let grace = Duration::from_secs(state.config.shutdown_grace_secs);
+    if !state.config.require_signatures {
+        warn!(
+            "!!! REQUIRE_SIGNATURES=false: transactions are accepted WITHOUT ownership or signature checks; never run this outside development !!!"
+        );
+    }
+
+    // blocks from PEERS, if any (the thread exits on shutdown)
+    node::spawn_sync(state.clone())?;
+
+    // signals are handled below so we can flush state afterwards
+    let server = api::server(state.clone(), (host.as_str(), port))?;
+    let handle = server.handle();
+    let mut server_task = actix_web::rt::spawn(server);
+
+    tokio::select! {
+        res = &mut server_task => {
+            // server stopped on its own (error); still try to save what we have
+            if let Err(e) = state.shutdown(grace).await {
+                error!("shutdown: flush failed: {e}");
+            }
+            return res.map_err(std::io::Error::other)?;
+        }
+        _ = shutdown_signal() => {}
+    }
+
+    info!("shutdown: signal received, no longer accepting connections");
+    // graceful stop: waits (up to the grace period) for in-flight requests, mining included
+    handle.stop(true).await;
+    let _ = server_task.await;
+    info!("shutdown: HTTP server stopped");
+    if let Err(e) = state.shutdown(grace).await {
+        error!("shutdown: flush failed: {e}");
+    }
+    info!("shutdown: done");
+    Ok(())
+}
 
-    HttpServer::new(move || {
-        App::new()
-            .app_data(state.clone())
-            .configure(api::init_routes)
-    })
-    .bind((host.as_str(), port))?
-    .run()
-    .await
+/// Resolves on SIGINT (Ctrl-C) or, on unix, SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use actix_web::rt::signal::unix::{SignalKind, signal};
+        let mut term = signal(SignalKind::terminate()).expect("install SIGTERM handler");
+        tokio::select! {
+            _ = actix_web::rt::signal::ctrl_c() => {}
+            _ = term.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = actix_web::rt::signal::ctrl_c().await;
+    }
 }
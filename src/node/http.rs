@@ -0,0 +1,187 @@
+use log::{debug, warn};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use super::peer::Peer;
+use crate::blockchain::Block;
+
+/// Largest response body read from a peer (a block is a few hundred KiB).
+const MAX_RESPONSE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Another node of this software, reached over its `/api/v1` HTTP API
+/// (`/chain/tip/` and `/block/{height}/`). Plain HTTP only: one short
+/// `Connection: close` request per call, no TLS.
+pub struct HttpPeer {
+    /// `http://host:port`, as configured
+    url: String,
+    /// `host:port`
+    authority: String,
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct Tip {
+    index: u64,
+}
+
+impl HttpPeer {
+    /// `url` is `http://host:port` (no path); `None` for anything else.
+    pub fn new(url: &str, timeout: Duration) -> Option<Self> {
+        let authority = url.strip_prefix("http://")?.trim_end_matches('/');
+        if authority.is_empty() || authority.contains('/') {
+            return None;
+        }
+        Some(Self {
+            url: url.trim_end_matches('/').to_string(),
+            authority: authority.to_string(),
+            timeout,
+        })
+    }
+
+    /// `GET /api/v1{path}` decoded as JSON; `None` on any network, HTTP or
+    /// decoding failure (logged).
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Option<T> {
+        match self.fetch(path) {
+            Ok(body) => serde_json::from_slice(&body)
+                .map_err(|e| warn!("peer {}: bad JSON from {path}: {e}", self.url))
+                .ok(),
+            Err(e) => {
+                debug!("peer {}: GET {path} failed: {e}", self.url);
+                None
+            }
+        }
+    }
+
+    /// Body of a `200` response to `GET /api/v1{path}`.
+    fn fetch(&self, path: &str) -> Result<Vec<u8>, String> {
+        let addr = self
+            .authority
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or("no address")?;
+        let mut stream =
+            TcpStream::connect_timeout(&addr, self.timeout).map_err(|e| e.to_string())?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .and_then(|()| stream.set_write_timeout(Some(self.timeout)))
+            .map_err(|e| e.to_string())?;
+        // HTTP/1.0: the server closes the connection after a plain (unchunked) body
+        write!(
+            stream,
+            "GET /api/v1{path} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n\r\n",
+            self.authority
+        )
+        .map_err(|e| e.to_string())?;
+        let mut raw = Vec::new();
+        stream
+            .take(MAX_RESPONSE_BYTES)
+            .read_to_end(&mut raw)
+            .map_err(|e| e.to_string())?;
+
+        let split = raw
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or("truncated response")?;
+        let head = String::from_utf8_lossy(&raw[..split]);
+        let status = head.split_whitespace().nth(1).unwrap_or_default();
+        if status != "200" {
+            return Err(format!("HTTP {status}"));
+        }
+        Ok(raw[split + 4..].to_vec())
+    }
+}
+
+impl Peer for HttpPeer {
+    fn id(&self) -> String {
+        self.url.clone()
+    }
+
+    fn tip_height(&self) -> Option<u64> {
+        self.get::<Tip>("/chain/tip/").map(|tip| tip.index)
+    }
+
+    fn block_at(&self, height: u64) -> Option<Block> {
+        self.get(&format!("/block/{height}/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HttpPeer;
+    use crate::api::{self, AppState};
+    use crate::blockchain::BASE_REWARD;
+    use crate::config::NodeConfig;
+    use crate::node::{Peer, sync_from_peer};
+    use crate::transaction::{Transaction, TxOutput};
+    use actix_web::web;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    fn node() -> web::Data<AppState> {
+        web::Data::new(AppState::with_config(NodeConfig {
+            initial_difficulty: 1,
+            workers: Some(1),
+            ..NodeConfig::default()
+        }))
+    }
+
+    #[actix_web::test]
+    async fn syncs_from_a_node_over_http() {
+        let remote = node();
+        for height in 1..=3 {
+            let out = TxOutput {
+                address: format!("miner{height}"),
+                amount: BASE_REWARD,
+            };
+            let mut bc = remote.blockchain.lock().unwrap();
+            bc.mine_block(vec![Transaction::coinbase(height, vec![out])])
+                .unwrap();
+        }
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = api::server(remote.clone(), ("127.0.0.1", port)).unwrap();
+        let handle = server.handle();
+        let task = actix_web::rt::spawn(server);
+
+        let local = node();
+        let synced = local.clone();
+        let (tip, missing, report) = web::block(move || {
+            let peer = HttpPeer::new(&format!("http://127.0.0.1:{port}/"), Duration::from_secs(5))
+                .unwrap();
+            (
+                peer.tip_height(),
+                peer.block_at(9),
+                sync_from_peer(&synced, &peer),
+            )
+        })
+        .await
+        .unwrap();
+        assert_eq!(tip, Some(3));
+        assert!(missing.is_none());
+        assert_eq!(report.applied, 3);
+        assert!(report.rejected.is_none());
+        let hashes = |state: &AppState| -> Vec<String> {
+            let bc = state.blockchain.lock().unwrap();
+            bc.chain.iter().map(|b| b.hash.clone()).collect()
+        };
+        assert_eq!(hashes(&local), hashes(&remote));
+        assert_eq!(local.utxo_set.lock().unwrap().len(), 3);
+
+        handle.stop(false).await;
+        let _ = task.await;
+    }
+
+    #[test]
+    fn only_plain_http_base_urls() {
+        assert!(HttpPeer::new("http://10.0.0.2:8080", Duration::from_secs(1)).is_some());
+        assert!(HttpPeer::new("https://10.0.0.2:8080", Duration::from_secs(1)).is_none());
+        assert!(HttpPeer::new("http://10.0.0.2:8080/api", Duration::from_secs(1)).is_none());
+    }
+}
@@ -0,0 +1,62 @@
+//! Multi-node pieces: talking to peers and comparing chains with them.
+
+pub mod http;
+pub mod peer;
+pub mod sync;
+
+use actix_web::web;
+use log::{info, warn};
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::api::AppState;
+pub use http::HttpPeer;
+pub use peer::Peer;
+pub use sync::{
+    SyncReport, adopt_chain, check_fork, reorg_to_peer, sync_from_peer, sync_with_peer,
+};
+
+/// How long one request to a peer may take.
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Start the background thread that checks `PEERS` for forks and pulls their
+/// new blocks every `SYNC_INTERVAL_SECS`, one peer after the other. `Ok(None)` without peers.
+/// The thread stops once the node starts shutting down.
+pub fn spawn_sync(state: web::Data<AppState>) -> io::Result<Option<JoinHandle<()>>> {
+    let peers: Vec<HttpPeer> = state
+        .config
+        .peers
+        .iter()
+        .filter_map(|url| {
+            let peer = HttpPeer::new(url, PEER_TIMEOUT);
+            if peer.is_none() {
+                warn!("sync: ignoring peer {url:?} (expected http://host:port)");
+            }
+            peer
+        })
+        .collect();
+    if peers.is_empty() {
+        return Ok(None);
+    }
+    let interval = Duration::from_secs(state.config.sync_interval_secs);
+    info!(
+        "sync: pulling blocks from {} peer(s) every {}s",
+        peers.len(),
+        interval.as_secs()
+    );
+    let handle = thread::Builder::new()
+        .name("peer-sync".into())
+        .spawn(move || {
+            while !state.is_shutting_down() {
+                for peer in &peers {
+                    if state.is_shutting_down() {
+                        break;
+                    }
+                    sync_with_peer(&state, peer);
+                }
+                thread::sleep(interval);
+            }
+        })?;
+    Ok(Some(handle))
+}
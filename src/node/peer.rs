@@ -0,0 +1,13 @@
+use crate::blockchain::Block;
+
+/// A remote node we can read blocks from.
+pub trait Peer {
+    /// Human-readable id for logs (e.g. its base URL).
+    fn id(&self) -> String;
+
+    /// Height of the peer's tip, `None` if unreachable.
+    fn tip_height(&self) -> Option<u64>;
+
+    /// The peer's block at `height`, `None` if it has none (or is unreachable).
+    fn block_at(&self, height: u64) -> Option<Block>;
+}
@@ -0,0 +1,468 @@
+use log::{info, warn};
+use std::collections::HashSet;
+
+use super::peer::Peer;
+use crate::api::AppState;
+use crate::blockchain::{Block, InvalidBlock, check_block_txs, rebuild_utxo};
+use crate::transaction::OutPoint;
+
+/// How far `sync_from_peer` got.
+#[derive(Debug)]
+pub struct SyncReport {
+    /// blocks appended, each one fully (chain and UTXO together)
+    pub applied: u64,
+    /// the block that was refused, if any; everything before it stays applied
+    pub rejected: Option<InvalidBlock>,
+}
+
+/// Download the blocks `peer` has past our tip and append them one by one.
+/// Each block is applied as a unit: it is checked in full (signatures,
+/// linkage, PoW, and every transaction against the UTXO set, which is only
+/// read at that point) before the chain or the UTXO set change, so a bad
+/// block mid-batch leaves the node exactly at the last good one.
+pub fn sync_from_peer(state: &AppState, peer: &dyn Peer) -> SyncReport {
+    let mut report = SyncReport {
+        applied: 0,
+        rejected: None,
+    };
+    let Some(peer_tip) = peer.tip_height() else {
+        return report;
+    };
+    while !state.is_shutting_down() {
+        let next = state.blockchain.lock().expect("mutex poisoned").len() as u64;
+        if next > peer_tip {
+            break;
+        }
+        let Some(block) = peer.block_at(next) else {
+            break;
+        };
+        if let Err(bad) = apply_peer_block(state, block) {
+            warn!("sync: peer {} sent an invalid {bad}", peer.id());
+            report.rejected = Some(bad);
+            break;
+        }
+        report.applied += 1;
+    }
+    if report.applied > 0 {
+        info!(
+            "sync: applied {} block(s) from {}",
+            report.applied,
+            peer.id()
+        );
+    }
+    report
+}
+
+/// Validate `block` completely, then commit it to the chain, the UTXO set
+/// and the mempool. Nothing is mutated unless every check passes.
+fn apply_peer_block(state: &AppState, block: Block) -> Result<(), InvalidBlock> {
+    let index = block.index;
+    let invalid = |reason: String| InvalidBlock { index, reason };
+    state
+        .verify_block_signatures(&block)
+        .map_err(|bad| invalid(format!("bad signature: {bad}")))?;
+
+    let txs = block.transactions.clone();
+    {
+        // the faucet ledger is only read: a snapshot keeps it out of the
+        // chain -> UTXO lock order (the faucet takes it after the UTXO)
+        let mut bc = state.blockchain.lock().expect("mutex poisoned");
+        let faucet = state
+            .faucet_outputs
+            .lock()
+            .expect("mutex poisoned")
+            .snapshot();
+        let mut utxo = state.utxo_set.lock().expect("mutex poisoned");
+        check_block_txs(&block, &utxo, &faucet, state.config.coinbase_maturity).map_err(invalid)?;
+        // linkage, PoW and structure; the chain only grows if they all pass
+        bc.append_premined_block(block).map_err(invalid)?;
+        utxo.apply_block_txs(&txs, index);
+    }
+
+    // the block's txs, and anything conflicting with them, leave the mempool
+    let included: HashSet<&str> = txs.iter().map(|t| t.txid.as_str()).collect();
+    let spent: HashSet<&OutPoint> = txs
+        .iter()
+        .flat_map(|t| t.inputs.iter().map(|i| &i.outpoint))
+        .collect();
+    {
+        let mut mempool = state.mempool.lock().expect("mutex poisoned");
+        mempool.retain(|e| {
+            !included.contains(e.tx.txid.as_str())
+                && !e.tx.inputs.iter().any(|i| spent.contains(&i.outpoint))
+        });
+        state.forget_spent_fees(&mempool, &txs);
+    }
+    state.notify_new_tip(index);
+    Ok(())
+}
+
+/// Compare our chain with `peer`'s and return the first height where the two
+/// disagree, recording it in `state.fork_detected_at` (which keeps the lowest
+/// such height). Doesn't reorg: adopting
+/// the peer's chain is `reorg_to_peer`'s job.
+///
+/// Blocks are hash-linked, so once the chains diverge they stay diverged: if
+/// the highest common height matches, nothing below can differ, otherwise a
+/// binary search finds the divergence with O(log n) peer requests.
+pub fn check_fork(state: &AppState, peer: &dyn Peer) -> Option<u64> {
+    let peer_tip = peer.tip_height()?;
+    let local_hash = |h: u64| -> Option<String> {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        bc.chain.get(h as usize).map(|b| b.hash.clone())
+    };
+    let local_tip = state
+        .blockchain
+        .lock()
+        .expect("mutex poisoned")
+        .last_block()
+        .index;
+    let differs = |h: u64| -> Option<bool> {
+        let remote = peer.block_at(h)?;
+        Some(local_hash(h)? != remote.hash)
+    };
+
+    let top = local_tip.min(peer_tip);
+    if !differs(top)? {
+        return None;
+    }
+    // first differing height in 0..=top (top is known to differ)
+    let (mut lo, mut hi) = (0, top);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if differs(mid)? {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    warn!(
+        "fork detected: peer {} diverges from the local chain at height {lo}",
+        peer.id()
+    );
+    // keep the lowest height seen, whichever peer reported it
+    let mut recorded = state.fork_detected_at.lock().expect("mutex poisoned");
+    *recorded = Some(recorded.map_or(lo, |h| h.min(lo)));
+    Some(lo)
+}
+
+/// One sync round with `peer`: look for a fork first, then pull the blocks
+/// past our tip. A forked peer's chain is adopted only through
+/// `reorg_to_peer` (more work, within `MAX_REORG_DEPTH`); otherwise nothing
+/// is pulled from it.
+pub fn sync_with_peer(state: &AppState, peer: &dyn Peer) -> SyncReport {
+    let mut report = SyncReport {
+        applied: 0,
+        rejected: None,
+    };
+    let Some(fork) = check_fork(state, peer) else {
+        return sync_from_peer(state, peer);
+    };
+    match reorg_to_peer(state, peer, fork) {
+        Ok(adopted) => report.applied = adopted,
+        Err(bad) => {
+            warn!("sync: not reorging to peer {}: {bad}", peer.id());
+            report.rejected = Some(bad);
+        }
+    }
+    report
+}
+
+/// Replace our blocks from `fork` up with `peer`'s, if its chain has more
+/// work (see `adopt_chain`). Returns how many of the peer's blocks were
+/// adopted (0 = kept ours).
+pub fn reorg_to_peer(state: &AppState, peer: &dyn Peer, fork: u64) -> Result<u64, InvalidBlock> {
+    let refuse = |reason: String| InvalidBlock {
+        index: fork,
+        reason,
+    };
+    let (shared, depth, max_depth) = {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        let shared = bc.chain[..fork as usize].to_vec();
+        (shared, bc.len() as u64 - fork, bc.max_reorg_depth)
+    };
+    // refuse before downloading anything: adopt_chain would anyway
+    if depth > max_depth {
+        return Err(refuse(format!(
+            "reorg too deep: candidate rolls back {depth} blocks (max {max_depth})"
+        )));
+    }
+    let peer_tip = peer
+        .tip_height()
+        .ok_or_else(|| refuse("peer unreachable".into()))?;
+    let mut candidate = shared;
+    for height in fork..=peer_tip {
+        let block = peer
+            .block_at(height)
+            .ok_or_else(|| refuse(format!("peer has no block #{height}")))?;
+        candidate.push(block);
+    }
+    adopt_chain(state, candidate, &format!("peer {}", peer.id()))
+}
+
+/// Switch to `candidate`, a whole chain from genesis, if it has more work
+/// than ours. Its blocks past the last one we share are validated in full
+/// against the UTXO set as of that fork before `Blockchain::try_replace`
+/// (depth limit, checkpoints, work) decides; then the UTXO set is rebuilt
+/// and the mempool rechecked against it. `source` names the candidate in
+/// the log. Returns how many blocks were adopted (0 = kept ours).
+pub fn adopt_chain(
+    state: &AppState,
+    candidate: Vec<Block>,
+    source: &str,
+) -> Result<u64, InvalidBlock> {
+    let (ours, max_depth) = {
+        let bc = state.blockchain.lock().expect("mutex poisoned");
+        (bc.chain.clone(), bc.max_reorg_depth)
+    };
+    let fork = ours
+        .iter()
+        .zip(&candidate)
+        .take_while(|(a, b)| a.hash == b.hash)
+        .count();
+    let refuse = |reason: String| InvalidBlock {
+        index: fork as u64,
+        reason,
+    };
+    if fork == 0 {
+        return Err(refuse("different genesis block".into()));
+    }
+    if fork == candidate.len() {
+        // nothing past what we already have
+        return Ok(0);
+    }
+    let depth = (ours.len() - fork) as u64;
+    if depth > max_depth {
+        return Err(refuse(format!(
+            "reorg too deep: candidate rolls back {depth} blocks (max {max_depth})"
+        )));
+    }
+    if ours[..fork].iter().any(|b| b.pruned) {
+        return Err(refuse("local blocks below the fork are pruned".into()));
+    }
+
+    let faucet = state
+        .faucet_outputs
+        .lock()
+        .expect("mutex poisoned")
+        .snapshot();
+    let mut utxo = rebuild_utxo(&candidate[..fork], &faucet);
+    for block in &candidate[fork..] {
+        let invalid = |reason: String| InvalidBlock {
+            index: block.index,
+            reason,
+        };
+        state
+            .verify_block_signatures(block)
+            .map_err(|bad| invalid(format!("bad signature: {bad}")))?;
+        check_block_txs(block, &utxo, &faucet, state.config.coinbase_maturity).map_err(invalid)?;
+        utxo.apply_block_txs(&block.transactions, block.index);
+    }
+    let rebuilt = rebuild_utxo(&candidate, &faucet);
+    let adopted = (candidate.len() - fork) as u64;
+
+    let tip = {
+        let mut bc = state.blockchain.lock().expect("mutex poisoned");
+        if bc.last_block().hash != ours[ours.len() - 1].hash {
+            return Err(refuse("local chain moved during the reorg".into()));
+        }
+        if !bc.try_replace(candidate).map_err(refuse)? {
+            return Ok(0);
+        }
+        let mut live = state.utxo_set.lock().expect("mutex poisoned");
+        *live = rebuilt;
+        state.revalidate_mempool(&live, bc.len() as u64);
+        bc.last_block().index
+    };
+    if depth > 0 {
+        warn!(
+            "sync: reorg to {source}: replaced {depth} block(s) from height {fork} with {adopted}"
+        );
+    } else {
+        info!("sync: extended the chain with {adopted} block(s) from {source}");
+    }
+    state.notify_new_tip(tip);
+    Ok(adopted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_fork, sync_from_peer, sync_with_peer};
+    use crate::api::AppState;
+    use crate::blockchain::{Block, Blockchain, rebuild_utxo};
+    use crate::config::NodeConfig;
+    use crate::node::Peer;
+    use crate::transaction::{Transaction, TxOutput, UtxoSet};
+
+    struct MockPeer(Vec<Block>);
+
+    impl Peer for MockPeer {
+        fn id(&self) -> String {
+            "mock".into()
+        }
+        fn tip_height(&self) -> Option<u64> {
+            self.0.last().map(|b| b.index)
+        }
+        fn block_at(&self, height: u64) -> Option<Block> {
+            self.0.get(height as usize).cloned()
+        }
+    }
+
+    fn coinbase(address: &str) -> Transaction {
+        Transaction::new(
+            vec![],
+            vec![TxOutput {
+                address: address.into(),
+                amount: 50,
+            }],
+        )
+    }
+
+    #[test]
+    fn sync_stops_cleanly_at_an_invalid_block() {
+        let state = AppState::with_config(NodeConfig {
+            initial_difficulty: 1,
+            ..NodeConfig::default()
+        });
+        let mut peer = Blockchain::new(1);
+        peer.chain = state.blockchain.lock().unwrap().chain.clone();
+        for (height, amount) in [(1, 50), (2, 50), (3, 51), (4, 50)] {
+            let out = TxOutput {
+                address: format!("miner{height}"),
+                amount,
+            };
+            // block 3's coinbase pays more than the reward
+            peer.mine_block(vec![Transaction::coinbase(height, vec![out])])
+                .unwrap();
+        }
+
+        let report = sync_from_peer(&state, &MockPeer(peer.chain.clone()));
+        assert_eq!(report.applied, 2);
+        assert_eq!(report.rejected.unwrap().index, 3);
+
+        let bc = state.blockchain.lock().unwrap();
+        assert_eq!(bc.len(), 3);
+        assert_eq!(bc.last_block().hash, peer.chain[2].hash);
+        let expected = rebuild_utxo(&peer.chain[..3], &UtxoSet::new());
+        let utxo = state.utxo_set.lock().unwrap();
+        assert_eq!(utxo.len(), expected.len());
+        assert!(expected.entries().all(|(op, e)| utxo.entry(op) == Some(e)));
+    }
+
+    #[test]
+    fn records_height_of_divergent_block() {
+        let state = AppState::with_config(NodeConfig {
+            initial_difficulty: 1,
+            ..NodeConfig::default()
+        });
+        {
+            let mut bc = state.blockchain.lock().unwrap();
+            for _ in 0..3 {
+                bc.mine_block(vec![coinbase("local")]).unwrap();
+            }
+        }
+
+        // same genesis + block 1, then its own blocks 2 and 3
+        let mut fork = Blockchain::new(1);
+        fork.chain = state.blockchain.lock().unwrap().chain[..2].to_vec();
+        fork.mine_block(vec![coinbase("peer")]).unwrap();
+        fork.mine_block(vec![coinbase("peer")]).unwrap();
+
+        assert_eq!(check_fork(&state, &MockPeer(fork.chain.clone())), Some(2));
+        assert_eq!(*state.fork_detected_at.lock().unwrap(), Some(2));
+
+        // a deeper fork lowers the record, a shallower one leaves it alone
+        let mut deeper = Blockchain::new(1);
+        deeper.chain = state.blockchain.lock().unwrap().chain[..1].to_vec();
+        deeper.mine_block(vec![coinbase("other")]).unwrap();
+        let mut shallower = Blockchain::new(1);
+        shallower.chain = state.blockchain.lock().unwrap().chain[..3].to_vec();
+        shallower.mine_block(vec![coinbase("other")]).unwrap();
+        assert_eq!(check_fork(&state, &MockPeer(deeper.chain)), Some(1));
+        assert_eq!(check_fork(&state, &MockPeer(shallower.chain)), Some(3));
+        assert_eq!(*state.fork_detected_at.lock().unwrap(), Some(1));
+
+        // the sync round notices the fork on its own; with no more work
+        // than ours, nothing is pulled from it
+        let report = sync_with_peer(&state, &MockPeer(fork.chain.clone()));
+        assert_eq!(report.applied, 0);
+        assert_eq!(state.blockchain.lock().unwrap().len(), 4);
+
+        // a peer that is merely behind on the same chain is not a fork
+        let state2 = AppState::with_config(NodeConfig::default());
+        let same = state2.blockchain.lock().unwrap().chain.clone();
+        assert_eq!(check_fork(&state2, &MockPeer(same)), None);
+        assert_eq!(*state2.fork_detected_at.lock().unwrap(), None);
+    }
+
+    /// Local chain of `local` blocks, and a peer chain sharing its first
+    /// `shared` blocks (genesis included) then going on with `peer` of its own.
+    fn forked(
+        config: NodeConfig,
+        local: usize,
+        shared: usize,
+        peer: usize,
+    ) -> (AppState, Vec<Block>) {
+        let state = AppState::with_config(NodeConfig {
+            initial_difficulty: 1,
+            ..config
+        });
+        {
+            let mut bc = state.blockchain.lock().unwrap();
+            for _ in 0..local {
+                bc.mine_block(vec![coinbase("local")]).unwrap();
+            }
+            *state.utxo_set.lock().unwrap() = rebuild_utxo(&bc.chain, &UtxoSet::new());
+        }
+        let mut fork = Blockchain::new(1);
+        fork.chain = state.blockchain.lock().unwrap().chain[..shared].to_vec();
+        for _ in 0..peer {
+            fork.mine_block(vec![coinbase("peer")]).unwrap();
+        }
+        (state, fork.chain)
+    }
+
+    #[test]
+    fn sync_reorgs_to_a_heavier_fork() {
+        let (state, peer) = forked(NodeConfig::default(), 3, 2, 3);
+        let report = sync_with_peer(&state, &MockPeer(peer.clone()));
+        assert_eq!(report.applied, 3);
+        assert!(report.rejected.is_none());
+
+        let bc = state.blockchain.lock().unwrap();
+        assert_eq!(bc.last_block().hash, peer[4].hash);
+        let expected = rebuild_utxo(&peer, &UtxoSet::new());
+        let utxo = state.utxo_set.lock().unwrap();
+        assert_eq!(utxo.len(), expected.len());
+        assert!(expected.entries().all(|(op, e)| utxo.entry(op) == Some(e)));
+    }
+
+    #[test]
+    fn sync_keeps_a_heavier_local_chain() {
+        let (state, peer) = forked(NodeConfig::default(), 3, 2, 1);
+        let tip = state.blockchain.lock().unwrap().last_block().hash.clone();
+        let report = sync_with_peer(&state, &MockPeer(peer));
+        assert_eq!(report.applied, 0);
+        assert!(report.rejected.is_none());
+        assert_eq!(state.blockchain.lock().unwrap().last_block().hash, tip);
+    }
+
+    #[test]
+    fn sync_refuses_reorgs_past_max_reorg_depth() {
+        let config = NodeConfig {
+            max_reorg_depth: 1,
+            ..NodeConfig::default()
+        };
+        let (state, peer) = forked(config, 3, 2, 5);
+        let tip = state.blockchain.lock().unwrap().last_block().hash.clone();
+        let report = sync_with_peer(&state, &MockPeer(peer));
+        assert_eq!(report.applied, 0);
+        let refused = report.rejected.unwrap();
+        assert_eq!(
+            refused.reason,
+            "reorg too deep: candidate rolls back 2 blocks (max 1)"
+        );
+        assert_eq!(state.blockchain.lock().unwrap().last_block().hash, tip);
+    }
+}
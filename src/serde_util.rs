@@ -0,0 +1,55 @@
+//! Serde helpers shared by the models of several modules.
+
+use serde::{Serialize, Serializer};
+use std::cell::Cell;
+use std::fmt::Display;
+
+/// `skip_serializing_if` for flags that are omitted from JSON when unset.
+pub(crate) fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+thread_local! {
+    /// Set while `with_amounts_as_strings` runs a serialization.
+    static AMOUNTS_AS_STRINGS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` (a synchronous serialization) with every field marked
+/// `serialize_with = "amount"` rendered as a decimal string.
+pub(crate) fn with_amounts_as_strings<R>(f: impl FnOnce() -> R) -> R {
+    /// Restores the previous mode even if `f` panics.
+    struct Restore(bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            AMOUNTS_AS_STRINGS.set(self.0);
+        }
+    }
+    let _restore = Restore(AMOUNTS_AS_STRINGS.replace(true));
+    f()
+}
+
+/// `serialize_with` for monetary fields: a JSON number, or a decimal string
+/// (exact at any size) inside `with_amounts_as_strings`.
+pub(crate) fn amount<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize + Display,
+    S: Serializer,
+{
+    if AMOUNTS_AS_STRINGS.get() {
+        serializer.collect_str(value)
+    } else {
+        value.serialize(serializer)
+    }
+}
+
+/// `amount` for optional fields; `None` stays `null`.
+pub(crate) fn opt_amount<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize + Display,
+    S: Serializer,
+{
+    match value {
+        Some(v) => amount(v, serializer),
+        None => serializer.serialize_none(),
+    }
+}
@@ -0,0 +1,199 @@
+//! On-disk persistence of node state (enabled with `DATA_DIR`).
+//!
+//! Plain JSON files, written atomically (tmp file + rename):
+//! - `chain.json`: difficulty + all blocks
+//! - `utxo.json`: every unspent output (faucet UTXOs aren't derivable from the chain)
+//! - `mempool.json`: pending transactions (only with `PERSIST_MEMPOOL`)
+//! - `faucet.json`: every output the faucet ever created (for full chain replays)
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+use crate::blockchain::{Block, Blockchain};
+use crate::transaction::{Transaction, UtxoRecord, UtxoSet};
+
+const CHAIN_FILE: &str = "chain.json";
+const UTXO_FILE: &str = "utxo.json";
+const MEMPOOL_FILE: &str = "mempool.json";
+const FAUCET_FILE: &str = "faucet.json";
+
+#[derive(Serialize, Deserialize)]
+struct ChainSnapshot {
+    difficulty: u32,
+    blocks: Vec<Block>,
+}
+
+/// JSON snapshots of the chain and UTXO set under a data directory.
+#[derive(Debug, Clone)]
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn save_chain(&self, bc: &Blockchain) -> io::Result<()> {
+        self.write_json(
+            CHAIN_FILE,
+            &ChainSnapshot {
+                difficulty: bc.difficulty(),
+                blocks: bc.chain.clone(),
+            },
+        )
+    }
+
+    pub fn save_utxo(&self, utxo: &UtxoSet) -> io::Result<()> {
+        self.save_utxo_file(UTXO_FILE, utxo)
+    }
+
+    pub fn save_faucet(&self, outputs: &UtxoSet) -> io::Result<()> {
+        self.save_utxo_file(FAUCET_FILE, outputs)
+    }
+
+    fn save_utxo_file(&self, name: &str, utxo: &UtxoSet) -> io::Result<()> {
+        // sorted: stable file contents across saves
+        self.write_json(name, &utxo.records())
+    }
+
+    pub fn save_mempool(&self, mempool: &[Transaction]) -> io::Result<()> {
+        self.write_json(MEMPOOL_FILE, &mempool)
+    }
+
+    /// Load the saved chain into `bc` (keeps `bc`'s PoW algo). `Ok(false)` if nothing saved yet.
+    pub fn load_chain(&self, bc: &mut Blockchain) -> io::Result<bool> {
+        let Some(snap) = self.read_json::<ChainSnapshot>(CHAIN_FILE)? else {
+            return Ok(false);
+        };
+        if snap.blocks.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "chain.json has no blocks",
+            ));
+        }
+        bc.chain = snap.blocks;
+        bc.set_difficulty(snap.difficulty);
+        Ok(true)
+    }
+
+    /// Load the saved UTXO set. `Ok(None)` if nothing saved yet.
+    pub fn load_utxo(&self) -> io::Result<Option<UtxoSet>> {
+        self.load_utxo_file(UTXO_FILE)
+    }
+
+    /// Load the faucet's output ledger. `Ok(None)` if nothing saved yet.
+    pub fn load_faucet(&self) -> io::Result<Option<UtxoSet>> {
+        self.load_utxo_file(FAUCET_FILE)
+    }
+
+    fn load_utxo_file(&self, name: &str) -> io::Result<Option<UtxoSet>> {
+        let Some(entries) = self.read_json::<Vec<UtxoRecord>>(name)? else {
+            return Ok(None);
+        };
+        Ok(Some(UtxoSet::from_records(entries)))
+    }
+
+    /// Load the saved mempool, txids recomputed from content. `Ok(None)` if nothing saved yet.
+    pub fn load_mempool(&self) -> io::Result<Option<Vec<Transaction>>> {
+        let Some(mut txs) = self.read_json::<Vec<Transaction>>(MEMPOOL_FILE)? else {
+            return Ok(None);
+        };
+        for tx in &mut txs {
+            tx.txid = tx.compute_txid();
+        }
+        Ok(Some(txs))
+    }
+
+    fn write_json<T: Serialize>(&self, name: &str, value: &T) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(name);
+        let tmp = path.with_extension("json.tmp");
+        let bytes = serde_json::to_vec(value).map_err(io::Error::other)?;
+        fs::write(&tmp, bytes)?;
+        fs::rename(tmp, path)
+    }
+
+    fn read_json<T: for<'de> Deserialize<'de>>(&self, name: &str) -> io::Result<Option<T>> {
+        match fs::read(self.dir.join(name)) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Blocks from an NDJSON chain export (`/chain/export/ndjson/`), decoded one
+/// line at a time so the chain never has to fit in memory as a single JSON
+/// value. Blank lines are skipped; a bad line is an `InvalidData` error.
+pub fn read_ndjson_blocks<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<Block>> {
+    reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()))
+        .map(|line| {
+            let line = line?;
+            serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Store;
+    use crate::blockchain::Blockchain;
+    use crate::transaction::{OutPoint, TxOutput, UtxoEntry, UtxoSet};
+
+    #[test]
+    fn chain_and_utxo_round_trip() {
+        let dir = std::env::temp_dir().join(format!("rust-blockchain-{}", uuid::Uuid::new_v4()));
+        let store = Store::new(&dir);
+
+        let mut bc = Blockchain::new(1);
+        bc.mine_block(vec![]).unwrap();
+        let mut utxo = UtxoSet::new();
+        utxo.insert(
+            OutPoint {
+                txid: "abc".into(),
+                vout: 1,
+            },
+            UtxoEntry {
+                is_coinbase: true,
+                ..UtxoEntry::chain(
+                    TxOutput {
+                        address: "alice".into(),
+                        amount: 42,
+                    },
+                    7,
+                )
+            },
+        );
+
+        let mut fresh = Blockchain::new(3);
+        assert!(!store.load_chain(&mut fresh).unwrap());
+        assert!(store.load_utxo().unwrap().is_none());
+
+        store.save_chain(&bc).unwrap();
+        store.save_utxo(&utxo).unwrap();
+
+        assert!(store.load_chain(&mut fresh).unwrap());
+        assert_eq!(fresh.len(), 2);
+        assert_eq!(fresh.last_block().hash, bc.last_block().hash);
+        assert_eq!(fresh.difficulty(), bc.difficulty());
+        let loaded = store.load_utxo().unwrap().unwrap();
+        assert_eq!(loaded.len(), 1);
+        let (_, entry) = loaded.entries().next().unwrap();
+        assert_eq!(
+            (entry.output.amount, entry.height, entry.is_coinbase),
+            (42, 7, true)
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use std::borrow::Cow;
+use std::fmt;
+
+/// Why a transaction was rejected (UTXO-level validation or mempool policy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxError {
+    /// `version` other than `TX_VERSION`
+    UnsupportedVersion,
+    NoInputs,
+    NoOutputs,
+    ZeroAmountOutput,
+    DuplicateInput,
+    UtxoNotFound,
+    /// pubkey's address differs from the spent UTXO's address
+    NotOwner,
+    /// malformed pubkey (reason from the wallet layer)
+    BadPubkey(&'static str),
+    MissingSignature,
+    /// signature didn't verify or is malformed (reason from the wallet layer)
+    BadSignature(&'static str),
+    /// outputs total more than the inputs; `shortfall` is the difference
+    InsufficientFunds {
+        shortfall: u128,
+    },
+    /// spends a coinbase output younger than `COINBASE_MATURITY` blocks
+    ImmatureCoinbase,
+    /// spends an outpoint already spent by a mempool tx that isn't `replaceable`
+    MempoolConflict,
+    /// replacement doesn't pay more than the transactions it would evict
+    ReplacementFeeTooLow,
+    /// mempool at `MEMPOOL_MAX_TXS` and the fee rate doesn't beat its cheapest entry
+    MempoolFull,
+    /// valid, but breaks the relay policy (`STANDARDNESS_CHECKS`)
+    NonStandard(&'static str),
+}
+
+impl TxError {
+    /// Stable machine-readable code for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnsupportedVersion => "unsupported_version",
+            Self::NoInputs => "no_inputs",
+            Self::NoOutputs => "no_outputs",
+            Self::ZeroAmountOutput => "zero_amount_output",
+            Self::DuplicateInput => "duplicate_input",
+            Self::UtxoNotFound => "utxo_not_found",
+            Self::NotOwner => "not_owner",
+            Self::BadPubkey(_) => "bad_pubkey",
+            Self::MissingSignature => "missing_signature",
+            Self::BadSignature(_) => "bad_signature",
+            Self::InsufficientFunds { .. } => "insufficient_funds",
+            Self::ImmatureCoinbase => "immature_coinbase",
+            Self::MempoolConflict => "mempool_conflict",
+            Self::ReplacementFeeTooLow => "replacement_fee_too_low",
+            Self::MempoolFull => "mempool_full",
+            Self::NonStandard(_) => "non_standard",
+        }
+    }
+
+    /// Human-readable message.
+    pub fn message(&self) -> Cow<'static, str> {
+        let text = match self {
+            Self::UnsupportedVersion => "unsupported tx version",
+            Self::NoInputs => {
+                "transactions must have at least one input (use /faucet/ to create UTXOs)"
+            }
+            Self::NoOutputs => "transaction must have at least one output",
+            Self::ZeroAmountOutput => "output amount must be > 0",
+            Self::DuplicateInput => "duplicate input outpoint in transaction",
+            Self::UtxoNotFound => "referenced UTXO not found",
+            Self::NotOwner => "pubkey does not own referenced UTXO (address mismatch)",
+            Self::BadPubkey(reason) | Self::BadSignature(reason) | Self::NonStandard(reason) => {
+                reason
+            }
+            Self::InsufficientFunds { shortfall } => {
+                return format!("outputs exceed inputs by {shortfall}").into();
+            }
+            Self::MissingSignature => "missing signature in input",
+            Self::ImmatureCoinbase => "spends a coinbase output that has not matured yet",
+            Self::MempoolConflict => {
+                "conflicts with a mempool transaction that did not signal replaceable"
+            }
+            Self::ReplacementFeeTooLow => {
+                "replacement must pay a higher fee than the transactions it replaces"
+            }
+            Self::MempoolFull => "mempool is full and the fee rate is too low to enter it",
+        };
+        text.into()
+    }
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for TxError {}
@@ -1,5 +1,15 @@
+pub mod encoding;
+pub mod error;
 pub mod model;
+pub mod package;
+pub mod policy;
 pub mod utxo;
 
-pub use model::{Transaction, TxInput, TxOutput};
-pub use utxo::{OutPoint, UtxoSet};
+pub use error::TxError;
+pub use model::{
+    SigScheme, SizeBreakdown, TX_VERSION, Transaction, TxInput, TxOutput, default_tx_version,
+    deserialize_amount,
+};
+pub use package::{Conflict, Package, mempool_conflicts, mempool_package};
+pub use policy::check_standard;
+pub use utxo::{OutPoint, UtxoEntry, UtxoRecord, UtxoSet, UtxoSource};
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use super::encoding::{Reader, put_str, put_u32, put_u64};
 use super::utxo::OutPoint;
+use crate::serde_util::is_false;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TxInput {
@@ -10,22 +12,145 @@ pub struct TxInput {
     // Placeholder for signatures (to be implemented later)
     // pub signature: String,
     pub pubkey: String,
-    /// Hex-encoded DER ECDSA signature
+    /// Hex-encoded signature: DER for ECDSA, 64 bytes for Schnorr
     pub signature: String,
+    /// Signature scheme of `signature`. Omitted from JSON when ECDSA.
+    #[serde(default, skip_serializing_if = "SigScheme::is_ecdsa")]
+    pub scheme: SigScheme,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How an input's `signature` is encoded and verified.
+/// - `Ecdsa`: DER ECDSA over the compressed pubkey (the original path)
+/// - `Schnorr`: BIP340 Schnorr over the x-only form of the same pubkey (taproot-style)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SigScheme {
+    #[default]
+    Ecdsa,
+    Schnorr,
+}
+
+impl SigScheme {
+    pub fn is_ecdsa(&self) -> bool {
+        *self == Self::Ecdsa
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ecdsa" => Some(Self::Ecdsa),
+            "schnorr" => Some(Self::Schnorr),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ecdsa => "ecdsa",
+            Self::Schnorr => "schnorr",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TxOutput {
     pub address: String,
+    #[serde(deserialize_with = "deserialize_amount")]
     pub amount: u64,
 }
 
+/// Deserialize a `u64` amount, with a clear message for values out of range
+/// (serde's default for a number above `u64::MAX` is a bare "invalid type").
+pub fn deserialize_amount<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct AmountVisitor;
+
+    impl serde::de::Visitor<'_> for AmountVisitor {
+        type Value = u64;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "an integer amount between 0 and {}", u64::MAX)
+        }
+
+        fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<u64, E> {
+            Ok(v)
+        }
+
+        fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<u64, E> {
+            u64::try_from(v).map_err(|_| E::custom("amount must not be negative"))
+        }
+
+        fn visit_u128<E: serde::de::Error>(self, v: u128) -> Result<u64, E> {
+            u64::try_from(v).map_err(|_| E::custom(amount_too_large()))
+        }
+
+        // serde_json hands integers wider than 64 bits over as floats
+        fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<u64, E> {
+            if v.fract() != 0.0 || v.is_nan() {
+                Err(E::custom("amount must be an integer"))
+            } else if v < 0.0 {
+                Err(E::custom("amount must not be negative"))
+            } else if v >= u64::MAX as f64 {
+                Err(E::custom(amount_too_large()))
+            } else {
+                Ok(v as u64)
+            }
+        }
+    }
+
+    deserializer.deserialize_any(AmountVisitor)
+}
+
+fn amount_too_large() -> String {
+    format!("amount exceeds maximum ({})", u64::MAX)
+}
+
+/// Current transaction format version. Validation rejects any other value.
+pub const TX_VERSION: u32 = 1;
+
+/// Serde default for `version` fields: `TX_VERSION`.
+pub fn default_tx_version() -> u32 {
+    TX_VERSION
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     /// A stable identifier computed from content.
     pub txid: String,
+    /// Format version (committed into the txid). Absent in JSON = 1.
+    #[serde(default = "default_tx_version")]
+    pub version: u32,
     pub inputs: Vec<TxInput>,
     pub outputs: Vec<TxOutput>,
+    /// BIP125-style opt-in: a mempool conflict paying a higher fee may replace it.
+    /// Covered by the signatures (see `signing_payload`) and committed into the
+    /// txid. Omitted from JSON when false.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub replaceable: bool,
+    /// Coinbase only: height of the block it pays (BIP34-style), so two blocks
+    /// paying the same reward to the same miner don't share a coinbase txid.
+    /// Committed into the txid. Omitted from JSON when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coinbase_height: Option<u64>,
+}
+
+/// Size of `value` as the JSON `vsize_bytes` measures.
+fn json_len<T: Serialize>(value: &T) -> usize {
+    serde_json::to_vec(value).map(|v| v.len()).unwrap_or(0)
+}
+
+/// Where a transaction's `vsize_bytes()` go. `base` is everything outside the
+/// input/output items (txid, version, flags, field names, JSON punctuation).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeBreakdown {
+    pub base: usize,
+    /// per input: everything but the signature (outpoint, pubkey, framing)
+    pub inputs: Vec<usize>,
+    /// per input: the hex signature
+    pub signatures: Vec<usize>,
+    /// per output
+    pub outputs: Vec<usize>,
 }
 
 // impl Transaction {
@@ -54,20 +179,182 @@ pub struct Transaction {
 impl Transaction {
     /// Build a transaction and compute its txid deterministically from its content.
     /// TXID includes signatures; SIGHASH (used for signing) excludes signatures/pubkeys.
-    pub fn new(mut inputs: Vec<TxInput>, mut outputs: Vec<TxOutput>) -> Self {
-        let payload = serde_json::json!({
-            "inputs": inputs,
-            "outputs": outputs,
-        });
-        let mut hasher = Sha256::new();
-        hasher.update(serde_json::to_vec(&payload).expect("json serialize"));
-        let txid = hex::encode(hasher.finalize());
+    pub fn new(inputs: Vec<TxInput>, outputs: Vec<TxOutput>) -> Self {
+        Self::with_version(TX_VERSION, inputs, outputs)
+    }
+
+    /// Same as `new`, with an explicit format `version`.
+    pub fn with_version(version: u32, inputs: Vec<TxInput>, outputs: Vec<TxOutput>) -> Self {
+        let mut tx = Self {
+            txid: String::new(),
+            version,
+            inputs,
+            outputs,
+            replaceable: false,
+            coinbase_height: None,
+        };
+        tx.txid = tx.compute_txid();
+        tx
+    }
+
+    /// The same transaction with `replaceable` set to `replaceable`, txid
+    /// recomputed.
+    pub fn with_replaceable(mut self, replaceable: bool) -> Self {
+        self.replaceable = replaceable;
+        self.txid = self.compute_txid();
+        self
+    }
+
+    /// Coinbase of the block at `height` paying `outputs`.
+    pub fn coinbase(height: u64, outputs: Vec<TxOutput>) -> Self {
+        let mut tx = Self::new(vec![], outputs);
+        tx.coinbase_height = Some(height);
+        tx.txid = tx.compute_txid();
+        tx
+    }
 
-        Self {
-            txid,
-            inputs: inputs.drain(..).collect(),
-            outputs: outputs.drain(..).collect(),
+    /// Canonical byte encoding of the transaction (everything except `txid`).
+    /// Field order is fixed and does not depend on serde or struct layout:
+    ///
+    /// ```text
+    /// u32 version
+    /// u32 n_inputs
+    ///   for each input:  str txid | u32 vout | str pubkey | str signature
+    /// u32 n_outputs
+    ///   for each output: str address | u64 amount
+    /// [only if some input is not ECDSA, or the tx is replaceable]
+    /// u32 n_tagged
+    ///   for each such input: u32 input_index | str scheme
+    /// [only if replaceable]
+    /// str "replaceable"
+    /// [only if coinbase_height is set]
+    /// str "coinbase_height" | u64 height
+    /// ```
+    ///
+    /// The optional sections are omitted entirely when unused (all-ECDSA inputs,
+    /// not replaceable, no coinbase height), so older transactions keep their
+    /// txids. A replaceable tx always carries the tag count (maybe 0) so the
+    /// marker after it can't be read as one.
+    /// Integers are little-endian; `str` is a `u32` byte length + UTF-8 bytes.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        put_u32(&mut buf, self.version);
+        put_u32(&mut buf, self.inputs.len() as u32);
+        for input in &self.inputs {
+            put_str(&mut buf, &input.outpoint.txid);
+            put_u32(&mut buf, input.outpoint.vout);
+            put_str(&mut buf, &input.pubkey);
+            put_str(&mut buf, &input.signature);
         }
+        put_u32(&mut buf, self.outputs.len() as u32);
+        for output in &self.outputs {
+            put_str(&mut buf, &output.address);
+            put_u64(&mut buf, output.amount);
+        }
+        let tagged: Vec<(usize, &TxInput)> = self
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| !i.scheme.is_ecdsa())
+            .collect();
+        if !tagged.is_empty() || (self.replaceable && !self.inputs.is_empty()) {
+            put_u32(&mut buf, tagged.len() as u32);
+            for (idx, input) in tagged {
+                put_u32(&mut buf, idx as u32);
+                put_str(&mut buf, input.scheme.as_str());
+            }
+        }
+        if self.replaceable {
+            put_str(&mut buf, "replaceable");
+        }
+        if let Some(height) = self.coinbase_height {
+            put_str(&mut buf, "coinbase_height");
+            put_u64(&mut buf, height);
+        }
+        buf
+    }
+
+    /// Wire form of the transaction (`/tx/raw/`): exactly `canonical_bytes()`,
+    /// so the txid is the SHA-256 of what was sent (`replaceable` included).
+    pub fn serialize_bytes(&self) -> Vec<u8> {
+        self.canonical_bytes()
+    }
+
+    /// Inverse of `serialize_bytes`. Only the canonical encoding is accepted:
+    /// anything that wouldn't re-encode to the same bytes (trailing data, an
+    /// explicit `ecdsa` tag, unordered tags) is an error.
+    pub fn deserialize_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        let mut r = Reader::new(bytes);
+        let version = r.get_u32()?;
+        let n_inputs = r.get_u32()?;
+        let mut inputs = Vec::new();
+        for _ in 0..n_inputs {
+            let txid = r.get_str()?;
+            let vout = r.get_u32()?;
+            inputs.push(TxInput {
+                outpoint: OutPoint { txid, vout },
+                pubkey: r.get_str()?,
+                signature: r.get_str()?,
+                scheme: SigScheme::Ecdsa,
+            });
+        }
+        let n_outputs = r.get_u32()?;
+        let mut outputs = Vec::new();
+        for _ in 0..n_outputs {
+            outputs.push(TxOutput {
+                address: r.get_str()?,
+                amount: r.get_u64()?,
+            });
+        }
+        // scheme tags need inputs; a coinbase (no inputs) can only carry a height
+        if !inputs.is_empty() && !r.is_empty() {
+            for _ in 0..r.get_u32()? {
+                let idx = r.get_u32()? as usize;
+                let scheme = SigScheme::parse(&r.get_str()?).ok_or("unknown signature scheme")?;
+                inputs
+                    .get_mut(idx)
+                    .ok_or("scheme tag for a missing input")?
+                    .scheme = scheme;
+            }
+        }
+        let mut replaceable = false;
+        let mut coinbase_height = None;
+        let mut section = if r.is_empty() {
+            None
+        } else {
+            Some(r.get_str()?)
+        };
+        if section.as_deref() == Some("replaceable") {
+            replaceable = true;
+            section = if r.is_empty() {
+                None
+            } else {
+                Some(r.get_str()?)
+            };
+        }
+        if let Some(section) = section {
+            if section != "coinbase_height" {
+                return Err("unexpected trailing section");
+            }
+            coinbase_height = Some(r.get_u64()?);
+        }
+        if !r.is_empty() {
+            return Err("trailing bytes after transaction");
+        }
+
+        let mut tx = Self::with_version(version, inputs, outputs);
+        tx.replaceable = replaceable;
+        tx.coinbase_height = coinbase_height;
+        tx.txid = tx.compute_txid();
+        if tx.canonical_bytes() != bytes {
+            return Err("not in canonical form");
+        }
+        Ok(tx)
+    }
+
+    /// Hex SHA-256 of `canonical_bytes()`.
+    pub fn compute_txid(&self) -> String {
+        hex::encode(Sha256::digest(self.canonical_bytes()))
     }
 
     pub fn total_output_amount(&self) -> u128 {
@@ -76,24 +363,32 @@ impl Transaction {
 
     /// Canonical signing payload (JSON) that excludes signatures and pubkeys.
     /// This is what should be hashed and signed by each input's owner.
-    pub fn signing_payload(&self) -> Vec<u8> {
-        // Only the outpoints (txid, vout) and outputs are included
+    /// `chain_id` binds the signature to one chain, so a tx signed for one
+    /// chain can't be replayed on a fork that shares the same UTXOs.
+    /// `"replaceable": true` is added only when set, so non-signaling txs keep
+    /// their original sighash.
+    pub fn signing_payload(&self, chain_id: &str) -> Vec<u8> {
+        // Only the chain id, the outpoints (txid, vout) and outputs are included
         let lite_inputs: Vec<_> = self
             .inputs
             .iter()
             .map(|i| serde_json::json!({ "txid": i.outpoint.txid, "vout": i.outpoint.vout }))
             .collect();
-        let payload = serde_json::json!({
+        let mut payload = serde_json::json!({
+            "chain_id": chain_id,
             "inputs": lite_inputs,
             "outputs": self.outputs,
         });
+        if self.replaceable {
+            payload["replaceable"] = serde_json::Value::Bool(true);
+        }
         serde_json::to_vec(&payload).expect("serialize signing payload")
     }
 
-    /// SHA-256 of the signing payload.
-    pub fn sighash(&self) -> [u8; 32] {
+    /// SHA-256 of the signing payload for `chain_id`.
+    pub fn sighash(&self, chain_id: &str) -> [u8; 32] {
         let mut hasher = Sha256::new();
-        hasher.update(self.signing_payload());
+        hasher.update(self.signing_payload(chain_id));
         let digest = hasher.finalize();
         let mut out = [0u8; 32];
         out.copy_from_slice(&digest[..]);
@@ -104,4 +399,146 @@ impl Transaction {
         // inclui pubkeys/assinaturas (como no wire real)
         serde_json::to_vec(self).map(|v| v.len()).unwrap_or(0)
     }
+
+    /// Split `vsize_bytes()` into its parts; they always add up to it.
+    pub fn size_breakdown(&self) -> SizeBreakdown {
+        let inputs: Vec<usize> = self
+            .inputs
+            .iter()
+            .map(|i| json_len(i) - i.signature.len())
+            .collect();
+        let signatures: Vec<usize> = self.inputs.iter().map(|i| i.signature.len()).collect();
+        let outputs: Vec<usize> = self.outputs.iter().map(json_len).collect();
+        let items: usize = inputs.iter().chain(&signatures).chain(&outputs).sum();
+        SizeBreakdown {
+            base: self.vsize_bytes() - items,
+            inputs,
+            signatures,
+            outputs,
+        }
+    }
+
+    /// Bytes of witness data (pubkeys + signatures) inside `vsize_bytes()`.
+    pub fn witness_bytes(&self) -> usize {
+        self.inputs
+            .iter()
+            .map(|i| i.pubkey.len() + i.signature.len())
+            .sum()
+    }
+
+    /// Segwit-style weight: base (non-witness) bytes count 4x, witness bytes 1x.
+    /// Signatures dominate the size of a tx, so weighting them less makes
+    /// fee rates reflect the data that actually has to be kept forever.
+    pub fn weight(&self) -> usize {
+        let total = self.vsize_bytes();
+        let witness = self.witness_bytes();
+        (total - witness) * 4 + witness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SigScheme, Transaction, TxInput, TxOutput};
+    use crate::transaction::OutPoint;
+
+    fn known_tx() -> Transaction {
+        Transaction::new(
+            vec![TxInput {
+                outpoint: OutPoint {
+                    txid: "aa".repeat(32),
+                    vout: 1,
+                },
+                pubkey: "02".to_string() + &"11".repeat(32),
+                signature: "3044".into(),
+                scheme: SigScheme::Ecdsa,
+            }],
+            vec![
+                TxOutput {
+                    address: "alice".into(),
+                    amount: 60,
+                },
+                TxOutput {
+                    address: "bob".into(),
+                    amount: 39,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn txid_is_pinned_to_canonical_encoding() {
+        // If this changes, every existing txid changes: don't update it casually.
+        assert_eq!(
+            known_tx().txid,
+            "50751f31551ef24052a8001a1d271e3b5d34ec66ead8cad9729d4fe5d209399b"
+        );
+    }
+
+    #[test]
+    fn canonical_bytes_layout() {
+        let tx = Transaction::new(
+            vec![],
+            vec![TxOutput {
+                address: "ab".into(),
+                amount: 5,
+            }],
+        );
+        let expected: Vec<u8> = [
+            &1u32.to_le_bytes()[..], // version
+            &0u32.to_le_bytes()[..], // no inputs
+            &1u32.to_le_bytes()[..], // one output
+            &2u32.to_le_bytes()[..],
+            b"ab",
+            &5u64.to_le_bytes()[..],
+        ]
+        .concat();
+        assert_eq!(tx.canonical_bytes(), expected);
+        assert_eq!(tx.txid, tx.compute_txid());
+    }
+
+    #[test]
+    fn raw_bytes_round_trip() {
+        let ecdsa = known_tx();
+        let mut inputs = ecdsa.inputs.clone();
+        inputs.push(TxInput {
+            scheme: SigScheme::Schnorr,
+            ..inputs[0].clone()
+        });
+        let schnorr = Transaction::new(inputs, ecdsa.outputs.clone());
+        let coinbase = Transaction::coinbase(7, ecdsa.outputs.clone());
+        let replaceable = ecdsa.clone().with_replaceable(true);
+        let replaceable_schnorr = schnorr.clone().with_replaceable(true);
+        assert_ne!(replaceable.txid, ecdsa.txid);
+        assert_ne!(replaceable_schnorr.txid, schnorr.txid);
+
+        for tx in [ecdsa, schnorr, coinbase, replaceable, replaceable_schnorr] {
+            let bytes = tx.serialize_bytes();
+            let back = Transaction::deserialize_bytes(&bytes).unwrap();
+            assert_eq!(back.txid, tx.txid);
+            assert_eq!(back.replaceable, tx.replaceable);
+            assert_eq!(back.coinbase_height, tx.coinbase_height);
+            assert_eq!(back.inputs.len(), tx.inputs.len());
+
+            assert!(Transaction::deserialize_bytes(&bytes[..bytes.len() - 1]).is_err());
+            let mut longer = bytes.clone();
+            longer.push(0);
+            assert!(Transaction::deserialize_bytes(&longer).is_err());
+        }
+    }
+
+    #[test]
+    fn scheme_tag_changes_txid_and_is_omitted_for_ecdsa() {
+        let ecdsa = known_tx();
+        let json = serde_json::to_value(&ecdsa.inputs[0]).unwrap();
+        assert!(json.get("scheme").is_none());
+
+        let mut inputs = ecdsa.inputs.clone();
+        inputs[0].scheme = SigScheme::Schnorr;
+        let schnorr = Transaction::new(inputs, ecdsa.outputs.clone());
+        assert_ne!(schnorr.txid, ecdsa.txid);
+        assert_eq!(
+            serde_json::to_value(&schnorr.inputs[0]).unwrap()["scheme"],
+            "schnorr"
+        );
+    }
 }
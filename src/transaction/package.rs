@@ -0,0 +1,182 @@
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use super::model::Transaction;
+use super::utxo::OutPoint;
+
+/// A mempool transaction together with its unconfirmed relatives.
+#[derive(Debug, Clone)]
+pub struct Package {
+    /// txids of in-mempool transactions it (transitively) spends from
+    pub ancestors: Vec<String>,
+    /// txids of in-mempool transactions (transitively) spending its outputs
+    pub descendants: Vec<String>,
+    /// ancestors + the transaction + descendants, parents always before children
+    pub transactions: Vec<Transaction>,
+}
+
+/// Build the dependency package of `txid` over `mempool` (plain txs or
+/// anything wrapping one). Returns `None` when `txid` is not in the mempool.
+pub fn mempool_package<T: Borrow<Transaction>>(mempool: &[T], txid: &str) -> Option<Package> {
+    let mempool: Vec<&Transaction> = mempool.iter().map(Borrow::borrow).collect();
+    let by_txid: HashMap<&str, usize> = mempool
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| (tx.txid.as_str(), i))
+        .collect();
+    let target = *by_txid.get(txid)?;
+
+    // parent/child edges between mempool transactions
+    let mut parents: Vec<Vec<usize>> = vec![Vec::new(); mempool.len()];
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); mempool.len()];
+    for (i, tx) in mempool.iter().enumerate() {
+        for input in &tx.inputs {
+            if let Some(&p) = by_txid.get(input.outpoint.txid.as_str())
+                && p != i
+                && !parents[i].contains(&p)
+            {
+                parents[i].push(p);
+                children[p].push(i);
+            }
+        }
+    }
+
+    let ancestors = reachable(target, &parents);
+    let descendants = reachable(target, &children);
+
+    let mut members: HashSet<usize> = ancestors.iter().chain(&descendants).copied().collect();
+    members.insert(target);
+
+    // Kahn's algorithm restricted to the package; ties keep mempool order
+    let mut pending: HashMap<usize, usize> = members
+        .iter()
+        .map(|&i| {
+            let deg = parents[i].iter().filter(|p| members.contains(p)).count();
+            (i, deg)
+        })
+        .collect();
+    let mut ordered = Vec::with_capacity(members.len());
+    while !pending.is_empty() {
+        let Some(next) = pending
+            .iter()
+            .filter(|(_, deg)| **deg == 0)
+            .map(|(&i, _)| i)
+            .min()
+        else {
+            break; // cycle: impossible with hash-linked txids
+        };
+        pending.remove(&next);
+        for c in &children[next] {
+            if let Some(deg) = pending.get_mut(c) {
+                *deg -= 1;
+            }
+        }
+        ordered.push(next);
+    }
+
+    let txids = |set: &HashSet<usize>| {
+        ordered
+            .iter()
+            .filter(|i| set.contains(i))
+            .map(|&i| mempool[i].txid.clone())
+            .collect()
+    };
+    Some(Package {
+        ancestors: txids(&ancestors),
+        descendants: txids(&descendants),
+        transactions: ordered.iter().map(|&i| mempool[i].clone()).collect(),
+    })
+}
+
+/// Mempool transactions spending the same outpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    pub outpoint: OutPoint,
+    /// in mempool order
+    pub txids: Vec<String>,
+}
+
+/// Every outpoint spent by more than one mempool transaction, ordered by
+/// where its first spender sits in the mempool. Admission refuses double
+/// spends, so this is empty unless that check was bypassed.
+pub fn mempool_conflicts<T: Borrow<Transaction>>(mempool: &[T]) -> Vec<Conflict> {
+    let mut spenders: HashMap<&OutPoint, Vec<&str>> = HashMap::new();
+    let mut order: Vec<&OutPoint> = Vec::new();
+    for tx in mempool.iter().map(Borrow::borrow) {
+        for input in &tx.inputs {
+            let txids = spenders.entry(&input.outpoint).or_insert_with(|| {
+                order.push(&input.outpoint);
+                Vec::new()
+            });
+            if !txids.contains(&tx.txid.as_str()) {
+                txids.push(&tx.txid);
+            }
+        }
+    }
+    order
+        .into_iter()
+        .filter_map(|op| {
+            let txids = &spenders[op];
+            (txids.len() > 1).then(|| Conflict {
+                outpoint: op.clone(),
+                txids: txids.iter().map(|t| t.to_string()).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Every node reachable from `start` following `edges` (excluding `start`).
+fn reachable(start: usize, edges: &[Vec<usize>]) -> HashSet<usize> {
+    let mut seen = HashSet::new();
+    let mut stack = edges[start].clone();
+    while let Some(n) = stack.pop() {
+        if n != start && seen.insert(n) {
+            stack.extend(&edges[n]);
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mempool_package;
+    use crate::transaction::{OutPoint, SigScheme, Transaction, TxInput, TxOutput};
+
+    fn spend(prev: &str, amount: u64) -> Transaction {
+        Transaction::new(
+            vec![TxInput {
+                outpoint: OutPoint {
+                    txid: prev.into(),
+                    vout: 0,
+                },
+                pubkey: String::new(),
+                signature: String::new(),
+                scheme: SigScheme::Ecdsa,
+            }],
+            vec![TxOutput {
+                address: "addr".into(),
+                amount,
+            }],
+        )
+    }
+
+    #[test]
+    fn package_is_topologically_ordered() {
+        let a = spend("confirmed", 10);
+        let b = spend(&a.txid, 9);
+        let c = spend(&b.txid, 8);
+        let unrelated = spend("other", 5);
+        // children first in the mempool to exercise the ordering
+        let mempool = vec![c.clone(), unrelated, b.clone(), a.clone()];
+
+        let pkg = mempool_package(&mempool, &b.txid).unwrap();
+        assert_eq!(pkg.ancestors, vec![a.txid.clone()]);
+        assert_eq!(pkg.descendants, vec![c.txid.clone()]);
+        let order: Vec<_> = pkg.transactions.iter().map(|t| t.txid.clone()).collect();
+        assert_eq!(order, vec![a.txid, b.txid, c.txid]);
+
+        assert!(mempool_package(&mempool, "missing").is_none());
+    }
+}
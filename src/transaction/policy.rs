@@ -0,0 +1,37 @@
+//! Relay policy ("standardness"): what this node accepts into its mempool,
+//! on top of consensus validity. A non-standard transaction is still valid in
+//! a block; it just isn't relayed or queued for mining by `/tx/`.
+
+use super::error::TxError;
+use super::model::Transaction;
+
+/// Most inputs a standard transaction may spend.
+pub const MAX_STANDARD_INPUTS: usize = 100;
+/// Most outputs a standard transaction may create.
+pub const MAX_STANDARD_OUTPUTS: usize = 100;
+/// Longest output address, in bytes. A real address is a 66-char pubkey hex;
+/// anything past Bitcoin's 80-byte `OP_RETURN` limit is data riding along.
+pub const MAX_STANDARD_ADDRESS_BYTES: usize = 80;
+/// Outputs below this amount are dust: not worth the input that spends them.
+pub const DUST_LIMIT: u64 = 5;
+
+/// Check `tx` against the relay policy; the first rule it breaks is returned.
+pub fn check_standard(tx: &Transaction) -> Result<(), TxError> {
+    if tx.inputs.len() > MAX_STANDARD_INPUTS {
+        return Err(TxError::NonStandard("too many inputs"));
+    }
+    if tx.outputs.len() > MAX_STANDARD_OUTPUTS {
+        return Err(TxError::NonStandard("too many outputs"));
+    }
+    if tx
+        .outputs
+        .iter()
+        .any(|o| o.address.len() > MAX_STANDARD_ADDRESS_BYTES)
+    {
+        return Err(TxError::NonStandard("output data over the limit"));
+    }
+    if tx.outputs.iter().any(|o| o.amount < DUST_LIMIT) {
+        return Err(TxError::NonStandard("dust output"));
+    }
+    Ok(())
+}
@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 use super::model::{Transaction, TxOutput};
+use crate::serde_util::is_false;
 
 /// Identifies a specific transaction output by its txid and index.
 #[derive(Debug, Clone, Serialize, Deserialize, Eq)]
@@ -24,31 +26,127 @@ impl Hash for OutPoint {
     }
 }
 
+/// What created an unspent output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UtxoSource {
+    /// a transaction in a block (coinbase or regular)
+    #[default]
+    Chain,
+    /// the dev `/faucet/`: created directly, or (`FAUCET_MODE=coinbase`) as
+    /// an extra coinbase output that matures like any other
+    Faucet,
+}
+
+/// A spendable output plus where it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtxoEntry {
+    pub output: TxOutput,
+    /// Height of the block that created it (faucet: tip height at creation).
+    #[serde(default)]
+    pub height: u64,
+    /// Created by a coinbase (no-input) transaction.
+    #[serde(default)]
+    pub is_coinbase: bool,
+    #[serde(default)]
+    pub source: UtxoSource,
+    /// Spendable right away even if `is_coinbase`: faucet payouts made through
+    /// a coinbase with `FAUCET_MATURITY_EXEMPT`. Omitted from JSON when false.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub maturity_exempt: bool,
+}
+
+/// One entry of a serialized UTXO set (`utxo.json`, `/utxo/export/`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtxoRecord {
+    pub outpoint: OutPoint,
+    #[serde(flatten)]
+    pub entry: UtxoEntry,
+}
+
+impl UtxoEntry {
+    /// `output` created by a non-coinbase tx in the block at `height`.
+    pub fn chain(output: TxOutput, height: u64) -> Self {
+        Self {
+            output,
+            height,
+            is_coinbase: false,
+            source: UtxoSource::Chain,
+            maturity_exempt: false,
+        }
+    }
+
+    /// Spendable in a block at `spend_height`: coinbase outputs need
+    /// `maturity` blocks on top of the one that created them.
+    pub fn is_mature(&self, spend_height: u64, maturity: u64) -> bool {
+        self.maturity_exempt
+            || !self.is_coinbase
+            || spend_height >= self.height.saturating_add(maturity)
+    }
+}
+
 /// A simple UTXO set wrapper over a HashMap.
 /// Stores spendable outputs keyed by (txid, vout).
-#[derive(Debug, Default)]
+///
+/// The map is copy-on-write behind an `Arc`: `snapshot()` is O(1), and the
+/// first write while a snapshot is alive pays for one copy of the map.
+#[derive(Debug, Default, Clone)]
 pub struct UtxoSet {
-    map: HashMap<OutPoint, TxOutput>,
+    map: Arc<HashMap<OutPoint, UtxoEntry>>,
 }
 
 impl UtxoSet {
     pub fn new() -> Self {
-        Self {
-            map: HashMap::new(),
+        Self::default()
+    }
+
+    /// Frozen view of the current set. Take it under the lock, release the
+    /// lock, then scan: long reads no longer block writers (mining).
+    pub fn snapshot(&self) -> UtxoSet {
+        self.clone()
+    }
+
+    /// Insert a single entry into the set.
+    pub fn insert(&mut self, outpoint: OutPoint, entry: UtxoEntry) {
+        Arc::make_mut(&mut self.map).insert(outpoint, entry);
+    }
+
+    /// Let `outpoint` be spent regardless of coinbase maturity. No-op if absent.
+    pub fn exempt_from_maturity(&mut self, outpoint: &OutPoint) {
+        if !self.map.contains_key(outpoint) {
+            return;
+        }
+        if let Some(entry) = Arc::make_mut(&mut self.map).get_mut(outpoint) {
+            entry.maturity_exempt = true;
         }
     }
 
-    /// Insert a single output into the set.
-    pub fn insert(&mut self, outpoint: OutPoint, output: TxOutput) {
-        self.map.insert(outpoint, output);
+    /// Tag `outpoint` as a faucet payout (one made through a coinbase).
+    /// No-op if absent.
+    pub fn mark_faucet(&mut self, outpoint: &OutPoint) {
+        if !self.map.contains_key(outpoint) {
+            return;
+        }
+        if let Some(entry) = Arc::make_mut(&mut self.map).get_mut(outpoint) {
+            entry.source = UtxoSource::Faucet;
+        }
     }
 
-    /// Spend (remove) a single outpoint. Returns the removed output if it existed.
-    pub fn spend(&mut self, outpoint: &OutPoint) -> Option<TxOutput> {
-        self.map.remove(outpoint)
+    /// Spend (remove) a single outpoint. Returns the removed entry if it existed.
+    pub fn spend(&mut self, outpoint: &OutPoint) -> Option<UtxoEntry> {
+        if !self.map.contains_key(outpoint) {
+            return None; // don't copy a shared map for a no-op
+        }
+        Arc::make_mut(&mut self.map).remove(outpoint)
     }
 
+    /// Just the output (amount/address) of an unspent outpoint.
     pub fn get(&self, outpoint: &OutPoint) -> Option<&TxOutput> {
+        self.map.get(outpoint).map(|e| &e.output)
+    }
+
+    /// Output plus creation metadata.
+    pub fn entry(&self, outpoint: &OutPoint) -> Option<&UtxoEntry> {
         self.map.get(outpoint)
     }
 
@@ -64,19 +162,128 @@ impl UtxoSet {
         self.map.is_empty()
     }
 
-    /// Read-only iterator over all entries (for debugging/observability).
+    /// Read-only iterator over all outputs (for debugging/observability).
     pub fn iter(&self) -> impl Iterator<Item = (&OutPoint, &TxOutput)> {
+        self.map.iter().map(|(op, e)| (op, &e.output))
+    }
+
+    /// Iterator over all entries with their metadata.
+    pub fn entries(&self) -> impl Iterator<Item = (&OutPoint, &UtxoEntry)> {
         self.map.iter()
     }
 
-    /// Utility to add all outputs of a tx (used when applying a mined block).
-    pub fn add_tx_outputs(&mut self, tx: &Transaction) {
+    /// Every entry, sorted by txid then vout: the same set always serializes
+    /// the same way.
+    pub fn records(&self) -> Vec<UtxoRecord> {
+        let mut records: Vec<UtxoRecord> = self
+            .entries()
+            .map(|(op, entry)| UtxoRecord {
+                outpoint: op.clone(),
+                entry: entry.clone(),
+            })
+            .collect();
+        records.sort_by(|a, b| {
+            (&a.outpoint.txid, a.outpoint.vout).cmp(&(&b.outpoint.txid, b.outpoint.vout))
+        });
+        records
+    }
+
+    /// Set built from `records` (a later duplicate outpoint wins).
+    pub fn from_records(records: impl IntoIterator<Item = UtxoRecord>) -> Self {
+        let mut utxo = UtxoSet::new();
+        for r in records {
+            utxo.insert(r.outpoint, r.entry);
+        }
+        utxo
+    }
+
+    /// Utility to add all outputs of a tx created at `height`
+    /// (used when applying a mined block).
+    pub fn add_tx_outputs(&mut self, tx: &Transaction, height: u64) {
+        let is_coinbase = tx.inputs.is_empty();
         for (i, out) in tx.outputs.iter().enumerate() {
             let op = OutPoint {
                 txid: tx.txid.clone(),
                 vout: i as u32,
             };
-            self.insert(op, out.clone());
+            self.insert(
+                op,
+                UtxoEntry {
+                    is_coinbase,
+                    ..UtxoEntry::chain(out.clone(), height)
+                },
+            );
+        }
+    }
+
+    /// Apply a block's transactions mined at `height`: spend every input,
+    /// then add every output (the coinbase's flagged as such).
+    pub fn apply_block_txs(&mut self, txs: &[Transaction], height: u64) {
+        for tx in txs {
+            for input in &tx.inputs {
+                self.spend(&input.outpoint);
+            }
+        }
+        for tx in txs {
+            self.add_tx_outputs(tx, height);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{OutPoint, UtxoSet};
+    use crate::transaction::{Transaction, TxOutput};
+
+    #[test]
+    fn add_tx_outputs_indexes_every_vout() {
+        let outputs = (1..=3)
+            .map(|amount| TxOutput {
+                address: format!("addr{amount}"),
+                amount,
+            })
+            .collect();
+        let tx = Transaction::new(vec![], outputs);
+
+        let mut utxo = UtxoSet::new();
+        utxo.add_tx_outputs(&tx, 0);
+        assert_eq!(utxo.len(), 3);
+        for vout in 0..3u32 {
+            let op = OutPoint {
+                txid: tx.txid.clone(),
+                vout,
+            };
+            assert_eq!(utxo.get(&op).unwrap().amount, vout as u64 + 1);
+        }
+
+        let spent = OutPoint {
+            txid: tx.txid.clone(),
+            vout: 1,
+        };
+        assert_eq!(utxo.spend(&spent).unwrap().output.address, "addr2");
+        assert_eq!(utxo.len(), 2);
+        assert!(utxo.spend(&spent).is_none());
+    }
+
+    #[test]
+    fn snapshot_is_unaffected_by_later_writes() {
+        let tx = Transaction::new(
+            vec![],
+            vec![TxOutput {
+                address: "a".into(),
+                amount: 1,
+            }],
+        );
+        let mut utxo = UtxoSet::new();
+        utxo.add_tx_outputs(&tx, 0);
+
+        let snap = utxo.snapshot();
+        let op = OutPoint {
+            txid: tx.txid.clone(),
+            vout: 0,
+        };
+        utxo.spend(&op);
+        assert!(utxo.is_empty());
+        assert!(snap.contains(&op));
+    }
+}
@@ -1,5 +1,12 @@
 use rand::rngs::OsRng;
-use secp256k1::{Message, PublicKey, Secp256k1, SecretKey, ecdsa::Signature};
+use rayon::prelude::*;
+use secp256k1::{Keypair, Message, PublicKey, Secp256k1, SecretKey, ecdsa::Signature, schnorr};
+
+use crate::transaction::SigScheme;
+
+pub mod sigcache;
+
+pub use sigcache::SigCache;
 
 /// Generate a new secp256k1 keypair and return (priv_hex, pub_hex_compressed, address_hex).
 /// Address is simply the hex of the compressed public key (didactic).
@@ -12,12 +19,52 @@ pub fn generate_keypair_hex() -> (String, String, String) {
     (sk_hex, pk_hex, address)
 }
 
+/// Decode hex that must already be canonical (lowercase). Pubkeys and
+/// signatures aren't covered by the sighash, so the same bytes spelled in
+/// another case would let anyone change a signed tx's txid.
+fn decode_canonical(
+    s: &str,
+    invalid: &'static str,
+    not_lowercase: &'static str,
+) -> Result<Vec<u8>, &'static str> {
+    let bytes = hex::decode(s).map_err(|_| invalid)?;
+    if hex::encode(&bytes) != s {
+        return Err(not_lowercase);
+    }
+    Ok(bytes)
+}
+
+/// Parse a hex pubkey. Only the compressed (33-byte) encoding, in lowercase
+/// hex, is accepted: addresses are compressed keys, and pubkeys aren't
+/// covered by the sighash, so a second encoding of the same key would let
+/// anyone change a txid.
+fn parse_pubkey(pubkey_hex: &str) -> Result<PublicKey, &'static str> {
+    let bytes = decode_canonical(
+        pubkey_hex,
+        "invalid pubkey hex",
+        "pubkey hex must be lowercase",
+    )?;
+    let pk = PublicKey::from_slice(&bytes).map_err(|_| "invalid pubkey bytes")?;
+    if bytes.len() != secp256k1::constants::PUBLIC_KEY_SIZE {
+        return Err("uncompressed pubkey not allowed");
+    }
+    Ok(pk)
+}
+
 /// Derive address (hex of compressed pubkey) from a given hex pubkey.
-/// Returns normalized hex (lowercase) if valid.
+/// Returns normalized hex (lowercase) if valid; uncompressed keys are refused.
 pub fn pubkey_to_address_hex(pubkey_hex: &str) -> Result<String, &'static str> {
-    let bytes = hex::decode(pubkey_hex).map_err(|_| "invalid pubkey hex")?;
-    let pk = PublicKey::from_slice(&bytes).map_err(|_| "invalid pubkey bytes")?;
-    Ok(hex::encode(pk.serialize()))
+    Ok(hex::encode(parse_pubkey(pubkey_hex)?.serialize()))
+}
+
+/// Sign a 32-byte message hash (e.g. a tx `sighash`) with a hex private key.
+/// Returns the hex DER signature expected in `TxInput.signature`.
+pub fn sign_sighash_hex(sk_hex: &str, msg32: [u8; 32]) -> Result<String, &'static str> {
+    let secp = Secp256k1::signing_only();
+    let sk_bytes = hex::decode(sk_hex).map_err(|_| "invalid private key hex")?;
+    let sk = SecretKey::from_slice(&sk_bytes).map_err(|_| "invalid private key bytes")?;
+    let msg = Message::from_digest_slice(&msg32).map_err(|_| "invalid message length")?;
+    Ok(hex::encode(secp.sign_ecdsa(&msg, &sk).serialize_der()))
 }
 
 /// Verify a signature (hex DER) against the given pubkey (hex, compressed) and message hash (32 bytes).
@@ -29,12 +76,90 @@ pub fn verify_signature_hex(
     // Use verification-only context (correct API for secp256k1 0.28)
     let secp = Secp256k1::verification_only();
 
-    let sig_bytes = hex::decode(sig_hex).map_err(|_| "invalid signature hex")?;
+    let sig_bytes = decode_canonical(
+        sig_hex,
+        "invalid signature hex",
+        "signature hex must be lowercase",
+    )?;
     let sig = Signature::from_der(&sig_bytes).map_err(|_| "invalid DER signature")?;
 
-    let pk_bytes = hex::decode(pubkey_hex).map_err(|_| "invalid pubkey hex")?;
-    let pk = PublicKey::from_slice(&pk_bytes).map_err(|_| "invalid pubkey bytes")?;
+    let pk = parse_pubkey(pubkey_hex)?;
 
-    let msg = Message::from_slice(&msg32).map_err(|_| "invalid message length")?;
+    let msg = Message::from_digest_slice(&msg32).map_err(|_| "invalid message length")?;
     Ok(secp.verify_ecdsa(&msg, &sig, &pk).is_ok())
 }
+
+/// Sign a 32-byte message hash with BIP340 Schnorr. Returns the hex 64-byte signature
+/// (to be used with `scheme: "schnorr"` on the input).
+pub fn sign_schnorr_hex(sk_hex: &str, msg32: [u8; 32]) -> Result<String, &'static str> {
+    let secp = Secp256k1::signing_only();
+    let sk_bytes = hex::decode(sk_hex).map_err(|_| "invalid private key hex")?;
+    let sk = SecretKey::from_slice(&sk_bytes).map_err(|_| "invalid private key bytes")?;
+    let keypair = Keypair::from_secret_key(&secp, &sk);
+    let msg = Message::from_digest_slice(&msg32).map_err(|_| "invalid message length")?;
+    let sig = secp.sign_schnorr_with_rng(&msg, &keypair, &mut OsRng);
+    Ok(hex::encode(sig.as_ref()))
+}
+
+/// Verify a Schnorr signature (hex, 64 bytes) against the x-only form of the
+/// given pubkey (hex, compressed — the same key/address used for ECDSA).
+pub fn verify_schnorr_hex(
+    pubkey_hex: &str,
+    sig_hex: &str,
+    msg32: [u8; 32],
+) -> Result<bool, &'static str> {
+    let secp = Secp256k1::verification_only();
+
+    let sig_bytes = decode_canonical(
+        sig_hex,
+        "invalid signature hex",
+        "signature hex must be lowercase",
+    )?;
+    let sig =
+        schnorr::Signature::from_slice(&sig_bytes).map_err(|_| "invalid schnorr signature")?;
+
+    let pk = parse_pubkey(pubkey_hex)?;
+    let (xonly, _parity) = pk.x_only_public_key();
+
+    let msg = Message::from_digest_slice(&msg32).map_err(|_| "invalid message length")?;
+    Ok(secp.verify_schnorr(&sig, &msg, &xonly).is_ok())
+}
+
+/// Verify `sig_hex` with the verifier for `scheme`.
+pub fn verify_with_scheme(
+    scheme: SigScheme,
+    pubkey_hex: &str,
+    sig_hex: &str,
+    msg32: [u8; 32],
+) -> Result<bool, &'static str> {
+    match scheme {
+        SigScheme::Ecdsa => verify_signature_hex(pubkey_hex, sig_hex, msg32),
+        SigScheme::Schnorr => verify_schnorr_hex(pubkey_hex, sig_hex, msg32),
+    }
+}
+
+/// One `(scheme, pubkey, signature, message)` check in a batch.
+#[derive(Debug, Clone)]
+pub struct SigCheck<'a> {
+    pub scheme: SigScheme,
+    pub pubkey_hex: &'a str,
+    pub sig_hex: &'a str,
+    pub msg32: [u8; 32],
+}
+
+/// Verify many signatures together. secp256k1 has no batch verification API, so the
+/// checks are spread across threads (rayon). A single bad signature fails the
+/// whole batch; the error carries the index of the first failing check.
+pub fn verify_signatures_batch(checks: &[SigCheck]) -> Result<(), (usize, &'static str)> {
+    let failure = checks.par_iter().enumerate().find_map_first(|(i, c)| {
+        match verify_with_scheme(c.scheme, c.pubkey_hex, c.sig_hex, c.msg32) {
+            Ok(true) => None,
+            Ok(false) => Some((i, "invalid signature")),
+            Err(e) => Some((i, e)),
+        }
+    });
+    match failure {
+        Some(f) => Err(f),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,186 @@
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::{SigCheck, verify_with_scheme};
+use crate::transaction::SigScheme;
+
+/// `(sighash, scheme, pubkey, signature)`: everything a verification depends on.
+type Key = ([u8; 32], SigScheme, String, String);
+
+/// Bounded LRU of signature verification results. A signature over a given
+/// sighash with a given key is valid or not forever, so nothing is ever
+/// invalidated; the least recently used entry makes room when full.
+pub struct SigCache {
+    capacity: usize,
+    inner: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Default)]
+struct Lru {
+    tick: u64,
+    entries: HashMap<Key, (bool, u64)>,
+    /// last-use tick -> key, oldest first
+    order: BTreeMap<u64, Key>,
+}
+
+impl Lru {
+    fn get(&mut self, key: &Key) -> Option<bool> {
+        self.tick += 1;
+        let (valid, used) = self.entries.get_mut(key)?;
+        let key = self.order.remove(used).expect("lru order out of sync");
+        *used = self.tick;
+        self.order.insert(self.tick, key);
+        Some(*valid)
+    }
+
+    fn insert(&mut self, key: Key, valid: bool, capacity: usize) {
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.remove(&key) {
+            self.order.remove(&used);
+        } else if self.entries.len() >= capacity
+            && let Some((_, oldest)) = self.order.pop_first()
+        {
+            self.entries.remove(&oldest);
+        }
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (valid, self.tick));
+    }
+}
+
+impl SigCache {
+    /// Cache holding up to `capacity` results (0 = caching off).
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Lru::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// `verify_signatures_batch`, answering from the cache where possible and
+    /// running the curve math (in parallel) only for unseen checks.
+    pub fn verify_batch(&self, checks: &[SigCheck]) -> Result<(), (usize, &'static str)> {
+        let keys: Vec<Key> = checks.iter().map(key_of).collect();
+        let mut results: Vec<Option<Result<bool, &'static str>>> = {
+            let mut lru = self.inner.lock().expect("mutex poisoned");
+            keys.iter().map(|k| lru.get(k).map(Ok)).collect()
+        };
+        let cached = results.iter().filter(|r| r.is_some()).count();
+        self.hits.fetch_add(cached as u64, Ordering::Relaxed);
+        self.misses
+            .fetch_add((checks.len() - cached) as u64, Ordering::Relaxed);
+
+        let fresh: Vec<(usize, Result<bool, &'static str>)> = results
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.is_none())
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|i| {
+                let c = &checks[i];
+                (
+                    i,
+                    verify_with_scheme(c.scheme, c.pubkey_hex, c.sig_hex, c.msg32),
+                )
+            })
+            .collect();
+        if self.capacity > 0 {
+            let mut lru = self.inner.lock().expect("mutex poisoned");
+            // malformed keys/signatures (Err) aren't worth remembering
+            for (i, res) in &fresh {
+                if let Ok(valid) = res {
+                    lru.insert(keys[*i].clone(), *valid, self.capacity);
+                }
+            }
+        }
+        for (i, res) in fresh {
+            results[i] = Some(res);
+        }
+
+        match results.into_iter().enumerate().find_map(|(i, r)| match r {
+            Some(Ok(true)) => None,
+            Some(Ok(false)) => Some((i, "invalid signature")),
+            Some(Err(e)) => Some((i, e)),
+            None => unreachable!("every check is resolved"),
+        }) {
+            Some(f) => Err(f),
+            None => Ok(()),
+        }
+    }
+
+    /// `(hits, misses)` since start; a miss is one real signature verification.
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().expect("mutex poisoned").entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn key_of(c: &SigCheck) -> Key {
+    (
+        c.msg32,
+        c.scheme,
+        c.pubkey_hex.to_string(),
+        c.sig_hex.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SigCache;
+    use crate::transaction::SigScheme;
+    use crate::wallet::{SigCheck, generate_keypair_hex, sign_sighash_hex};
+
+    #[test]
+    fn second_verification_is_served_from_the_cache() {
+        let (sk, pk, _) = generate_keypair_hex();
+        let sigs: Vec<(String, [u8; 32])> = (0..3u8)
+            .map(|n| {
+                let msg = [n; 32];
+                (sign_sighash_hex(&sk, msg).unwrap(), msg)
+            })
+            .collect();
+        let checks: Vec<SigCheck> = sigs
+            .iter()
+            .map(|(sig, msg)| SigCheck {
+                scheme: SigScheme::Ecdsa,
+                pubkey_hex: &pk,
+                sig_hex: sig,
+                msg32: *msg,
+            })
+            .collect();
+
+        let cache = SigCache::new(2);
+        assert_eq!(cache.verify_batch(&checks), Ok(()));
+        assert_eq!(cache.stats(), (0, 3));
+        // bounded: the oldest result was dropped
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.verify_batch(&checks[1..]), Ok(()));
+        assert_eq!(cache.stats(), (2, 3)); // no new ECDSA call
+
+        // a wrong message is a miss, and a cached failure
+        let mut bad = checks[2].clone();
+        bad.msg32 = [9; 32];
+        assert_eq!(
+            cache.verify_batch(&[bad.clone()]),
+            Err((0, "invalid signature"))
+        );
+        assert_eq!(cache.verify_batch(&[bad]), Err((0, "invalid signature")));
+        assert_eq!(cache.stats(), (3, 4));
+    }
+}